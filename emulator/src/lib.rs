@@ -4,5 +4,6 @@ pub mod memory;
 pub mod opcode_id;
 pub mod page;
 pub mod state;
+pub mod syscall;
 pub mod tests;
 pub mod utils;
@@ -1,5 +1,6 @@
 use crate::memory::{Memory, INIT_SP, POSEIDON_RATE_BYTES};
 use crate::page::{PAGE_ADDR_MASK, PAGE_SIZE};
+use crate::syscall::SyscallHandlers;
 use elf::abi::{PT_LOAD, PT_TLS};
 use elf::endian::AnyEndian;
 use log::{trace, warn};
@@ -143,6 +144,11 @@ impl State {
     pub fn load_seg(seg_path: &str) -> (Box<Self>, u64) {
         let reader = BufReader::new(File::open(seg_path).unwrap());
         let segment: Segment = serde_json::from_reader(reader).unwrap();
+        Self::from_segment(segment)
+    }
+
+    /// Restore the state at the start of `segment`, also returning the number of steps it runs.
+    pub fn from_segment(segment: Segment) -> (Box<Self>, u64) {
         let mut s = Box::new(Self {
             memory: Box::new(Memory::new()),
             registers: Default::default(),
@@ -538,6 +544,13 @@ impl State {
         regs_bytes_be[38 * 4..38 * 4 + 4].copy_from_slice(&self.local_user.to_be_bytes());
         regs_bytes_be
     }
+
+    /// Continue the execution at `pc`, with the next instruction right after it, i.e. not in a
+    /// branch delay slot.
+    pub fn set_pc(&mut self, pc: u32) {
+        self.pc = pc;
+        self.next_pc = pc.wrapping_add(4);
+    }
 }
 
 pub struct InstrumentedState {
@@ -558,6 +571,9 @@ pub struct InstrumentedState {
     pre_input_ptr: usize,
     pre_public_values: Vec<u8>,
     pre_public_values_ptr: usize,
+
+    /// handlers of the syscalls which are not built-in
+    pub syscall_handlers: SyscallHandlers,
 }
 
 impl Display for InstrumentedState {
@@ -592,6 +608,7 @@ impl InstrumentedState {
             pre_input_ptr: 0,
             pre_public_values: Vec::new(),
             pre_public_values_ptr: 0,
+            syscall_handlers: SyscallHandlers::default(),
         })
     }
 
@@ -612,28 +629,36 @@ impl InstrumentedState {
             0x300105 => {
                 // SHA_EXTEND
                 let w_ptr = a0;
-                assert!(a1 == 0, "arg2 must be 0");
+                // arg2 is the distance in bytes between consecutive words of `w`, 0 meaning
+                // contiguous.
+                let stride = if a1 == 0 { 4 } else { a1 };
+                assert!(stride % 4 == 0, "arg2 must be a multiple of 4");
+                let w_addr = |i: u32| {
+                    i.checked_mul(stride)
+                        .and_then(|offset| w_ptr.checked_add(offset))
+                        .expect("SHA extend address overflows the address space")
+                };
 
                 for i in 16..64 {
                     // Read w[i-15].
-                    let w_i_minus_15 = self.state.memory.get_memory(w_ptr + (i - 15) * 4);
+                    let w_i_minus_15 = self.state.memory.get_memory(w_addr(i - 15));
                     // Compute `s0`.
                     let s0 = w_i_minus_15.rotate_right(7)
                         ^ w_i_minus_15.rotate_right(18)
                         ^ (w_i_minus_15 >> 3);
 
                     // Read w[i-2].
-                    let w_i_minus_2 = self.state.memory.get_memory(w_ptr + (i - 2) * 4);
+                    let w_i_minus_2 = self.state.memory.get_memory(w_addr(i - 2));
                     // Compute `s1`.
                     let s1 = w_i_minus_2.rotate_right(17)
                         ^ w_i_minus_2.rotate_right(19)
                         ^ (w_i_minus_2 >> 10);
 
                     // Read w[i-16].
-                    let w_i_minus_16 = self.state.memory.get_memory(w_ptr + (i - 16) * 4);
+                    let w_i_minus_16 = self.state.memory.get_memory(w_addr(i - 16));
 
                     // Read w[i-7].
-                    let w_i_minus_7 = self.state.memory.get_memory(w_ptr + (i - 7) * 4);
+                    let w_i_minus_7 = self.state.memory.get_memory(w_addr(i - 7));
 
                     // Compute `w_i`.
                     let w_i = s1
@@ -651,8 +676,8 @@ impl InstrumentedState {
                         w_i_minus_15,
                         w_i_minus_2
                     );
-                    self.state.memory.set_memory(w_ptr + i * 4, w_i);
-                    log::debug!("extend write {:X} {:X}", w_ptr + i * 4, w_i);
+                    self.state.memory.set_memory(w_addr(i), w_i);
+                    log::debug!("extend write {:X} {:X}", w_addr(i), w_i);
                 }
             }
             0x010106 => {
@@ -907,7 +932,11 @@ impl InstrumentedState {
                 log::trace!("sys_verify {:X} {:X} {:X}", a0, a1, a2);
                 // DO Nothing Here
             }
-            _ => {}
+            _ => {
+                if let Some(handler) = self.syscall_handlers.get(syscall_num) {
+                    [v0, v1] = handler.handle([a0, a1, a2]);
+                }
+            }
         }
 
         self.state.registers[2] = v0;
@@ -1022,10 +1051,16 @@ impl InstrumentedState {
                 self.state.hi = (acc >> 32) as u32;
                 self.state.lo = acc as u32;
             }
+            // The result of a division by zero is unpredictable on MIPS;
+            // use the quotient 0 and remainder rs, as the prover does.
+            0x1a | 0x1b if rt == 0 => {
+                self.state.hi = rs;
+                self.state.lo = 0;
+            }
             0x1a => {
                 // div
-                self.state.hi = ((rs as i32) % (rt as i32)) as u32;
-                self.state.lo = ((rs as i32) / (rt as i32)) as u32;
+                self.state.hi = (rs as i32).wrapping_rem(rt as i32) as u32;
+                self.state.lo = (rs as i32).wrapping_div(rt as i32) as u32;
             }
             0x1b => {
                 // divu
@@ -1480,6 +1515,19 @@ impl InstrumentedState {
         output: &str,
         new_writer: fn(&str) -> Option<W>,
     ) {
+        let segment_id = self.pre_segment_id;
+        if let Some(segment) = self.take_segment(proof) {
+            let name = format!("{output}/{}", segment_id);
+            log::debug!("split: file {}", name);
+            let mut f = new_writer(&name).unwrap();
+            let data = serde_json::to_vec(&segment).unwrap();
+            f.write_all(data.as_slice()).unwrap();
+        }
+    }
+
+    /// Close the current segment at the current step and start the next one. The closed
+    /// segment is returned if `proof` is true.
+    pub fn take_segment(&mut self, proof: bool) -> Option<Segment> {
         self.state.total_cycle +=
             self.state.cycle + (self.state.memory.page_count() + 1) * PAGE_CYCLES;
         self.state.total_step += self.state.step;
@@ -1494,7 +1542,7 @@ impl InstrumentedState {
             self.state.memory.compute_image_id(self.state.pc, &regiters);
         let image = self.state.memory.get_input_image();
 
-        if proof {
+        let segment = proof.then(|| {
             let segment = Segment {
                 mem_image: image,
                 segment_id: self.pre_segment_id,
@@ -1510,13 +1558,9 @@ impl InstrumentedState {
                 public_values_stream: self.pre_public_values.clone(),
                 public_values_stream_ptr: self.pre_public_values_ptr,
             };
-            let name = format!("{output}/{}", self.pre_segment_id);
-            log::debug!("split: file {}", name);
-            let mut f = new_writer(&name).unwrap();
-            let data = serde_json::to_vec(&segment).unwrap();
-            f.write_all(data.as_slice()).unwrap();
             self.pre_segment_id += 1;
-        }
+            segment
+        });
 
         self.pre_input = self.state.input_stream.clone();
         self.pre_input_ptr = self.state.input_stream_ptr;
@@ -1527,6 +1571,7 @@ impl InstrumentedState {
         self.pre_hash_root = page_hash_root;
         self.state.cycle = 0;
         self.state.step = 0;
+        segment
     }
 
     pub fn dump_memory(&mut self) {
@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+pub const SYS_READ: u32 = 4003;
+pub const SYS_WRITE: u32 = 4004;
+pub const SYS_GETPID: u32 = 4020;
+pub const SYS_BRK: u32 = 4045;
+pub const SYS_FCNTL: u32 = 4055;
+pub const SYS_MMAP: u32 = 4090;
+pub const SYS_CLONE: u32 = 4120;
+pub const SYS_MMAP2: u32 = 4210;
+pub const SYS_EXIT_GROUP: u32 = 4246;
+pub const SYS_SET_THREAD_AREA: u32 = 4283;
+pub const SYS_HINT_LEN: u32 = 0xF0;
+pub const SYS_HINT_READ: u32 = 0xF1;
+pub const SYS_VERIFY: u32 = 0xF2;
+pub const SYS_SHA_COMPRESS: u32 = 0x010106;
+pub const SYS_KECCAK: u32 = 0x010109;
+pub const SYS_SHA_EXTEND: u32 = 0x300105;
+
+/// The syscall numbers handled by the emulator and the prover themselves: the Linux syscalls
+/// used by the guest runtime, the hint and verify syscalls and the hash precompiles.
+pub const BUILTIN_SYSCALLS: [u32; 16] = [
+    SYS_READ,
+    SYS_WRITE,
+    SYS_GETPID,
+    SYS_BRK,
+    SYS_FCNTL,
+    SYS_MMAP,
+    SYS_CLONE,
+    SYS_MMAP2,
+    SYS_EXIT_GROUP,
+    SYS_SET_THREAD_AREA,
+    SYS_HINT_LEN,
+    SYS_HINT_READ,
+    SYS_VERIFY,
+    SYS_SHA_COMPRESS,
+    SYS_KECCAK,
+    SYS_SHA_EXTEND,
+];
+
+/// A syscall outside of `BUILTIN_SYSCALLS`. It is given `$a0`, `$a1` and `$a2`, and returns the
+/// values written to `$v0` and `$a3`.
+///
+/// The handler must be deterministic, since the emulator and the prover both run it.
+pub trait SyscallHandler: Send + Sync {
+    fn handle(&self, args: [u32; 3]) -> [u32; 2];
+}
+
+impl<F: Fn([u32; 3]) -> [u32; 2] + Send + Sync> SyscallHandler for F {
+    fn handle(&self, args: [u32; 3]) -> [u32; 2] {
+        self(args)
+    }
+}
+
+/// The handlers of the syscalls outside of `BUILTIN_SYSCALLS`, keyed by syscall number, i.e. by
+/// the value of `$v0`. Unknown syscalls write 0 to `$v0` and `$a3`.
+#[derive(Clone, Default)]
+pub struct SyscallHandlers {
+    handlers: BTreeMap<u32, Arc<dyn SyscallHandler>>,
+}
+
+impl SyscallHandlers {
+    /// Handle the syscall `num` with `handler`, replacing its previous handler if any.
+    ///
+    /// Panics if `num` is one of `BUILTIN_SYSCALLS`.
+    pub fn register(&mut self, num: u32, handler: impl SyscallHandler + 'static) -> &mut Self {
+        assert!(
+            !BUILTIN_SYSCALLS.contains(&num),
+            "Syscall {num:#x} is built-in"
+        );
+        self.handlers.insert(num, Arc::new(handler));
+        self
+    }
+
+    pub fn get(&self, num: u32) -> Option<&dyn SyscallHandler> {
+        self.handlers.get(&num).map(|handler| handler.as_ref())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.handlers.is_empty()
+    }
+}
+
+impl Debug for SyscallHandlers {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.handlers.keys()).finish()
+    }
+}
+
+/// Handlers can't be compared by value, so two registries are equal if they hold the same handler
+/// instances for the same syscalls, e.g. if one is a clone of the other. Registering the same
+/// closure in two registries makes them unequal.
+impl PartialEq for SyscallHandlers {
+    fn eq(&self, other: &Self) -> bool {
+        self.handlers.len() == other.handlers.len()
+            && self
+                .handlers
+                .iter()
+                .zip(&other.handlers)
+                .all(|((lhs_num, lhs), (rhs_num, rhs))| lhs_num == rhs_num && Arc::ptr_eq(lhs, rhs))
+    }
+}
+
+impl Eq for SyscallHandlers {}
@@ -6,8 +6,13 @@ mod tests {
         path::{Path, PathBuf},
     };
 
+    use crate::state::Segment;
     use crate::state::{InstrumentedState, State};
-    use crate::utils::{get_block_path, load_elf_with_patch, split_prog_into_segs, SEGMENT_STEPS};
+    use crate::syscall::SyscallHandlers;
+    use crate::utils::{
+        get_block_path, load_elf_with_patch, split_prog_into_segs, split_seg_at_steps,
+        SEGMENT_STEPS,
+    };
 
     const END_ADDR: u32 = 0xa7ef00d0;
     const OUTPUT: &str = "/tmp/segment";
@@ -93,4 +98,74 @@ mod tests {
         let state = load_elf_with_patch("test-vectors/hello", vec![]);
         let _ = split_prog_into_segs(state, OUTPUT, "", SEGMENT_STEPS);
     }
+
+    #[test]
+    fn test_split_seg_at_steps() {
+        let output = "/tmp/segment_at_steps";
+        let state = load_elf_with_patch("test-vectors/hello", vec![]);
+        let (total_step, num_segments, _) = split_prog_into_segs(state, output, "", 1 << 30);
+        assert_eq!(num_segments, 1);
+
+        let reader = fs::File::open(format!("{output}/0")).unwrap();
+        let segment: Segment = serde_json::from_reader(reader).unwrap();
+        let boundaries = [total_step / 3, 2 * total_step / 3];
+        let segments = split_seg_at_steps(
+            segment.clone(),
+            "",
+            &boundaries,
+            &SyscallHandlers::default(),
+        );
+
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[0].pre_image_id, segment.pre_image_id);
+        assert_eq!(segments[2].image_id, segment.image_id);
+        assert_eq!(segments[2].end_pc, segment.end_pc);
+        for pair in segments.windows(2) {
+            assert_eq!(pair[0].image_id, pair[1].pre_image_id);
+            assert_eq!(pair[0].end_pc, pair[1].pc);
+        }
+        let steps = segments
+            .iter()
+            .map(|seg| seg.step as usize)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            steps,
+            [
+                boundaries[0],
+                boundaries[1] - boundaries[0],
+                total_step - boundaries[1]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_custom_syscall() {
+        const SYSCALL: u32 = 0x0000_000c;
+        const SYS_ADD_ONE: u32 = 0x1234;
+
+        let mut state = State::new();
+        let code = SYSCALL.to_be_bytes();
+        state
+            .memory
+            .set_memory_range(0, Box::new(code.as_slice()))
+            .expect("set memory range failed");
+        state.registers[2] = SYS_ADD_ONE;
+        state.registers[4] = 41;
+
+        let mut instrumented_state = InstrumentedState::new(state, String::from(""));
+        instrumented_state
+            .syscall_handlers
+            .register(SYS_ADD_ONE, |[a0, _, _]: [u32; 3]| [a0 + 1, 0]);
+        instrumented_state.step();
+
+        assert_eq!(instrumented_state.state.registers[2], 42);
+        assert_eq!(instrumented_state.state.registers[7], 0);
+        assert_eq!(instrumented_state.state.pc, 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "built-in")]
+    fn test_register_builtin_syscall() {
+        SyscallHandlers::default().register(4246, |_: [u32; 3]| [0, 0]);
+    }
 }
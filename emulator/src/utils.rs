@@ -1,4 +1,5 @@
-use crate::state::{InstrumentedState, State};
+use crate::state::{InstrumentedState, Segment, State};
+use crate::syscall::SyscallHandlers;
 use elf::{endian::AnyEndian, ElfBytes};
 use std::fs;
 use std::fs::File;
@@ -102,3 +103,54 @@ pub fn split_seg_into_segs(
         instrumented_state.state,
     )
 }
+
+/// Re-split `segment` at the given step `boundaries`, counted from the start of `segment`,
+/// keeping the resulting segments in memory. Boundaries must be strictly increasing and lie
+/// strictly inside the segment.
+///
+/// Each segment starts from the memory image the previous one ends with, so the `image_id` of
+/// every segment is the `pre_image_id` of the next one, and the first and last segments keep the
+/// `pre_image_id` and `image_id` of `segment`. The syscalls which are not built-in are run by
+/// `syscall_handlers`, which must be the ones `segment` was executed with.
+pub fn split_seg_at_steps(
+    segment: Segment,
+    block_path: &str,
+    boundaries: &[usize],
+    syscall_handlers: &SyscallHandlers,
+) -> Vec<Segment> {
+    let (state, final_step) = State::from_segment(segment);
+    assert!(
+        boundaries
+            .iter()
+            .zip(boundaries.iter().skip(1))
+            .all(|(lhs, rhs)| lhs < rhs),
+        "Segment boundaries must be strictly increasing"
+    );
+    assert!(
+        boundaries
+            .iter()
+            .all(|&boundary| 0 < boundary && (boundary as u64) < final_step),
+        "Segment boundaries must lie inside the segment"
+    );
+
+    let mut instrumented_state = InstrumentedState::new(state, block_path.to_string());
+    instrumented_state.syscall_handlers = syscall_handlers.clone();
+    instrumented_state.take_segment(false);
+    let mut segments = Vec::with_capacity(boundaries.len() + 1);
+    let mut boundaries = boundaries.iter().peekable();
+    loop {
+        instrumented_state.step();
+        let step = instrumented_state.state.total_step + instrumented_state.state.step;
+        if step == final_step {
+            break;
+        }
+        if boundaries
+            .next_if(|&&boundary| boundary as u64 == step)
+            .is_some()
+        {
+            segments.extend(instrumented_state.take_segment(true));
+        }
+    }
+    segments.extend(instrumented_state.take_segment(true));
+    segments
+}
@@ -169,6 +169,8 @@ pub fn prove_segments(
         circuit.set_data(all_circuits.block.circuit);
         let mut bit_size = vec![32usize; 16];
         bit_size.extend(vec![8; 32]);
+        // userdata length
+        bit_size.push(32);
         bit_size.extend(vec![64; 68]);
         let wrapped_circuit = WrappedCircuit::<InnerParameters, OuterParameters, D>::build(
             circuit,
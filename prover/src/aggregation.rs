@@ -0,0 +1,247 @@
+use std::collections::BTreeMap;
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+
+use anyhow::{anyhow, ensure, Result};
+use plonky2::field::extension::Extendable;
+use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::config::{AlgebraicHasher, GenericConfig};
+
+use crate::fixed_recursive_verifier::AllRecursiveCircuits;
+use crate::generation::state::Receipt;
+
+/// A node of the aggregation tree that hasn't been merged with a sibling yet.
+struct PendingReceipt<F, C, const D: usize>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    /// Height of the subtree; segment (root) receipts have height 0.
+    height: usize,
+    /// Whether `receipt` is an aggregation proof, as opposed to a root proof.
+    is_agg: bool,
+    receipt: Receipt<F, C, D>,
+}
+
+/// Folds consecutive segment receipts into a balanced aggregation tree as they are pushed.
+///
+/// The accumulator keeps a stack of subtrees of strictly decreasing height, like a binary
+/// counter: pushing a receipt merges it with every subtree of the same height on top of the
+/// stack, so at most `log2(n)` receipts are pending at any time.
+pub struct AggregationAccumulator<F, C, const D: usize>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    stack: Vec<PendingReceipt<F, C, D>>,
+    num_segments: usize,
+}
+
+impl<F, C, const D: usize> Default for AggregationAccumulator<F, C, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    fn default() -> Self {
+        Self {
+            stack: vec![],
+            num_segments: 0,
+        }
+    }
+}
+
+impl<F, C, const D: usize> AggregationAccumulator<F, C, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F> + 'static,
+    C::Hasher: AlgebraicHasher<F>,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of segment receipts pushed so far.
+    pub fn num_segments(&self) -> usize {
+        self.num_segments
+    }
+
+    /// Push the root receipt of the next segment. Segments must be pushed in execution order.
+    pub fn push(
+        &mut self,
+        all_circuits: &AllRecursiveCircuits<F, C, D>,
+        receipt: Receipt<F, C, D>,
+    ) -> Result<()> {
+        self.num_segments += 1;
+        let mut node = PendingReceipt {
+            height: 0,
+            is_agg: false,
+            receipt,
+        };
+        while self
+            .stack
+            .last()
+            .is_some_and(|top| top.height == node.height)
+        {
+            let lhs = self.stack.pop().unwrap();
+            node = Self::merge(all_circuits, lhs, node)?;
+        }
+        self.stack.push(node);
+        Ok(())
+    }
+
+    /// Merge the remaining subtrees, from right to left, into a single receipt.
+    ///
+    /// A single segment yields its root receipt; otherwise the result is an aggregation receipt.
+    pub fn finish(
+        mut self,
+        all_circuits: &AllRecursiveCircuits<F, C, D>,
+    ) -> Result<Receipt<F, C, D>> {
+        let mut node = self
+            .stack
+            .pop()
+            .ok_or_else(|| anyhow!("No segment receipts to aggregate."))?;
+        while let Some(lhs) = self.stack.pop() {
+            node = Self::merge(all_circuits, lhs, node)?;
+        }
+        Ok(node.receipt)
+    }
+
+    fn merge(
+        all_circuits: &AllRecursiveCircuits<F, C, D>,
+        lhs: PendingReceipt<F, C, D>,
+        rhs: PendingReceipt<F, C, D>,
+    ) -> Result<PendingReceipt<F, C, D>> {
+        let receipt =
+            all_circuits.prove_aggregation(lhs.is_agg, &lhs.receipt, rhs.is_agg, &rhs.receipt)?;
+        Ok(PendingReceipt {
+            height: lhs.height.max(rhs.height) + 1,
+            is_agg: true,
+            receipt,
+        })
+    }
+}
+
+/// Messages accepted by an `AggregationCoordinator`.
+pub enum AggregationMessage<F, C, const D: usize>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    /// The root receipt of the segment at position `index` in execution order.
+    Segment {
+        index: usize,
+        receipt: Receipt<F, C, D>,
+    },
+    /// All segments have been sent.
+    Complete,
+}
+
+/// Receives segment receipts through a channel, in any order, and folds them into an
+/// aggregation tree as soon as they can be merged, ending with a block receipt.
+///
+/// Senders can be cloned and moved to the threads proving the segments, while the coordinator
+/// runs on its own thread.
+pub struct AggregationCoordinator<F, C, const D: usize>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    receiver: Receiver<AggregationMessage<F, C, D>>,
+    accumulator: AggregationAccumulator<F, C, D>,
+    /// Receipts that arrived before one of their predecessors.
+    out_of_order: BTreeMap<usize, Receipt<F, C, D>>,
+    complete: bool,
+}
+
+impl<F, C, const D: usize> AggregationCoordinator<F, C, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F> + 'static,
+    C::Hasher: AlgebraicHasher<F>,
+{
+    pub fn new() -> (Sender<AggregationMessage<F, C, D>>, Self) {
+        let (sender, receiver) = channel();
+        let coordinator = Self {
+            receiver,
+            accumulator: AggregationAccumulator::new(),
+            out_of_order: BTreeMap::new(),
+            complete: false,
+        };
+        (sender, coordinator)
+    }
+
+    /// Whether `AggregationMessage::Complete` has been received.
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Process all messages currently in the channel without blocking.
+    pub fn poll(&mut self, all_circuits: &AllRecursiveCircuits<F, C, D>) -> Result<()> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(message) => self.handle(all_circuits, message)?,
+                Err(TryRecvError::Empty) => return Ok(()),
+                Err(TryRecvError::Disconnected) => {
+                    ensure!(self.complete, "All senders dropped before completion.");
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Block until `AggregationMessage::Complete` is received, then aggregate what remains and
+    /// prove the block receipt.
+    ///
+    /// Fails for a single segment: the block circuit verifies an aggregation proof, which needs
+    /// two segments. Prove a single segment with `AllRecursiveCircuits::prove_root` instead.
+    pub fn run(mut self, all_circuits: &AllRecursiveCircuits<F, C, D>) -> Result<Receipt<F, C, D>> {
+        while !self.complete {
+            let message = self
+                .receiver
+                .recv()
+                .map_err(|_| anyhow!("All senders dropped before completion."))?;
+            self.handle(all_circuits, message)?;
+        }
+        ensure!(
+            self.out_of_order.is_empty(),
+            "Missing segment {}.",
+            self.accumulator.num_segments()
+        );
+
+        ensure!(
+            self.accumulator.num_segments() >= 2,
+            "A block needs at least two segments, got {}.",
+            self.accumulator.num_segments()
+        );
+        let receipt = self.accumulator.finish(all_circuits)?;
+        all_circuits.prove_block(None, &receipt)
+    }
+
+    fn handle(
+        &mut self,
+        all_circuits: &AllRecursiveCircuits<F, C, D>,
+        message: AggregationMessage<F, C, D>,
+    ) -> Result<()> {
+        match message {
+            AggregationMessage::Segment { index, receipt } => {
+                ensure!(
+                    !self.complete,
+                    "Segment {} received after completion.",
+                    index
+                );
+                ensure!(
+                    index >= self.accumulator.num_segments()
+                        && !self.out_of_order.contains_key(&index),
+                    "Segment {} received twice.",
+                    index
+                );
+                self.out_of_order.insert(index, receipt);
+                while let Some(receipt) = self.out_of_order.remove(&self.accumulator.num_segments())
+                {
+                    self.accumulator.push(all_circuits, receipt)?;
+                }
+            }
+            AggregationMessage::Complete => self.complete = true,
+        }
+        Ok(())
+    }
+}
@@ -3,8 +3,10 @@ use crate::arithmetic::arithmetic_stark::ArithmeticStark;
 use crate::config::StarkConfig;
 use crate::cpu::cpu_stark;
 use crate::cpu::cpu_stark::CpuStark;
+use crate::cpu::kernel::assembler::Kernel;
 use crate::cpu::membus::NUM_GP_CHANNELS;
 use crate::cross_table_lookup::{CrossTableLookup, TableWithColumns};
+use crate::generation::{generate_traces, run_kernel};
 use crate::keccak::keccak_stark;
 use crate::keccak::keccak_stark::KeccakStark;
 use crate::keccak_sponge::columns::KECCAK_RATE_BYTES;
@@ -34,6 +36,12 @@ use crate::stark::Stark;
 use plonky2::field::extension::Extendable;
 use plonky2::field::types::Field;
 use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::config::GenericConfig;
+use plonky2::util::timing::TimingTree;
+use plonky2_util::log2_ceil;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Range;
 
 #[derive(Clone)]
 pub struct AllStark<F: RichField + Extendable<D>, const D: usize> {
@@ -91,6 +99,212 @@ impl<F: RichField + Extendable<D>, const D: usize> AllStark<F, D> {
             self.memory_stark.num_lookup_helper_columns(config),
         ]
     }
+
+    /// Returns, for each table, the number of trace, auxiliary and quotient polynomials its
+    /// proof commits to.
+    pub(crate) fn num_polys_by_table(&self, config: &StarkConfig) -> [[usize; 3]; NUM_TABLES] {
+        fn num_polys<F: RichField + Extendable<D>, S: Stark<F, D>, const D: usize>(
+            stark: &S,
+            table: Table,
+            cross_table_lookups: &[CrossTableLookup<F>],
+            config: &StarkConfig,
+        ) -> [usize; 3] {
+            let (num_ctl_helpers, num_ctl_zs, _) = CrossTableLookup::num_ctl_helpers_zs_all(
+                cross_table_lookups,
+                table,
+                config.num_challenges,
+                stark.constraint_degree(),
+            );
+            [
+                S::COLUMNS,
+                stark.num_lookup_helper_columns(config) + num_ctl_helpers + num_ctl_zs,
+                stark.num_quotient_polys(config),
+            ]
+        }
+
+        let ctls = &self.cross_table_lookups;
+        [
+            num_polys(&self.arithmetic_stark, Table::Arithmetic, ctls, config),
+            num_polys(&self.cpu_stark, Table::Cpu, ctls, config),
+            num_polys(&self.poseidon_stark, Table::Poseidon, ctls, config),
+            num_polys(
+                &self.poseidon_sponge_stark,
+                Table::PoseidonSponge,
+                ctls,
+                config,
+            ),
+            num_polys(&self.keccak_stark, Table::Keccak, ctls, config),
+            num_polys(&self.keccak_sponge_stark, Table::KeccakSponge, ctls, config),
+            num_polys(&self.sha_extend_stark, Table::ShaExtend, ctls, config),
+            num_polys(
+                &self.sha_extend_sponge_stark,
+                Table::ShaExtendSponge,
+                ctls,
+                config,
+            ),
+            num_polys(&self.sha_compress_stark, Table::ShaCompress, ctls, config),
+            num_polys(
+                &self.sha_compress_sponge_stark,
+                Table::ShaCompressSponge,
+                ctls,
+                config,
+            ),
+            num_polys(&self.logic_stark, Table::Logic, ctls, config),
+            num_polys(&self.memory_stark, Table::Memory, ctls, config),
+        ]
+    }
+
+    /// Estimates the size in bytes of an `AllProof` whose tables have the given degrees, as
+    /// serialized by `AllProof::to_bytes`. Only the Merkle caps, the openings and the FRI proofs
+    /// are accounted for, which dominate the size of the proof.
+    pub fn estimate_proof_bytes(
+        &self,
+        config: &StarkConfig,
+        degree_bits_per_table: &[usize; NUM_TABLES],
+    ) -> usize {
+        self.num_polys_by_table(config)
+            .iter()
+            .zip(degree_bits_per_table)
+            .map(|(&num_polys, &degree_bits)| {
+                config.estimate_table_proof_bytes(degree_bits, num_polys)
+            })
+            .sum()
+    }
+
+    /// Checks that the constraint degree of every STARK is supported by its lookups and by the
+    /// helper columns of the cross-table lookups it's involved in, see
+    /// `check_constraint_degree`.
+    pub(crate) fn check_constraint_degrees(&self) -> anyhow::Result<()> {
+        let ctls = &self.cross_table_lookups;
+        check_constraint_degree(&self.arithmetic_stark, Table::Arithmetic, ctls)?;
+        check_constraint_degree(&self.cpu_stark, Table::Cpu, ctls)?;
+        check_constraint_degree(&self.poseidon_stark, Table::Poseidon, ctls)?;
+        check_constraint_degree(&self.poseidon_sponge_stark, Table::PoseidonSponge, ctls)?;
+        check_constraint_degree(&self.keccak_stark, Table::Keccak, ctls)?;
+        check_constraint_degree(&self.keccak_sponge_stark, Table::KeccakSponge, ctls)?;
+        check_constraint_degree(&self.sha_extend_stark, Table::ShaExtend, ctls)?;
+        check_constraint_degree(&self.sha_extend_sponge_stark, Table::ShaExtendSponge, ctls)?;
+        check_constraint_degree(&self.sha_compress_stark, Table::ShaCompress, ctls)?;
+        check_constraint_degree(
+            &self.sha_compress_sponge_stark,
+            Table::ShaCompressSponge,
+            ctls,
+        )?;
+        check_constraint_degree(&self.logic_stark, Table::Logic, ctls)?;
+        check_constraint_degree(&self.memory_stark, Table::Memory, ctls)
+    }
+
+    /// Runs `kernel` and generates the trace of every table, without committing to or proving
+    /// them, and returns the number of rows of each trace. The traces are padded as for proving,
+    /// so each count is `2^degree_bits` of the corresponding STARK proof.
+    pub fn profile<C: GenericConfig<D, F = F>>(
+        &self,
+        kernel: &Kernel,
+        config: &StarkConfig,
+    ) -> anyhow::Result<[usize; NUM_TABLES]> {
+        let mut timing = TimingTree::new("profile", log::Level::Debug);
+        let (tables, _, _) = generate_traces::<F, C, D>(self, kernel, config, &mut timing)?;
+        timing.print();
+        Ok(tables.map(|trace| trace[0].len()))
+    }
+
+    /// Runs `kernel` and counts the instructions it executed by opcode, e.g. to check that a test
+    /// program exercises the intended instructions. Instructions which fail, and are handled as
+    /// exceptions, aren't counted.
+    ///
+    /// Proofs don't hold their traces, so the histogram is computed by running the kernel again
+    /// rather than from an `AllProof`.
+    pub fn opcode_histogram<C: GenericConfig<D, F = F>>(
+        &self,
+        kernel: &Kernel,
+    ) -> anyhow::Result<BTreeMap<Opcode, usize>> {
+        let mut timing = TimingTree::new("opcode histogram", log::Level::Debug);
+        let state = run_kernel::<F, C, D>(self, kernel, true, &mut timing)?;
+        Ok(state.traces.opcode_counts.unwrap_or_default())
+    }
+
+    /// Returns, for each table, the range of degree bits for which to preprocess circuits so that
+    /// a trace with the row counts of `expected_ops` can be proven, once padded to a power of two
+    /// as during trace generation. The ranges are a single degree wide, except for the Memory
+    /// table, which gets one more as `MemoryStark` may add rows to fill gaps in addresses and
+    /// timestamps.
+    pub fn recommend_degree_ranges(
+        &self,
+        expected_ops: &ProfileInput,
+    ) -> [Range<usize>; NUM_TABLES] {
+        Table::all().map(|table| {
+            let num_rows = expected_ops.num_rows[table as usize];
+            let min_rows = match table {
+                Table::Arithmetic => arithmetic_stark::RANGE_MAX,
+                Table::Cpu | Table::Memory => 1,
+                _ => MIN_TRACE_LEN,
+            };
+            let degree_bits = log2_ceil(num_rows.max(min_rows));
+            match table {
+                Table::Memory => degree_bits..degree_bits + 2,
+                _ => degree_bits..degree_bits + 1,
+            }
+        })
+    }
+}
+
+/// Checks that the constraint degree of `stark`, proving `table`, is supported:
+/// - cross-table lookups batch `constraint_degree - 1` looking columns per helper column, so a
+///   table involved in any of them needs a degree of at least 2,
+/// - lookups are only implemented for a constraint degree of 3.
+fn check_constraint_degree<F: RichField + Extendable<D>, S: Stark<F, D>, const D: usize>(
+    stark: &S,
+    table: Table,
+    cross_table_lookups: &[CrossTableLookup<F>],
+) -> anyhow::Result<()> {
+    let constraint_degree = stark.constraint_degree();
+    if CrossTableLookup::tables_touched(cross_table_lookups)[table as usize] {
+        anyhow::ensure!(
+            constraint_degree >= 2,
+            "{:?} table: constraint degree {} is too low for cross-table lookups, which need at \
+             least 2.",
+            table,
+            constraint_degree
+        );
+    }
+    if stark.uses_lookups() {
+        anyhow::ensure!(
+            constraint_degree == 3,
+            "{:?} table: lookups only support a constraint degree of 3, but the STARK reports {}.",
+            table,
+            constraint_degree
+        );
+    }
+    Ok(())
+}
+
+/// The number of rows of each table's trace before padding, e.g. as measured by a dry run of
+/// the program to prove.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ProfileInput {
+    pub num_rows: [usize; NUM_TABLES],
+}
+
+impl From<[usize; NUM_TABLES]> for ProfileInput {
+    fn from(num_rows: [usize; NUM_TABLES]) -> Self {
+        Self { num_rows }
+    }
+}
+
+/// A MIPS instruction mnemonic, e.g. `ADDIU` or `LW`, as counted by `AllStark::opcode_histogram`.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Opcode(pub(crate) String);
+
+impl Opcode {
+    pub fn mnemonic(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -110,6 +324,8 @@ pub enum Table {
 }
 
 pub(crate) const NUM_TABLES: usize = Table::Memory as usize + 1;
+/// Every table is proven and verified, see `AllRecursiveCircuits::new_with_active`.
+pub(crate) const ALL_TABLES_ACTIVE: [bool; NUM_TABLES] = [true; NUM_TABLES];
 pub(crate) const NUM_PUBLIC_INPUT_USERDATA: usize = 32;
 
 pub(crate) const MIN_TRACE_LEN: usize = 1 << 6;
@@ -154,10 +370,11 @@ pub(crate) fn all_cross_table_lookups<F: Field>() -> Vec<CrossTableLookup<F>> {
 }
 
 fn ctl_arithmetic<F: Field>() -> CrossTableLookup<F> {
-    CrossTableLookup::new(
+    CrossTableLookup::new_unchecked(
         vec![
             cpu_stark::ctl_arithmetic_base_rows(),
             cpu_stark::ctl_arithmetic_imm_base_rows(),
+            cpu_stark::ctl_arithmetic_hilo_rows(),
         ],
         arithmetic_stark::ctl_arithmetic_rows(),
     )
@@ -177,7 +394,7 @@ fn ctl_poseidon_inputs<F: Field>() -> CrossTableLookup<F> {
         poseidon_stark::ctl_data_inputs(),
         Some(poseidon_stark::ctl_filter_inputs()),
     );
-    CrossTableLookup::new(vec![poseidon_sponge_looking], poseidon_looked)
+    CrossTableLookup::new_unchecked(vec![poseidon_sponge_looking], poseidon_looked)
 }
 
 fn ctl_poseidon_outputs<F: Field>() -> CrossTableLookup<F> {
@@ -191,7 +408,7 @@ fn ctl_poseidon_outputs<F: Field>() -> CrossTableLookup<F> {
         poseidon_stark::ctl_data_outputs(),
         Some(poseidon_stark::ctl_filter_outputs()),
     );
-    CrossTableLookup::new(vec![poseidon_sponge_looking], poseidon_looked)
+    CrossTableLookup::new_unchecked(vec![poseidon_sponge_looking], poseidon_looked)
 }
 
 fn ctl_poseidon_sponge<F: Field>() -> CrossTableLookup<F> {
@@ -205,7 +422,7 @@ fn ctl_poseidon_sponge<F: Field>() -> CrossTableLookup<F> {
         poseidon_sponge_stark::ctl_looked_data(),
         Some(poseidon_sponge_stark::ctl_looked_filter()),
     );
-    CrossTableLookup::new(vec![cpu_looking], poseidon_sponge_looked)
+    CrossTableLookup::new_unchecked(vec![cpu_looking], poseidon_sponge_looked)
 }
 
 // We now need two different looked tables for `KeccakStark`:
@@ -222,7 +439,7 @@ fn ctl_keccak_inputs<F: Field>() -> CrossTableLookup<F> {
         keccak_stark::ctl_data_inputs(),
         Some(keccak_stark::ctl_filter_inputs()),
     );
-    CrossTableLookup::new(vec![keccak_sponge_looking], keccak_looked)
+    CrossTableLookup::new_unchecked(vec![keccak_sponge_looking], keccak_looked)
 }
 
 fn ctl_keccak_outputs<F: Field>() -> CrossTableLookup<F> {
@@ -236,7 +453,7 @@ fn ctl_keccak_outputs<F: Field>() -> CrossTableLookup<F> {
         keccak_stark::ctl_data_outputs(),
         Some(keccak_stark::ctl_filter_outputs()),
     );
-    CrossTableLookup::new(vec![keccak_sponge_looking], keccak_looked)
+    CrossTableLookup::new_unchecked(vec![keccak_sponge_looking], keccak_looked)
 }
 
 fn ctl_keccak_sponge<F: Field>() -> CrossTableLookup<F> {
@@ -250,35 +467,35 @@ fn ctl_keccak_sponge<F: Field>() -> CrossTableLookup<F> {
         keccak_sponge_stark::ctl_looked_data(),
         Some(keccak_sponge_stark::ctl_looked_filter()),
     );
-    CrossTableLookup::new(vec![cpu_looking], keccak_sponge_looked)
+    CrossTableLookup::new_unchecked(vec![cpu_looking], keccak_sponge_looked)
 }
 
-fn ctl_sha_extend_inputs<F: Field>() -> CrossTableLookup<F> {
+pub(crate) fn ctl_sha_extend_inputs<F: Field>() -> CrossTableLookup<F> {
     let sha_extend_sponge_looking = TableWithColumns::new(
         Table::ShaExtendSponge,
         sha_extend_sponge_stark::ctl_looking_sha_extend_inputs(),
-        Some(sha_extend_sponge_stark::ctl_looking_sha_extend_filter()),
+        Some(sha_extend_sponge_stark::sponge_active_filter()),
     );
     let sha_extend_looked = TableWithColumns::new(
         Table::ShaExtend,
         sha_extend_stark::ctl_data_inputs(),
         Some(sha_extend_stark::ctl_filter()),
     );
-    CrossTableLookup::new(vec![sha_extend_sponge_looking], sha_extend_looked)
+    CrossTableLookup::new_unchecked(vec![sha_extend_sponge_looking], sha_extend_looked)
 }
 
-fn ctl_sha_extend_outputs<F: Field>() -> CrossTableLookup<F> {
+pub(crate) fn ctl_sha_extend_outputs<F: Field>() -> CrossTableLookup<F> {
     let sha_extend_sponge_looking = TableWithColumns::new(
         Table::ShaExtendSponge,
         sha_extend_sponge_stark::ctl_looking_sha_extend_outputs(),
-        Some(sha_extend_sponge_stark::ctl_looking_sha_extend_filter()),
+        Some(sha_extend_sponge_stark::sponge_active_filter()),
     );
     let sha_extend_looked = TableWithColumns::new(
         Table::ShaExtend,
         sha_extend_stark::ctl_data_outputs(),
         Some(sha_extend_stark::ctl_filter()),
     );
-    CrossTableLookup::new(vec![sha_extend_sponge_looking], sha_extend_looked)
+    CrossTableLookup::new_unchecked(vec![sha_extend_sponge_looking], sha_extend_looked)
 }
 
 fn ctl_sha_extend_sponge<F: Field>() -> CrossTableLookup<F> {
@@ -290,9 +507,9 @@ fn ctl_sha_extend_sponge<F: Field>() -> CrossTableLookup<F> {
     let sha_extend_sponge_looked = TableWithColumns::new(
         Table::ShaExtendSponge,
         sha_extend_sponge_stark::ctl_looked_data(),
-        Some(sha_extend_sponge_stark::ctl_looking_sha_extend_filter()),
+        Some(sha_extend_sponge_stark::sponge_active_filter()),
     );
-    CrossTableLookup::new(vec![cpu_looking], sha_extend_sponge_looked)
+    CrossTableLookup::new_unchecked(vec![cpu_looking], sha_extend_sponge_looked)
 }
 
 fn ctl_sha_compress_inputs<F: Field>() -> CrossTableLookup<F> {
@@ -306,7 +523,7 @@ fn ctl_sha_compress_inputs<F: Field>() -> CrossTableLookup<F> {
         sha_compress_stark::ctl_data_inputs(),
         Some(sha_compress_stark::ctl_filter_inputs()),
     );
-    CrossTableLookup::new(vec![sha_compress_sponge_looking], sha_compress_looked)
+    CrossTableLookup::new_unchecked(vec![sha_compress_sponge_looking], sha_compress_looked)
 }
 
 fn ctl_sha_compress_outputs<F: Field>() -> CrossTableLookup<F> {
@@ -320,7 +537,7 @@ fn ctl_sha_compress_outputs<F: Field>() -> CrossTableLookup<F> {
         sha_compress_stark::ctl_data_outputs(),
         Some(sha_compress_stark::ctl_filter_outputs()),
     );
-    CrossTableLookup::new(vec![sha_compress_sponge_looking], sha_compress_looked)
+    CrossTableLookup::new_unchecked(vec![sha_compress_sponge_looking], sha_compress_looked)
 }
 
 fn ctl_sha_compress_sponge<F: Field>() -> CrossTableLookup<F> {
@@ -334,7 +551,7 @@ fn ctl_sha_compress_sponge<F: Field>() -> CrossTableLookup<F> {
         sha_compress_sponge_stark::ctl_looked_data(),
         Some(sha_compress_sponge_stark::ctl_looked_filter()),
     );
-    CrossTableLookup::new(vec![cpu_looking], sha_compress_sponge_looked)
+    CrossTableLookup::new_unchecked(vec![cpu_looking], sha_compress_sponge_looked)
 }
 
 pub(crate) fn ctl_logic<F: Field>() -> CrossTableLookup<F> {
@@ -473,7 +690,7 @@ pub(crate) fn ctl_logic<F: Field>() -> CrossTableLookup<F> {
     let logic_looked =
         TableWithColumns::new(Table::Logic, logic::ctl_data(), Some(logic::ctl_filter()));
 
-    CrossTableLookup::new(all_lookers, logic_looked)
+    CrossTableLookup::new_unchecked(all_lookers, logic_looked)
 }
 
 fn ctl_memory<F: Field>() -> CrossTableLookup<F> {
@@ -504,7 +721,7 @@ fn ctl_memory<F: Field>() -> CrossTableLookup<F> {
         TableWithColumns::new(
             Table::ShaExtendSponge,
             sha_extend_sponge_stark::ctl_looking_memory(i),
-            Some(sha_extend_sponge_stark::ctl_looking_sha_extend_filter()),
+            Some(sha_extend_sponge_stark::sponge_active_filter()),
         )
     });
 
@@ -538,5 +755,316 @@ fn ctl_memory<F: Field>() -> CrossTableLookup<F> {
         memory_stark::ctl_data(),
         Some(memory_stark::ctl_filter()),
     );
-    CrossTableLookup::new(all_lookers, memory_looked)
+    CrossTableLookup::new_unchecked(all_lookers, memory_looked)
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::field::extension::FieldExtension;
+    use plonky2::field::packed::PackedField;
+    use plonky2::iop::ext_target::ExtensionTarget;
+    use plonky2::plonk::circuit_builder::CircuitBuilder;
+    use plonky2::plonk::config::PoseidonGoldilocksConfig;
+    use plonky2_util::log2_strict;
+
+    use super::*;
+    use crate::arithmetic::columns::NUM_ARITH_COLUMNS;
+    use crate::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
+    use crate::cross_table_lookup::Column;
+    use crate::evaluation_frame::StarkFrame;
+    use crate::lookup::Lookup;
+    use crate::prover::prove;
+    use crate::prover::testutils::{exit_kernel, prove_and_verify, prove_exit_kernel};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// `ArithmeticStark`, whose range checks use a lookup, reporting a constraint degree of 4.
+    #[derive(Copy, Clone, Default)]
+    struct Degree4Stark(ArithmeticStark<F, D>);
+
+    impl Stark<F, D> for Degree4Stark {
+        type EvaluationFrame<FE, P, const D2: usize>
+            = StarkFrame<P, NUM_ARITH_COLUMNS>
+        where
+            FE: FieldExtension<D2, BaseField = F>,
+            P: PackedField<Scalar = FE>;
+
+        type EvaluationFrameTarget = StarkFrame<ExtensionTarget<D>, NUM_ARITH_COLUMNS>;
+
+        fn eval_packed_generic<FE, P, const D2: usize>(
+            &self,
+            vars: &Self::EvaluationFrame<FE, P, D2>,
+            yield_constr: &mut ConstraintConsumer<P>,
+        ) where
+            FE: FieldExtension<D2, BaseField = F>,
+            P: PackedField<Scalar = FE>,
+        {
+            self.0.eval_packed_generic(vars, yield_constr)
+        }
+
+        fn eval_ext_circuit(
+            &self,
+            builder: &mut CircuitBuilder<F, D>,
+            vars: &Self::EvaluationFrameTarget,
+            yield_constr: &mut RecursiveConstraintConsumer<F, D>,
+        ) {
+            self.0.eval_ext_circuit(builder, vars, yield_constr)
+        }
+
+        fn constraint_degree(&self) -> usize {
+            4
+        }
+
+        fn lookups(&self) -> Vec<Lookup<F>> {
+            self.0.lookups()
+        }
+    }
+
+    #[test]
+    fn test_check_constraint_degrees() {
+        let all_stark = AllStark::<F, D>::default();
+        assert!(all_stark.check_constraint_degrees().is_ok());
+
+        let ctls = &all_stark.cross_table_lookups;
+        let err =
+            check_constraint_degree(&Degree4Stark::default(), Table::Arithmetic, ctls).unwrap_err();
+        assert!(err.to_string().starts_with("Arithmetic table: "), "{err}");
+    }
+
+    #[test]
+    fn test_opcode_histogram() -> anyhow::Result<()> {
+        // add $t0, $t1, $t2; lw $t3, 0($zero); addiu $v0, $zero, 4246; syscall
+        let kernel = Kernel::from_asm(
+            ".word 0x012A4020, 0x8C0B0000, 0x24021096, 0x0000000c",
+            0x0040_0000,
+        )?;
+        let all_stark = AllStark::<F, D>::default();
+        let histogram = all_stark.opcode_histogram::<C>(&kernel)?;
+        let count = |mnemonic: &str| histogram.get(&Opcode(mnemonic.into())).copied();
+        assert_eq!(count("ADD"), Some(1));
+        assert_eq!(count("LW"), Some(1));
+        assert_eq!(count("ADDIU"), Some(1));
+        assert_eq!(count("SYSCALL"), Some(1));
+
+        // Trace generation doesn't count them.
+        let mut timing = TimingTree::default();
+        let state = run_kernel::<F, C, D>(&all_stark, &kernel, false, &mut timing)?;
+        assert!(state.traces.opcode_counts.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_recommend_degree_ranges() -> anyhow::Result<()> {
+        let all_stark = AllStark::<F, D>::default();
+
+        let mut num_rows = [0; NUM_TABLES];
+        num_rows[Table::Arithmetic as usize] = 10;
+        num_rows[Table::Cpu as usize] = 1 << 10;
+        num_rows[Table::Logic as usize] = 65;
+        num_rows[Table::Memory as usize] = 3000;
+        let ranges = all_stark.recommend_degree_ranges(&num_rows.into());
+        assert_eq!(ranges[Table::Arithmetic as usize], 16..17);
+        assert_eq!(ranges[Table::Cpu as usize], 10..11);
+        assert_eq!(ranges[Table::Keccak as usize], 6..7);
+        assert_eq!(ranges[Table::Logic as usize], 7..8);
+        assert_eq!(ranges[Table::Memory as usize], 12..14);
+
+        let kernel = exit_kernel()?;
+        let config = StarkConfig::standard_fast_config();
+        let num_rows = all_stark.profile::<C>(&kernel, &config)?;
+        let ranges = all_stark.recommend_degree_ranges(&num_rows.into());
+        for ((table, rows), range) in Table::all().iter().zip(num_rows).zip(&ranges) {
+            let degree_bits = log2_strict(rows);
+            assert!(
+                range.contains(&degree_bits),
+                "{table:?}: degree {degree_bits} outside of {range:?}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_profile_matches_proof() -> anyhow::Result<()> {
+        let kernel = exit_kernel()?;
+        let all_stark = AllStark::<F, D>::default();
+        let config = StarkConfig::standard_fast_config();
+
+        let num_rows = all_stark.profile::<C>(&kernel, &config)?;
+        let mut timing = TimingTree::new("prove", log::Level::Debug);
+        let proof = prove::<F, C, D>(&all_stark, &kernel, &config, &mut timing)?;
+        for (table, (&rows, stark_proof)) in Table::all()
+            .iter()
+            .zip(num_rows.iter().zip(&proof.stark_proofs))
+        {
+            assert_eq!(
+                rows,
+                1 << stark_proof.proof.recover_degree_bits(&config),
+                "{table:?}"
+            );
+        }
+
+        let ranges = all_stark.recommend_degree_ranges(&num_rows.into());
+        let memory_degree_bits = log2_strict(num_rows[Table::Memory as usize]);
+        assert_eq!(ranges[Table::Memory as usize].start, memory_degree_bits);
+        Ok(())
+    }
+
+    #[test]
+    fn test_estimate_proof_bytes() -> anyhow::Result<()> {
+        let (all_stark, config, proof) = prove_exit_kernel::<F, C, D>()?;
+        let proof_bytes = proof.to_bytes()?.len();
+        let estimate = all_stark.estimate_proof_bytes(&config, &proof.degree_bits(&config));
+        assert!(
+            estimate.abs_diff(proof_bytes) * 10 <= proof_bytes,
+            "estimated {estimate} bytes, got {proof_bytes}"
+        );
+        Ok(())
+    }
+
+    // The SHA extend sponge reads its input words from memory, and its output word is written by
+    // the CPU, which the sponge is looked up by.
+    #[test]
+    fn test_sha_extend_sponge_memory_ctl() {
+        let memory_ctl = ctl_memory::<F>();
+        assert!(CrossTableLookup::tables_touched(&[memory_ctl])[Table::ShaExtendSponge as usize]);
+        let sponge_ctl = ctl_sha_extend_sponge::<F>();
+        let touched = CrossTableLookup::tables_touched(&[sponge_ctl]);
+        assert!(touched[Table::Cpu as usize] && touched[Table::ShaExtendSponge as usize]);
+    }
+
+    #[test]
+    fn test_prove_sha_extend() -> anyhow::Result<()> {
+        // lui $v0, 0x30; ori $v0, $v0, 0x105; addiu $a0, $zero, 0x1000; addiu $a1, $zero, 0;
+        // syscall (SHA extend of the schedule at 0x1000); addiu $v0, $zero, 4246; syscall
+        let kernel = Kernel::from_asm(
+            ".word 0x3C020030, 0x34420105, 0x24041000, 0x24050000, 0x0000000c, 0x24021096, 0x0000000c",
+            0x0040_0000,
+        )?;
+        let all_stark = AllStark::<F, D>::default();
+        let mut timing = TimingTree::default();
+        let state = run_kernel::<F, C, D>(&all_stark, &kernel, false, &mut timing)?;
+        assert_eq!(state.traces.sha_extend_sponge_ops.len(), 48);
+
+        prove_and_verify::<F, C, D>(&all_stark, &kernel)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_sha_extend_bad_stride() -> anyhow::Result<()> {
+        // As in `test_prove_sha_extend`, with a stride of 6 bytes in $a1.
+        let kernel = Kernel::from_asm(
+            ".word 0x3C020030, 0x34420105, 0x24041000, 0x24050006, 0x0000000c, 0x24021096, 0x0000000c",
+            0x0040_0000,
+        )?;
+        let all_stark = AllStark::<F, D>::default();
+        let mut timing = TimingTree::default();
+        assert!(run_kernel::<F, C, D>(&all_stark, &kernel, false, &mut timing).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_prove_sha_extend_strided() -> anyhow::Result<()> {
+        // As in `test_prove_sha_extend`, with a stride of 8 bytes in $a1.
+        let kernel = Kernel::from_asm(
+            ".word 0x3C020030, 0x34420105, 0x24041000, 0x24050008, 0x0000000c, 0x24021096, 0x0000000c",
+            0x0040_0000,
+        )?;
+        prove_and_verify::<F, C, D>(&AllStark::default(), &kernel)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_sha_extend_address_overflow() -> anyhow::Result<()> {
+        // As in `test_prove_sha_extend`, with the schedule at 0xffff0000 and a stride of 0x7ffc
+        // bytes, so that the last words are past the end of the address space.
+        let kernel = Kernel::from_asm(
+            ".word 0x3C020030, 0x34420105, 0x3C04FFFF, 0x24057FFC, 0x0000000c, 0x24021096, 0x0000000c",
+            0x0040_0000,
+        )?;
+        let all_stark = AllStark::<F, D>::default();
+        let mut timing = TimingTree::default();
+        assert!(run_kernel::<F, C, D>(&all_stark, &kernel, false, &mut timing).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_prove_logup_logic() -> anyhow::Result<()> {
+        // and $a0, $a1, $a2; addiu $v0, $zero, 4246; syscall
+        let kernel = Kernel::from_asm(".word 0x00a62024, 0x24021096, 0x0000000c", 0x0040_0000)?;
+        let mut all_stark = AllStark::<F, D>::default();
+        // Every logic row is looked up once, so its multiplicity is its filter.
+        let looked =
+            TableWithColumns::new(Table::Logic, logic::ctl_data(), Some(logic::ctl_filter()))
+                .with_multiplicity(Column::sum([
+                    logic::columns::IS_AND,
+                    logic::columns::IS_OR,
+                    logic::columns::IS_XOR,
+                    logic::columns::IS_NOR,
+                ]));
+        let ctl = all_stark
+            .cross_table_lookups
+            .iter_mut()
+            .find(|ctl| ctl.looked_tables[0].table == Table::Logic)
+            .unwrap();
+        *ctl = CrossTableLookup::new_logup(ctl.looking_tables.clone(), looked)?;
+
+        prove_and_verify::<F, C, D>(&all_stark, &kernel)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_prove_logup_logic_repeated_op() -> anyhow::Result<()> {
+        use logic::columns::{IS_AND, IS_NOR, IS_OR, IS_XOR};
+
+        // and $a0, $a1, $a2; and $a0, $a1, $a2; addiu $v0, $zero, 4246; syscall
+        let kernel = Kernel::from_asm(
+            ".word 0x00a62024, 0x00a62024, 0x24021096, 0x0000000c",
+            0x0040_0000,
+        )?;
+        let mut all_stark = AllStark::<F, D>::default();
+        // The Logic table proves the repeated AND once, and it is looked up twice.
+        let looked =
+            TableWithColumns::new(Table::Logic, logic::ctl_data(), Some(logic::ctl_filter()))
+                .with_multiplicity(Column::linear_combination([
+                    (IS_AND, F::TWO),
+                    (IS_OR, F::ONE),
+                    (IS_XOR, F::ONE),
+                    (IS_NOR, F::ONE),
+                ]));
+        let ctl = all_stark
+            .cross_table_lookups
+            .iter_mut()
+            .find(|ctl| ctl.looked_tables[0].table == Table::Logic)
+            .unwrap();
+        *ctl = CrossTableLookup::new_logup(ctl.looking_tables.clone(), looked)?;
+
+        let config = StarkConfig::standard_fast_config();
+        let mut timing = TimingTree::new("prove", log::Level::Debug);
+        let (mut traces, public_values, _) =
+            generate_traces::<F, C, D>(&all_stark, &kernel, &config, &mut timing)?;
+
+        // Drop the second AND row, keeping the trace length with a padding row.
+        let logic_trace = &mut traces[Table::Logic as usize];
+        let and_rows = (0..logic_trace[0].len())
+            .filter(|&row| logic_trace[IS_AND].values[row].is_one())
+            .collect::<Vec<_>>();
+        assert_eq!(and_rows.len(), 2);
+        for column in logic_trace.iter_mut() {
+            assert_eq!(column.values[and_rows[0]], column.values[and_rows[1]]);
+            column.values.remove(and_rows[1]);
+            column.values.push(F::ZERO);
+        }
+
+        let proof = crate::prover::prove_with_traces::<F, C, D>(
+            &all_stark,
+            &config,
+            traces,
+            public_values,
+            &ALL_TABLES_ACTIVE,
+            &mut timing,
+        )?;
+        crate::verifier::verify_proof(&all_stark, proof, &config)
+    }
 }
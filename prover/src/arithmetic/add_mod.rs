@@ -0,0 +1,158 @@
+//! Addition modulo 2^32 of words given as little-endian bit columns.
+//!
+//! The sum bits and the carry-out are witness columns, constrained to be boolean, which also
+//! range checks the sum. All constraints have degree at most 2 and the sum bits are linear in
+//! the columns, so additions can be chained, e.g. `((a + b) + c) + d` with three `AddMod32Op`s,
+//! without raising the constraint degree.
+
+use plonky2::field::extension::Extendable;
+use plonky2::field::packed::PackedField;
+use plonky2::field::types::Field;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::ext_target::ExtensionTarget;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+
+use crate::util::{limb_from_bits_le, limb_from_bits_le_recursive};
+
+pub const WORD_BITS: usize = 32;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AddMod32Op<T> {
+    /// The bits of `(a + b) mod 2^32`, least significant first.
+    pub sum: [T; WORD_BITS],
+
+    /// The carry-out, i.e. whether `a + b` overflowed.
+    pub carry: T,
+}
+
+impl<F: Field> AddMod32Op<F> {
+    pub fn generate_trace(&mut self, a: u32, b: u32) -> u32 {
+        let (expected, carry) = a.overflowing_add(b);
+
+        self.sum = core::array::from_fn(|i| F::from_bool((expected >> i) & 1 == 1));
+        self.carry = F::from_bool(carry);
+
+        expected
+    }
+}
+
+pub fn add_mod_2_32_packed_constraints<P: PackedField>(
+    a_bits: [P; WORD_BITS],
+    b_bits: [P; WORD_BITS],
+    cols: &AddMod32Op<P>,
+) -> Vec<P> {
+    let mut result = vec![];
+
+    // The sum bits and the carry are boolean.
+    for bit in cols.sum.iter().chain([&cols.carry]) {
+        result.push(*bit * (P::ONES - *bit));
+    }
+
+    let two_pow_32 = P::Scalar::from_canonical_u64(1 << WORD_BITS);
+    let a = limb_from_bits_le(a_bits);
+    let b = limb_from_bits_le(b_bits);
+    let sum = limb_from_bits_le(cols.sum);
+    result.push(a + b - sum - cols.carry * two_pow_32);
+
+    result
+}
+
+pub fn add_mod_2_32_ext_circuit_constraints<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    a_bits: [ExtensionTarget<D>; WORD_BITS],
+    b_bits: [ExtensionTarget<D>; WORD_BITS],
+    cols: &AddMod32Op<ExtensionTarget<D>>,
+) -> Vec<ExtensionTarget<D>> {
+    let mut result = vec![];
+
+    // The sum bits and the carry are boolean.
+    for &bit in cols.sum.iter().chain([&cols.carry]) {
+        let constr = builder.mul_sub_extension(bit, bit, bit);
+        result.push(constr);
+    }
+
+    let two_pow_32 = F::from_canonical_u64(1 << WORD_BITS);
+    let a = limb_from_bits_le_recursive(builder, a_bits);
+    let b = limb_from_bits_le_recursive(builder, b_bits);
+    let sum = limb_from_bits_le_recursive(builder, cols.sum);
+    let a_plus_b = builder.add_extension(a, b);
+    let computed = builder.mul_const_add_extension(two_pow_32, cols.carry, sum);
+    result.push(builder.sub_extension(a_plus_b, computed));
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::field::types::Field;
+    use plonky2::iop::witness::PartialWitness;
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn to_bits(x: u32) -> [F; WORD_BITS] {
+        core::array::from_fn(|i| F::from_bool((x >> i) & 1 == 1))
+    }
+
+    #[test]
+    fn test_add_mod_2_32() -> anyhow::Result<()> {
+        let mut rng = ChaCha8Rng::seed_from_u64(0x2a0d_5b1c);
+        let mut pairs = vec![(u32::MAX, 1), (0, 0), (u32::MAX, u32::MAX)];
+        pairs.extend((0..16).map(|_| (rng.gen::<u32>(), rng.gen::<u32>())));
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        for (a, b) in pairs {
+            let mut op = AddMod32Op::<F>::default();
+            assert_eq!(op.generate_trace(a, b), a.wrapping_add(b));
+            assert_eq!(op.carry, F::from_bool(a.checked_add(b).is_none()));
+            for constr in add_mod_2_32_packed_constraints(to_bits(a), to_bits(b), &op) {
+                assert_eq!(constr, F::ZERO);
+            }
+
+            let a_bits = to_bits(a).map(|bit| builder.constant_extension(bit.into()));
+            let b_bits = to_bits(b).map(|bit| builder.constant_extension(bit.into()));
+            let cols = AddMod32Op {
+                sum: op.sum.map(|bit| builder.constant_extension(bit.into())),
+                carry: builder.constant_extension(op.carry.into()),
+            };
+            for constr in add_mod_2_32_ext_circuit_constraints(&mut builder, a_bits, b_bits, &cols)
+            {
+                builder.assert_zero_extension(constr);
+            }
+        }
+        let circuit = builder.build::<C>();
+        circuit.verify(circuit.prove(PartialWitness::new())?)
+    }
+
+    #[test]
+    fn test_chained_add_mod_2_32() {
+        let terms = [0xffff_fff0u32, 0x8000_0000, 0x7fff_ffff, 0x1234_5678];
+        let mut acc = terms[0];
+        let mut acc_bits = to_bits(acc);
+        for &term in &terms[1..] {
+            let mut op = AddMod32Op::<F>::default();
+            acc = op.generate_trace(acc, term);
+            for constr in add_mod_2_32_packed_constraints(acc_bits, to_bits(term), &op) {
+                assert_eq!(constr, F::ZERO);
+            }
+            acc_bits = op.sum;
+        }
+        let expected = terms.iter().fold(0u32, |acc, &x| acc.wrapping_add(x));
+        assert_eq!(acc, expected);
+        assert_eq!(limb_from_bits_le(acc_bits), F::from_canonical_u32(expected));
+
+        // A wrong sum bit breaks the sum constraint.
+        let mut op = AddMod32Op::<F>::default();
+        op.generate_trace(1, 2);
+        op.sum[5] = F::ONE;
+        let constraints = add_mod_2_32_packed_constraints(to_bits(1), to_bits(2), &op);
+        assert_ne!(*constraints.last().unwrap(), F::ZERO);
+    }
+}
@@ -93,10 +93,11 @@ pub fn ctl_arithmetic_rows<F: Field>() -> TableWithColumns<F> {
         (columns::IS_MTLO, 0b010011 * (1 << 6)),
     ];
 
-    const REGISTER_MAP: [Range<usize>; 3] = [
+    const REGISTER_MAP: [Range<usize>; 4] = [
         columns::INPUT_REGISTER_0,
         columns::INPUT_REGISTER_1,
         columns::OUTPUT_REGISTER,
+        columns::HI_OUTPUT,
     ];
 
     let filter = Some(Filter::new_simple(Column::sum(
@@ -115,12 +116,51 @@ pub fn ctl_arithmetic_rows<F: Field>() -> TableWithColumns<F> {
     )
 }
 
+/// The operations writing a HI word.
+const HILO_OPS: [usize; 4] = [
+    columns::IS_MULT,
+    columns::IS_MULTU,
+    columns::IS_DIV,
+    columns::IS_DIVU,
+];
+
+/// HI_OUTPUT must repeat OUTPUT_REGISTER_HI for the HILO_OPS and be
+/// zero on every other row, since it is sent to the CPU table for all
+/// arithmetic operations.
+fn eval_packed_hi_output<P: PackedField>(
+    lv: &[P; NUM_ARITH_COLUMNS],
+    yield_constr: &mut ConstraintConsumer<P>,
+) {
+    let is_hilo: P = HILO_OPS.iter().map(|&op| lv[op]).sum();
+    for (hi, out) in columns::HI_OUTPUT.zip(columns::OUTPUT_REGISTER_HI) {
+        yield_constr.constraint(is_hilo * (lv[hi] - lv[out]));
+        yield_constr.constraint((P::ONES - is_hilo) * lv[hi]);
+    }
+}
+
+fn eval_ext_circuit_hi_output<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    lv: &[ExtensionTarget<D>; NUM_ARITH_COLUMNS],
+    yield_constr: &mut RecursiveConstraintConsumer<F, D>,
+) {
+    let is_hilo = builder.add_many_extension(HILO_OPS.iter().map(|&op| lv[op]));
+    let one = builder.one_extension();
+    let not_hilo = builder.sub_extension(one, is_hilo);
+    for (hi, out) in columns::HI_OUTPUT.zip(columns::OUTPUT_REGISTER_HI) {
+        let diff = builder.sub_extension(lv[hi], lv[out]);
+        let t = builder.mul_extension(is_hilo, diff);
+        yield_constr.constraint(builder, t);
+        let t = builder.mul_extension(not_hilo, lv[hi]);
+        yield_constr.constraint(builder, t);
+    }
+}
+
 #[derive(Copy, Clone, Default)]
 pub struct ArithmeticStark<F, const D: usize> {
     pub f: PhantomData<F>,
 }
 
-const RANGE_MAX: usize = 1usize << 16; // Range check strict upper bound
+pub(crate) const RANGE_MAX: usize = 1usize << 16; // Range check strict upper bound
 
 impl<F: RichField, const D: usize> ArithmeticStark<F, D> {
     /// Expects input in *column*-major layout
@@ -227,6 +267,7 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for ArithmeticSta
         shift::eval_packed_generic(lv, nv, yield_constr);
         sra::eval_packed_generic(lv, nv, yield_constr);
         lo_hi::eval_packed_generic(lv, yield_constr);
+        eval_packed_hi_output(lv, yield_constr);
     }
 
     fn eval_ext_circuit(
@@ -260,6 +301,7 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for ArithmeticSta
         shift::eval_ext_circuit(builder, lv, nv, yield_constr);
         sra::eval_ext_circuit(builder, lv, nv, yield_constr);
         lo_hi::eval_ext_circuit(builder, lv, yield_constr);
+        eval_ext_circuit_hi_output(builder, lv, yield_constr);
     }
 
     fn constraint_degree(&self) -> usize {
@@ -96,6 +96,11 @@ pub(crate) const AUX_COEFF_ABS_MAX: i64 = 1 << 20;
 pub(crate) const MUL_AUX_INPUT_LO: Range<usize> = AUX_INPUT_REGISTER_0;
 pub(crate) const MUL_AUX_INPUT_HI: Range<usize> = AUX_INPUT_REGISTER_1;
 
+// SLT(I) splits the top limb of each input as `sign * 2^15 + rest`
+// and stores `2 * rest` here, so that the range check on these
+// columns bounds `rest` by 2^15 and pins down the sign bits.
+pub(crate) const SLT_DOUBLED_REST: Range<usize> = AUX_INPUT_REGISTER_2;
+
 pub(crate) const MODULAR_INPUT_0: Range<usize> = INPUT_REGISTER_0;
 pub(crate) const MODULAR_INPUT_1: Range<usize> = INPUT_REGISTER_1;
 pub(crate) const MODULAR_MODULUS: Range<usize> = INPUT_REGISTER_2;
@@ -116,7 +121,11 @@ pub(crate) const RC_FREQUENCIES: usize = RANGE_COUNTER + 1;
 // These counter columns only used in SRA(V) and DIV, and do not check range
 pub(crate) const AUX_EXTRA: Range<usize> = RC_FREQUENCIES + 1..RC_FREQUENCIES + 9;
 
-pub const NUM_ARITH_COLUMNS: usize = START_SHARED_COLS + NUM_SHARED_COLS + 10;
+/// Copy of OUTPUT_REGISTER_HI for MULT(U)/DIV(U), zero for every
+/// other operation, so that the CPU table can look up the HI word.
+pub(crate) const HI_OUTPUT: Range<usize> = AUX_EXTRA.end..AUX_EXTRA.end + N_LIMBS;
+
+pub const NUM_ARITH_COLUMNS: usize = HI_OUTPUT.end;
 
 // These counters are only be used in mul and div that use LO and HI.
 pub(crate) const OUTPUT_REGISTER_LO: Range<usize> = OUTPUT_REGISTER;
@@ -17,6 +17,12 @@ use crate::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer
 
 const QUOT_ABS: Range<usize> = AUX_INPUT_REGISTER_2.end..AUX_INPUT_REGISTER_2.end + N_LIMBS;
 const REM_ABS: Range<usize> = QUOT_ABS.end..QUOT_ABS.end + N_LIMBS;
+// INT_MIN / -1 wraps to INT_MIN, so the quotient of DIV is allowed to
+// be INT_MIN whatever the input signs. These hold
+// `(quot[1] - 2^15)^2 + quot[0]`, which is zero iff quot == INT_MIN,
+// and that value times the sum of the quotient limbs.
+const QUOT_INT_MIN_DIST: usize = RC_FREQUENCIES + 6;
+const QUOT_SIGN_SELECTOR: usize = RC_FREQUENCIES + 7;
 
 /// Generate the output and auxiliary values for div/divu operations.
 pub(crate) fn generate<F: PrimeField64>(
@@ -42,6 +48,7 @@ pub(crate) fn generate<F: PrimeField64>(
     } else {
         panic!();
     }
+    lv.copy_within(OUTPUT_REGISTER_HI, HI_OUTPUT.start);
 }
 
 pub(crate) fn generate_divu<F: PrimeField64>(lv: &mut [F], nv: &mut [F]) {
@@ -107,6 +114,13 @@ pub(crate) fn generate_div<F: PrimeField64>(
     );
     nv[RC_FREQUENCIES + 5] = F::from_bool(is_input0_neg ^ is_input1_neg);
 
+    let quot_lo = (quot & 0xffff) as u64;
+    let quot_hi = (quot >> LIMB_BITS) as i64 - (1 << (LIMB_BITS - 1));
+    let int_min_dist = (quot_hi * quot_hi) as u64 + quot_lo;
+    nv[QUOT_INT_MIN_DIST] = F::from_canonical_u64(int_min_dist);
+    nv[QUOT_SIGN_SELECTOR] =
+        F::from_canonical_u64(int_min_dist * (quot_lo + (quot >> LIMB_BITS) as u64));
+
     fill_columns(
         lv,
         nv,
@@ -581,8 +595,17 @@ pub(crate) fn eval_packed_div<P: PackedField>(
                 - P::Scalar::from_canonical_u8(2) * is_input0_neg * is_input1_neg
                 - is_same_sign),
     );
+    // or quot==INT_MIN
     let quot_limbs_sum: P = OUTPUT_REGISTER_LO.map(|i| lv[i]).sum();
-    yield_constr.constraint_transition(filter * (is_quot_neg - is_same_sign) * quot_limbs_sum);
+    let quot_hi_shifted =
+        lv[OUTPUT_REGISTER_LO.end - 1] - P::Scalar::from_canonical_u64(1 << (LIMB_BITS - 1));
+    let int_min_dist = nv[QUOT_INT_MIN_DIST];
+    yield_constr.constraint_transition(
+        filter * (quot_hi_shifted * quot_hi_shifted + lv[OUTPUT_REGISTER_LO.start] - int_min_dist),
+    );
+    let sign_selector = nv[QUOT_SIGN_SELECTOR];
+    yield_constr.constraint_transition(filter * (quot_limbs_sum * int_min_dist - sign_selector));
+    yield_constr.constraint_transition(filter * (is_quot_neg - is_same_sign) * sign_selector);
 
     // check sign of rem
     // sign(rem) == sign(input0) or rem==0
@@ -907,8 +930,27 @@ pub(crate) fn eval_div_ext_circuit<F: RichField + Extendable<D>, const D: usize>
         builder.add_extension(acc, lv[i])
     });
 
+    // or quot==INT_MIN
+    let int_min_hi =
+        builder.constant_extension(F::Extension::from_canonical_u64(1 << (LIMB_BITS - 1)));
+    let quot_hi_shifted = builder.sub_extension(lv[OUTPUT_REGISTER_LO.end - 1], int_min_hi);
+    let int_min_dist = nv[QUOT_INT_MIN_DIST];
+    let t0 = builder.mul_add_extension(
+        quot_hi_shifted,
+        quot_hi_shifted,
+        lv[OUTPUT_REGISTER_LO.start],
+    );
+    let t1 = builder.sub_extension(t0, int_min_dist);
+    let t = builder.mul_extension(filter, t1);
+    yield_constr.constraint_transition(builder, t);
+
+    let sign_selector = nv[QUOT_SIGN_SELECTOR];
+    let t0 = builder.mul_sub_extension(quot_limbs_sum, int_min_dist, sign_selector);
+    let t = builder.mul_extension(filter, t0);
+    yield_constr.constraint_transition(builder, t);
+
     let t0 = builder.sub_extension(is_quot_neg, is_same_sign);
-    let t = builder.mul_many_extension([filter, t0, quot_limbs_sum]);
+    let t = builder.mul_many_extension([filter, t0, sign_selector]);
     yield_constr.constraint_transition(builder, t);
 
     // check sign of rem
@@ -943,6 +985,7 @@ mod tests {
     use rand_chacha::ChaCha8Rng;
 
     use super::*;
+    use crate::arithmetic::BinaryOperator;
 
     const N_RND_TESTS: usize = 1000;
     const MODULAR_OPS: [usize; 2] = [IS_DIV, IS_DIVU];
@@ -1090,4 +1133,88 @@ mod tests {
             }
         }
     }
+
+    fn constraints_hold(
+        lv: &[GoldilocksField; NUM_ARITH_COLUMNS],
+        nv: &[GoldilocksField; NUM_ARITH_COLUMNS],
+    ) -> bool {
+        let mut constraint_consumer = ConstraintConsumer::new(
+            vec![GoldilocksField(2), GoldilocksField(3), GoldilocksField(5)],
+            GoldilocksField::ONE,
+            GoldilocksField::ZERO,
+            GoldilocksField::ZERO,
+        );
+        eval_packed(lv, nv, &mut constraint_consumer);
+        constraint_consumer
+            .constraint_accs
+            .iter()
+            .all(|&acc| acc == GoldilocksField::ZERO)
+    }
+
+    fn div_rows(
+        op_filter: usize,
+        input0: u32,
+        input1: u32,
+        quot: u32,
+        rem: u32,
+    ) -> (
+        [GoldilocksField; NUM_ARITH_COLUMNS],
+        [GoldilocksField; NUM_ARITH_COLUMNS],
+    ) {
+        type F = GoldilocksField;
+
+        let mut lv = [F::ZERO; NUM_ARITH_COLUMNS];
+        let mut nv = [F::ZERO; NUM_ARITH_COLUMNS];
+        lv[op_filter] = F::ONE;
+        generate(&mut lv, &mut nv, op_filter, input0, input1, quot, rem);
+        (lv, nv)
+    }
+
+    #[test]
+    fn reference_quotients() {
+        type F = GoldilocksField;
+        const INT_MIN: i32 = i32::MIN;
+
+        // (op, input0, input1, quot, rem)
+        let cases = [
+            (BinaryOperator::DIV, -7, 2, -3, -1),
+            (BinaryOperator::DIV, 7, -2, -3, 1),
+            (BinaryOperator::DIV, -7, -2, 3, -1),
+            (BinaryOperator::DIV, 7, 2, 3, 1),
+            (BinaryOperator::DIV, INT_MIN, -1, INT_MIN, 0),
+            (BinaryOperator::DIV, INT_MIN, 1, INT_MIN, 0),
+            (BinaryOperator::DIV, INT_MIN, 2, -(1 << 30), 0),
+            (BinaryOperator::DIV, -5, 0, 0, -5),
+            (BinaryOperator::DIVU, -5, 0, 0, -5),
+            (BinaryOperator::DIVU, INT_MIN, -1, 0, INT_MIN),
+        ];
+
+        for (op, input0, input1, quot, rem) in cases {
+            let (input0, input1) = (input0 as u32, input1 as u32);
+            assert_eq!(op.result(input0, input1), (quot as u32, rem as u32));
+
+            let (lv, nv) = div_rows(op.row_filter(), input0, input1, quot as u32, rem as u32);
+            assert!(
+                constraints_hold(&lv, &nv),
+                "{op:?} {input0:#x} / {input1:#x}"
+            );
+
+            let mut expected = [F::ZERO; N_LIMBS];
+            u32_to_array(&mut expected, quot as u32);
+            assert_eq!(lv[OUTPUT_REGISTER_LO], expected);
+            u32_to_array(&mut expected, rem as u32);
+            assert_eq!(lv[OUTPUT_REGISTER_HI], expected);
+        }
+    }
+
+    #[test]
+    fn wrong_quotient_sign() {
+        // |7| == 3 * |-2| + 1, but the quotient must be negative.
+        let (lv, nv) = div_rows(IS_DIV, 7, -2i32 as u32, 3, 1);
+        assert!(!constraints_hold(&lv, &nv));
+
+        // Only INT_MIN may ignore the sign rule.
+        let (lv, nv) = div_rows(IS_DIV, i32::MAX as u32, -1i32 as u32, i32::MAX as u32, 0);
+        assert!(!constraints_hold(&lv, &nv));
+    }
 }
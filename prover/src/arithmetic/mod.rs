@@ -1,3 +1,4 @@
+pub mod add_mod;
 pub mod addcy;
 pub mod arithmetic_stark;
 pub mod columns;
@@ -120,9 +121,13 @@ impl BinaryOperator {
                 let out = input0 as u64 * input1 as u64;
                 (out as u32, (out >> 32) as u32) //lo,hi
             }
+            // MIPS leaves LO/HI undefined on a zero divisor; like the
+            // DIV/DIVU constraints, we take the quotient to be zero and
+            // the remainder to be the dividend.
+            BinaryOperator::DIV | BinaryOperator::DIVU if input1 == 0 => (0, input0),
             BinaryOperator::DIV => (
-                ((input0 as i32) / (input1 as i32)) as u32, // lo
-                ((input0 as i32) % (input1 as i32)) as u32, // hi
+                (input0 as i32).wrapping_div(input1 as i32) as u32, // lo
+                (input0 as i32).wrapping_rem(input1 as i32) as u32, // hi
             ),
             BinaryOperator::DIVU => (input0 / input1, input0 % input1), //lo,hi
             BinaryOperator::MFHI
@@ -65,6 +65,7 @@ pub(crate) fn generate<F: PrimeField64>(lv: &mut [F], filter: usize, input0: u32
     } else {
         panic!()
     }
+    lv.copy_within(OUTPUT_REGISTER_HI, HI_OUTPUT.start);
 }
 pub(crate) fn generate_mult<F: PrimeField64>(lv: &mut [F], input0: u32, input1: u32) {
     log::debug!("generate_mult");
@@ -572,4 +573,45 @@ mod tests {
             }
         }
     }
+    #[test]
+    fn reference_products() {
+        type F = GoldilocksField;
+
+        // (op, input0, input1, hi, lo)
+        let cases = [
+            (IS_MULTU, 0xffffffff, 0xffffffff, 0xfffffffe, 0x00000001),
+            (IS_MULTU, 0x80000000, 0x00000002, 0x00000001, 0x00000000),
+            (IS_MULTU, 0x12345678, 0x00000000, 0x00000000, 0x00000000),
+            (IS_MULT, 0xffffffff, 0xffffffff, 0x00000000, 0x00000001),
+            (IS_MULT, 0x80000000, 0x80000000, 0x40000000, 0x00000000),
+            (IS_MULT, 0x80000000, 0x00000001, 0xffffffff, 0x80000000),
+            (IS_MULT, 0x7fffffff, 0xffffffff, 0xffffffff, 0x80000001),
+            (IS_MULT, 0x00010000, 0x00010000, 0x00000001, 0x00000000),
+        ];
+
+        for (op_filter, input0, input1, hi, lo) in cases {
+            let mut lv = [F::ZERO; NUM_ARITH_COLUMNS];
+            lv[op_filter] = F::ONE;
+            generate(&mut lv, op_filter, input0, input1);
+
+            let mut constraint_consumer = ConstraintConsumer::new(
+                vec![GoldilocksField(2), GoldilocksField(3), GoldilocksField(5)],
+                GoldilocksField::ONE,
+                GoldilocksField::ONE,
+                GoldilocksField::ONE,
+            );
+            eval_packed_generic(&lv, &mut constraint_consumer);
+            for &acc in &constraint_consumer.constraint_accs {
+                assert_eq!(acc, GoldilocksField::ZERO);
+            }
+
+            let mut expected_lo = [F::ZERO; N_LIMBS];
+            let mut expected_hi = [F::ZERO; N_LIMBS];
+            u32_to_array(&mut expected_lo, lo);
+            u32_to_array(&mut expected_hi, hi);
+            assert_eq!(lv[OUTPUT_REGISTER_LO], expected_lo);
+            assert_eq!(lv[OUTPUT_REGISTER_HI], expected_hi);
+            assert_eq!(lv[HI_OUTPUT], expected_hi);
+        }
+    }
 }
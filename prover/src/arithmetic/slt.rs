@@ -33,6 +33,10 @@ pub(crate) fn generate<F: PrimeField64>(
             u32_to_array(&mut lv[AUX_INPUT_REGISTER_0], diff);
             u32_to_array(&mut lv[AUX_INPUT_REGISTER_1], cy_val);
             u32_to_array(&mut lv[OUTPUT_REGISTER], rd);
+
+            let doubled_rest = |x: u32| F::from_canonical_u32(((x >> 16) & 0x7fff) << 1);
+            lv[SLT_DOUBLED_REST.start] = doubled_rest(left_in);
+            lv[SLT_DOUBLED_REST.start + 1] = doubled_rest(right_in);
         }
         IS_SLTU | IS_SLTIU => {
             let (diff, cy) = left_in.overflowing_sub(right_in);
@@ -61,10 +65,38 @@ pub fn eval_packed_generic<P: PackedField>(
     let out = &lv[OUTPUT_REGISTER];
     let aux = &lv[AUX_INPUT_REGISTER_0];
     let rd = &lv[AUX_INPUT_REGISTER_1];
+    let doubled_rest = &lv[SLT_DOUBLED_REST];
 
+    eval_packed_sign_bits(yield_constr, is_sign, in0, in1, doubled_rest, rd);
     eval_packed_generic_slt(yield_constr, is_lt, is_sign, in1, aux, in0, rd, out);
 }
 
+/// Constrain `given_cy[1]` to be one exactly when the inputs of a
+/// signed comparison have different signs.
+///
+/// The sign of each input is recovered from its top limb as
+/// `(2 * limb - doubled_rest) / 2^16`, which is a bit only if
+/// `doubled_rest` is twice the limb's lower 15 bits.
+fn eval_packed_sign_bits<P: PackedField>(
+    yield_constr: &mut ConstraintConsumer<P>,
+    sign: P,
+    left: &[P],
+    right: &[P],
+    doubled_rest: &[P],
+    given_cy: &[P],
+) {
+    let overflow_inv = P::Scalar::from_canonical_u64(GOLDILOCKS_INVERSE_65536);
+    let two = P::Scalar::TWO;
+
+    let s0 = (left[N_LIMBS - 1] * two - doubled_rest[0]) * overflow_inv;
+    let s1 = (right[N_LIMBS - 1] * two - doubled_rest[1]) * overflow_inv;
+    yield_constr.constraint(sign * s0 * (s0 - P::ONES));
+    yield_constr.constraint(sign * s1 * (s1 - P::ONES));
+
+    let signs_differ = s0 + s1 - s0 * s1 * two;
+    yield_constr.constraint(sign * (given_cy[1] - signs_differ));
+}
+
 pub(crate) fn eval_packed_generic_slt<P: PackedField>(
     yield_constr: &mut ConstraintConsumer<P>,
     filter: P,
@@ -102,6 +134,8 @@ pub(crate) fn eval_packed_generic_slt<P: PackedField>(
         yield_constr.constraint(filter * given_cy[0] * (given_cy[0] - P::ONES));
         yield_constr.constraint(filter * (cy - given_cy[0]) * (P::ONES - sign));
         yield_constr.constraint(filter * given_cy[1] * (P::ONES - cy - given_cy[0]));
+        // With equal signs the signed and unsigned orders agree.
+        yield_constr.constraint(sign * (P::ONES - given_cy[1]) * (cy - given_cy[0]));
         yield_constr.constraint_transition(filter * (rd[0] - given_cy[0]));
         for i in 1..N_LIMBS {
             yield_constr.constraint(filter * given_cy[i] * (P::ONES - sign));
@@ -125,7 +159,9 @@ pub fn eval_ext_circuit<F: RichField + Extendable<D>, const D: usize>(
     let out = &lv[OUTPUT_REGISTER];
     let aux = &lv[AUX_INPUT_REGISTER_0];
     let rd = &lv[AUX_INPUT_REGISTER_1];
+    let doubled_rest = &lv[SLT_DOUBLED_REST];
 
+    eval_ext_circuit_sign_bits(builder, yield_constr, is_sign, in0, in1, doubled_rest, rd);
     eval_ext_circuit_slt(
         builder,
         yield_constr,
@@ -139,6 +175,38 @@ pub fn eval_ext_circuit<F: RichField + Extendable<D>, const D: usize>(
     );
 }
 
+fn eval_ext_circuit_sign_bits<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    yield_constr: &mut RecursiveConstraintConsumer<F, D>,
+    sign: ExtensionTarget<D>,
+    left: &[ExtensionTarget<D>],
+    right: &[ExtensionTarget<D>],
+    doubled_rest: &[ExtensionTarget<D>],
+    given_cy: &[ExtensionTarget<D>],
+) {
+    let overflow_inv = F::from_canonical_u64(GOLDILOCKS_INVERSE_65536);
+
+    let mut sign_bit = |limb: ExtensionTarget<D>, doubled: ExtensionTarget<D>| {
+        let t = builder.mul_const_extension(F::TWO, limb);
+        let t = builder.sub_extension(t, doubled);
+        builder.mul_const_extension(overflow_inv, t)
+    };
+    let s0 = sign_bit(left[N_LIMBS - 1], doubled_rest[0]);
+    let s1 = sign_bit(right[N_LIMBS - 1], doubled_rest[1]);
+
+    for s in [s0, s1] {
+        let t = builder.mul_sub_extension(s, s, s);
+        let t = builder.mul_extension(sign, t);
+        yield_constr.constraint(builder, t);
+    }
+
+    let sum = builder.add_extension(s0, s1);
+    let signs_differ = builder.arithmetic_extension(-F::TWO, F::ONE, s0, s1, sum);
+    let t = builder.sub_extension(given_cy[1], signs_differ);
+    let t = builder.mul_extension(sign, t);
+    yield_constr.constraint(builder, t);
+}
+
 #[allow(clippy::needless_collect)]
 pub(crate) fn eval_ext_circuit_slt<F: RichField + Extendable<D>, const D: usize>(
     builder: &mut CircuitBuilder<F, D>,
@@ -191,6 +259,11 @@ pub(crate) fn eval_ext_circuit_slt<F: RichField + Extendable<D>, const D: usize>
     let cy_filter2 = builder.mul_extension(given_cy[1], good_cy2);
     let cy_filter2 = builder.mul_extension(filter, cy_filter2);
 
+    // With equal signs the signed and unsigned orders agree.
+    let same_sign = builder.sub_extension(one, given_cy[1]);
+    let cy_filter3 = builder.mul_extension(same_sign, good_cy1);
+    let cy_filter3 = builder.mul_extension(sign, cy_filter3);
+
     // Check given carry is one bit
     let bit_constr = builder.mul_sub_extension(given_cy[0], given_cy[0], given_cy[0]);
     let bit_filter = builder.mul_extension(filter, bit_constr);
@@ -199,6 +272,7 @@ pub(crate) fn eval_ext_circuit_slt<F: RichField + Extendable<D>, const D: usize>
         yield_constr.constraint(builder, bit_filter);
         yield_constr.constraint(builder, cy_filter1);
         yield_constr.constraint(builder, cy_filter2);
+        yield_constr.constraint(builder, cy_filter3);
         let rd_filter = builder.sub_extension(rd[0], given_cy[0]);
         let rd_filter = builder.mul_extension(filter, rd_filter);
         yield_constr.constraint_transition(builder, rd_filter);
@@ -211,3 +285,107 @@ pub(crate) fn eval_ext_circuit_slt<F: RichField + Extendable<D>, const D: usize>
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use plonky2::field::goldilocks_field::GoldilocksField;
+    use plonky2::field::types::Sample;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::arithmetic::BinaryOperator;
+
+    type F = GoldilocksField;
+
+    const INT_MIN: u32 = i32::MIN as u32;
+    const INT_MAX: u32 = i32::MAX as u32;
+
+    fn constraints_hold(lv: &[F; NUM_ARITH_COLUMNS]) -> bool {
+        let mut constraint_consumer = ConstraintConsumer::new(
+            vec![GoldilocksField(2), GoldilocksField(3), GoldilocksField(5)],
+            F::ONE,
+            F::ONE,
+            F::ONE,
+        );
+        eval_packed_generic(lv, &mut constraint_consumer);
+        constraint_consumer
+            .constraint_accs
+            .iter()
+            .all(|&acc| acc == F::ZERO)
+    }
+
+    fn slt_row(operator: BinaryOperator, left_in: u32, right_in: u32) -> [F; NUM_ARITH_COLUMNS] {
+        let mut rng = ChaCha8Rng::seed_from_u64(0x2c1f5e8a90d3b647);
+        let mut lv = [F::default(); NUM_ARITH_COLUMNS].map(|_| F::sample(&mut rng));
+        for filter in [IS_SLT, IS_SLTU, IS_SLTI, IS_SLTIU] {
+            lv[filter] = F::ZERO;
+        }
+        lv[operator.row_filter()] = F::ONE;
+
+        let (rd, _) = operator.result(left_in, right_in);
+        generate(&mut lv, operator.row_filter(), left_in, right_in, rd);
+        lv
+    }
+
+    fn check_cases(operator: BinaryOperator, cases: &[(u32, u32, u32)]) {
+        for &(left_in, right_in, expected) in cases {
+            let lv = slt_row(operator, left_in, right_in);
+            assert!(
+                constraints_hold(&lv),
+                "{operator:?} {left_in:#x} < {right_in:#x}"
+            );
+            assert_eq!(lv[OUTPUT_REGISTER.start], F::from_canonical_u32(expected));
+        }
+    }
+
+    #[test]
+    fn signed_comparison_edge_cases() {
+        check_cases(
+            BinaryOperator::SLT,
+            &[
+                (INT_MIN, 0, 1),
+                (0, INT_MIN, 0),
+                (INT_MIN, INT_MAX, 1),
+                (INT_MAX, INT_MIN, 0),
+                (INT_MIN, INT_MIN, 0),
+                (INT_MAX, INT_MAX, 0),
+                (u32::MAX, u32::MAX, 0),
+                (u32::MAX, 0, 1),
+            ],
+        );
+        // The immediate reaches the table already sign-extended.
+        check_cases(
+            BinaryOperator::SLTI,
+            &[
+                (INT_MIN, 0, 1),
+                (INT_MIN, 0xffff8000, 1),
+                (INT_MAX, 0xffff8000, 0),
+                (0x7fff, 0x7fff, 0),
+                (u32::MAX, u32::MAX, 0),
+            ],
+        );
+    }
+
+    #[test]
+    fn forged_signed_comparison_fails() {
+        // INT_MIN < 0, but claim the inputs share a sign and the
+        // comparison is false.
+        let mut lv = slt_row(BinaryOperator::SLT, INT_MIN, 0);
+        lv[AUX_INPUT_REGISTER_1.start] = F::ZERO;
+        lv[AUX_INPUT_REGISTER_1.start + 1] = F::ZERO;
+        lv[OUTPUT_REGISTER.start] = F::ZERO;
+        assert!(!constraints_hold(&lv));
+
+        // -1 and -2 share a sign, so the unsigned borrow decides.
+        let mut lv = slt_row(BinaryOperator::SLT, u32::MAX, u32::MAX - 1);
+        lv[AUX_INPUT_REGISTER_1.start] = F::ONE;
+        lv[OUTPUT_REGISTER.start] = F::ONE;
+        assert!(!constraints_hold(&lv));
+
+        // A sign bit that is not a bit.
+        let mut lv = slt_row(BinaryOperator::SLT, INT_MIN, 0);
+        lv[SLT_DOUBLED_REST.start] += F::ONE;
+        assert!(!constraints_hold(&lv));
+    }
+}
@@ -0,0 +1,27 @@
+//! Verification of block proofs from the block circuit's verifier data alone. Unlike the rest of
+//! the crate, this module doesn't need the `std` feature.
+
+use plonky2::field::extension::Extendable;
+use plonky2::hash::hash_types::RichField;
+use plonky2::plonk::circuit_data::{CommonCircuitData, VerifierOnlyCircuitData};
+use plonky2::plonk::config::{AlgebraicHasher, GenericConfig};
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use plonky2::recursion::cyclic_recursion::check_cyclic_proof_verifier_data;
+
+/// Verifies a block proof against the block circuit's verifier data alone, e.g. as returned by
+/// `AllRecursiveCircuits::block_verifier_data`, without building the recursive circuits. This
+/// checks the proof itself and that its cyclic verifier data matches `verifier_only`; the
+/// assumptions of a composite receipt are not covered, see `AllRecursiveCircuits::verify_block`.
+pub fn verify_block_with_vk<F, C, const D: usize>(
+    proof: ProofWithPublicInputs<F, C, D>,
+    verifier_only: &VerifierOnlyCircuitData<C, D>,
+    common: &CommonCircuitData<F, D>,
+) -> anyhow::Result<()>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    C::Hasher: AlgebraicHasher<F>,
+{
+    check_cyclic_proof_verifier_data(&proof, verifier_only, common)?;
+    plonky2::plonk::verifier::verify(proof, verifier_only, common)
+}
@@ -28,7 +28,138 @@ impl StarkConfig {
         }
     }
 
+    /// A configuration targeting at least `bits` of conjectured security, among the presets:
+    ///
+    /// | `bits`    | `rate_bits` | `cap_height` | `num_query_rounds` | `proof_of_work_bits` | `num_challenges` |
+    /// |-----------|-------------|--------------|--------------------|----------------------|------------------|
+    /// | ..=90     | 2           | 4            | 37                 | 16                   | 3                |
+    /// | 91..=100  | 3           | 4            | 28                 | 16                   | 3                |
+    /// | 101..=128 | 4           | 4            | 28                 | 16                   | 4                |
+    ///
+    /// FRI yields `rate_bits * num_query_rounds + proof_of_work_bits` bits of conjectured
+    /// security, i.e. 90, 100 and 128 bits. Each grand product challenge of the cross-table
+    /// lookups has a soundness error of about `degree / |F|`, so about 42 bits for tables of up
+    /// to 2^22 rows, and the `num_challenges` independent challenges bring this to 126, 126 and
+    /// 168 bits respectively. The `..=90` preset is `standard_fast_config` with a third
+    /// challenge, since its two would only give the lookups 84 bits.
+    ///
+    /// Panics if `bits` is above 128.
+    pub fn security_level(bits: usize) -> Self {
+        let (rate_bits, num_query_rounds, num_challenges) = match bits {
+            0..=90 => (2, 37, 3),
+            91..=100 => (3, 28, 3),
+            101..=128 => (4, 28, 4),
+            _ => panic!("No preset for {bits} bits of security, the maximum is 128."),
+        };
+        let proof_of_work_bits = 16;
+        let config = Self {
+            security_bits: rate_bits * num_query_rounds + proof_of_work_bits as usize,
+            num_challenges,
+            fri_config: FriConfig {
+                rate_bits,
+                cap_height: 4,
+                proof_of_work_bits,
+                reduction_strategy: FriReductionStrategy::ConstantArityBits(4, 5),
+                num_query_rounds,
+            },
+        };
+        debug_assert!(config.security_bits >= bits);
+        config
+    }
+
     pub(crate) fn fri_params(&self, degree_bits: usize) -> FriParams {
         self.fri_config.fri_params(degree_bits, false)
     }
+
+    /// Estimates the size in bytes of a `StarkProof` committing to `[trace, auxiliary, quotient]`
+    /// polynomials of degree `2^degree_bits`, as serialized with `bincode`.
+    pub(crate) fn estimate_table_proof_bytes(
+        &self,
+        degree_bits: usize,
+        num_polys: [usize; 3],
+    ) -> usize {
+        const FIELD_BYTES: usize = 8;
+        const EXTENSION_BYTES: usize = 2 * FIELD_BYTES;
+        const HASH_BYTES: usize = 4 * FIELD_BYTES;
+        // `bincode` prefixes vectors with their length as a `u64`.
+        let vec_bytes = |len: usize, elem_bytes: usize| FIELD_BYTES + len * elem_bytes;
+
+        let fri_params = self.fri_params(degree_bits);
+        let cap_height = self.fri_config.cap_height;
+        let cap_bytes = vec_bytes(1 << cap_height, HASH_BYTES);
+        let lde_bits = degree_bits + self.fri_config.rate_bits;
+        let [num_trace, num_aux, num_quotient] = num_polys;
+
+        let caps = (3 + fri_params.reduction_arity_bits.len()) * cap_bytes;
+        let openings = 2 * vec_bytes(num_trace, EXTENSION_BYTES)
+            + 2 * vec_bytes(num_aux, EXTENSION_BYTES)
+            + vec_bytes(num_quotient, EXTENSION_BYTES);
+
+        let initial_trees = FIELD_BYTES
+            + num_polys
+                .iter()
+                .map(|&num| {
+                    vec_bytes(num, FIELD_BYTES) + vec_bytes(lde_bits - cap_height, HASH_BYTES)
+                })
+                .sum::<usize>();
+        let mut steps = FIELD_BYTES;
+        let mut step_lde_bits = lde_bits;
+        for &arity_bits in &fri_params.reduction_arity_bits {
+            step_lde_bits -= arity_bits;
+            steps += vec_bytes(1 << arity_bits, EXTENSION_BYTES)
+                + vec_bytes(step_lde_bits.saturating_sub(cap_height), HASH_BYTES);
+        }
+        let query_rounds = vec_bytes(self.fri_config.num_query_rounds, initial_trees + steps);
+        let final_poly = vec_bytes(fri_params.final_poly_len(), EXTENSION_BYTES);
+
+        caps + openings + query_rounds + final_poly + FIELD_BYTES
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::field::goldilocks_field::GoldilocksField;
+    use plonky2::hash::poseidon::PoseidonHash;
+    use plonky2::iop::challenger::Challenger;
+
+    use crate::config::StarkConfig;
+    use crate::cross_table_lookup::get_grand_product_challenge_set;
+
+    #[test]
+    fn test_security_level_128() {
+        let config = StarkConfig::security_level(128);
+        assert_eq!(config.security_bits, 128);
+        assert_eq!(config.num_challenges, 4);
+        assert_eq!(config.fri_config.rate_bits, 4);
+        assert_eq!(config.fri_config.cap_height, 4);
+        assert_eq!(config.fri_config.num_query_rounds, 28);
+        assert_eq!(config.fri_config.proof_of_work_bits, 16);
+    }
+
+    #[test]
+    fn test_security_level_presets() {
+        for bits in [80, 90, 100, 112, 128] {
+            let config = StarkConfig::security_level(bits);
+            let fri = &config.fri_config;
+            let fri_bits = fri.rate_bits * fri.num_query_rounds + fri.proof_of_work_bits as usize;
+            assert_eq!(config.security_bits, fri_bits);
+            assert!(config.security_bits >= bits);
+
+            // Each grand product challenge has about 42 bits of soundness for tables of up to
+            // 2^22 rows, so the CTLs are at least as sound as FRI.
+            assert!(42 * config.num_challenges >= config.security_bits);
+
+            // The CTLs sample one grand product challenge per `num_challenges`.
+            let mut challenger = Challenger::<GoldilocksField, PoseidonHash>::new();
+            let challenges =
+                get_grand_product_challenge_set(&mut challenger, config.num_challenges);
+            assert_eq!(challenges.challenges.len(), config.num_challenges);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_security_level_too_high() {
+        StarkConfig::security_level(129);
+    }
 }
@@ -7,7 +7,8 @@ use crate::util::transmute_no_compile_time_size_checks;
 #[repr(C)]
 #[derive(Clone, Copy, Eq, PartialEq, Debug)]
 pub struct OpsColumnsView<T: Copy> {
-    pub binary_op: T,     // Combines ADD, MUL, SUB, DIV, SLL, ... flags.
+    pub binary_op: T,     // Combines ADD, MUL, SUB, SLT, MFHI, ... flags.
+    pub hilo_op: T,       // Combines MULT, MULTU, DIV, DIVU flags.
     pub binary_imm_op: T, // Combines ADDI, ADDIU, SLTI, SLTIU, LUI
     pub eq_iszero: T,     // Combines EQ and ISZERO flags.
     pub logic_op: T,      // Combines AND, OR, XOR, Nor flags.
@@ -13,8 +13,10 @@ use super::columns::CpuColumnsView;
 use crate::all_stark::Table;
 use crate::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
 use crate::cpu::columns::{COL_MAP, NUM_CPU_COLUMNS};
+use crate::cpu::memio::AlignmentMode;
 use crate::cpu::{
-    bits, bootstrap_kernel, count, decode, jumps, membus, memio, misc, shift, syscall,
+    bits, bootstrap_kernel, count, decode, jumps, keccak_sponge, membus, memio, misc,
+    sha_extend_sponge, shift, syscall,
 };
 use crate::cross_table_lookup::{Column, Filter, TableWithColumns};
 use crate::evaluation_frame::{StarkEvaluationFrame, StarkFrame};
@@ -28,7 +30,7 @@ pub fn ctl_data_keccak_sponge<F: Field>() -> Vec<Column<F>> {
     // GP channel 1: stack[-2] = segment
     // GP channel 2: stack[-3] = virt
     // GP channel 3: stack[-4] = len
-    // GP channel 4: pushed = outputs
+    // GP channel 4: read of the digest pointer, see `keccak_sponge`
     let context = Column::single(COL_MAP.mem_channels[0].value);
     let segment = Column::single(COL_MAP.mem_channels[1].value);
     let virt = Column::single(COL_MAP.mem_channels[2].value);
@@ -47,7 +49,7 @@ pub fn ctl_data_sha_extend_sponge<F: Field>() -> Vec<Column<F>> {
     // GP channel 0: stack[-1] = context
     // GP channel 1: stack[-2] = segment
     // GP channel 2: stack[-3] = virt
-    // GP channel 3: pushed = outputs
+    // GP channel 3: read of the stride argument, see `sha_extend_sponge`
     let context = Column::single(COL_MAP.mem_channels[0].value);
     let segment = Column::single(COL_MAP.mem_channels[1].value);
     let virt = Column::single(COL_MAP.mem_channels[2].value);
@@ -57,6 +59,9 @@ pub fn ctl_data_sha_extend_sponge<F: Field>() -> Vec<Column<F>> {
 
     let mut cols = vec![context, segment, virt, timestamp];
     cols.push(Column::single(COL_MAP.general.element().value));
+    cols.push(Column::single(
+        COL_MAP.mem_channels[sha_extend_sponge::STRIDE_CHANNEL].value,
+    ));
     cols
 }
 
@@ -152,6 +157,8 @@ pub fn ctl_arithmetic_base_rows<F: Field>() -> TableWithColumns<F> {
     base[COL_MAP.opcode_bits.len()..].copy_from_slice(&COL_MAP.func_bits[..]);
     let mut columns = vec![Column::le_bits(base)];
     columns.extend(ctl_data_binops());
+    // Only MULT(U)/DIV(U) produce a HI word.
+    columns.push(Column::zero());
 
     // Create the CPU Table whose columns are those with the two
     // inputs and one output of the ternary operations listed in `ops`
@@ -167,10 +174,29 @@ pub fn ctl_arithmetic_base_rows<F: Field>() -> TableWithColumns<F> {
     )
 }
 
+/// MULT(U)/DIV(U) write LO on the third memory channel and HI on the
+/// fourth, so both halves of the result are checked against the
+/// Arithmetic table.
+pub fn ctl_arithmetic_hilo_rows<F: Field>() -> TableWithColumns<F> {
+    let mut base = [0usize; COL_MAP.opcode_bits.len() + COL_MAP.func_bits.len()];
+    base[0..COL_MAP.opcode_bits.len()].copy_from_slice(&COL_MAP.opcode_bits[..]);
+    base[COL_MAP.opcode_bits.len()..].copy_from_slice(&COL_MAP.func_bits[..]);
+    let mut columns = vec![Column::le_bits(base)];
+    columns.extend(ctl_data_binops());
+    columns.push(Column::single(COL_MAP.mem_channels[3].value));
+
+    TableWithColumns::new(
+        Table::Cpu,
+        columns,
+        Some(Filter::new_simple(Column::single(COL_MAP.op.hilo_op))),
+    )
+}
+
 pub fn ctl_arithmetic_imm_base_rows<F: Field>() -> TableWithColumns<F> {
     // Instead of taking single columns, we reconstruct the entire opcode value directly.
     let mut columns = vec![Column::le_bits(COL_MAP.opcode_bits)];
     columns.extend(ctl_data_binops());
+    columns.push(Column::zero());
 
     // Create the CPU Table whose columns are those with the two
     // inputs and one output of the ternary operations listed in `ops`
@@ -246,6 +272,8 @@ pub fn ctl_filter_gp_memory<F: Field>(channel: usize) -> Filter<F> {
 #[derive(Copy, Clone, Default)]
 pub struct CpuStark<F, const D: usize> {
     pub f: PhantomData<F>,
+    /// How word and halfword accesses at unaligned addresses are treated.
+    pub alignment: AlignmentMode,
 }
 
 impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for CpuStark<F, D> {
@@ -276,11 +304,16 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for CpuStark<F, D
         jumps::eval_packed(local_values, next_values, yield_constr);
         membus::eval_packed(local_values, yield_constr);
         memio::eval_packed(local_values, next_values, yield_constr);
+        if self.alignment == AlignmentMode::Enforced {
+            memio::eval_packed_alignment(local_values, yield_constr);
+        }
         shift::eval_packed(local_values, yield_constr);
         count::eval_packed(local_values, yield_constr);
         syscall::eval_packed(local_values, yield_constr);
         bits::eval_packed(local_values, yield_constr);
         misc::eval_packed(local_values, yield_constr);
+        keccak_sponge::eval_packed(local_values, next_values, yield_constr);
+        sha_extend_sponge::eval_packed(local_values, yield_constr);
         //exit_kernel::eval_exit_kernel_packed(local_values, next_values, yield_constr);
     }
 
@@ -308,11 +341,16 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for CpuStark<F, D
         jumps::eval_ext_circuit(builder, local_values, next_values, yield_constr);
         membus::eval_ext_circuit(builder, local_values, yield_constr);
         memio::eval_ext_circuit(builder, local_values, next_values, yield_constr);
+        if self.alignment == AlignmentMode::Enforced {
+            memio::eval_ext_circuit_alignment(builder, local_values, yield_constr);
+        }
         shift::eval_ext_circuit(builder, local_values, yield_constr);
         count::eval_ext_circuit(builder, local_values, yield_constr);
         syscall::eval_ext_circuit(builder, local_values, yield_constr);
         bits::eval_ext_circuit(builder, local_values, yield_constr);
         misc::eval_ext_circuit(builder, local_values, yield_constr);
+        keccak_sponge::eval_ext_circuit(builder, local_values, next_values, yield_constr);
+        sha_extend_sponge::eval_ext_circuit(builder, local_values, yield_constr);
         //exit_kernel::eval_exit_kernel_ext_circuit(builder, local_values, next_values, yield_constr);
     }
 
@@ -326,6 +364,7 @@ mod tests {
     use anyhow::Result;
 
     use crate::cpu::cpu_stark::CpuStark;
+    use crate::cpu::memio::AlignmentMode;
 
     use crate::stark_testing::{test_stark_circuit_constraints, test_stark_low_degree};
 
@@ -340,6 +379,7 @@ mod tests {
 
         let stark = S {
             f: Default::default(),
+            alignment: Default::default(),
         };
         test_stark_low_degree(stark)
     }
@@ -354,7 +394,23 @@ mod tests {
 
         let stark = S {
             f: Default::default(),
+            alignment: Default::default(),
+        };
+        test_stark_circuit_constraints::<F, C, S, D>(stark)
+    }
+
+    #[test]
+    fn test_stark_enforced_alignment() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type S = CpuStark<F, D>;
+
+        let stark = S {
+            f: Default::default(),
+            alignment: AlignmentMode::Enforced,
         };
+        test_stark_low_degree(stark)?;
         test_stark_circuit_constraints::<F, C, S, D>(stark)
     }
 
@@ -44,9 +44,10 @@ const OPCODES: [(u32, usize, bool, usize); 8] = [
 /// List of combined opcodes requiring a special handling.
 /// Each index in the list corresponds to an arbitrary combination
 /// of opcodes defined in evm/src/cpu/columns/ops.rs.
-const COMBINED_OPCODES: [usize; 7] = [
+const COMBINED_OPCODES: [usize; 8] = [
     COL_MAP.op.logic_op,
     COL_MAP.op.binary_op,
+    COL_MAP.op.hilo_op,
     COL_MAP.op.binary_imm_op,
     COL_MAP.op.shift,
     COL_MAP.op.shift_imm,
@@ -0,0 +1,251 @@
+use plonky2::field::extension::Extendable;
+use plonky2::field::packed::PackedField;
+use plonky2::field::types::Field;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::ext_target::ExtensionTarget;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+
+use crate::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
+use crate::cpu::columns::CpuColumnsView;
+use crate::memory::segments::Segment;
+
+/// Number of 32-bit words in a Keccak256 digest.
+const DIGEST_WORDS: usize = 8;
+
+/// The register holding the digest pointer, `$a2`, as passed to SYSKECCAK.
+pub(crate) const DIGEST_PTR_REG: u8 = 6;
+
+/// The channel of the Keccak sponge row reading `DIGEST_PTR_REG`. Channels 0 to 3 only carry
+/// the sponge CTL inputs on that row.
+pub(crate) const DIGEST_PTR_CHANNEL: usize = 4;
+
+/// The row following a Keccak sponge row writes the digest to memory. Its first eight channels
+/// must be writes of the sponge output, at consecutive word addresses of the code segment of the
+/// kernel context, starting at the digest pointer. The sponge row reads that pointer from `$a2`
+/// itself, which the syscall doesn't overwrite, so the memory CTL binds it to the register file.
+/// A sponge row can't be the last row, so the write row always exists. The sponge output itself
+/// is bound to the Keccak sponge table through the CTL on `khash`, whose words are stored in
+/// reverse order.
+pub fn eval_packed<P: PackedField>(
+    lv: &CpuColumnsView<P>,
+    nv: &CpuColumnsView<P>,
+    yield_constr: &mut ConstraintConsumer<P>,
+) {
+    let filter = lv.is_keccak_sponge;
+    yield_constr.constraint(filter * (filter - P::ONES));
+    yield_constr.constraint_last_row(filter);
+
+    let ptr_channel = lv.mem_channels[DIGEST_PTR_CHANNEL];
+    let register_file = P::Scalar::from_canonical_u32(Segment::REGISTER_FILE);
+    let ptr_reg = P::Scalar::from_canonical_u8(DIGEST_PTR_REG);
+    yield_constr.constraint(filter * (ptr_channel.used - P::ONES));
+    yield_constr.constraint(filter * (ptr_channel.is_read - P::ONES));
+    yield_constr.constraint(filter * ptr_channel.addr_context);
+    yield_constr.constraint(filter * (ptr_channel.addr_segment - register_file));
+    yield_constr.constraint(filter * (ptr_channel.addr_virtual - ptr_reg));
+
+    let code_segment = P::Scalar::from_canonical_u32(Segment::CODE);
+    let word_size = P::Scalar::from_canonical_usize(4);
+    let khash = lv.general.khash().value;
+    for (i, channel) in nv.mem_channels[..DIGEST_WORDS].iter().enumerate() {
+        yield_constr.constraint_transition(filter * (channel.used - P::ONES));
+        yield_constr.constraint_transition(filter * channel.is_read);
+        yield_constr.constraint_transition(filter * channel.addr_context);
+        yield_constr.constraint_transition(filter * (channel.addr_segment - code_segment));
+        yield_constr.constraint_transition(filter * (channel.value - khash[DIGEST_WORDS - 1 - i]));
+        let diff = if i == 0 {
+            channel.addr_virtual - ptr_channel.value
+        } else {
+            channel.addr_virtual - nv.mem_channels[i - 1].addr_virtual - word_size
+        };
+        yield_constr.constraint_transition(filter * diff);
+    }
+}
+
+pub fn eval_ext_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    lv: &CpuColumnsView<ExtensionTarget<D>>,
+    nv: &CpuColumnsView<ExtensionTarget<D>>,
+    yield_constr: &mut RecursiveConstraintConsumer<F, D>,
+) {
+    let filter = lv.is_keccak_sponge;
+    let constr = builder.mul_sub_extension(filter, filter, filter);
+    yield_constr.constraint(builder, constr);
+    yield_constr.constraint_last_row(builder, filter);
+
+    let ptr_channel = lv.mem_channels[DIGEST_PTR_CHANNEL];
+    let constr = builder.mul_sub_extension(filter, ptr_channel.used, filter);
+    yield_constr.constraint(builder, constr);
+    let constr = builder.mul_sub_extension(filter, ptr_channel.is_read, filter);
+    yield_constr.constraint(builder, constr);
+    let constr = builder.mul_extension(filter, ptr_channel.addr_context);
+    yield_constr.constraint(builder, constr);
+    let register_file =
+        builder.constant_extension(F::Extension::from_canonical_u32(Segment::REGISTER_FILE));
+    let diff = builder.sub_extension(ptr_channel.addr_segment, register_file);
+    let constr = builder.mul_extension(filter, diff);
+    yield_constr.constraint(builder, constr);
+    let ptr_reg = builder.constant_extension(F::Extension::from_canonical_u8(DIGEST_PTR_REG));
+    let diff = builder.sub_extension(ptr_channel.addr_virtual, ptr_reg);
+    let constr = builder.mul_extension(filter, diff);
+    yield_constr.constraint(builder, constr);
+
+    let code_segment = builder.constant_extension(F::Extension::from_canonical_u32(Segment::CODE));
+    let word_size = builder.constant_extension(F::Extension::from_canonical_usize(4));
+    let khash = lv.general.khash().value;
+    for (i, channel) in nv.mem_channels[..DIGEST_WORDS].iter().enumerate() {
+        let constr = builder.mul_sub_extension(filter, channel.used, filter);
+        yield_constr.constraint_transition(builder, constr);
+
+        let constr = builder.mul_extension(filter, channel.is_read);
+        yield_constr.constraint_transition(builder, constr);
+
+        let constr = builder.mul_extension(filter, channel.addr_context);
+        yield_constr.constraint_transition(builder, constr);
+
+        let diff = builder.sub_extension(channel.addr_segment, code_segment);
+        let constr = builder.mul_extension(filter, diff);
+        yield_constr.constraint_transition(builder, constr);
+
+        let diff = builder.sub_extension(channel.value, khash[DIGEST_WORDS - 1 - i]);
+        let constr = builder.mul_extension(filter, diff);
+        yield_constr.constraint_transition(builder, constr);
+
+        let diff = if i == 0 {
+            builder.sub_extension(channel.addr_virtual, ptr_channel.value)
+        } else {
+            let prev_virt = nv.mem_channels[i - 1].addr_virtual;
+            let diff = builder.sub_extension(channel.addr_virtual, prev_virt);
+            builder.sub_extension(diff, word_size)
+        };
+        let constr = builder.mul_extension(filter, diff);
+        yield_constr.constraint_transition(builder, constr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use keccak_hash::keccak;
+    use plonky2::field::goldilocks_field::GoldilocksField;
+    use plonky2::field::types::Field;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    use super::*;
+    use crate::cpu::kernel::assembler::Kernel;
+    use crate::cpu::kernel::elf::Program;
+    use crate::generation::state::GenerationState;
+    use crate::witness::memory::MemoryAddress;
+    use crate::witness::operation::generate_keccak;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    const INPUT_ADDR: usize = 0x1000;
+    const OUTPUT_ADDR: usize = 0x2000;
+
+    fn constraints_hold(lv: &CpuColumnsView<F>, nv: &CpuColumnsView<F>) -> bool {
+        let mut constraint_consumer = ConstraintConsumer::new(
+            vec![GoldilocksField(2), GoldilocksField(3), GoldilocksField(5)],
+            F::ONE,
+            F::ONE,
+            F::ONE,
+        );
+        eval_packed(lv, nv, &mut constraint_consumer);
+        constraint_consumer
+            .constraint_accs
+            .iter()
+            .all(|&acc| acc == F::ZERO)
+    }
+
+    /// Runs the precompile on `message` and returns the generation state afterwards.
+    fn hash_in_memory(message: &[u8]) -> GenerationState<F, C, D> {
+        let kernel = Kernel {
+            program: Program::default(),
+            ordered_labels: vec![],
+            global_labels: HashMap::new(),
+            blockpath: String::new(),
+            syscall_handlers: Default::default(),
+        };
+        let mut state = GenerationState::<F, C, D>::new(0, &kernel).unwrap();
+        for (i, chunk) in message.chunks(4).enumerate() {
+            let mut word = [0u8; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            let address = MemoryAddress::new(0, Segment::Code, INPUT_ADDR + 4 * i);
+            state.memory.set(address, u32::from_le_bytes(word));
+        }
+
+        state.registers.gprs[DIGEST_PTR_REG as usize] = OUTPUT_ADDR;
+        generate_keccak(&mut state, INPUT_ADDR, message.len()).unwrap();
+        state.memory.apply_ops(&state.traces.memory_ops);
+        state
+    }
+
+    fn sponge_row(state: &GenerationState<F, C, D>) -> usize {
+        state
+            .traces
+            .cpu
+            .iter()
+            .position(|row| row.is_keccak_sponge == F::ONE)
+            .unwrap()
+    }
+
+    #[test]
+    fn digest_is_written_to_memory() {
+        // Empty, shorter than a word, not a multiple of the rate, exactly the rate and longer.
+        for len in [0, 3, 55, 136, 200] {
+            let message = (0..len).map(|i| (i * 7 + 1) as u8).collect::<Vec<_>>();
+            let state = hash_in_memory(&message);
+
+            let sponge_ops = &state.traces.keccak_sponge_ops;
+            assert_eq!(sponge_ops.len(), 1);
+            assert_eq!(sponge_ops[0].input, message);
+
+            let digest = (0..DIGEST_WORDS)
+                .flat_map(|i| {
+                    let address = MemoryAddress::new(0, Segment::Code, OUTPUT_ADDR + 4 * i);
+                    state.memory.get(address).to_le_bytes()
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(digest, keccak(&message).0, "len {len}");
+
+            let i = sponge_row(&state);
+            let cpu = &state.traces.cpu;
+            assert!(constraints_hold(&cpu[i], &cpu[i + 1]), "len {len}");
+        }
+    }
+
+    #[test]
+    fn forged_digest_write_fails() {
+        let state = hash_in_memory(b"hello world");
+        let i = sponge_row(&state);
+        let lv = state.traces.cpu[i];
+
+        let mut nv = state.traces.cpu[i + 1];
+        nv.mem_channels[3].value += F::ONE;
+        assert!(!constraints_hold(&lv, &nv));
+
+        let mut nv = state.traces.cpu[i + 1];
+        nv.mem_channels[5].addr_virtual += F::ONE;
+        assert!(!constraints_hold(&lv, &nv));
+
+        let mut nv = state.traces.cpu[i + 1];
+        nv.mem_channels[0].is_read = F::ONE;
+        assert!(!constraints_hold(&lv, &nv));
+
+        // The digest goes where `$a2` points, all of it moved at once.
+        let nv = state.traces.cpu[i + 1];
+        let mut moved = nv;
+        for channel in &mut moved.mem_channels[..DIGEST_WORDS] {
+            channel.addr_virtual += F::from_canonical_usize(4);
+        }
+        assert!(!constraints_hold(&lv, &moved));
+
+        // The pointer must be read from `$a2`.
+        let mut lv_other_reg = lv;
+        lv_other_reg.mem_channels[DIGEST_PTR_CHANNEL].addr_virtual = F::from_canonical_u8(5);
+        assert!(!constraints_hold(&lv_other_reg, &nv));
+    }
+}
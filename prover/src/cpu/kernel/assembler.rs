@@ -1,9 +1,17 @@
 use super::elf::Program;
 use crate::all_stark::NUM_PUBLIC_INPUT_USERDATA;
-use serde::{Deserialize, Serialize};
+use anyhow::{anyhow, bail, ensure};
+use elf::abi::{EM_MIPS, PT_LOAD};
+use elf::endian::AnyEndian;
+use elf::file::Class;
+use elf::ElfBytes;
+use serde::ser::Error as _;
+use serde::{Deserialize, Serialize, Serializer};
 use sha2::{Digest, Sha256};
 use std::{collections::HashMap, io::Read};
-use zkm_emulator::utils::get_block_path;
+use zkm_emulator::state::{InstrumentedState, State};
+use zkm_emulator::syscall::SyscallHandlers;
+use zkm_emulator::utils::{get_block_path, split_seg_at_steps};
 
 #[derive(PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct Kernel {
@@ -14,10 +22,32 @@ pub struct Kernel {
     //  should be preprocessed after loading code
     pub(crate) global_labels: HashMap<String, usize>,
     pub blockpath: String,
+    /// The syscalls which are not built-in, run by both the emulator and the trace generation.
+    ///
+    /// Handlers can't be serialized: serializing a kernel with handlers fails, and a deserialized
+    /// kernel has none, see `with_syscall_handlers` to register them again.
+    #[serde(serialize_with = "serialize_syscall_handlers", skip_deserializing)]
+    pub syscall_handlers: SyscallHandlers,
+}
+
+fn serialize_syscall_handlers<S: Serializer>(
+    syscall_handlers: &SyscallHandlers,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    if !syscall_handlers.is_empty() {
+        return Err(S::Error::custom(format!(
+            "Can't serialize a kernel with custom syscall handlers: {syscall_handlers:?}"
+        )));
+    }
+    serializer.serialize_unit()
 }
 
 pub const MAX_MEM: u32 = 0x80000000;
 
+/// The number of steps after which a program run by `Kernel::from_elf`, `Kernel::from_asm` or
+/// the `with_*` methods is considered not to exit.
+pub const MAX_RUN_STEPS: u64 = 1 << 32;
+
 pub fn segment_kernel<T: Read>(basedir: &str, block: &str, file: &str, seg_reader: T) -> Kernel {
     let p: Program = Program::load_segment(seg_reader).unwrap();
     let blockpath = get_block_path(basedir, block, file);
@@ -27,10 +57,209 @@ pub fn segment_kernel<T: Read>(basedir: &str, block: &str, file: &str, seg_reade
         ordered_labels: vec![],
         global_labels: HashMap::new(),
         blockpath,
+        syscall_handlers: SyscallHandlers::default(),
     }
 }
 
 impl Kernel {
+    /// Load a 32-bit MIPS ELF executable and run it in the emulator until it exits, returning the
+    /// kernel of the whole execution as a single segment, see `split_at_steps` to split it.
+    ///
+    /// The initial memory image holds the loadable segments in `Segment::Code`, at their virtual
+    /// addresses, with the part of each segment beyond its file size zero-filled (BSS). Only the
+    /// pages touched by the execution are kept. The bootstrap kernel writes this image to memory
+    /// and hashes it against `roots_before`, which constrains the initial values of the memory
+    /// table.
+    ///
+    /// Fails if the loadable segments overlap, are not word-aligned, are larger in the file than
+    /// in memory, extend past the end of the file, or don't fit in the 32-bit address space, and
+    /// if the program doesn't exit within `MAX_RUN_STEPS` steps.
+    pub fn from_elf(bytes: &[u8]) -> anyhow::Result<Kernel> {
+        let file = ElfBytes::<AnyEndian>::minimal_parse(bytes)
+            .map_err(|e| anyhow!("Invalid ELF file: {e:?}"))?;
+        ensure!(
+            file.ehdr.class == Class::ELF32 && file.ehdr.e_machine == EM_MIPS,
+            "Not a 32-bit MIPS ELF file."
+        );
+
+        let mut loadable = file
+            .segments()
+            .ok_or_else(|| anyhow!("ELF file without program headers."))?
+            .iter()
+            .filter(|phdr| phdr.p_type == PT_LOAD)
+            .collect::<Vec<_>>();
+        loadable.sort_by_key(|phdr| phdr.p_vaddr);
+        for phdr in &loadable {
+            ensure!(
+                phdr.p_vaddr % 4 == 0,
+                "Loadable segment at {:#x} is not word-aligned.",
+                phdr.p_vaddr
+            );
+            // Segments are mapped page by page, so the file offset and the address must agree
+            // modulo the alignment.
+            ensure!(
+                phdr.p_align <= 1 || phdr.p_align.is_power_of_two(),
+                "Loadable segment at {:#x} has an alignment ({:#x}) which isn't a power of two.",
+                phdr.p_vaddr,
+                phdr.p_align
+            );
+            ensure!(
+                phdr.p_align <= 1 || phdr.p_offset % phdr.p_align == phdr.p_vaddr % phdr.p_align,
+                "Loadable segment at {:#x} has a file offset ({:#x}) not congruent to its address \
+                 modulo its alignment ({:#x}).",
+                phdr.p_vaddr,
+                phdr.p_offset,
+                phdr.p_align
+            );
+            ensure!(
+                phdr.p_filesz <= phdr.p_memsz,
+                "Loadable segment at {:#x} has a file size ({:#x}) above its memory size ({:#x}).",
+                phdr.p_vaddr,
+                phdr.p_filesz,
+                phdr.p_memsz
+            );
+            ensure!(
+                phdr.p_offset
+                    .checked_add(phdr.p_filesz)
+                    .is_some_and(|end| end <= bytes.len() as u64),
+                "Loadable segment at {:#x} extends past the end of the file.",
+                phdr.p_vaddr
+            );
+            ensure!(
+                phdr.p_vaddr + phdr.p_memsz <= 1 << 32,
+                "Loadable segment at {:#x} of size {:#x} is out of the 32-bit address space.",
+                phdr.p_vaddr,
+                phdr.p_memsz
+            );
+        }
+        for pair in loadable.windows(2) {
+            if pair[0].p_vaddr + pair[0].p_memsz > pair[1].p_vaddr {
+                bail!(
+                    "Loadable segments at {:#x} and {:#x} overlap.",
+                    pair[0].p_vaddr,
+                    pair[1].p_vaddr
+                );
+            }
+        }
+
+        let mut state = State::load_elf(&file);
+        if let Ok(Some(_)) = file.symbol_table() {
+            state.patch_elf(&file);
+        }
+        state.patch_stack(vec![]);
+
+        Self::run_to_exit(
+            state,
+            String::new(),
+            SyscallHandlers::default(),
+            MAX_RUN_STEPS,
+        )
+    }
+
+    /// Assemble `source` at `base` and run it in the emulator from `base` until it exits,
+    /// returning the kernel of the whole execution like `from_elf`.
+    ///
+    /// Only data directives are supported, one per line, with `#` starting a comment:
+    /// - `.word`, `.half` and `.byte` place comma-separated values of 32, 16 and 8 bits, in
+    ///   big-endian order, after aligning the location counter to their size;
+    /// - `.ascii "..."` places the bytes of a string, without terminator;
+    /// - `.align n` aligns the location counter to `2^n` bytes.
+    ///
+    /// Instructions are placed with `.word` and their encoding. Padding bytes are zero.
+    pub fn from_asm(source: &str, base: u32) -> anyhow::Result<Kernel> {
+        Self::run_to_exit(
+            asm_state(source, base)?,
+            String::new(),
+            SyscallHandlers::default(),
+            MAX_RUN_STEPS,
+        )
+    }
+
+    /// Run this segment again from the same memory image, starting at `pc` instead of
+    /// `program.entry`, until the program exits.
+    ///
+    /// The entrypoint is part of `roots_before`, which the bootstrap kernel checks against the
+    /// initial memory image, so the returned kernel is proven for this entrypoint only.
+    pub fn with_entrypoint(&self, pc: u32) -> anyhow::Result<Kernel> {
+        ensure!(pc % 4 == 0, "Entrypoint {pc:#x} is not word-aligned.");
+        let (mut state, _) = State::from_segment(self.program.to_segment());
+        state.set_pc(pc);
+        Self::run_to_exit(
+            state,
+            self.blockpath.clone(),
+            self.syscall_handlers.clone(),
+            MAX_RUN_STEPS,
+        )
+    }
+
+    /// Run this segment again from the same memory image, starting with the general purpose
+    /// registers `gprs` instead of `program.gprs`, until the program exits. All 32 registers are
+    /// replaced, including `$sp`, so start from `program.gprs` to only set some of them.
+    ///
+    /// Like the entrypoint, the registers are part of the initial memory image hashed into
+    /// `roots_before`.
+    pub fn with_initial_registers(&self, gprs: [u32; 32]) -> anyhow::Result<Kernel> {
+        let (mut state, _) = State::from_segment(self.program.to_segment());
+        state.registers = gprs;
+        Self::run_to_exit(
+            state,
+            self.blockpath.clone(),
+            self.syscall_handlers.clone(),
+            MAX_RUN_STEPS,
+        )
+    }
+
+    /// Run this segment again from the same memory image until the program exits, handling the
+    /// syscalls which are not built-in with `syscall_handlers`. The returned kernel keeps them for
+    /// the trace generation.
+    ///
+    /// The results of these syscalls are not constrained by the CPU table, like the hints read
+    /// from the input stream: a guest must check them when they matter.
+    pub fn with_syscall_handlers(
+        &self,
+        syscall_handlers: SyscallHandlers,
+    ) -> anyhow::Result<Kernel> {
+        let (state, _) = State::from_segment(self.program.to_segment());
+        Self::run_to_exit(
+            state,
+            self.blockpath.clone(),
+            syscall_handlers,
+            MAX_RUN_STEPS,
+        )
+    }
+
+    /// Run `state` until the program exits, failing if it doesn't within `max_steps` steps.
+    fn run_to_exit(
+        state: Box<State>,
+        blockpath: String,
+        syscall_handlers: SyscallHandlers,
+        max_steps: u64,
+    ) -> anyhow::Result<Kernel> {
+        let mut instrumented_state = InstrumentedState::new(state, blockpath.clone());
+        instrumented_state.syscall_handlers = syscall_handlers.clone();
+        instrumented_state.take_segment(false);
+        let mut steps = 0;
+        while !instrumented_state.state.exited {
+            ensure!(
+                steps < max_steps,
+                "The program didn't exit within {max_steps} steps."
+            );
+            instrumented_state.step();
+            steps += 1;
+        }
+        let segment = instrumented_state
+            .take_segment(true)
+            .expect("A segment is returned when asked for");
+
+        Ok(Kernel {
+            program: Program::from_segment(segment)?,
+            ordered_labels: vec![],
+            global_labels: HashMap::new(),
+            blockpath,
+            syscall_handlers,
+        })
+    }
+
     /// Get a string representation of the current offset for debugging purposes.
     pub(crate) fn offset_name(&self, offset: usize) -> String {
         match self
@@ -49,6 +278,44 @@ impl Kernel {
             .find_map(|(k, v)| (*v == offset).then(|| k.clone()))
     }
 
+    /// Split this segment into consecutive segments ending at the given step `boundaries`,
+    /// counted from the start of the segment, by running the emulator over it.
+    ///
+    /// Segment `i` ends with the memory image segment `i + 1` starts from, so the `roots_after`
+    /// of each segment proof are the `roots_before` of the next one, which is what aggregation
+    /// checks. The first and last segments keep the `roots_before` and `roots_after` of `self`.
+    pub fn split_at_steps(&self, boundaries: &[usize]) -> anyhow::Result<Vec<Kernel>> {
+        anyhow::ensure!(
+            boundaries.windows(2).all(|pair| pair[0] < pair[1]),
+            "Segment boundaries must be strictly increasing."
+        );
+        anyhow::ensure!(
+            boundaries
+                .iter()
+                .all(|&boundary| 0 < boundary && boundary < self.program.step),
+            "Segment boundaries must lie strictly inside the {} steps of the segment.",
+            self.program.step
+        );
+
+        split_seg_at_steps(
+            self.program.to_segment(),
+            &self.blockpath,
+            boundaries,
+            &self.syscall_handlers,
+        )
+        .into_iter()
+        .map(|segment| {
+            Ok(Kernel {
+                program: Program::from_segment(segment)?,
+                ordered_labels: vec![],
+                global_labels: HashMap::new(),
+                blockpath: self.blockpath.clone(),
+                syscall_handlers: self.syscall_handlers.clone(),
+            })
+        })
+        .collect()
+    }
+
     /// Read public input from input stream index 0
     pub fn read_public_inputs(&self) -> Vec<u8> {
         let mut hasher = Sha256::new();
@@ -68,3 +335,395 @@ impl Kernel {
         result.to_vec()
     }
 }
+
+/// The emulator state with the image assembled from `source` loaded at `base`, starting there.
+fn asm_state(source: &str, base: u32) -> anyhow::Result<Box<State>> {
+    ensure!(base % 4 == 0, "Base address {base:#x} is not word-aligned.");
+    let mut image = assemble(source)?;
+    image.resize(image.len().div_ceil(4) * 4, 0);
+    ensure!(
+        base as u64 + image.len() as u64 <= 1 << 32,
+        "Image of {:#x} bytes at {base:#x} is out of the 32-bit address space.",
+        image.len()
+    );
+
+    let mut state = State::new();
+    state
+        .memory
+        .set_memory_range(base, Box::new(image.as_slice()))
+        .map_err(|e| anyhow!("Failed to load the image: {e:?}"))?;
+    state.set_pc(base);
+    state.patch_stack(vec![]);
+    Ok(state)
+}
+
+/// The bytes placed by the data directives of `source`, see `Kernel::from_asm`.
+fn assemble(source: &str) -> anyhow::Result<Vec<u8>> {
+    let mut image = Vec::new();
+    for (i, line) in source.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (directive, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let args = args.trim();
+        let placed: anyhow::Result<()> = match directive {
+            ".word" | ".half" | ".byte" => {
+                let size = match directive {
+                    ".word" => 4,
+                    ".half" => 2,
+                    _ => 1,
+                };
+                align(&mut image, size);
+                args.split(',').try_for_each(|arg| {
+                    let value = parse_value(arg.trim(), 8 * size as u32)?;
+                    image.extend(&value.to_be_bytes()[4 - size..]);
+                    Ok(())
+                })
+            }
+            ".ascii" => parse_string(args).map(|bytes| image.extend(bytes)),
+            ".align" => match args.parse::<u32>() {
+                Ok(log_alignment) if log_alignment < 32 => {
+                    align(&mut image, 1 << log_alignment);
+                    Ok(())
+                }
+                _ => Err(anyhow!("Invalid alignment `{args}`.")),
+            },
+            _ => Err(anyhow!("Unknown directive `{directive}`.")),
+        };
+        placed.map_err(|e| anyhow!("Line {}: {e}", i + 1))?;
+    }
+    Ok(image)
+}
+
+/// `line` up to its first `#` outside of a string.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn align(image: &mut Vec<u8>, alignment: usize) {
+    image.resize(image.len().div_ceil(alignment) * alignment, 0);
+}
+
+/// Parse a decimal or `0x`-prefixed hexadecimal value fitting in `bits` bits, either unsigned or
+/// signed, and return its two's complement.
+fn parse_value(arg: &str, bits: u32) -> anyhow::Result<u32> {
+    let (negative, digits) = match arg.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, arg),
+    };
+    let magnitude = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => i64::from_str_radix(hex, 16),
+        None => digits.parse::<i64>(),
+    }
+    .map_err(|_| anyhow!("Invalid value `{arg}`."))?;
+    let value = if negative { -magnitude } else { magnitude };
+    ensure!(
+        -(1 << (bits - 1)) <= value && value < 1 << bits,
+        "Value `{arg}` does not fit in {bits} bits."
+    );
+    Ok(value as u32 & (u32::MAX >> (32 - bits)))
+}
+
+/// Parse a double-quoted string with the escapes `\\`, `\"`, `\n`, `\t` and `\0`.
+fn parse_string(arg: &str) -> anyhow::Result<Vec<u8>> {
+    let contents = arg
+        .strip_prefix('"')
+        .and_then(|arg| arg.strip_suffix('"'))
+        .ok_or_else(|| anyhow!("Expected a double-quoted string, got `{arg}`."))?;
+    let mut bytes = Vec::with_capacity(contents.len());
+    let mut chars = contents.chars();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next() {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('0') => '\0',
+                Some(c @ ('\\' | '"')) => c,
+                _ => bail!("Invalid escape in `{arg}`."),
+            },
+            '"' => bail!("Unescaped quote in `{arg}`."),
+            c => c,
+        };
+        let mut buf = [0; 4];
+        bytes.extend(c.encode_utf8(&mut buf).as_bytes());
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use zkm_emulator::syscall::SyscallHandlers;
+
+    use super::{asm_state, Kernel};
+    use crate::cpu::bootstrap_kernel::generate_bootstrap_kernel;
+    use crate::cpu::columns::CpuColumnsView;
+    use crate::generation::simulate_cpu;
+    use crate::generation::state::GenerationState;
+    use crate::witness::operation::generate_syscall;
+
+    /// `addiu $v0, $zero, 4246`, i.e. the `exit_group` syscall number.
+    const LOAD_EXIT_GROUP: u32 = 0x2402_1096;
+    const SYSCALL: u32 = 0x0000_000c;
+    /// `addu $a0, $v0, $zero`.
+    const MOVE_V0_TO_A0: u32 = 0x0040_2021;
+    const BASE: u32 = 0x0040_0000;
+    const EHDR_SIZE: u32 = 52;
+    const PHDR_SIZE: u32 = 32;
+
+    /// A big-endian MIPS ELF file with a single loadable segment per `(vaddr, memsz)`, all mapping
+    /// the whole file, and whose entrypoint is the code right after the program headers.
+    fn tiny_elf(segments: &[(u32, u32)]) -> Vec<u8> {
+        tiny_elf_with_code(segments, &[LOAD_EXIT_GROUP, SYSCALL])
+    }
+
+    /// Like `tiny_elf`, running the instructions `code`.
+    fn tiny_elf_with_code(segments: &[(u32, u32)], code: &[u32]) -> Vec<u8> {
+        let code_offset = EHDR_SIZE + PHDR_SIZE * segments.len() as u32;
+        let file_size = code_offset + 4 * code.len() as u32;
+
+        let mut elf = vec![0x7f, b'E', b'L', b'F', 1, 2, 1];
+        elf.resize(16, 0);
+        elf.extend(2u16.to_be_bytes()); // e_type: EXEC
+        elf.extend(8u16.to_be_bytes()); // e_machine: MIPS
+        elf.extend(1u32.to_be_bytes()); // e_version
+        elf.extend((BASE + code_offset).to_be_bytes()); // e_entry
+        elf.extend(EHDR_SIZE.to_be_bytes()); // e_phoff
+        elf.extend(0u32.to_be_bytes()); // e_shoff
+        elf.extend(0u32.to_be_bytes()); // e_flags
+        elf.extend((EHDR_SIZE as u16).to_be_bytes()); // e_ehsize
+        elf.extend((PHDR_SIZE as u16).to_be_bytes()); // e_phentsize
+        elf.extend((segments.len() as u16).to_be_bytes()); // e_phnum
+        elf.extend([0; 6]); // e_shentsize, e_shnum, e_shstrndx
+        for &(vaddr, memsz) in segments {
+            elf.extend(1u32.to_be_bytes()); // p_type: LOAD
+            elf.extend(0u32.to_be_bytes()); // p_offset
+            elf.extend(vaddr.to_be_bytes()); // p_vaddr
+            elf.extend(vaddr.to_be_bytes()); // p_paddr
+            elf.extend(file_size.to_be_bytes()); // p_filesz
+            elf.extend(memsz.to_be_bytes()); // p_memsz
+            elf.extend(5u32.to_be_bytes()); // p_flags: R E
+            elf.extend(0x1000u32.to_be_bytes()); // p_align
+        }
+        for instruction in code {
+            elf.extend(instruction.to_be_bytes());
+        }
+        elf
+    }
+
+    #[test]
+    fn test_from_elf() {
+        // The memory size covers a BSS section after the code.
+        let kernel = Kernel::from_elf(&tiny_elf(&[(BASE, 0x200)])).unwrap();
+        let entry = kernel.program.entry;
+        assert_eq!(entry, BASE + EHDR_SIZE + PHDR_SIZE);
+        assert_eq!(kernel.program.image[&entry].to_be(), LOAD_EXIT_GROUP);
+        assert_eq!(kernel.program.image[&(entry + 4)].to_be(), SYSCALL);
+        assert_eq!(kernel.program.image[&(BASE + 0x100)], 0);
+        assert_eq!(kernel.program.step, 2);
+    }
+
+    #[test]
+    fn test_from_elf_invalid_segments() {
+        let overlapping = Kernel::from_elf(&tiny_elf(&[(BASE, 0x2000), (BASE + 0x1000, 0x200)]));
+        assert!(overlapping.unwrap_err().to_string().contains("overlap"));
+
+        let unaligned = Kernel::from_elf(&tiny_elf(&[(BASE + 2, 0x200)]));
+        assert!(unaligned.unwrap_err().to_string().contains("word-aligned"));
+
+        // Mapped from offset 0, but word-aligned only.
+        let misplaced = Kernel::from_elf(&tiny_elf(&[(BASE + 0x100, 0x200)]));
+        assert!(misplaced.unwrap_err().to_string().contains("not congruent"));
+
+        let mut bad_align = tiny_elf(&[(BASE, 0x200)]);
+        let p_align = (EHDR_SIZE + 28) as usize;
+        bad_align[p_align..p_align + 4].copy_from_slice(&0x1800u32.to_be_bytes());
+        let bad_align = Kernel::from_elf(&bad_align);
+        assert!(bad_align
+            .unwrap_err()
+            .to_string()
+            .contains("isn't a power of two"));
+
+        let truncated = Kernel::from_elf(&tiny_elf(&[(BASE, 0x10)]));
+        assert!(truncated.unwrap_err().to_string().contains("file size"));
+
+        assert!(Kernel::from_elf(b"not an ELF file").is_err());
+
+        // The segment maps the whole file, which lost its last instruction.
+        let mut cut = tiny_elf(&[(BASE, 0x200)]);
+        cut.truncate(cut.len() - 4);
+        let cut = Kernel::from_elf(&cut);
+        assert!(cut
+            .unwrap_err()
+            .to_string()
+            .contains("past the end of the file"));
+    }
+
+    /// A kernel resumed at the `syscall` of `tiny_elf`, with `$v0` already holding `exit_group`
+    /// and the exit code in `$a0`.
+    fn resumed_exit_kernel(exit_code: u32) -> Kernel {
+        let kernel = Kernel::from_elf(&tiny_elf(&[(BASE, 0x200)])).unwrap();
+        let mut gprs = kernel.program.gprs.map(|r| r as u32);
+        gprs[2] = LOAD_EXIT_GROUP & 0xffff;
+        gprs[4] = exit_code;
+        kernel
+            .with_initial_registers(gprs)
+            .unwrap()
+            .with_entrypoint(kernel.program.entry + 4)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_with_entrypoint_and_registers() {
+        let kernel = Kernel::from_elf(&tiny_elf(&[(BASE, 0x200)])).unwrap();
+        let resumed = resumed_exit_kernel(7);
+        assert_eq!(resumed.program.entry, kernel.program.entry + 4);
+        assert_eq!(resumed.program.next_pc, kernel.program.entry as usize + 8);
+        assert_eq!(resumed.program.gprs[2], 4246);
+        assert_eq!(resumed.program.gprs[4], 7);
+        assert_eq!(resumed.program.gprs[29], kernel.program.gprs[29]);
+        assert_eq!(resumed.program.step, 1);
+        assert_ne!(resumed.program.pre_image_id, kernel.program.pre_image_id);
+        assert_ne!(
+            resumed.program.pre_image_id,
+            resumed_exit_kernel(8).program.pre_image_id
+        );
+
+        assert!(kernel.with_entrypoint(kernel.program.entry + 2).is_err());
+    }
+
+    #[test]
+    fn test_from_asm() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let source = r#"
+            # Exit with the word embedded at BASE + 0x14.
+            .word 0x3c040040    # lui $a0, 0x0040
+            .word 0x8c840014    # lw $a0, 0x14($a0)
+            .word 0x24021096    # addiu $v0, $zero, 4246
+            .word 0x0000000c    # syscall
+            .byte 1
+            .word 42            # aligned to BASE + 0x14
+            .ascii "zk#"
+            .half 0x1234, -1    # aligned to BASE + 0x1c
+            .align 3
+            .byte 0xff
+        "#;
+        let kernel = Kernel::from_asm(source, BASE).unwrap();
+        let image = &kernel.program.image;
+        assert_eq!(kernel.program.entry, BASE);
+        assert_eq!(image[&(BASE + 0x10)].to_be(), 0x0100_0000);
+        assert_eq!(image[&(BASE + 0x14)].to_be(), 42);
+        assert_eq!(image[&(BASE + 0x18)].to_be(), u32::from_be_bytes(*b"zk#\0"));
+        assert_eq!(image[&(BASE + 0x1c)].to_be(), 0x1234_ffff);
+        assert_eq!(image[&(BASE + 0x20)].to_be(), 0xff00_0000);
+        assert_eq!(kernel.program.step, 4);
+
+        let mut state = GenerationState::<F, C, D>::new(kernel.program.step, &kernel).unwrap();
+        generate_bootstrap_kernel(&mut state, &kernel);
+        simulate_cpu(&mut state, &kernel).unwrap();
+        assert_eq!(state.registers.gprs[4], 42);
+        assert_eq!(state.registers.exit_code, 42);
+    }
+
+    #[test]
+    fn test_from_asm_invalid_directives() {
+        let error = |source: &str| Kernel::from_asm(source, BASE).unwrap_err().to_string();
+        assert!(error(".word 1\n.quad 1").contains("Line 2: Unknown directive"));
+        assert!(error(".byte 256").contains("does not fit"));
+        assert!(error(".half -32769").contains("does not fit"));
+        assert!(error(".word zkm").contains("Invalid value"));
+        assert!(error(".ascii zkm").contains("double-quoted"));
+        assert!(error(".align 32").contains("Invalid alignment"));
+        assert!(Kernel::from_asm(".word 0", BASE + 2).is_err());
+    }
+
+    #[test]
+    fn test_run_to_exit_max_steps() {
+        // beq $zero, $zero, -1; nop
+        let state = asm_state(".word 0x1000ffff, 0", BASE).unwrap();
+        let result = Kernel::run_to_exit(state, String::new(), SyscallHandlers::default(), 1000);
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("within 1000 steps"));
+
+        let state = asm_state(".word 0x24021096, 0x0000000c", BASE).unwrap();
+        assert!(Kernel::run_to_exit(state, String::new(), SyscallHandlers::default(), 2).is_ok());
+    }
+
+    #[test]
+    fn test_custom_syscall() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        const SYS_CONSTANT: u32 = 0x1234;
+
+        // Exit with the value returned by the custom syscall.
+        let code = [
+            0x2402_0000 | SYS_CONSTANT,
+            SYSCALL,
+            MOVE_V0_TO_A0,
+            LOAD_EXIT_GROUP,
+            SYSCALL,
+        ];
+        let mut syscall_handlers = SyscallHandlers::default();
+        syscall_handlers.register(SYS_CONSTANT, |_: [u32; 3]| [42, 0]);
+        let kernel = Kernel::from_elf(&tiny_elf_with_code(&[(BASE, 0x200)], &code))
+            .unwrap()
+            .with_syscall_handlers(syscall_handlers.clone())
+            .unwrap();
+        assert_eq!(kernel.program.step, 5);
+        assert_eq!(kernel.syscall_handlers, syscall_handlers);
+
+        // The handlers would be lost.
+        let err = bincode::serialize(&kernel).unwrap_err();
+        assert!(err.to_string().contains("custom syscall handlers"), "{err}");
+        let exit_kernel = Kernel::from_asm(".word 0x24021096, 0x0000000c", BASE).unwrap();
+        let bytes = bincode::serialize(&exit_kernel).unwrap();
+        assert_eq!(bincode::deserialize::<Kernel>(&bytes).unwrap(), exit_kernel);
+
+        let mut state = GenerationState::<F, C, D>::new(0, &kernel).unwrap();
+        state.registers.gprs[2] = SYS_CONSTANT as usize;
+        state.registers.gprs[7] = 1;
+        generate_syscall(&mut state, CpuColumnsView::default(), &kernel).unwrap();
+        assert_eq!(state.registers.gprs[2], 42);
+        assert_eq!(state.registers.gprs[7], 0);
+    }
+
+    #[test]
+    fn test_prove_with_argument_register() -> anyhow::Result<()> {
+        use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+        use crate::all_stark::AllStark;
+        use crate::prover::testutils::prove_and_verify;
+
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let kernel = resumed_exit_kernel(7);
+        let proof = prove_and_verify::<F, C, D>(&AllStark::default(), &kernel)?;
+        assert_eq!(proof.public_values.roots_before.root, unsafe {
+            std::mem::transmute::<[u8; 32], [u32; 8]>(kernel.program.pre_image_id)
+        });
+        Ok(())
+    }
+}
@@ -57,7 +57,10 @@ impl Program {
 
     pub fn load_segment<T: Read>(reader: T) -> Result<Program> {
         let segment: Segment = serde_json::from_reader(reader).unwrap();
+        Self::from_segment(segment)
+    }
 
+    pub fn from_segment(segment: Segment) -> Result<Program> {
         let entry = segment.pc;
         let image = segment.mem_image;
         let end_pc = segment.end_pc as usize;
@@ -137,4 +140,24 @@ impl Program {
             public_values_stream_ptr: segment.public_values_stream_ptr,
         })
     }
+
+    /// The emulator segment this program was loaded from. Its `segment_id` is not kept and is
+    /// reset to 0.
+    pub fn to_segment(&self) -> Segment {
+        Segment {
+            mem_image: self.image.clone(),
+            pc: self.entry,
+            segment_id: 0,
+            pre_image_id: self.pre_image_id,
+            pre_hash_root: self.pre_hash_root,
+            image_id: self.image_id,
+            page_hash_root: self.page_hash_root,
+            end_pc: self.end_pc as u32,
+            step: self.step as u64,
+            input_stream: self.input_stream.clone(),
+            input_stream_ptr: self.input_stream_ptr,
+            public_values_stream: self.public_values_stream.clone(),
+            public_values_stream_ptr: self.public_values_stream_ptr,
+        }
+    }
 }
@@ -1214,11 +1214,130 @@ fn eval_ext_circuit_store<F: RichField + Extendable<D>, const D: usize>(
     }
 }
 
+/// How the CPU table treats word and halfword accesses whose address is not a multiple of their
+/// size. `lwl`, `lwr`, `swl` and `swr` access unaligned words by design and are always allowed.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum AlignmentMode {
+    /// The low bits of the address are ignored and the enclosing aligned word is accessed, like
+    /// the emulator does.
+    #[default]
+    Unchecked,
+    /// Unaligned accesses fault: trace generation fails and the constraints require `lw`, `ll`,
+    /// `sw` and `sc` addresses to be multiples of 4, and `lh`, `lhu` and `sh` addresses to be
+    /// even.
+    Enforced,
+}
+
+/// The opcodes of the loads and stores, along with their flag in `MemIOView`.
+fn memio_opcodes<T: Copy>(lv: &CpuColumnsView<T>) -> ([(T, u64); 8], [(T, u64); 7]) {
+    let io = &lv.memio;
+    let loads = [
+        (io.is_lb, 0b100000),
+        (io.is_lh, 0b100001),
+        (io.is_lwl, 0b100010),
+        (io.is_lw, 0b100011),
+        (io.is_lbu, 0b100100),
+        (io.is_lhu, 0b100101),
+        (io.is_lwr, 0b100110),
+        (io.is_ll, 0b110000),
+    ];
+    let stores = [
+        (io.is_sb, 0b101000),
+        (io.is_sh, 0b101001),
+        (io.is_swl, 0b101010),
+        (io.is_sw, 0b101011),
+        (io.is_swr, 0b101110),
+        (io.is_sc, 0b111000),
+        (io.is_sdc1, 0b111101),
+    ];
+    (loads, stores)
+}
+
+/// Binds the load and store flags to the opcode: exactly one flag is set in load and store rows,
+/// the one of the opcode. The load and store constraints, and the alignment ones, rely on it.
+fn eval_packed_flags<P: PackedField>(
+    lv: &CpuColumnsView<P>,
+    yield_constr: &mut ConstraintConsumer<P>,
+) {
+    let opcode = limb_from_bits_le(lv.opcode_bits);
+    let (loads, stores) = memio_opcodes(lv);
+    let load_filter = lv.op.m_op_load * lv.opcode_bits[5];
+    let store_filter = lv.op.m_op_store * lv.opcode_bits[5];
+    for (filter, flags) in [(load_filter, &loads[..]), (store_filter, &stores[..])] {
+        let mut flag_sum = P::ZEROS;
+        let mut flag_opcode = P::ZEROS;
+        for &(flag, code) in flags {
+            yield_constr.constraint(flag * (flag - P::ONES));
+            flag_sum += flag;
+            flag_opcode += flag * P::Scalar::from_canonical_u64(code);
+        }
+        yield_constr.constraint(filter * (flag_sum - P::ONES));
+        yield_constr.constraint(filter * (opcode - flag_opcode));
+    }
+}
+
+/// Constraints of `AlignmentMode::Enforced`, on top of the flags bound by `eval_packed`.
+pub fn eval_packed_alignment<P: PackedField>(
+    lv: &CpuColumnsView<P>,
+    yield_constr: &mut ConstraintConsumer<P>,
+) {
+    let io = &lv.memio;
+    let rs_limbs = lv.general.io().rs_le;
+    let is_word = io.is_lw + io.is_ll + io.is_sw + io.is_sc;
+    let is_half = io.is_lh + io.is_lhu + io.is_sh;
+    yield_constr.constraint((is_word + is_half) * rs_limbs[0]);
+    yield_constr.constraint(is_word * rs_limbs[1]);
+}
+
+fn eval_ext_circuit_flags<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    lv: &CpuColumnsView<ExtensionTarget<D>>,
+    yield_constr: &mut RecursiveConstraintConsumer<F, D>,
+) {
+    let opcode = limb_from_bits_le_recursive(builder, lv.opcode_bits);
+    let (loads, stores) = memio_opcodes(lv);
+    let load_filter = builder.mul_extension(lv.op.m_op_load, lv.opcode_bits[5]);
+    let store_filter = builder.mul_extension(lv.op.m_op_store, lv.opcode_bits[5]);
+    for (filter, flags) in [(load_filter, &loads[..]), (store_filter, &stores[..])] {
+        let mut flag_sum = builder.zero_extension();
+        let mut flag_opcode = builder.zero_extension();
+        for &(flag, code) in flags {
+            let constr = builder.mul_sub_extension(flag, flag, flag);
+            yield_constr.constraint(builder, constr);
+            flag_sum = builder.add_extension(flag_sum, flag);
+            let code = F::from_canonical_u64(code);
+            flag_opcode = builder.scalar_mul_add_extension(code, flag, flag_opcode);
+        }
+        let constr = builder.mul_sub_extension(filter, flag_sum, filter);
+        yield_constr.constraint(builder, constr);
+        let diff = builder.sub_extension(opcode, flag_opcode);
+        let constr = builder.mul_extension(filter, diff);
+        yield_constr.constraint(builder, constr);
+    }
+}
+
+pub fn eval_ext_circuit_alignment<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    lv: &CpuColumnsView<ExtensionTarget<D>>,
+    yield_constr: &mut RecursiveConstraintConsumer<F, D>,
+) {
+    let io = &lv.memio;
+    let rs_limbs = lv.general.io().rs_le;
+    let is_word = builder.add_many_extension([io.is_lw, io.is_ll, io.is_sw, io.is_sc]);
+    let is_half = builder.add_many_extension([io.is_lh, io.is_lhu, io.is_sh]);
+    let is_aligned_access = builder.add_extension(is_word, is_half);
+    let constr = builder.mul_extension(is_aligned_access, rs_limbs[0]);
+    yield_constr.constraint(builder, constr);
+    let constr = builder.mul_extension(is_word, rs_limbs[1]);
+    yield_constr.constraint(builder, constr);
+}
+
 pub fn eval_packed<P: PackedField>(
     lv: &CpuColumnsView<P>,
     nv: &CpuColumnsView<P>,
     yield_constr: &mut ConstraintConsumer<P>,
 ) {
+    eval_packed_flags(lv, yield_constr);
     eval_packed_load(lv, nv, yield_constr);
     eval_packed_store(lv, nv, yield_constr);
 }
@@ -1229,6 +1348,117 @@ pub fn eval_ext_circuit<F: RichField + Extendable<D>, const D: usize>(
     nv: &CpuColumnsView<ExtensionTarget<D>>,
     yield_constr: &mut RecursiveConstraintConsumer<F, D>,
 ) {
+    eval_ext_circuit_flags(builder, lv, yield_constr);
     eval_ext_circuit_load(builder, lv, nv, yield_constr);
     eval_ext_circuit_store(builder, lv, nv, yield_constr);
 }
+
+#[cfg(test)]
+mod tests {
+    use plonky2::field::goldilocks_field::GoldilocksField;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    use super::*;
+    use crate::cpu::bootstrap_kernel::generate_bootstrap_kernel;
+    use crate::cpu::kernel::assembler::Kernel;
+    use crate::generation::simulate_cpu;
+    use crate::generation::state::GenerationState;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    const BASE: u32 = 0x0040_0000;
+
+    /// Generate the CPU trace of `lw $a1, offset($a0)` with `$a0 = BASE`, followed by an exit.
+    fn generate_lw(
+        offset: u16,
+        alignment: AlignmentMode,
+    ) -> anyhow::Result<GenerationState<F, C, D>> {
+        let source = format!(
+            "
+            .word 0x3c040040            # lui $a0, 0x0040
+            .word {:#x}                 # lw $a1, offset($a0)
+            .word 0x24021096            # addiu $v0, $zero, 4246
+            .word 0x0000000c            # syscall
+            .word 0x11223344
+            .word 0x55667788
+            ",
+            0x8c85_0000 | offset as u32
+        );
+        let kernel = Kernel::from_asm(&source, BASE)?;
+        let mut state = GenerationState::<F, C, D>::new(kernel.program.step, &kernel).unwrap();
+        state.alignment = alignment;
+        generate_bootstrap_kernel(&mut state, &kernel);
+        simulate_cpu(&mut state, &kernel)?;
+        Ok(state)
+    }
+
+    fn lw_row(state: &GenerationState<F, C, D>) -> CpuColumnsView<F> {
+        *state
+            .traces
+            .cpu
+            .iter()
+            .find(|row| row.memio.is_lw == F::ONE)
+            .unwrap()
+    }
+
+    fn constraints_hold(
+        lv: &CpuColumnsView<F>,
+        eval: fn(&CpuColumnsView<F>, &mut ConstraintConsumer<F>),
+    ) -> bool {
+        let mut constraint_consumer = ConstraintConsumer::new(
+            vec![GoldilocksField(2), GoldilocksField(3), GoldilocksField(5)],
+            F::ONE,
+            F::ONE,
+            F::ONE,
+        );
+        eval(lv, &mut constraint_consumer);
+        constraint_consumer
+            .constraint_accs
+            .iter()
+            .all(|&acc| acc == F::ZERO)
+    }
+
+    fn alignment_constraints_hold(lv: &CpuColumnsView<F>) -> bool {
+        constraints_hold(lv, eval_packed_alignment)
+    }
+
+    fn flag_constraints_hold(lv: &CpuColumnsView<F>) -> bool {
+        constraints_hold(lv, eval_packed_flags)
+    }
+
+    #[test]
+    fn test_aligned_lw() {
+        for alignment in [AlignmentMode::Unchecked, AlignmentMode::Enforced] {
+            let state = generate_lw(0x14, alignment).unwrap();
+            assert_eq!(state.registers.gprs[5], 0x5566_7788);
+            assert!(state.traces.cpu.iter().all(alignment_constraints_hold));
+        }
+    }
+
+    #[test]
+    fn test_unaligned_lw() {
+        // Unchecked, the enclosing word is loaded.
+        let state = generate_lw(0x16, AlignmentMode::Unchecked).unwrap();
+        assert_eq!(state.registers.gprs[5], 0x5566_7788);
+        assert!(!alignment_constraints_hold(&lw_row(&state)));
+
+        let error = generate_lw(0x16, AlignmentMode::Enforced).unwrap_err();
+        assert!(error.to_string().contains("UnalignedAccess"));
+    }
+
+    #[test]
+    fn test_memio_flags_bound_to_opcode() {
+        // The flags are bound whatever the alignment mode.
+        let state = generate_lw(0x14, AlignmentMode::Unchecked).unwrap();
+        assert!(state.traces.cpu.iter().all(flag_constraints_hold));
+        let mut lv = lw_row(&state);
+
+        // An `lw` can't pass for an `lwr` or an unflagged access.
+        lv.memio.is_lw = F::ZERO;
+        assert!(!flag_constraints_hold(&lv));
+        lv.memio.is_lwr = F::ONE;
+        assert!(!flag_constraints_hold(&lv));
+    }
+}
@@ -6,9 +6,11 @@ pub mod cpu_stark;
 pub(crate) mod decode;
 pub(crate) mod exit_kernel;
 pub(crate) mod jumps;
+pub(crate) mod keccak_sponge;
 pub mod kernel;
 pub(crate) mod membus;
 pub(crate) mod memio;
 pub(crate) mod misc;
+pub(crate) mod sha_extend_sponge;
 pub(crate) mod shift;
 pub(crate) mod syscall;
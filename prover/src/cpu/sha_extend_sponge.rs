@@ -0,0 +1,64 @@
+use plonky2::field::extension::Extendable;
+use plonky2::field::packed::PackedField;
+use plonky2::field::types::Field;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::ext_target::ExtensionTarget;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+
+use crate::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
+use crate::cpu::columns::CpuColumnsView;
+use crate::memory::segments::Segment;
+
+/// The register holding the stride argument, `$a1`, as passed to SHA_EXTEND.
+pub(crate) const STRIDE_REG: u8 = 5;
+
+/// The channel of the SHA extend sponge row reading `STRIDE_REG`. Channels 0 to 2 only carry
+/// the sponge CTL inputs on that row.
+pub(crate) const STRIDE_CHANNEL: usize = 3;
+
+/// Each SHA extend sponge row reads the stride argument from `$a1` itself, which the syscall
+/// doesn't overwrite, so the memory CTL binds it to the register file. The value read is passed
+/// to the SHA extend sponge table through the sponge CTL, which derives the stride from it.
+pub fn eval_packed<P: PackedField>(
+    lv: &CpuColumnsView<P>,
+    yield_constr: &mut ConstraintConsumer<P>,
+) {
+    let filter = lv.is_sha_extend_sponge;
+    yield_constr.constraint(filter * (filter - P::ONES));
+
+    let stride_channel = lv.mem_channels[STRIDE_CHANNEL];
+    let register_file = P::Scalar::from_canonical_u32(Segment::REGISTER_FILE);
+    let stride_reg = P::Scalar::from_canonical_u8(STRIDE_REG);
+    yield_constr.constraint(filter * (stride_channel.used - P::ONES));
+    yield_constr.constraint(filter * (stride_channel.is_read - P::ONES));
+    yield_constr.constraint(filter * stride_channel.addr_context);
+    yield_constr.constraint(filter * (stride_channel.addr_segment - register_file));
+    yield_constr.constraint(filter * (stride_channel.addr_virtual - stride_reg));
+}
+
+pub fn eval_ext_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    lv: &CpuColumnsView<ExtensionTarget<D>>,
+    yield_constr: &mut RecursiveConstraintConsumer<F, D>,
+) {
+    let filter = lv.is_sha_extend_sponge;
+    let constr = builder.mul_sub_extension(filter, filter, filter);
+    yield_constr.constraint(builder, constr);
+
+    let stride_channel = lv.mem_channels[STRIDE_CHANNEL];
+    let constr = builder.mul_sub_extension(filter, stride_channel.used, filter);
+    yield_constr.constraint(builder, constr);
+    let constr = builder.mul_sub_extension(filter, stride_channel.is_read, filter);
+    yield_constr.constraint(builder, constr);
+    let constr = builder.mul_extension(filter, stride_channel.addr_context);
+    yield_constr.constraint(builder, constr);
+    let register_file =
+        builder.constant_extension(F::Extension::from_canonical_u32(Segment::REGISTER_FILE));
+    let diff = builder.sub_extension(stride_channel.addr_segment, register_file);
+    let constr = builder.mul_extension(filter, diff);
+    yield_constr.constraint(builder, constr);
+    let stride_reg = builder.constant_extension(F::Extension::from_canonical_u8(STRIDE_REG));
+    let diff = builder.sub_extension(stride_channel.addr_virtual, stride_reg);
+    let constr = builder.mul_extension(filter, diff);
+    yield_constr.constraint(builder, constr);
+}
@@ -1,7 +1,9 @@
 use std::borrow::Borrow;
-use std::cmp::min;
 use std::fmt::Debug;
 use std::iter::repeat;
+use std::ops::Range;
+
+use hashbrown::HashMap;
 
 use anyhow::{ensure, Result};
 use itertools::Itertools;
@@ -9,7 +11,7 @@ use plonky2::field::batch_util::batch_add_inplace;
 use plonky2::field::extension::{Extendable, FieldExtension};
 use plonky2::field::packed::PackedField;
 use plonky2::field::polynomial::PolynomialValues;
-use plonky2::field::types::Field;
+use plonky2::field::types::{Field, PrimeField64};
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::challenger::{Challenger, RecursiveChallenger};
 use plonky2::iop::ext_target::ExtensionTarget;
@@ -19,7 +21,8 @@ use plonky2::plonk::config::{AlgebraicHasher, GenericConfig, Hasher};
 use plonky2::plonk::plonk_common::{
     reduce_with_powers, reduce_with_powers_circuit, reduce_with_powers_ext_circuit,
 };
-use plonky2::util::serialization::{Buffer, IoResult, Read, Write};
+use plonky2::util::serialization::{Buffer, IoError, IoResult, Read, Write};
+use plonky2_maybe_rayon::*;
 use plonky2_util::ceil_div_usize;
 
 use crate::all_stark::{Table, NUM_TABLES};
@@ -29,19 +32,34 @@ use crate::evaluation_frame::StarkEvaluationFrame;
 use crate::proof::{StarkProofTarget, StarkProofWithMetadata};
 use crate::stark::Stark;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Filter<F: Field> {
     products: Vec<(Column<F>, Column<F>)>,
     constants: Vec<Column<F>>,
 }
 
 impl<F: Field> Filter<F> {
-    /// Returns a filter from the provided `products` and `constants` vectors.
+    /// Returns a filter from the provided `products` and `constants` vectors. The columns can't
+    /// read the row after the next one, see `TableWithColumns::new`.
     pub fn new(products: Vec<(Column<F>, Column<F>)>, constants: Vec<Column<F>>) -> Self {
-        Self {
+        let filter = Self {
             products,
             constants,
-        }
+        };
+        assert!(
+            !filter.reads_next_next_row(),
+            "CTL filters can't read the row after the next one"
+        );
+        filter
+    }
+
+    /// Whether one of the columns reads the row after the next one.
+    fn reads_next_next_row(&self) -> bool {
+        self.products
+            .iter()
+            .flat_map(|(col1, col2)| [col1, col2])
+            .chain(&self.constants)
+            .any(Column::reads_next_next_row)
     }
 
     /// Returns a filter made of a single column.
@@ -52,6 +70,21 @@ impl<F: Field> Filter<F> {
         }
     }
 
+    /// Returns a filter evaluating to `1 - col`, e.g. "not the final row" for a binary `col`.
+    pub fn not(col: Column<F>) -> Self {
+        Self::new_simple(col.scale(F::NEG_ONE).add_constant(F::ONE))
+    }
+
+    /// Returns a filter evaluating to `a * b`, the conjunction of two binary selectors.
+    pub fn and(a: Column<F>, b: Column<F>) -> Self {
+        Self::new(vec![(a, b)], vec![])
+    }
+
+    /// Returns a filter evaluating to `a + b - a * b`, the disjunction of two binary selectors.
+    pub fn or(a: Column<F>, b: Column<F>) -> Self {
+        Self::new(vec![(a.clone(), b.scale(F::NEG_ONE))], vec![a, b])
+    }
+
     /// Given the column values for the current and next rows, evaluates the filter.
     pub(crate) fn eval_filter<FE, P, const D: usize>(&self, v: &[P], next_v: &[P]) -> P
     where
@@ -101,6 +134,17 @@ impl<F: Field> Filter<F> {
         builder.add_extension(prods, consts)
     }
 
+    /// Returns the largest column index referenced by this filter, or `None` if it only
+    /// involves constants.
+    pub fn max_index(&self) -> Option<usize> {
+        self.products
+            .iter()
+            .flat_map(|(col1, col2)| [col1.max_index(), col2.max_index()])
+            .chain(self.constants.iter().map(|col| col.max_index()))
+            .flatten()
+            .max()
+    }
+
     /// Evaluate on a row of a table given in column-major form.
     pub(crate) fn eval_table(&self, table: &[PolynomialValues<F>], row: usize) -> F {
         self.products
@@ -116,10 +160,11 @@ impl<F: Field> Filter<F> {
 }
 
 /// Represent a linear combination of columns.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Column<F: Field> {
     linear_combination: Vec<(usize, F)>,
     next_row_linear_combination: Vec<(usize, F)>,
+    next_next_row_linear_combination: Vec<(usize, F)>,
     constant: F,
 }
 
@@ -128,6 +173,7 @@ impl<F: Field> Column<F> {
         Self {
             linear_combination: vec![(c, F::ONE)],
             next_row_linear_combination: vec![],
+            next_next_row_linear_combination: vec![],
             constant: F::ZERO,
         }
     }
@@ -138,10 +184,23 @@ impl<F: Field> Column<F> {
         cs.into_iter().map(|c| Self::single(*c.borrow()))
     }
 
+    /// The columns of `range`, e.g. a block of fields of a columns view, see `field_range`.
+    pub fn columns_from_range(range: Range<usize>) -> Vec<Self> {
+        Self::singles(range).collect()
+    }
+
+    /// The columns of a field of a columns view, given by its indices in the view's column map,
+    /// e.g. `Column::columns_from_field(SHA_EXTEND_SPONGE_COL_MAP.w_i)`. Panics if the field
+    /// isn't a block of consecutive columns, see `field_range`.
+    pub fn columns_from_field<const N: usize>(indices: [usize; N]) -> Vec<Self> {
+        Self::columns_from_range(field_range(indices))
+    }
+
     pub fn single_next_row(c: usize) -> Self {
         Self {
             linear_combination: vec![],
             next_row_linear_combination: vec![(c, F::ONE)],
+            next_next_row_linear_combination: vec![],
             constant: F::ZERO,
         }
     }
@@ -152,10 +211,22 @@ impl<F: Field> Column<F> {
         cs.into_iter().map(|c| Self::single_next_row(*c.borrow()))
     }
 
+    /// Returns the column `c` of the row after the next one. It can be evaluated on traces, e.g.
+    /// with `eval_table`, but not used in a CTL, see `TableWithColumns::new`.
+    pub fn single_next_next_row(c: usize) -> Self {
+        Self {
+            linear_combination: vec![],
+            next_row_linear_combination: vec![],
+            next_next_row_linear_combination: vec![(c, F::ONE)],
+            constant: F::ZERO,
+        }
+    }
+
     pub fn constant(constant: F) -> Self {
         Self {
             linear_combination: vec![],
             next_row_linear_combination: vec![],
+            next_next_row_linear_combination: vec![],
             constant,
         }
     }
@@ -182,6 +253,7 @@ impl<F: Field> Column<F> {
         Self {
             linear_combination: v,
             next_row_linear_combination: vec![],
+            next_next_row_linear_combination: vec![],
             constant,
         }
     }
@@ -209,6 +281,7 @@ impl<F: Field> Column<F> {
         Self {
             linear_combination: v,
             next_row_linear_combination: next_row_v,
+            next_next_row_linear_combination: vec![],
             constant,
         }
     }
@@ -222,10 +295,16 @@ impl<F: Field> Column<F> {
     }
 
     pub fn le_bytes<I: IntoIterator<Item = impl Borrow<usize>>>(cs: I) -> Self {
+        Self::le_base(cs, 256)
+    }
+
+    /// Returns the little-endian recombination of limbs in radix `base`, i.e. the `i`-th column
+    /// is weighted by `base^i`.
+    pub fn le_base<I: IntoIterator<Item = impl Borrow<usize>>>(cs: I, base: u64) -> Self {
         Self::linear_combination(
             cs.into_iter()
                 .map(|c| *c.borrow())
-                .zip(F::from_canonical_u16(256).powers()),
+                .zip(F::from_canonical_u64(base).powers()),
         )
     }
 
@@ -233,6 +312,102 @@ impl<F: Field> Column<F> {
         Self::linear_combination(cs.into_iter().map(|c| *c.borrow()).zip(repeat(F::ONE)))
     }
 
+    /// Returns the sum of `self` and `other`. Coefficients of columns referenced by both are
+    /// added together.
+    pub fn add(&self, other: &Column<F>) -> Self {
+        debug_assert!(
+            self.next_row_linear_combination.iter().all(|&(c, f)| other
+                .next_row_linear_combination
+                .iter()
+                .all(|&(c2, f2)| c != c2 || f == f2)),
+            "Overlapping next row columns with different weights."
+        );
+        debug_assert!(
+            self.next_next_row_linear_combination
+                .iter()
+                .all(|&(c, f)| other
+                    .next_next_row_linear_combination
+                    .iter()
+                    .all(|&(c2, f2)| c != c2 || f == f2)),
+            "Overlapping next next row columns with different weights."
+        );
+        Self {
+            linear_combination: Self::merge_linear_combinations(
+                &self.linear_combination,
+                &other.linear_combination,
+            ),
+            next_row_linear_combination: Self::merge_linear_combinations(
+                &self.next_row_linear_combination,
+                &other.next_row_linear_combination,
+            ),
+            next_next_row_linear_combination: Self::merge_linear_combinations(
+                &self.next_next_row_linear_combination,
+                &other.next_next_row_linear_combination,
+            ),
+            constant: self.constant + other.constant,
+        }
+    }
+
+    /// Returns `self` multiplied by the constant `c`.
+    pub fn scale(&self, c: F) -> Self {
+        Self {
+            linear_combination: self
+                .linear_combination
+                .iter()
+                .map(|&(col, f)| (col, f * c))
+                .collect(),
+            next_row_linear_combination: self
+                .next_row_linear_combination
+                .iter()
+                .map(|&(col, f)| (col, f * c))
+                .collect(),
+            next_next_row_linear_combination: self
+                .next_next_row_linear_combination
+                .iter()
+                .map(|&(col, f)| (col, f * c))
+                .collect(),
+            constant: self.constant * c,
+        }
+    }
+
+    /// Returns `self` plus the constant `c`.
+    pub fn add_constant(&self, c: F) -> Self {
+        Self {
+            constant: self.constant + c,
+            ..self.clone()
+        }
+    }
+
+    /// Merges two linear combinations, summing the coefficients of repeated columns.
+    fn merge_linear_combinations(lhs: &[(usize, F)], rhs: &[(usize, F)]) -> Vec<(usize, F)> {
+        let mut res = lhs.to_vec();
+        for &(c, f) in rhs {
+            if let Some(entry) = res.iter_mut().find(|(c2, _)| *c2 == c) {
+                entry.1 += f;
+            } else {
+                res.push((c, f));
+            }
+        }
+        res
+    }
+
+    /// Whether this column reads the row after the next one. Such columns can be evaluated on
+    /// traces, but not in CTLs, whose constraints only see the current and next rows.
+    pub fn reads_next_next_row(&self) -> bool {
+        !self.next_next_row_linear_combination.is_empty()
+    }
+
+    /// Returns the largest column index referenced in any of the row linear combinations,
+    /// or `None` for a constant column.
+    pub fn max_index(&self) -> Option<usize> {
+        self.linear_combination
+            .iter()
+            .chain(&self.next_row_linear_combination)
+            .chain(&self.next_next_row_linear_combination)
+            .map(|&(c, _)| c)
+            .max()
+    }
+
     pub fn eval<FE, P, const D: usize>(&self, v: &[P]) -> P
     where
         FE: FieldExtension<D, BaseField = F>,
@@ -246,6 +421,33 @@ impl<F: Field> Column<F> {
     }
 
     pub fn eval_with_next<FE, P, const D: usize>(&self, v: &[P], next_v: &[P]) -> P
+    where
+        FE: FieldExtension<D, BaseField = F>,
+        P: PackedField<Scalar = FE>,
+    {
+        debug_assert!(
+            self.next_next_row_linear_combination.is_empty(),
+            "Use `eval_with_next_next` for columns spanning three rows."
+        );
+        self.linear_combination
+            .iter()
+            .map(|&(c, f)| v[c] * FE::from_basefield(f))
+            .sum::<P>()
+            + self
+                .next_row_linear_combination
+                .iter()
+                .map(|&(c, f)| next_v[c] * FE::from_basefield(f))
+                .sum::<P>()
+            + FE::from_basefield(self.constant)
+    }
+
+    /// Like `eval_with_next`, with the values `next_next_v` of the row after the next one.
+    pub fn eval_with_next_next<FE, P, const D: usize>(
+        &self,
+        v: &[P],
+        next_v: &[P],
+        next_next_v: &[P],
+    ) -> P
     where
         FE: FieldExtension<D, BaseField = F>,
         P: PackedField<Scalar = FE>,
@@ -259,11 +461,47 @@ impl<F: Field> Column<F> {
                 .iter()
                 .map(|&(c, f)| next_v[c] * FE::from_basefield(f))
                 .sum::<P>()
+            + self
+                .next_next_row_linear_combination
+                .iter()
+                .map(|&(c, f)| next_next_v[c] * FE::from_basefield(f))
+                .sum::<P>()
             + FE::from_basefield(self.constant)
     }
 
+    /// Checks that the columns and the `row` this column reads are in `table`.
+    fn check_in_table(&self, table: &[PolynomialValues<F>], row: usize) -> Result<()> {
+        let Some(max_index) = self.max_index() else {
+            return Ok(());
+        };
+        ensure!(
+            max_index < table.len(),
+            "Column index {max_index} is out of a table of width {}",
+            table.len()
+        );
+        let height = table[0].values.len();
+        ensure!(
+            row < height,
+            "Row {row} is out of a table of height {height}"
+        );
+        Ok(())
+    }
+
+    /// Evaluate on a row of a table given in column-major form, like `eval_table`, but fails
+    /// instead of panicking if the column reads outside of `table`, e.g. for a CTL written
+    /// against the wrong table.
+    pub fn try_eval_table(&self, table: &[PolynomialValues<F>], row: usize) -> Result<F> {
+        self.check_in_table(table, row)?;
+        Ok(self.eval_table(table, row))
+    }
+
     /// Evaluate on an row of a table given in column-major form.
     pub fn eval_table(&self, table: &[PolynomialValues<F>], row: usize) -> F {
+        #[cfg(debug_assertions)]
+        if let Err(e) = self.check_in_table(table, row) {
+            panic!("{e}");
+        }
+
         let mut res = self
             .linear_combination
             .iter()
@@ -280,10 +518,19 @@ impl<F: Field> Column<F> {
                 .map(|&(c, f)| table[c].values[row + 1] * f)
                 .sum::<F>();
         }
+        // Likewise, rows past the end are considered to be 0.
+        if !self.next_next_row_linear_combination.is_empty() && row + 2 < table[0].values.len() {
+            res += self
+                .next_next_row_linear_combination
+                .iter()
+                .map(|&(c, f)| table[c].values[row + 2] * f)
+                .sum::<F>();
+        }
 
         res
     }
 
+    /// Evaluate on every row of a table given in column-major form.
     pub(crate) fn eval_all_rows(&self, table: &[PolynomialValues<F>]) -> Vec<F> {
         let length = table[0].len();
         (0..length)
@@ -342,43 +589,321 @@ impl<F: Field> Column<F> {
         let constant = builder.constant_extension(F::Extension::from_basefield(self.constant));
         builder.inner_product_extension(F::ONE, constant, pairs)
     }
+
+    pub fn eval_with_next_next_circuit<const D: usize>(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        v: &[ExtensionTarget<D>],
+        next_v: &[ExtensionTarget<D>],
+        next_next_v: &[ExtensionTarget<D>],
+    ) -> ExtensionTarget<D>
+    where
+        F: RichField + Extendable<D>,
+    {
+        let pairs = [
+            (&self.linear_combination, v),
+            (&self.next_row_linear_combination, next_v),
+            (&self.next_next_row_linear_combination, next_next_v),
+        ]
+        .into_iter()
+        .flat_map(|(lc, values)| lc.iter().map(move |&(c, f)| (values[c], f)))
+        .map(|(t, f)| {
+            (
+                t,
+                builder.constant_extension(F::Extension::from_basefield(f)),
+            )
+        })
+        .collect::<Vec<_>>();
+        let constant = builder.constant_extension(F::Extension::from_basefield(self.constant));
+        builder.inner_product_extension(F::ONE, constant, pairs)
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct TableWithColumns<F: Field> {
     table: Table,
     columns: Vec<Column<F>>,
     filter: Option<Filter<F>>,
+    /// Number of times each row is looked up, used instead of `filter` in `CtlKind::LogUp` CTLs.
+    multiplicity: Option<Column<F>>,
 }
 
 impl<F: Field> TableWithColumns<F> {
+    /// Panics if a column or the filter reads the row after the next one: the CTL constraints
+    /// only see the current and next rows, so such a lookup wouldn't be checked.
     pub fn new(table: Table, columns: Vec<Column<F>>, filter: Option<Filter<F>>) -> Self {
+        assert!(
+            !columns.iter().any(Column::reads_next_next_row)
+                && !filter.as_ref().is_some_and(Filter::reads_next_next_row),
+            "Columns of a CTL on {table:?} can't read the row after the next one"
+        );
         Self {
             table,
             columns,
             filter,
+            multiplicity: None,
+        }
+    }
+
+    /// The table whose columns are looked up.
+    ///
+    /// ```
+    /// use plonky2::field::goldilocks_field::GoldilocksField as F;
+    /// use zkm_prover::all_stark::Table;
+    /// use zkm_prover::cross_table_lookup::{Column, Filter, TableWithColumns};
+    ///
+    /// let columns = vec![Column::<F>::single(0), Column::single(1)];
+    /// let filter = Filter::new_simple(Column::single(2));
+    /// let twc = TableWithColumns::new(Table::Cpu, columns.clone(), Some(filter.clone()));
+    /// assert_eq!(twc.table(), Table::Cpu);
+    /// assert_eq!(twc.columns(), columns.as_slice());
+    /// assert_eq!(twc.filter(), Some(&filter));
+    /// assert_eq!(twc.multiplicity(), None);
+    /// ```
+    pub fn table(&self) -> Table {
+        self.table
+    }
+
+    /// The looked up values, one linear combination of columns each.
+    pub fn columns(&self) -> &[Column<F>] {
+        &self.columns
+    }
+
+    /// The filter selecting the rows that are looked up, if any.
+    pub fn filter(&self) -> Option<&Filter<F>> {
+        self.filter.as_ref()
+    }
+
+    /// The multiplicity column set with `with_multiplicity`, if any.
+    pub fn multiplicity(&self) -> Option<&Column<F>> {
+        self.multiplicity.as_ref()
+    }
+
+    /// Sets the multiplicity column of this table. In a `CtlKind::LogUp` CTL, each row is then
+    /// counted `multiplicity` times rather than `filter` times.
+    pub fn with_multiplicity(mut self, multiplicity: Column<F>) -> Self {
+        assert!(
+            !multiplicity.reads_next_next_row(),
+            "The multiplicity of a CTL on {:?} can't read the row after the next one",
+            self.table
+        );
+        self.multiplicity = Some(multiplicity);
+        self
+    }
+
+    /// Returns the weight of each row in the lookup sums of a CTL of the given kind.
+    pub(crate) fn ctl_filter(&self, kind: CtlKind) -> Option<Filter<F>> {
+        match (kind, &self.multiplicity) {
+            (CtlKind::LogUp, Some(multiplicity)) => Some(Filter::new_simple(multiplicity.clone())),
+            _ => self.filter.clone(),
         }
     }
+
+    /// Returns the largest column index referenced by the columns or the filter, or `None` if
+    /// they are all constants. A well-formed CTL satisfies `max_column_index() < table_width`.
+    pub fn max_column_index(&self) -> Option<usize> {
+        self.columns
+            .iter()
+            .map(|col| col.max_index())
+            .chain(self.filter.iter().map(|filter| filter.max_index()))
+            .chain(self.multiplicity.iter().map(|m| m.max_index()))
+            .flatten()
+            .max()
+    }
 }
 
-#[derive(Clone)]
+/// How the rows of the tables of a cross-table lookup are weighted in the lookup sums
+/// `\sum_i w_i / combine_i`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum CtlKind {
+    /// Rows are weighted by their binary filter, so each looked row must appear in the looked
+    /// table as many times as it is looked up.
+    #[default]
+    GrandProduct,
+    /// Rows of tables with a multiplicity column are weighted by it, so each looked row only
+    /// needs to appear once in the looked table, along with the number of times it is looked up.
+    LogUp,
+}
+
+/// The range of columns of a field of a columns view, given by its indices in the view's column
+/// map. Panics if the indices aren't consecutive, which means the column map doesn't match the
+/// layout of the view.
+pub fn field_range<const N: usize>(indices: [usize; N]) -> Range<usize> {
+    let Some(&start) = indices.first() else {
+        return 0..0;
+    };
+    assert!(
+        indices.iter().enumerate().all(|(i, &c)| c == start + i),
+        "Field columns {indices:?} aren't consecutive"
+    );
+    start..start + N
+}
+
+/// A lookup of the rows of `looking_tables` in `looked_tables`: every row must appear as many
+/// times in the looking tables as in the looked tables, all taken together. The looking tables
+/// of a `Table` must be adjacent: they share one Z polynomial per challenge, and the Z
+/// polynomials of the looking tables are in the order of their `Table`s. Each looked table has
+/// its own Z polynomial per challenge, in order.
+#[derive(Clone, Debug)]
 pub struct CrossTableLookup<F: Field> {
     pub(crate) looking_tables: Vec<TableWithColumns<F>>,
-    pub(crate) looked_table: TableWithColumns<F>,
+    pub(crate) looked_tables: Vec<TableWithColumns<F>>,
+    pub(crate) kind: CtlKind,
+    /// Label shown in error messages. It is neither serialized nor compared.
+    pub(crate) name: Option<String>,
+}
+
+impl<F: Field> PartialEq for CrossTableLookup<F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.looking_tables == other.looking_tables
+            && self.looked_tables == other.looked_tables
+            && self.kind == other.kind
+    }
 }
 
+impl<F: Field> Eq for CrossTableLookup<F> {}
+
 impl<F: Field> CrossTableLookup<F> {
+    /// Creates a cross-table lookup, checking that every looking table has as many columns as
+    /// the looked table, and that the looking tables of a `Table` are adjacent.
     pub fn new(
         looking_tables: Vec<TableWithColumns<F>>,
         looked_table: TableWithColumns<F>,
+    ) -> Result<Self, CtlError<F>> {
+        Self::new_multi_looked(looking_tables, vec![looked_table])
+    }
+
+    /// Like `new`, with several looked tables whose rows are looked up together. All looking
+    /// and looked tables must have as many columns as the first looked table.
+    pub fn new_multi_looked(
+        looking_tables: Vec<TableWithColumns<F>>,
+        looked_tables: Vec<TableWithColumns<F>>,
+    ) -> Result<Self, CtlError<F>> {
+        let Some(first_looked) = looked_tables.first() else {
+            return Err(CtlError::NoLookedTable);
+        };
+        if let Some(twc) = looking_tables
+            .iter()
+            .find(|twc| twc.columns.len() != first_looked.columns.len())
+        {
+            return Err(CtlError::ColumnCountMismatch {
+                looking_table: twc.table,
+                looking_len: twc.columns.len(),
+                looked_table: first_looked.table,
+                looked_len: first_looked.columns.len(),
+            });
+        }
+        if let Some(twc) = looked_tables[1..]
+            .iter()
+            .find(|twc| twc.columns.len() != first_looked.columns.len())
+        {
+            return Err(CtlError::LookedColumnCountMismatch {
+                looked_table: twc.table,
+                looked_len: twc.columns.len(),
+                first_looked_table: first_looked.table,
+                first_looked_len: first_looked.columns.len(),
+            });
+        }
+        if let Some(table) = ungrouped_table(&looking_tables) {
+            return Err(CtlError::UngroupedLookingTables { table });
+        }
+        Ok(Self {
+            looking_tables,
+            looked_tables,
+            kind: CtlKind::GrandProduct,
+            name: None,
+        })
+    }
+
+    /// Like `new`, but only checks the column counts and the grouping of the looking tables in
+    /// debug builds. Meant for the built-in lookups, which are known to be well-formed.
+    pub fn new_unchecked(
+        looking_tables: Vec<TableWithColumns<F>>,
+        looked_table: TableWithColumns<F>,
     ) -> Self {
-        assert!(looking_tables
+        debug_assert!(looking_tables
             .iter()
             .all(|twc| twc.columns.len() == looked_table.columns.len()));
+        debug_assert!(ungrouped_table(&looking_tables).is_none());
         Self {
             looking_tables,
-            looked_table,
+            looked_tables: vec![looked_table],
+            kind: CtlKind::GrandProduct,
+            name: None,
+        }
+    }
+
+    /// Like `new`, with a name used to identify the lookup in error messages.
+    pub fn new_named(
+        name: impl Into<String>,
+        looking_tables: Vec<TableWithColumns<F>>,
+        looked_table: TableWithColumns<F>,
+    ) -> Result<Self, CtlError<F>> {
+        Ok(Self {
+            name: Some(name.into()),
+            ..Self::new(looking_tables, looked_table)?
+        })
+    }
+
+    /// Creates a `CtlKind::LogUp` cross-table lookup. The looked table must have a multiplicity
+    /// column, see `TableWithColumns::with_multiplicity`.
+    pub fn new_logup(
+        looking_tables: Vec<TableWithColumns<F>>,
+        looked_table: TableWithColumns<F>,
+    ) -> Result<Self, CtlError<F>> {
+        Self::new_logup_multi_looked(looking_tables, vec![looked_table])
+    }
+
+    /// Like `new_logup`, with several looked tables, each of which must have a multiplicity
+    /// column.
+    pub fn new_logup_multi_looked(
+        looking_tables: Vec<TableWithColumns<F>>,
+        looked_tables: Vec<TableWithColumns<F>>,
+    ) -> Result<Self, CtlError<F>> {
+        if let Some(twc) = looked_tables.iter().find(|twc| twc.multiplicity.is_none()) {
+            return Err(CtlError::MissingMultiplicity { table: twc.table });
+        }
+        Ok(Self {
+            kind: CtlKind::LogUp,
+            ..Self::new_multi_looked(looking_tables, looked_tables)?
+        })
+    }
+
+    pub fn kind(&self) -> CtlKind {
+        self.kind
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Which tables are looking or looked tables of at least one of `ctls`.
+    pub fn tables_touched(ctls: &[Self]) -> [bool; NUM_TABLES] {
+        let mut touched = [false; NUM_TABLES];
+        for ctl in ctls {
+            for twc in ctl.looked_tables.iter().chain(&ctl.looking_tables) {
+                touched[twc.table as usize] = true;
+            }
         }
+        touched
+    }
+
+    /// The number of Z polynomials of `table`, over all cross-table lookups: one per challenge
+    /// for its looking tables, if any, and one per challenge for each of its looked tables.
+    pub(crate) fn num_ctl_zs(ctls: &[Self], table: Table, num_challenges: usize) -> usize {
+        let num_ctls: usize = ctls
+            .iter()
+            .map(|ctl| {
+                let is_looking = ctl.looking_tables.iter().any(|twc| twc.table == table);
+                let num_looked = ctl
+                    .looked_tables
+                    .iter()
+                    .filter(|twc| twc.table == table)
+                    .count();
+                usize::from(is_looking) + num_looked
+            })
+            .sum();
+        num_ctls * num_challenges
     }
 
     /// Given a table, returns:
@@ -391,77 +916,231 @@ impl<F: Field> CrossTableLookup<F> {
         num_challenges: usize,
         constraint_degree: usize,
     ) -> (usize, usize, Vec<usize>) {
-        let mut num_helpers = 0;
-        let mut num_ctls = 0;
-        let mut num_helpers_by_ctl = vec![0; ctls.len()];
-        for (i, ctl) in ctls.iter().enumerate() {
-            let all_tables = std::iter::once(&ctl.looked_table).chain(&ctl.looking_tables);
-            let num_appearances = all_tables.filter(|twc| twc.table == table).count();
-            let is_helpers = num_appearances > 1;
-            if is_helpers {
-                num_helpers_by_ctl[i] = ceil_div_usize(num_appearances, constraint_degree - 1);
-                num_helpers += num_helpers_by_ctl[i];
-            }
-
-            if num_appearances > 0 {
-                num_ctls += 1;
-            }
-        }
+        // Only the looking tables have helper columns, see `ctl_zs_for_challenge`.
+        let num_helpers_by_ctl = num_ctl_helper_columns_by_table(ctls, constraint_degree)
+            .iter()
+            .map(|num_by_table| num_by_table[table as usize])
+            .collect::<Vec<_>>();
+        let num_helpers = num_helpers_by_ctl.iter().sum::<usize>();
         (
             num_helpers * num_challenges,
-            num_ctls * num_challenges,
+            Self::num_ctl_zs(ctls, table, num_challenges),
             num_helpers_by_ctl,
         )
     }
 }
 
-/// Cross-table lookup data for one table.
-#[derive(Clone, Default)]
-pub struct CtlData<'a, F: Field> {
-    pub(crate) zs_columns: Vec<CtlZData<'a, F>>,
-}
-
-/// Cross-table lookup data associated with one Z(x) polynomial.
-/// One Z(x) polynomial can be associated to multiple tables,
-/// built from the same STARK.
-#[derive(Clone)]
-pub(crate) struct CtlZData<'a, F: Field> {
-    /// Helper columns to verify the Z polynomial values.
-    pub(crate) helper_columns: Vec<PolynomialValues<F>>,
-    pub(crate) z: PolynomialValues<F>,
-    pub(crate) challenge: GrandProductChallenge<F>,
-    /// Vector of column linear combinations for the current tables.
-    pub(crate) columns: Vec<&'a [Column<F>]>,
-    /// Vector of filter columns for the current table.
-    /// Each filter evaluates to either 1 or 0.
-    pub(crate) filter: Vec<Option<Filter<F>>>,
+/// Reads a vector written as its length followed by its elements. The length is untrusted, so
+/// nothing is allocated for it upfront: a truncated buffer fails on its first missing element.
+fn read_vec<T>(
+    buffer: &mut Buffer,
+    mut read: impl FnMut(&mut Buffer) -> IoResult<T>,
+) -> IoResult<Vec<T>> {
+    let length = buffer.read_usize()?;
+    let mut res = Vec::new();
+    for _ in 0..length {
+        res.push(read(buffer)?);
+    }
+    Ok(res)
 }
 
-impl<F: Field> CtlData<'_, F> {
-    pub fn len(&self) -> usize {
-        self.zs_columns.len()
+impl<F: RichField> Filter<F> {
+    pub fn to_buffer<W: Write>(&self, buffer: &mut W) -> IoResult<()> {
+        buffer.write_usize(self.products.len())?;
+        for (col1, col2) in &self.products {
+            col1.to_buffer(buffer)?;
+            col2.to_buffer(buffer)?;
+        }
+        buffer.write_usize(self.constants.len())?;
+        for col in &self.constants {
+            col.to_buffer(buffer)?;
+        }
+        Ok(())
     }
 
-    pub fn is_empty(&self) -> bool {
-        self.zs_columns.is_empty()
+    pub fn from_buffer(buffer: &mut Buffer) -> IoResult<Self> {
+        let products = read_vec(buffer, |buffer| {
+            Ok((Column::from_buffer(buffer)?, Column::from_buffer(buffer)?))
+        })?;
+        let constants = read_vec(buffer, Column::from_buffer)?;
+        Ok(Self {
+            products,
+            constants,
+        })
     }
+}
 
-    /// Returns all the cross-table lookup helper polynomials.
-    pub(crate) fn ctl_helper_polys(&self) -> Vec<PolynomialValues<F>> {
-        let num_polys = self
-            .zs_columns
-            .iter()
-            .fold(0, |acc, z| acc + z.helper_columns.len());
-        let mut res = Vec::with_capacity(num_polys);
-        for z in &self.zs_columns {
-            res.extend(z.helper_columns.clone());
+impl<F: RichField> Column<F> {
+    pub fn to_buffer<W: Write>(&self, buffer: &mut W) -> IoResult<()> {
+        for lc in [&self.linear_combination, &self.next_row_linear_combination] {
+            buffer.write_usize(lc.len())?;
+            for &(c, f) in lc {
+                buffer.write_usize(c)?;
+                buffer.write_field(f)?;
+            }
         }
+        buffer.write_field(self.constant)?;
+        // Written last so that the layout of the other fields is unchanged.
+        let lc = &self.next_next_row_linear_combination;
+        buffer.write_usize(lc.len())?;
+        for &(c, f) in lc {
+            buffer.write_usize(c)?;
+            buffer.write_field(f)?;
+        }
+        Ok(())
+    }
 
-        res
+    pub fn from_buffer(buffer: &mut Buffer) -> IoResult<Self> {
+        let read_linear_combination = |buffer: &mut Buffer| -> IoResult<Vec<(usize, F)>> {
+            read_vec(buffer, |buffer| {
+                Ok((buffer.read_usize()?, buffer.read_field()?))
+            })
+        };
+        let linear_combination = read_linear_combination(buffer)?;
+        let next_row_linear_combination = read_linear_combination(buffer)?;
+        let constant = buffer.read_field()?;
+        let next_next_row_linear_combination = read_linear_combination(buffer)?;
+        Ok(Self {
+            linear_combination,
+            next_row_linear_combination,
+            next_next_row_linear_combination,
+            constant,
+        })
     }
+}
 
-    /// Returns all the Z cross-table-lookup polynomials.
-    pub(crate) fn ctl_z_polys(&self) -> Vec<PolynomialValues<F>> {
+impl<F: RichField> TableWithColumns<F> {
+    pub fn to_buffer<W: Write>(&self, buffer: &mut W) -> IoResult<()> {
+        buffer.write_usize(self.table as usize)?;
+        buffer.write_usize(self.columns.len())?;
+        for col in &self.columns {
+            col.to_buffer(buffer)?;
+        }
+        buffer.write_bool(self.filter.is_some())?;
+        if let Some(filter) = &self.filter {
+            filter.to_buffer(buffer)?;
+        }
+        buffer.write_bool(self.multiplicity.is_some())?;
+        if let Some(multiplicity) = &self.multiplicity {
+            multiplicity.to_buffer(buffer)?;
+        }
+        Ok(())
+    }
+
+    pub fn from_buffer(buffer: &mut Buffer) -> IoResult<Self> {
+        let table = *Table::all().get(buffer.read_usize()?).ok_or(IoError)?;
+        let columns = read_vec(buffer, Column::from_buffer)?;
+        let filter = if buffer.read_bool()? {
+            Some(Filter::from_buffer(buffer)?)
+        } else {
+            None
+        };
+        let multiplicity = if buffer.read_bool()? {
+            Some(Column::from_buffer(buffer)?)
+        } else {
+            None
+        };
+        Ok(Self {
+            table,
+            columns,
+            filter,
+            multiplicity,
+        })
+    }
+}
+
+/// The version of the `CrossTableLookup::to_buffer` format, written first. To be bumped on any
+/// layout change.
+pub const CTL_FORMAT_VERSION: u8 = 1;
+
+impl<F: RichField> CrossTableLookup<F> {
+    pub fn to_buffer<W: Write>(&self, buffer: &mut W) -> IoResult<()> {
+        buffer.write_u8(CTL_FORMAT_VERSION)?;
+        buffer.write_usize(self.looking_tables.len())?;
+        for table in &self.looking_tables {
+            table.to_buffer(buffer)?;
+        }
+        buffer.write_usize(self.looked_tables.len())?;
+        for table in &self.looked_tables {
+            table.to_buffer(buffer)?;
+        }
+        buffer.write_bool(self.kind == CtlKind::LogUp)?;
+        Ok(())
+    }
+
+    pub fn from_buffer(buffer: &mut Buffer) -> IoResult<Self> {
+        if buffer.read_u8()? != CTL_FORMAT_VERSION {
+            return Err(IoError);
+        }
+        let looking_tables = read_vec(buffer, TableWithColumns::from_buffer)?;
+        let looked_tables = read_vec(buffer, TableWithColumns::from_buffer)?;
+        let kind = if buffer.read_bool()? {
+            CtlKind::LogUp
+        } else {
+            CtlKind::GrandProduct
+        };
+        Ok(Self {
+            looking_tables,
+            looked_tables,
+            kind,
+            name: None,
+        })
+    }
+}
+
+/// Cross-table lookup data for one table.
+#[derive(Clone, Default)]
+pub struct CtlData<'a, F: Field> {
+    pub(crate) zs_columns: Vec<CtlZData<'a, F>>,
+}
+
+/// Cross-table lookup data associated with one Z(x) polynomial.
+/// One Z(x) polynomial can be associated to multiple tables,
+/// built from the same STARK.
+#[derive(Clone)]
+pub(crate) struct CtlZData<'a, F: Field> {
+    /// Helper columns to verify the Z polynomial values.
+    pub(crate) helper_columns: Vec<PolynomialValues<F>>,
+    pub(crate) z: PolynomialValues<F>,
+    pub(crate) challenge: GrandProductChallenge<F>,
+    /// Vector of column linear combinations for the current tables.
+    pub(crate) columns: Vec<&'a [Column<F>]>,
+    /// Vector of filter columns for the current table.
+    /// Each filter evaluates to either 1 or 0.
+    pub(crate) filter: Vec<Option<Filter<F>>>,
+}
+
+impl<F: Field> CtlData<'_, F> {
+    pub fn len(&self) -> usize {
+        self.zs_columns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.zs_columns.is_empty()
+    }
+
+    /// The total number of helper columns, over all `CtlZData`.
+    pub(crate) fn total_helper_cols(&self) -> usize {
+        self.zs_columns.iter().map(|z| z.helper_columns.len()).sum()
+    }
+
+    /// The total number of Z polynomials, i.e. the number of `CtlZData`.
+    pub(crate) fn total_z_polys(&self) -> usize {
+        self.zs_columns.len()
+    }
+
+    /// Returns all the cross-table lookup helper polynomials.
+    pub(crate) fn ctl_helper_polys(&self) -> Vec<PolynomialValues<F>> {
+        let mut res = Vec::with_capacity(self.total_helper_cols());
+        for z in &self.zs_columns {
+            res.extend(z.helper_columns.clone());
+        }
+
+        res
+    }
+
+    /// Returns all the Z cross-table-lookup polynomials.
+    pub(crate) fn ctl_z_polys(&self) -> Vec<PolynomialValues<F>> {
         let mut res = Vec::with_capacity(self.zs_columns.len());
         for z in &self.zs_columns {
             res.push(z.z.clone());
@@ -502,6 +1181,28 @@ impl<F: Field> GrandProductChallenge<F> {
     {
         reduce_with_powers(terms, FE::from_basefield(self.beta)) + FE::from_basefield(self.gamma)
     }
+
+    /// Like `combine`, with the powers `beta^0, beta^1, ...` precomputed, e.g. once for all the
+    /// rows of a trace. `powers` must have at least as many elements as `terms`.
+    pub(crate) fn combine_with_powers<'a, FE, P, T: IntoIterator<Item = &'a P>, const D2: usize>(
+        &self,
+        terms: T,
+        powers: &[FE],
+    ) -> P
+    where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>,
+    {
+        let mut terms = terms.into_iter();
+        // `powers` comes first so that `zip` doesn't consume a term left without a power.
+        let combined = powers
+            .iter()
+            .zip(terms.by_ref())
+            .map(|(&power, &term)| term * power)
+            .sum::<P>();
+        debug_assert!(terms.next().is_none(), "Fewer powers than terms");
+        combined + FE::from_basefield(self.gamma)
+    }
 }
 
 impl GrandProductChallenge<Target> {
@@ -534,7 +1235,7 @@ pub(crate) struct GrandProductChallengeSet<T: Copy + Eq + PartialEq + Debug> {
 }
 
 impl GrandProductChallengeSet<Target> {
-    pub fn to_buffer(&self, buffer: &mut Vec<u8>) -> IoResult<()> {
+    pub fn to_buffer<W: Write>(&self, buffer: &mut W) -> IoResult<()> {
         buffer.write_usize(self.challenges.len())?;
         for challenge in &self.challenges {
             buffer.write_target(challenge.beta)?;
@@ -544,14 +1245,34 @@ impl GrandProductChallengeSet<Target> {
     }
 
     pub fn from_buffer(buffer: &mut Buffer) -> IoResult<Self> {
-        let length = buffer.read_usize()?;
-        let mut challenges = Vec::with_capacity(length);
-        for _ in 0..length {
-            challenges.push(GrandProductChallenge {
+        let challenges = read_vec(buffer, |buffer| {
+            Ok(GrandProductChallenge {
                 beta: buffer.read_target()?,
                 gamma: buffer.read_target()?,
-            });
+            })
+        })?;
+
+        Ok(GrandProductChallengeSet { challenges })
+    }
+}
+
+impl<F: RichField> GrandProductChallengeSet<F> {
+    pub fn to_buffer<W: Write>(&self, buffer: &mut W) -> IoResult<()> {
+        buffer.write_usize(self.challenges.len())?;
+        for challenge in &self.challenges {
+            buffer.write_field(challenge.beta)?;
+            buffer.write_field(challenge.gamma)?;
         }
+        Ok(())
+    }
+
+    pub fn from_buffer(buffer: &mut Buffer) -> IoResult<Self> {
+        let challenges = read_vec(buffer, |buffer| {
+            Ok(GrandProductChallenge {
+                beta: buffer.read_field()?,
+                gamma: buffer.read_field()?,
+            })
+        })?;
 
         Ok(GrandProductChallengeSet { challenges })
     }
@@ -610,16 +1331,11 @@ pub(crate) fn num_ctl_helper_columns_by_table<F: Field>(
 ) -> Vec<[usize; NUM_TABLES]> {
     let mut res = vec![[0; NUM_TABLES]; ctls.len()];
     for (i, ctl) in ctls.iter().enumerate() {
-        let CrossTableLookup {
-            looking_tables,
-            looked_table: _,
-        } = ctl;
+        let CrossTableLookup { looking_tables, .. } = ctl;
         let mut num_by_table = [0; NUM_TABLES];
 
-        let grouped_lookups = looking_tables.iter().group_by(|&a| a.table);
-
-        for (table, group) in grouped_lookups.into_iter() {
-            let sum = group.count();
+        for (table, group) in group_by_table(looking_tables) {
+            let sum = group.len();
             if sum > 1 {
                 // We only need helper columns if there are more than 2 columns.
                 num_by_table[table as usize] = ceil_div_usize(sum, constraint_degree - 1);
@@ -638,155 +1354,193 @@ pub(crate) fn cross_table_lookup_data<'a, F: RichField, const D: usize>(
     constraint_degree: usize,
 ) -> [CtlData<'a, F>; NUM_TABLES] {
     let mut ctl_data_per_table = [0; NUM_TABLES].map(|_| CtlData::default());
-    for CrossTableLookup {
+    for ctl in cross_table_lookups {
+        log::debug!(
+            "Processing CTL for {:?}",
+            ctl.looked_tables.iter().map(|twc| twc.table).collect_vec()
+        );
+        // `collect` preserves the order of the challenges, so the Z polynomials are pushed, and
+        // later committed and observed, in the same order as with a serial loop.
+        let zs_by_challenge = ctl_challenges
+            .challenges
+            .par_iter()
+            .map(|&challenge| {
+                ctl_zs_for_challenge(trace_poly_values, ctl, challenge, constraint_degree)
+            })
+            .collect::<Vec<_>>();
+        for (table, z_data) in zs_by_challenge.into_iter().flatten() {
+            ctl_data_per_table[table].zs_columns.push(z_data);
+        }
+    }
+    check_ctl_data_sizes(
+        &ctl_data_per_table,
+        cross_table_lookups,
+        ctl_challenges.challenges.len(),
+        constraint_degree,
+    );
+    ctl_data_per_table
+}
+
+/// Panics if the number of helper columns or Z polynomials of some table differs from what
+/// `CrossTableLookup::num_ctl_helpers_zs_all` predicts, which is what the verifiers expect.
+fn check_ctl_data_sizes<F: Field>(
+    ctl_data_per_table: &[CtlData<F>; NUM_TABLES],
+    cross_table_lookups: &[CrossTableLookup<F>],
+    num_challenges: usize,
+    constraint_degree: usize,
+) {
+    for (table, ctl_data) in Table::all().into_iter().zip(ctl_data_per_table) {
+        let (num_helpers, num_zs, _) = CrossTableLookup::num_ctl_helpers_zs_all(
+            cross_table_lookups,
+            table,
+            num_challenges,
+            constraint_degree,
+        );
+        assert_eq!(
+            (ctl_data.total_helper_cols(), ctl_data.total_z_polys()),
+            (num_helpers, num_zs),
+            "Table {table:?}: unexpected number of CTL (helper columns, Z polynomials)"
+        );
+    }
+}
+
+/// Computes the `CtlZData` of one cross-table lookup for one challenge, as `(table, data)` pairs:
+/// one per looking `Table`, in order of first appearance, followed by one per looked table.
+fn ctl_zs_for_challenge<'a, F: Field>(
+    trace_poly_values: &[Vec<PolynomialValues<F>>; NUM_TABLES],
+    ctl: &'a CrossTableLookup<F>,
+    challenge: GrandProductChallenge<F>,
+    constraint_degree: usize,
+) -> Vec<(usize, CtlZData<'a, F>)> {
+    let CrossTableLookup {
         looking_tables,
-        looked_table,
-    } in cross_table_lookups
-    {
-        log::debug!("Processing CTL for {:?}", looked_table.table);
-        for &challenge in &ctl_challenges.challenges {
-            let helper_zs_looking = ctl_helper_zs_cols(
-                trace_poly_values,
-                looking_tables.clone(),
-                challenge,
-                constraint_degree,
-            );
+        looked_tables,
+        kind,
+        ..
+    } = ctl;
+    let helper_zs_looking = match kind {
+        CtlKind::GrandProduct => ctl_helper_zs_cols(
+            trace_poly_values,
+            looking_tables,
+            challenge,
+            constraint_degree,
+        ),
+        CtlKind::LogUp => logup_helper_cols(
+            trace_poly_values,
+            looking_tables,
+            challenge,
+            constraint_degree,
+        ),
+    };
 
-            let z_looked = partial_sums(
+    let mut res = Vec::with_capacity(helper_zs_looking.len() + looked_tables.len());
+    for (table, helpers_zs) in helper_zs_looking {
+        let num_helpers = helpers_zs.len() - 1;
+        let count = looking_tables
+            .iter()
+            .filter(|looking_table| looking_table.table as usize == table)
+            .count();
+        let cols_filts = looking_tables.iter().filter_map(|looking_table| {
+            if looking_table.table as usize == table {
+                Some((&looking_table.columns, looking_table.ctl_filter(*kind)))
+            } else {
+                None
+            }
+        });
+        let mut columns = Vec::with_capacity(count);
+        let mut filter = Vec::with_capacity(count);
+        for (col, filt) in cols_filts {
+            columns.push(&col[..]);
+            filter.push(filt);
+        }
+        res.push((
+            table,
+            CtlZData {
+                helper_columns: helpers_zs[..num_helpers].to_vec(),
+                z: helpers_zs[num_helpers].clone(),
+                challenge,
+                columns,
+                filter,
+            },
+        ));
+    }
+    for looked_table in looked_tables {
+        let mut z_looked = match kind {
+            CtlKind::GrandProduct => partial_sums(
                 &trace_poly_values[looked_table.table as usize],
                 &[(&looked_table.columns, &looked_table.filter)],
                 challenge,
                 constraint_degree,
-            );
-
-            for (table, helpers_zs) in helper_zs_looking {
-                let num_helpers = helpers_zs.len() - 1;
-                let count = looking_tables
-                    .iter()
-                    .filter(|looking_table| looking_table.table as usize == table)
-                    .count();
-                let cols_filts = looking_tables.iter().filter_map(|looking_table| {
-                    if looking_table.table as usize == table {
-                        Some((&looking_table.columns, &looking_table.filter))
-                    } else {
-                        None
-                    }
-                });
-                let mut columns = Vec::with_capacity(count);
-                let mut filter = Vec::with_capacity(count);
-                for (col, filt) in cols_filts {
-                    columns.push(&col[..]);
-                    filter.push(filt.clone());
-                }
-                ctl_data_per_table[table].zs_columns.push(CtlZData {
-                    helper_columns: helpers_zs[..num_helpers].to_vec(),
-                    z: helpers_zs[num_helpers].clone(),
+                false,
+            ),
+            CtlKind::LogUp => {
+                logup_helper_cols(
+                    trace_poly_values,
+                    std::slice::from_ref(looked_table),
                     challenge,
-                    columns,
-                    filter,
-                });
+                    constraint_degree,
+                )
+                .swap_remove(0)
+                .1
             }
-            // There is no helper column for the looking table.
-            let looked_poly = z_looked[0].clone();
-            ctl_data_per_table[looked_table.table as usize]
-                .zs_columns
-                .push(CtlZData {
-                    helper_columns: vec![],
-                    z: looked_poly,
-                    challenge,
-                    columns: vec![&looked_table.columns[..]],
-                    filter: vec![looked_table.filter.clone()],
-                });
-        }
+        };
+        // There is no helper column for a looked table.
+        res.push((
+            looked_table.table as usize,
+            CtlZData {
+                helper_columns: vec![],
+                z: z_looked.swap_remove(0),
+                challenge,
+                columns: vec![&looked_table.columns[..]],
+                filter: vec![looked_table.ctl_filter(*kind)],
+            },
+        ));
     }
-    ctl_data_per_table
+    res
 }
 
 type ColumnFilter<'a, F> = (&'a [Column<F>], &'a Option<Filter<F>>);
 
 /// Given a STARK's trace, and the data associated to one lookup (either CTL or range check),
 /// returns the associated helper polynomials.
+///
+/// Each helper column is `\sum_i f_i / combine_i` over a chunk of `constraint_degree - 1`
+/// lookups. Unless `weighted`, the filters `f_i` must be binary. With `weighted`, they are
+/// multiplicities, e.g. for `CtlKind::LogUp`, and may take any value.
 pub(crate) fn get_helper_cols<F: Field>(
     trace: &[PolynomialValues<F>],
     degree: usize,
     columns_filters: &[ColumnFilter<F>],
     challenge: GrandProductChallenge<F>,
     constraint_degree: usize,
+    weighted: bool,
 ) -> Vec<PolynomialValues<F>> {
     let num_helper_columns = ceil_div_usize(columns_filters.len(), constraint_degree - 1);
 
     let mut helper_columns = Vec::with_capacity(num_helper_columns);
 
+    // The powers of `beta` are shared by all rows of all looking tables.
+    let max_num_columns = columns_filters
+        .iter()
+        .map(|(columns, _)| columns.len())
+        .max()
+        .unwrap_or(0);
+    let powers = challenge.beta.powers().take(max_num_columns).collect_vec();
+
     for mut cols_filts in &columns_filters.iter().chunks(constraint_degree - 1) {
         let (first_col, first_filter) = cols_filts.next().unwrap();
-
-        let mut filter_col = Vec::with_capacity(degree);
-        let first_combined = (0..degree)
-            .map(|d| {
-                let f = if let Some(filter) = first_filter {
-                    let f = filter.eval_table(trace, d);
-                    filter_col.push(f);
-                    f
-                } else {
-                    filter_col.push(F::ONE);
-                    F::ONE
-                };
-                if f.is_one() {
-                    let evals = first_col
-                        .iter()
-                        .map(|c| c.eval_table(trace, d))
-                        .collect::<Vec<F>>();
-                    challenge.combine(evals.iter())
-                } else {
-                    assert_eq!(f, F::ZERO, "Non-binary filter?");
-                    // Dummy value. Cannot be zero since it will be batch-inverted.
-                    F::ONE
-                }
-            })
-            .collect::<Vec<F>>();
-
-        let mut acc = F::batch_multiplicative_inverse(&first_combined);
-        for d in 0..degree {
-            if filter_col[d].is_zero() {
-                acc[d] = F::ZERO;
-            }
-        }
-
+        let mut acc = filtered_inverses(
+            trace,
+            degree,
+            first_col,
+            first_filter,
+            challenge,
+            &powers,
+            weighted,
+        );
         for (col, filt) in cols_filts {
-            let mut filter_col = Vec::with_capacity(degree);
-            let mut combined = (0..degree)
-                .map(|d| {
-                    let f = if let Some(filter) = filt {
-                        let f = filter.eval_table(trace, d);
-                        filter_col.push(f);
-                        f
-                    } else {
-                        filter_col.push(F::ONE);
-                        F::ONE
-                    };
-                    if f.is_one() {
-                        let evals = col
-                            .iter()
-                            .map(|c| c.eval_table(trace, d))
-                            .collect::<Vec<F>>();
-                        challenge.combine(evals.iter())
-                    } else {
-                        assert_eq!(f, F::ZERO, "Non-binary filter?");
-                        // Dummy value. Cannot be zero since it will be batch-inverted.
-                        F::ONE
-                    }
-                })
-                .collect::<Vec<F>>();
-
-            combined = F::batch_multiplicative_inverse(&combined);
-
-            for d in 0..degree {
-                if filter_col[d].is_zero() {
-                    combined[d] = F::ZERO;
-                }
-            }
-
-            batch_add_inplace(&mut acc, &combined);
+            let terms = filtered_inverses(trace, degree, col, filt, challenge, &powers, weighted);
+            batch_add_inplace(&mut acc, &terms);
         }
 
         helper_columns.push(acc.into());
@@ -796,22 +1550,102 @@ pub(crate) fn get_helper_cols<F: Field>(
     helper_columns
 }
 
+/// The values `f / combine(columns)` on every row, where `f` is the value of `filter`, or 1
+/// without filter. They are zero where `f` is, without combining the columns there. Unless
+/// `weighted`, panics on a filter value other than 0 or 1.
+fn filtered_inverses<F: Field>(
+    trace: &[PolynomialValues<F>],
+    degree: usize,
+    columns: &[Column<F>],
+    filter: &Option<Filter<F>>,
+    challenge: GrandProductChallenge<F>,
+    powers: &[F],
+    weighted: bool,
+) -> Vec<F> {
+    let filter_col = (0..degree)
+        .map(|d| match filter {
+            Some(filter) => filter.eval_table(trace, d),
+            None => F::ONE,
+        })
+        .collect::<Vec<F>>();
+    let combined = filter_col
+        .iter()
+        .enumerate()
+        .map(|(d, &f)| {
+            if f.is_zero() {
+                // Dummy value. Cannot be zero since it will be batch-inverted.
+                return F::ONE;
+            }
+            assert!(weighted || f.is_one(), "Non-binary filter?");
+            let evals = columns
+                .iter()
+                .map(|c| c.eval_table(trace, d))
+                .collect::<Vec<F>>();
+            challenge.combine_with_powers(evals.iter(), powers)
+        })
+        .collect::<Vec<F>>();
+
+    let mut terms = F::batch_multiplicative_inverse(&combined);
+    for (term, f) in terms.iter_mut().zip(filter_col) {
+        *term *= f;
+    }
+    terms
+}
+
+/// Groups adjacent `tables` of the same `Table`. The looking tables of a `Table` must be
+/// adjacent, so that they share their helper columns and Z polynomial.
+fn group_by_table<F: Field>(
+    tables: &[TableWithColumns<F>],
+) -> Vec<(Table, Vec<&TableWithColumns<F>>)> {
+    tables
+        .iter()
+        .group_by(|twc| twc.table)
+        .into_iter()
+        .map(|(table, group)| (table, group.collect()))
+        .collect()
+}
+
+/// The first `Table` of `tables` that appears again after another `Table`, if any.
+fn ungrouped_table<F: Field>(tables: &[TableWithColumns<F>]) -> Option<Table> {
+    let groups = group_by_table(tables);
+    groups
+        .iter()
+        .enumerate()
+        .find(|(i, (table, _))| groups[..*i].iter().any(|(t, _)| t == table))
+        .map(|(_, (table, _))| *table)
+}
+
+/// The distinct `Table`s of `looking_tables`, in the order of `group_by_table`, which is the
+/// order of their Z polynomials. The verifier must read the Z openings in this order.
+fn looking_table_order<F: Field>(looking_tables: &[TableWithColumns<F>]) -> Vec<usize> {
+    group_by_table(looking_tables)
+        .into_iter()
+        .map(|(table, _)| table as usize)
+        .collect()
+}
+
 /// Computes helper columns and Z polynomials for all looking tables
 /// of one cross-table lookup (i.e. for one looked table).
 fn ctl_helper_zs_cols<F: Field>(
     all_stark_traces: &[Vec<PolynomialValues<F>>; NUM_TABLES],
-    looking_tables: Vec<TableWithColumns<F>>,
+    looking_tables: &[TableWithColumns<F>],
     challenge: GrandProductChallenge<F>,
     constraint_degree: usize,
 ) -> Vec<(usize, Vec<PolynomialValues<F>>)> {
-    let grouped_lookups = looking_tables.iter().group_by(|a| a.table);
-
-    grouped_lookups
+    let grouped_lookups = group_by_table(looking_tables)
         .into_iter()
         .map(|(table, group)| {
             let columns_filters = group
+                .into_iter()
                 .map(|table| (&table.columns[..], &table.filter))
                 .collect::<Vec<(&[Column<F>], &Option<Filter<F>>)>>();
+            (table, columns_filters)
+        })
+        .collect::<Vec<_>>();
+
+    grouped_lookups
+        .into_par_iter()
+        .map(|(table, columns_filters)| {
             (
                 table as usize,
                 partial_sums(
@@ -819,12 +1653,54 @@ fn ctl_helper_zs_cols<F: Field>(
                     &columns_filters,
                     challenge,
                     constraint_degree,
+                    false,
                 ),
             )
         })
         .collect::<Vec<(usize, Vec<PolynomialValues<F>>)>>()
 }
 
+/// Like `ctl_helper_zs_cols`, but for the tables of a `CtlKind::LogUp` cross-table lookup:
+/// the rows of tables with a multiplicity column `m` are weighted by it, so the Z polynomial
+/// accumulates `\sum_i m_i / combine_i` rather than `\sum_i filter_i / combine_i`.
+fn logup_helper_cols<F: Field>(
+    all_stark_traces: &[Vec<PolynomialValues<F>>; NUM_TABLES],
+    tables: &[TableWithColumns<F>],
+    challenge: GrandProductChallenge<F>,
+    constraint_degree: usize,
+) -> Vec<(usize, Vec<PolynomialValues<F>>)> {
+    let grouped_lookups = group_by_table(tables)
+        .into_iter()
+        .map(|(table, group)| {
+            let weighted = group
+                .into_iter()
+                .map(|twc| (&twc.columns[..], twc.ctl_filter(CtlKind::LogUp)))
+                .collect::<Vec<_>>();
+            (table, weighted)
+        })
+        .collect::<Vec<_>>();
+
+    grouped_lookups
+        .into_par_iter()
+        .map(|(table, weighted)| {
+            let columns_filters = weighted
+                .iter()
+                .map(|(columns, filter)| (*columns, filter))
+                .collect::<Vec<ColumnFilter<F>>>();
+            (
+                table as usize,
+                partial_sums(
+                    &all_stark_traces[table as usize],
+                    &columns_filters,
+                    challenge,
+                    constraint_degree,
+                    true,
+                ),
+            )
+        })
+        .collect()
+}
+
 /// Computes the cross-table lookup partial sums for one table and given column linear combinations.
 /// `trace` represents the trace values for the given table.
 /// `columns` is a vector of column linear combinations to evaluate. Each element in the vector represents columns that need to be combined.
@@ -837,18 +1713,26 @@ fn ctl_helper_zs_cols<F: Field>(
 /// column: h = \sum_i 1/(v_i).
 ///
 /// The sum is updated: `s += \sum h_i`, and is pushed to the vector of partial sums `z``.
+/// With `weighted`, the filters are multiplicities rather than selectors, see `get_helper_cols`.
 /// Returns the helper columns and `z`.
 fn partial_sums<F: Field>(
     trace: &[PolynomialValues<F>],
     columns_filters: &[ColumnFilter<F>],
     challenge: GrandProductChallenge<F>,
     constraint_degree: usize,
+    weighted: bool,
 ) -> Vec<PolynomialValues<F>> {
     let degree = trace[0].len();
     let mut z = Vec::with_capacity(degree);
 
-    let mut helper_columns =
-        get_helper_cols(trace, degree, columns_filters, challenge, constraint_degree);
+    let mut helper_columns = get_helper_cols(
+        trace,
+        degree,
+        columns_filters,
+        challenge,
+        constraint_degree,
+        weighted,
+    );
 
     let x = helper_columns
         .iter()
@@ -891,6 +1775,7 @@ impl<'a, F: RichField + Extendable<D>, const D: usize>
 {
     pub(crate) fn from_proofs<C: GenericConfig<D, F = F>>(
         proofs: &[StarkProofWithMetadata<F, C, D>; NUM_TABLES],
+        active_tables: &[bool; NUM_TABLES],
         cross_table_lookups: &'a [CrossTableLookup<F>],
         ctl_challenges: &'a GrandProductChallengeSet<F>,
         num_lookup_columns: &[usize; NUM_TABLES],
@@ -903,11 +1788,16 @@ impl<'a, F: RichField + Extendable<D>, const D: usize>
             }
         }
 
-        // Get all cross-table lookup polynomial openings for each STARK proof.
+        // Get all cross-table lookup polynomial openings for each STARK proof. Inactive tables
+        // have empty proofs, and get no `CtlCheckVars`.
         let ctl_zs = proofs
             .iter()
             .zip(num_lookup_columns)
-            .map(|(p, &num_lookup)| {
+            .zip(active_tables)
+            .map(|((p, &num_lookup), &active)| {
+                if !active {
+                    return vec![];
+                }
                 let openings = &p.proof.openings;
 
                 let ctl_zs = &openings.auxiliary_polys[num_lookup..];
@@ -923,7 +1813,9 @@ impl<'a, F: RichField + Extendable<D>, const D: usize>
         for (
             CrossTableLookup {
                 looking_tables,
-                looked_table,
+                looked_tables,
+                kind,
+                ..
             },
             num_ctls,
         ) in cross_table_lookups.iter().zip(num_helper_ctl_columns)
@@ -931,15 +1823,12 @@ impl<'a, F: RichField + Extendable<D>, const D: usize>
             for &challenges in &ctl_challenges.challenges {
                 // Group looking tables by `Table`, since we bundle the looking tables taken from the same `Table` together thanks to helper columns.
                 // We want to only iterate on each `Table` once.
-                let mut filtered_looking_tables =
-                    Vec::with_capacity(min(looking_tables.len(), NUM_TABLES));
-                for table in looking_tables {
-                    if !filtered_looking_tables.contains(&(table.table as usize)) {
-                        filtered_looking_tables.push(table.table as usize);
-                    }
-                }
+                let filtered_looking_tables = looking_table_order(looking_tables);
 
                 for &table in filtered_looking_tables.iter() {
+                    if !active_tables[table] {
+                        continue;
+                    }
                     // We have first all the helper polynomials, then all the z polynomials.
                     let (looking_z, looking_z_next) =
                         ctl_zs[table][total_num_helper_cols_by_table[table] + z_indices[table]];
@@ -950,7 +1839,7 @@ impl<'a, F: RichField + Extendable<D>, const D: usize>
                         .count();
                     let cols_filts = looking_tables.iter().filter_map(|looking_table| {
                         if looking_table.table as usize == table {
-                            Some((&looking_table.columns, &looking_table.filter))
+                            Some((&looking_table.columns, looking_table.ctl_filter(*kind)))
                         } else {
                             None
                         }
@@ -959,7 +1848,7 @@ impl<'a, F: RichField + Extendable<D>, const D: usize>
                     let mut filter = Vec::with_capacity(count);
                     for (col, filt) in cols_filts {
                         columns.push(&col[..]);
-                        filter.push(filt.clone());
+                        filter.push(filt);
                     }
                     let helper_columns = ctl_zs[table]
                         [start_indices[table]..start_indices[table] + num_ctls[table]]
@@ -980,24 +1869,29 @@ impl<'a, F: RichField + Extendable<D>, const D: usize>
                     });
                 }
 
-                let (looked_z, looked_z_next) = ctl_zs[looked_table.table as usize]
-                    [total_num_helper_cols_by_table[looked_table.table as usize]
-                        + z_indices[looked_table.table as usize]];
-
-                z_indices[looked_table.table as usize] += 1;
-
-                let columns = vec![&looked_table.columns[..]];
-                let filter = vec![looked_table.filter.clone()];
-                ctl_vars_per_table[looked_table.table as usize].push(Self {
-                    helper_columns: vec![],
-                    local_z: *looked_z,
-                    next_z: *looked_z_next,
-                    challenges,
-                    columns,
-                    filter,
-                });
-            }
-        }
+                for looked_table in looked_tables {
+                    let table = looked_table.table as usize;
+                    if !active_tables[table] {
+                        continue;
+                    }
+                    let (looked_z, looked_z_next) =
+                        ctl_zs[table][total_num_helper_cols_by_table[table] + z_indices[table]];
+
+                    z_indices[table] += 1;
+
+                    let columns = vec![&looked_table.columns[..]];
+                    let filter = vec![looked_table.ctl_filter(*kind)];
+                    ctl_vars_per_table[table].push(Self {
+                        helper_columns: vec![],
+                        local_z: *looked_z,
+                        next_z: *looked_z_next,
+                        challenges,
+                        columns,
+                        filter,
+                    });
+                }
+            }
+        }
         ctl_vars_per_table
     }
 }
@@ -1023,36 +1917,22 @@ pub(crate) fn eval_helper_columns<F, FE, P, const D: usize, const D2: usize>(
                 &filter[(constraint_degree - 1) * j..(constraint_degree - 1) * j + chunk.len()];
             let h = helper_columns[j];
 
-            match chunk.len() {
-                2 => {
-                    let combin0 = challenges.combine(&chunk[0]);
-                    let combin1 = challenges.combine(chunk[1].iter());
-
-                    let f0 = if let Some(filter0) = &fs[0] {
-                        filter0.eval_filter(local_values, next_values)
-                    } else {
-                        P::ONES
-                    };
-                    let f1 = if let Some(filter1) = &fs[1] {
-                        filter1.eval_filter(local_values, next_values)
-                    } else {
-                        P::ONES
-                    };
-
-                    consumer.constraint(combin1 * combin0 * h - f0 * combin1 - f1 * combin0);
-                }
-                1 => {
-                    let combin = challenges.combine(&chunk[0]);
-                    let f0 = if let Some(filter1) = &fs[0] {
-                        filter1.eval_filter(local_values, next_values)
-                    } else {
-                        P::ONES
-                    };
-                    consumer.constraint(combin * h - f0);
-                }
-
-                _ => todo!("Allow other constraint degrees"),
+            // We accumulate `sum_i f_i / combin_i` as a fraction `numerator / denominator`,
+            // so that the constraint is `h * prod_i combin_i - sum_i f_i * prod_{k != i} combin_k`.
+            let mut numerator = P::ZEROS;
+            let mut denominator = P::ONES;
+            for (col, f) in chunk.iter().zip(fs) {
+                let combin = challenges.combine(col.iter());
+                let f = if let Some(filter) = f {
+                    filter.eval_filter(local_values, next_values)
+                } else {
+                    P::ONES
+                };
+                numerator = numerator * combin + f * denominator;
+                denominator *= combin;
             }
+
+            consumer.constraint(denominator * h - numerator);
         }
     }
 }
@@ -1188,7 +2068,9 @@ impl<'a, F: Field, const D: usize> CtlCheckVarsTarget<F, D> {
             i,
             CrossTableLookup {
                 looking_tables,
-                looked_table,
+                looked_tables,
+                kind,
+                ..
             },
         ) in cross_table_lookups.iter().enumerate()
         {
@@ -1199,7 +2081,7 @@ impl<'a, F: Field, const D: usize> CtlCheckVarsTarget<F, D> {
                     .count();
                 let cols_filts = looking_tables.iter().filter_map(|looking_table| {
                     if looking_table.table == table {
-                        Some((&looking_table.columns, &looking_table.filter))
+                        Some((&looking_table.columns, looking_table.ctl_filter(*kind)))
                     } else {
                         None
                     }
@@ -1209,7 +2091,7 @@ impl<'a, F: Field, const D: usize> CtlCheckVarsTarget<F, D> {
                     let mut filter = Vec::with_capacity(count);
                     for (col, filt) in cols_filts {
                         columns.push(col.clone());
-                        filter.push(filt.clone());
+                        filter.push(filt);
                     }
                     let (looking_z, looking_z_next) = ctl_zs[total_num_helper_columns + z_index];
                     let helper_columns = ctl_zs
@@ -1230,12 +2112,12 @@ impl<'a, F: Field, const D: usize> CtlCheckVarsTarget<F, D> {
                     });
                 }
 
-                if looked_table.table == table {
+                for looked_table in looked_tables.iter().filter(|twc| twc.table == table) {
                     let (looked_z, looked_z_next) = ctl_zs[total_num_helper_columns + z_index];
                     z_index += 1;
 
                     let columns = vec![looked_table.columns.clone()];
-                    let filter = vec![looked_table.filter.clone()];
+                    let filter = vec![looked_table.ctl_filter(*kind)];
                     ctl_vars.push(Self {
                         helper_columns: vec![],
                         local_z: *looked_z,
@@ -1272,42 +2154,22 @@ pub(crate) fn eval_helper_columns_circuit<F: RichField + Extendable<D>, const D:
             let h = helper_columns[j];
 
             let one = builder.one_extension();
-            match chunk.len() {
-                2 => {
-                    let combin0 = challenges.combine_circuit(builder, &chunk[0]);
-                    let combin1 = challenges.combine_circuit(builder, &chunk[1]);
-
-                    let f0 = if let Some(filter0) = &fs[0] {
-                        filter0.eval_filter_circuit(builder, local_values, next_values)
-                    } else {
-                        one
-                    };
-                    let f1 = if let Some(filter1) = &fs[1] {
-                        filter1.eval_filter_circuit(builder, local_values, next_values)
-                    } else {
-                        one
-                    };
-
-                    let constr = builder.mul_sub_extension(combin0, h, f0);
-                    let constr = builder.mul_extension(constr, combin1);
-                    let f1_constr = builder.mul_extension(f1, combin0);
-                    let constr = builder.sub_extension(constr, f1_constr);
-
-                    consumer.constraint(builder, constr);
-                }
-                1 => {
-                    let combin = challenges.combine_circuit(builder, &chunk[0]);
-                    let f0 = if let Some(filter1) = &fs[0] {
-                        filter1.eval_filter_circuit(builder, local_values, next_values)
-                    } else {
-                        one
-                    };
-                    let constr = builder.mul_sub_extension(combin, h, f0);
-                    consumer.constraint(builder, constr);
-                }
-
-                _ => todo!("Allow other constraint degrees"),
+            let mut numerator = builder.zero_extension();
+            let mut denominator = one;
+            for (col, f) in chunk.iter().zip(fs) {
+                let combin = challenges.combine_circuit(builder, col);
+                let f = if let Some(filter) = f {
+                    filter.eval_filter_circuit(builder, local_values, next_values)
+                } else {
+                    one
+                };
+                let f_denominator = builder.mul_extension(f, denominator);
+                numerator = builder.mul_add_extension(numerator, combin, f_denominator);
+                denominator = builder.mul_extension(denominator, combin);
             }
+
+            let constr = builder.mul_sub_extension(denominator, h, numerator);
+            consumer.constraint(builder, constr);
         }
     }
 }
@@ -1422,27 +2284,33 @@ pub(crate) fn verify_cross_table_lookups<F: RichField + Extendable<D>, const D:
         index,
         CrossTableLookup {
             looking_tables,
-            looked_table,
+            looked_tables,
+            name,
+            ..
         },
     ) in cross_table_lookups.iter().enumerate()
     {
-        let mut filtered_looking_tables = vec![];
-        for table in looking_tables {
-            if !filtered_looking_tables.contains(&(table.table as usize)) {
-                filtered_looking_tables.push(table.table as usize);
-            }
-        }
-        for _c in 0..config.num_challenges {
+        let filtered_looking_tables = looking_table_order(looking_tables);
+        for c in 0..config.num_challenges {
             let looking_zs_sum = filtered_looking_tables
                 .iter()
                 .map(|&table| *ctl_zs_openings[table].next().unwrap())
                 .sum::<F>();
 
-            let looked_z = *ctl_zs_openings[looked_table.table as usize].next().unwrap();
+            let looked_zs_sum = looked_tables
+                .iter()
+                .map(|twc| *ctl_zs_openings[twc.table as usize].next().unwrap())
+                .sum::<F>();
             ensure!(
-                looking_zs_sum == looked_z,
-                "Cross-table lookup {:?} verification failed.",
-                index
+                looking_zs_sum == looked_zs_sum,
+                "Cross-table lookup {} verification failed for challenge {}: looking tables {:?}, looked tables {:?}.",
+                ctl_label(index, name),
+                c,
+                filtered_looking_tables
+                    .iter()
+                    .map(|&table| Table::all()[table])
+                    .collect::<Vec<_>>(),
+                looked_tables.iter().map(|twc| twc.table).collect::<Vec<_>>()
             );
         }
     }
@@ -1460,15 +2328,11 @@ pub(crate) fn verify_cross_table_lookups_circuit<F: RichField + Extendable<D>, c
     let mut ctl_zs_openings = ctl_zs_first.iter().map(|v| v.iter()).collect::<Vec<_>>();
     for CrossTableLookup {
         looking_tables,
-        looked_table,
+        looked_tables,
+        ..
     } in cross_table_lookups.into_iter()
     {
-        let mut filtered_looking_tables = vec![];
-        for table in looking_tables {
-            if !filtered_looking_tables.contains(&(table.table as usize)) {
-                filtered_looking_tables.push(table.table as usize);
-            }
-        }
+        let filtered_looking_tables = looking_table_order(looking_tables);
         for _c in 0..inner_config.num_challenges {
             let looking_zs_sum = builder.add_many(
                 filtered_looking_tables
@@ -1476,106 +2340,368 @@ pub(crate) fn verify_cross_table_lookups_circuit<F: RichField + Extendable<D>, c
                     .map(|&table| *ctl_zs_openings[table].next().unwrap()),
             );
 
-            let looked_z = *ctl_zs_openings[looked_table.table as usize].next().unwrap();
-            builder.connect(looked_z, looking_zs_sum);
+            let looked_zs_sum = builder.add_many(
+                looked_tables
+                    .iter()
+                    .map(|twc| *ctl_zs_openings[twc.table as usize].next().unwrap()),
+            );
+            builder.connect(looked_zs_sum, looking_zs_sum);
         }
     }
     debug_assert!(ctl_zs_openings.iter_mut().all(|iter| iter.next().is_none()));
 }
 
-#[cfg(any(feature = "test", test))]
-pub(crate) mod testutils {
-    use super::*;
-    use plonky2::plonk::config::PoseidonGoldilocksConfig;
-    use std::collections::HashMap;
+/// Formats a CTL as `#index`, followed by its name if it has one.
+fn ctl_label(index: usize, name: &Option<String>) -> String {
+    match name {
+        Some(name) => format!("#{index} ({name})"),
+        None => format!("#{index}"),
+    }
+}
 
-    type MultiSet<F> = HashMap<Vec<F>, Vec<(Table, usize)>>;
+/// An inconsistency between the traces and the cross-table lookups, see
+/// `verify_ctl_consistency`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CtlError<F: Field> {
+    /// A row appears a different number of times in the looking tables and the looked table.
+    MultiplicityMismatch {
+        ctl_index: usize,
+        ctl_name: Option<String>,
+        row: Vec<F>,
+        /// Locations `(table, row index)` of the row in the looking tables.
+        looking_locations: Vec<(Table, usize)>,
+        /// Locations `(table, row index)` of the row in the looked table.
+        looked_locations: Vec<(Table, usize)>,
+    },
+    /// A filter evaluated to a value other than 0 or 1.
+    NonBinaryFilter {
+        ctl_index: usize,
+        ctl_name: Option<String>,
+        table: Table,
+        row_index: usize,
+        value: F,
+    },
+    /// A looking table doesn't have as many columns as the first looked table.
+    ColumnCountMismatch {
+        looking_table: Table,
+        looking_len: usize,
+        looked_table: Table,
+        looked_len: usize,
+    },
+    /// A looked table after the first one doesn't have as many columns as the first looked table.
+    LookedColumnCountMismatch {
+        looked_table: Table,
+        looked_len: usize,
+        first_looked_table: Table,
+        first_looked_len: usize,
+    },
+    /// The looked table of a `CtlKind::LogUp` lookup has no multiplicity column.
+    MissingMultiplicity { table: Table },
+    /// A cross-table lookup was created without any looked table.
+    NoLookedTable,
+    /// The looking tables of `table` aren't adjacent.
+    UngroupedLookingTables { table: Table },
+    /// A multiplicity exceeds the number of rows of all tables, so it can't be the number of
+    /// times a row is looked up.
+    MultiplicityTooLarge {
+        ctl_index: usize,
+        ctl_name: Option<String>,
+        table: Table,
+        row_index: usize,
+        value: F,
+    },
+}
 
-    /// Check that the provided traces and cross-table lookups are consistent.
-    #[allow(unused)] // TODO: used later?
-    pub(crate) fn check_ctls<F: Field>(
-        trace_poly_values: &[Vec<PolynomialValues<F>>],
-        cross_table_lookups: &[CrossTableLookup<F>],
-    ) {
-        for (i, ctl) in cross_table_lookups.iter().enumerate() {
-            check_ctl(trace_poly_values, ctl, i);
+impl<F: Field> std::fmt::Display for CtlError<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CtlError::MultiplicityMismatch {
+                ctl_index,
+                ctl_name,
+                row,
+                looking_locations,
+                looked_locations,
+            } => write!(
+                f,
+                "CTL {label}:\n\
+                 Row {row:?} is present {l0} times in the looking tables, but {l1} times in the looked table.\n\
+                 Looking locations (Table, Row index): {looking_locations:?}.\n\
+                 Looked locations (Table, Row index): {looked_locations:?}.",
+                label = ctl_label(*ctl_index, ctl_name),
+                l0 = looking_locations.len(),
+                l1 = looked_locations.len(),
+            ),
+            CtlError::NonBinaryFilter {
+                ctl_index,
+                ctl_name,
+                table,
+                row_index,
+                value,
+            } => write!(
+                f,
+                "CTL {label}: non-binary filter {value:?} in table {table:?} at row {row_index}.",
+                label = ctl_label(*ctl_index, ctl_name),
+            ),
+            CtlError::ColumnCountMismatch {
+                looking_table,
+                looking_len,
+                looked_table,
+                looked_len,
+            } => write!(
+                f,
+                "Looking table {looking_table:?} has {looking_len} columns, but looked table {looked_table:?} has {looked_len}."
+            ),
+            CtlError::LookedColumnCountMismatch {
+                looked_table,
+                looked_len,
+                first_looked_table,
+                first_looked_len,
+            } => write!(
+                f,
+                "Looked table {looked_table:?} has {looked_len} columns, but the first looked table {first_looked_table:?} has {first_looked_len}."
+            ),
+            CtlError::MissingMultiplicity { table } => write!(
+                f,
+                "The looked table {table:?} of a logUp CTL has no multiplicity column."
+            ),
+            CtlError::NoLookedTable => write!(f, "A CTL must have at least one looked table."),
+            CtlError::UngroupedLookingTables { table } => write!(
+                f,
+                "The looking tables of {table:?} must be adjacent in a CTL."
+            ),
+            CtlError::MultiplicityTooLarge {
+                ctl_index,
+                ctl_name,
+                table,
+                row_index,
+                value,
+            } => write!(
+                f,
+                "CTL {label}: multiplicity {value:?} in table {table:?} at row {row_index} exceeds the number of rows of all tables.",
+                label = ctl_label(*ctl_index, ctl_name),
+            ),
         }
     }
+}
 
-    fn check_ctl<F: Field>(
-        trace_poly_values: &[Vec<PolynomialValues<F>>],
-        ctl: &CrossTableLookup<F>,
-        ctl_index: usize,
-    ) {
-        let CrossTableLookup {
-            looking_tables,
-            looked_table,
-        } = ctl;
+impl<F: Field> std::error::Error for CtlError<F> {}
 
-        // Maps `m` with `(table, i) in m[row]` iff the `i`-th row of `table` is equal to `row` and
-        // the filter is 1. Without default values, the CTL check holds iff `looking_multiset == looked_multiset`.
-        let mut looking_multiset = MultiSet::<F>::new();
-        let mut looked_multiset = MultiSet::<F>::new();
+type MultiSet<F> = HashMap<Vec<F>, Vec<(Table, usize)>>;
 
-        for table in looking_tables {
-            process_table(trace_poly_values, table, &mut looking_multiset);
-        }
-        process_table(trace_poly_values, looked_table, &mut looked_multiset);
+/// Check that the provided traces and cross-table lookups are consistent, i.e. that for each CTL,
+/// the rows of the looking tables are the rows of the looked table, with multiplicities.
+pub fn verify_ctl_consistency<F: PrimeField64>(
+    trace_poly_values: &[Vec<PolynomialValues<F>>],
+    cross_table_lookups: &[CrossTableLookup<F>],
+) -> Result<(), CtlError<F>> {
+    for (i, ctl) in cross_table_lookups.iter().enumerate() {
+        verify_ctl(trace_poly_values, ctl, i)?;
+    }
+    Ok(())
+}
 
-        let empty = &vec![];
-        // Check that every row in the looking tables appears in the looked table the same number of times.
-        for (row, looking_locations) in &looking_multiset {
-            let looked_locations = looked_multiset.get(row).unwrap_or(empty);
-            check_locations(looking_locations, looked_locations, ctl_index, row);
-        }
-        // Check that every row in the looked tables appears in the looked table the same number of times.
-        for (row, looked_locations) in &looked_multiset {
-            let looking_locations = looking_multiset.get(row).unwrap_or(empty);
-            check_locations(looking_locations, looked_locations, ctl_index, row);
-        }
+fn verify_ctl<F: PrimeField64>(
+    trace_poly_values: &[Vec<PolynomialValues<F>>],
+    ctl: &CrossTableLookup<F>,
+    ctl_index: usize,
+) -> Result<(), CtlError<F>> {
+    let CrossTableLookup {
+        looking_tables,
+        looked_tables,
+        kind,
+        name,
+    } = ctl;
+
+    // Maps `m` with `(table, i) in m[row]` iff the `i`-th row of `table` is equal to `row` and
+    // the filter is 1. Without default values, the CTL check holds iff `looking_multiset == looked_multiset`.
+    let mut looking_multiset = MultiSet::<F>::new();
+    let mut looked_multiset = MultiSet::<F>::new();
+
+    for table in looking_tables {
+        process_table(
+            trace_poly_values,
+            table,
+            *kind,
+            ctl_index,
+            name,
+            &mut looking_multiset,
+        )?;
+    }
+    for table in looked_tables {
+        process_table(
+            trace_poly_values,
+            table,
+            *kind,
+            ctl_index,
+            name,
+            &mut looked_multiset,
+        )?;
     }
 
-    fn process_table<F: Field>(
-        trace_poly_values: &[Vec<PolynomialValues<F>>],
-        table: &TableWithColumns<F>,
-        multiset: &mut MultiSet<F>,
-    ) {
-        let trace = &trace_poly_values[table.table as usize];
+    let empty = &vec![];
+    // Check that every row in the looking tables appears in the looked table the same number of times.
+    for (row, looking_locations) in &looking_multiset {
+        let looked_locations = looked_multiset.get(row).unwrap_or(empty);
+        check_locations(looking_locations, looked_locations, ctl_index, name, row)?;
+    }
+    // Check that every row in the looked tables appears in the looked table the same number of times.
+    for (row, looked_locations) in &looked_multiset {
+        let looking_locations = looking_multiset.get(row).unwrap_or(empty);
+        check_locations(looking_locations, looked_locations, ctl_index, name, row)?;
+    }
+    Ok(())
+}
+
+fn process_table<F: PrimeField64>(
+    trace_poly_values: &[Vec<PolynomialValues<F>>],
+    table: &TableWithColumns<F>,
+    kind: CtlKind,
+    ctl_index: usize,
+    ctl_name: &Option<String>,
+    multiset: &mut MultiSet<F>,
+) -> Result<(), CtlError<F>> {
+    let trace = &trace_poly_values[table.table as usize];
+    if let (CtlKind::LogUp, Some(multiplicity)) = (kind, &table.multiplicity) {
+        // A row can't be looked up more times than there are rows in all tables, which also
+        // bounds the locations below.
+        let max_multiplicity = trace_poly_values
+            .iter()
+            .map(|trace| trace.first().map_or(0, |column| column.len()))
+            .sum::<usize>() as u64;
+        // Each row is counted as many times as its multiplicity.
         for i in 0..trace[0].len() {
-            // Eval at filter column: \sum trace[lc.column].values[i] * lc.value
-            let filter = if let Some(column) = &table.filter {
-                column.eval_table(trace, i)
-            } else {
-                F::ONE
-            };
-            if filter.is_one() {
-                // Evaluate at columns \sum trace[lc.column][row] * lc.value
+            let value = multiplicity.eval_table(trace, i);
+            let multiplicity = value.to_canonical_u64();
+            if multiplicity > max_multiplicity {
+                return Err(CtlError::MultiplicityTooLarge {
+                    ctl_index,
+                    ctl_name: ctl_name.clone(),
+                    table: table.table,
+                    row_index: i,
+                    value,
+                });
+            }
+            if multiplicity > 0 {
                 let row = table
                     .columns
                     .iter()
                     .map(|c| c.eval_table(trace, i))
                     .collect::<Vec<_>>();
-                multiset.entry(row).or_default().push((table.table, i));
-            } else {
-                assert_eq!(filter, F::ZERO, "Non-binary filter?")
+                let locations = multiset.entry(row).or_default();
+                locations.extend(repeat((table.table, i)).take(multiplicity as usize));
             }
         }
+        return Ok(());
+    }
+    for i in 0..trace[0].len() {
+        // Eval at filter column: \sum trace[lc.column].values[i] * lc.value
+        let filter = if let Some(column) = &table.filter {
+            column.eval_table(trace, i)
+        } else {
+            F::ONE
+        };
+        if filter.is_one() {
+            // Evaluate at columns \sum trace[lc.column][row] * lc.value
+            let row = table
+                .columns
+                .iter()
+                .map(|c| c.eval_table(trace, i))
+                .collect::<Vec<_>>();
+            multiset.entry(row).or_default().push((table.table, i));
+        } else if !filter.is_zero() {
+            return Err(CtlError::NonBinaryFilter {
+                ctl_index,
+                ctl_name: ctl_name.clone(),
+                table: table.table,
+                row_index: i,
+                value: filter,
+            });
+        }
     }
+    Ok(())
+}
 
-    fn check_locations<F: Field>(
-        looking_locations: &[(Table, usize)],
-        looked_locations: &[(Table, usize)],
-        ctl_index: usize,
-        row: &[F],
+fn check_locations<F: Field>(
+    looking_locations: &[(Table, usize)],
+    looked_locations: &[(Table, usize)],
+    ctl_index: usize,
+    ctl_name: &Option<String>,
+    row: &[F],
+) -> Result<(), CtlError<F>> {
+    if looking_locations.len() != looked_locations.len() {
+        return Err(CtlError::MultiplicityMismatch {
+            ctl_index,
+            ctl_name: ctl_name.clone(),
+            row: row.to_vec(),
+            looking_locations: looking_locations.to_vec(),
+            looked_locations: looked_locations.to_vec(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(any(feature = "test", test))]
+pub(crate) mod testutils {
+    use super::*;
+    use plonky2::field::goldilocks_field::GoldilocksField;
+    use plonky2::field::types::Sample;
+    use plonky2::plonk::config::PoseidonGoldilocksConfig;
+
+    impl<F: Field> CtlData<'_, F> {
+        /// CTL data for proving a table on its own, e.g. in benchmarks: one all-zero helper
+        /// column and Z polynomial of length `degree` per challenge, with a filter that is
+        /// always zero.
+        pub fn fake(degree: usize, num_challenges: usize) -> Self {
+            let ctl_z_data = CtlZData {
+                helper_columns: vec![PolynomialValues::zero(degree)],
+                z: PolynomialValues::zero(degree),
+                challenge: GrandProductChallenge {
+                    beta: F::ZERO,
+                    gamma: F::ZERO,
+                },
+                columns: vec![],
+                filter: vec![Some(Filter::new_simple(Column::constant(F::ZERO)))],
+            };
+            Self {
+                zs_columns: vec![ctl_z_data; num_challenges],
+            }
+        }
+    }
+
+    /// Check that the provided traces and cross-table lookups are consistent.
+    pub(crate) fn check_ctls<F: PrimeField64>(
+        trace_poly_values: &[Vec<PolynomialValues<F>>],
+        cross_table_lookups: &[CrossTableLookup<F>],
+    ) {
+        if let Err(e) = verify_ctl_consistency(trace_poly_values, cross_table_lookups) {
+            panic!("{e}");
+        }
+    }
+
+    /// Check that every CTL column of `table` evaluates to zero on the rows where its filter
+    /// is zero. Such rows don't contribute to the lookup, but nonzero values there usually
+    /// indicate a trace generation bug.
+    #[cfg(test)]
+    pub(crate) fn assert_columns_zero_where_filter_zero<F: Field>(
+        trace: &[PolynomialValues<F>],
+        table: &TableWithColumns<F>,
     ) {
-        if looking_locations.len() != looked_locations.len() {
+        let Some(filter) = &table.filter else {
+            return;
+        };
+        let offending_rows = (0..trace[0].len())
+            .filter(|&i| {
+                filter.eval_table(trace, i).is_zero()
+                    && table
+                        .columns
+                        .iter()
+                        .any(|c| !c.eval_table(trace, i).is_zero())
+            })
+            .collect::<Vec<_>>();
+        if !offending_rows.is_empty() {
             panic!(
-                "CTL #{ctl_index}:\n\
-                 Row {row:?} is present {l0} times in the looking tables, but {l1} times in the looked table.\n\
-                 Looking locations (Table, Row index): {looking_locations:?}.\n\
-                 Looked locations (Table, Row index): {looked_locations:?}.",
-                l0 = looking_locations.len(),
-                l1 = looked_locations.len(),
+                "Table {:?}: CTL columns are nonzero on filtered-out rows {offending_rows:?}.",
+                table.table,
             );
         }
     }
@@ -1619,7 +2745,1374 @@ pub(crate) mod testutils {
         // select_f * f = trace[looking_col[i]][row] * \sum_{row=0..n, i=0..m}, where n is domain size, m is the looking_col.size,
 
         // check select_f * f \in select_t * t
-        let cross_tables = CrossTableLookup::new(lookings, looked);
-        check_ctls(&[trace_poly_values], &[cross_tables]);
+        let cross_tables = CrossTableLookup::new(lookings, looked).unwrap();
+        assert_eq!(
+            verify_ctl_consistency(&[trace_poly_values], &[cross_tables]),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_verify_ctl_consistency_mismatch() {
+        type F = <PoseidonGoldilocksConfig as GenericConfig<2>>::F;
+        let trace = vec![
+            PolynomialValues::<F>::new(vec![F::ONE, F::TWO]),
+            PolynomialValues::<F>::new(vec![F::ONE, F::ONE]),
+        ];
+        let looking = TableWithColumns::<F>::new(Table::Arithmetic, vec![Column::single(0)], None);
+        let looked = TableWithColumns::<F>::new(Table::Arithmetic, vec![Column::single(1)], None);
+        let ctl = CrossTableLookup::new_named("doubling", vec![looking], looked).unwrap();
+
+        let res = verify_ctl_consistency(&[trace], &[ctl]);
+        match &res {
+            Err(CtlError::MultiplicityMismatch {
+                ctl_index: 0,
+                looking_locations,
+                looked_locations,
+                ..
+            }) => {
+                assert_ne!(looking_locations.len(), looked_locations.len());
+            }
+            res => panic!("Unexpected result {res:?}"),
+        }
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .starts_with("CTL #0 (doubling):"));
+    }
+
+    #[test]
+    fn test_cross_table_lookup_column_count_mismatch() {
+        type F = <PoseidonGoldilocksConfig as GenericConfig<2>>::F;
+        let looking = TableWithColumns::<F>::new(
+            Table::Cpu,
+            vec![Column::single(0), Column::single(1)],
+            None,
+        );
+        let looked = TableWithColumns::<F>::new(Table::Logic, vec![Column::single(0)], None);
+
+        assert_eq!(
+            CrossTableLookup::new(vec![looking.clone()], looked.clone()),
+            Err(CtlError::ColumnCountMismatch {
+                looking_table: Table::Cpu,
+                looking_len: 2,
+                looked_table: Table::Logic,
+                looked_len: 1,
+            })
+        );
+        assert_eq!(
+            CrossTableLookup::new_multi_looked(
+                vec![looked.clone()],
+                vec![looked.clone(), looking.clone()]
+            ),
+            Err(CtlError::LookedColumnCountMismatch {
+                looked_table: Table::Cpu,
+                looked_len: 2,
+                first_looked_table: Table::Logic,
+                first_looked_len: 1,
+            })
+        );
+        assert_eq!(
+            CrossTableLookup::new_logup(vec![looking], looked),
+            Err(CtlError::MissingMultiplicity {
+                table: Table::Logic
+            })
+        );
+    }
+
+    #[test]
+    fn test_helper_columns_constraint_degree_4() {
+        type F = <PoseidonGoldilocksConfig as GenericConfig<2>>::F;
+        const CONSTRAINT_DEGREE: usize = 4;
+
+        let n = 8;
+        let mut trace = (0..3)
+            .map(|_| PolynomialValues::new(F::rand_vec(n)))
+            .collect::<Vec<_>>();
+        trace.push(PolynomialValues::new(
+            (0..n).map(|i| F::from_bool(i % 3 != 0)).collect(),
+        ));
+
+        // Three looking tables of the same `Table`, all bundled into a single helper column.
+        let looking_tables = vec![
+            TableWithColumns::<F>::new(
+                Table::Arithmetic,
+                vec![Column::single(0)],
+                Some(Filter::new_simple(Column::single(3))),
+            ),
+            TableWithColumns::<F>::new(Table::Arithmetic, vec![Column::single(1)], None),
+            TableWithColumns::<F>::new(
+                Table::Arithmetic,
+                vec![Column::single(2)],
+                Some(Filter::new_simple(Column::single(3))),
+            ),
+        ];
+        let columns_filters = looking_tables
+            .iter()
+            .map(|twc| (&twc.columns[..], &twc.filter))
+            .collect::<Vec<_>>();
+        let filters = looking_tables
+            .iter()
+            .map(|twc| twc.filter.clone())
+            .collect::<Vec<_>>();
+        let challenge = GrandProductChallenge {
+            beta: F::rand(),
+            gamma: F::rand(),
+        };
+        let helper_columns = get_helper_cols(
+            &trace,
+            n,
+            &columns_filters,
+            challenge,
+            CONSTRAINT_DEGREE,
+            false,
+        );
+        assert_eq!(helper_columns.len(), 1);
+
+        for row in 0..n {
+            let local_values = trace.iter().map(|col| col.values[row]).collect::<Vec<_>>();
+            let next_values = trace
+                .iter()
+                .map(|col| col.values[(row + 1) % n])
+                .collect::<Vec<_>>();
+            let evals = looking_tables
+                .iter()
+                .map(|twc| {
+                    twc.columns
+                        .iter()
+                        .map(|c| c.eval_with_next::<F, F, 1>(&local_values, &next_values))
+                        .collect::<Vec<F>>()
+                })
+                .collect::<Vec<_>>();
+
+            let mut consumer =
+                ConstraintConsumer::<F>::new(vec![F::rand()], F::ONE, F::ONE, F::ONE);
+            eval_helper_columns::<F, F, F, 2, 1>(
+                &filters,
+                &evals,
+                &local_values,
+                &next_values,
+                &[helper_columns[0].values[row]],
+                CONSTRAINT_DEGREE,
+                &challenge,
+                &mut consumer,
+            );
+            assert_eq!(consumer.accumulators(), vec![F::ZERO]);
+        }
+    }
+
+    /// Evaluates the CTL constraints of `ctl_data` on every row of `trace`.
+    fn assert_ctl_constraints_hold<F, S>(trace: &[PolynomialValues<F>], ctl_data: &CtlData<F>)
+    where
+        F: RichField + Extendable<2>,
+        S: Stark<F, 2>,
+    {
+        let n = trace[0].len();
+        for row in 0..n {
+            let local_values = trace.iter().map(|col| col.values[row]).collect::<Vec<_>>();
+            let next_values = trace
+                .iter()
+                .map(|col| col.values[(row + 1) % n])
+                .collect::<Vec<_>>();
+            let vars = <S::EvaluationFrame<F, F, 1> as StarkEvaluationFrame<F>>::from_values(
+                &local_values,
+                &next_values,
+            );
+            let ctl_vars = ctl_data
+                .zs_columns
+                .iter()
+                .map(|zs| CtlCheckVars::<F, F, F, 1> {
+                    helper_columns: zs.helper_columns.iter().map(|h| h.values[row]).collect(),
+                    local_z: zs.z.values[row],
+                    next_z: zs.z.values[(row + 1) % n],
+                    challenges: zs.challenge,
+                    columns: zs.columns.clone(),
+                    filter: zs.filter.clone(),
+                })
+                .collect::<Vec<_>>();
+
+            let is_last = row == n - 1;
+            let mut consumer = ConstraintConsumer::<F>::new(
+                vec![F::rand()],
+                F::from_bool(!is_last),
+                F::from_bool(row == 0),
+                F::from_bool(is_last),
+            );
+            eval_cross_table_lookup_checks::<F, F, F, S, 2, 1>(&vars, &ctl_vars, &mut consumer, 3);
+            assert_eq!(consumer.accumulators(), vec![F::ZERO]);
+        }
+    }
+
+    /// Evaluates the CTL constraints of `ctl_data` natively and in a circuit, on the same random
+    /// openings, and checks that both evaluations agree.
+    fn assert_ctl_circuit_matches_native<S>(ctl_data: &CtlData<GoldilocksField>) -> Result<()>
+    where
+        S: Stark<GoldilocksField, 2>,
+    {
+        use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+        use plonky2::plonk::circuit_data::CircuitConfig;
+
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = GoldilocksField;
+        type FE = <F as Extendable<D>>::Extension;
+
+        let local_values = FE::rand_vec(S::COLUMNS);
+        let next_values = FE::rand_vec(S::COLUMNS);
+        let alpha = F::rand();
+        let [z_last, lagrange_first, lagrange_last] = [(); 3].map(|_| FE::rand());
+        let openings = ctl_data
+            .zs_columns
+            .iter()
+            .map(|zs| {
+                (
+                    FE::rand_vec(zs.helper_columns.len()),
+                    FE::rand(),
+                    FE::rand(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let vars = S::EvaluationFrame::from_values(&local_values, &next_values);
+        let ctl_vars = ctl_data
+            .zs_columns
+            .iter()
+            .zip(&openings)
+            .map(
+                |(zs, (helper_columns, local_z, next_z))| CtlCheckVars::<F, FE, FE, D> {
+                    helper_columns: helper_columns.clone(),
+                    local_z: *local_z,
+                    next_z: *next_z,
+                    challenges: zs.challenge,
+                    columns: zs.columns.clone(),
+                    filter: zs.filter.clone(),
+                },
+            )
+            .collect::<Vec<_>>();
+        let mut consumer = ConstraintConsumer::<FE>::new(
+            vec![FE::from_basefield(alpha)],
+            z_last,
+            lagrange_first,
+            lagrange_last,
+        );
+        eval_cross_table_lookup_checks::<F, FE, FE, S, D, D>(&vars, &ctl_vars, &mut consumer, 3);
+        let native_eval = consumer.accumulators()[0];
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let mut pw = PartialWitness::<F>::new();
+
+        let locals_t = builder.add_virtual_extension_targets(S::COLUMNS);
+        pw.set_extension_targets(&locals_t, &local_values);
+        let nexts_t = builder.add_virtual_extension_targets(S::COLUMNS);
+        pw.set_extension_targets(&nexts_t, &next_values);
+        let alpha_t = builder.add_virtual_target();
+        pw.set_target(alpha_t, alpha);
+        let [z_last_t, lagrange_first_t, lagrange_last_t] = [z_last, lagrange_first, lagrange_last]
+            .map(|value| {
+                let target = builder.add_virtual_extension_target();
+                pw.set_extension_target(target, value);
+                target
+            });
+        let mut ctl_vars_t = Vec::with_capacity(openings.len());
+        for (zs, (helper_columns, local_z, next_z)) in ctl_data.zs_columns.iter().zip(&openings) {
+            let helper_columns_t = builder.add_virtual_extension_targets(helper_columns.len());
+            pw.set_extension_targets(&helper_columns_t, helper_columns);
+            let local_z_t = builder.add_virtual_extension_target();
+            pw.set_extension_target(local_z_t, *local_z);
+            let next_z_t = builder.add_virtual_extension_target();
+            pw.set_extension_target(next_z_t, *next_z);
+            let beta_t = builder.add_virtual_target();
+            pw.set_target(beta_t, zs.challenge.beta);
+            let gamma_t = builder.add_virtual_target();
+            pw.set_target(gamma_t, zs.challenge.gamma);
+            ctl_vars_t.push(CtlCheckVarsTarget {
+                helper_columns: helper_columns_t,
+                local_z: local_z_t,
+                next_z: next_z_t,
+                challenges: GrandProductChallenge {
+                    beta: beta_t,
+                    gamma: gamma_t,
+                },
+                columns: zs.columns.iter().map(|cols| cols.to_vec()).collect(),
+                filter: zs.filter.clone(),
+            });
+        }
+
+        let vars = S::EvaluationFrameTarget::from_values(&locals_t, &nexts_t);
+        let mut consumer = RecursiveConstraintConsumer::<F, D>::new(
+            builder.zero_extension(),
+            vec![alpha_t],
+            z_last_t,
+            lagrange_first_t,
+            lagrange_last_t,
+        );
+        eval_cross_table_lookup_checks_circuit::<S, F, D>(
+            &mut builder,
+            &vars,
+            &ctl_vars_t,
+            &mut consumer,
+            3,
+        );
+        let circuit_eval = consumer.accumulators()[0];
+        let native_eval_t = builder.constant_extension(native_eval);
+        builder.connect_extension(circuit_eval, native_eval_t);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    /// Groups of 1, 3 and 5 looking tables of the same `Table`, with constraint degree 3, so that
+    /// the last helper column is only half full when the group size is odd.
+    #[test]
+    fn test_ragged_helper_columns() -> Result<()> {
+        use crate::logic::LogicStark;
+
+        const D: usize = 2;
+        type F = GoldilocksField;
+        type S = LogicStark<F, D>;
+        const CONSTRAINT_DEGREE: usize = 3;
+
+        let n = 8;
+        let pad = |mut trace: Vec<PolynomialValues<F>>| {
+            let len = trace[0].len();
+            trace.resize(S::COLUMNS, PolynomialValues::zero(len));
+            trace
+        };
+        let cases = [
+            vec![Table::Arithmetic],
+            vec![Table::Arithmetic; 3],
+            vec![Table::Arithmetic; 5],
+        ];
+        for tables in cases {
+            let group_size = |table| tables.iter().filter(|&&t| t == table).count();
+
+            // Each table has one value column per looking table, followed by a filter column.
+            let mut trace_poly_values: [Vec<PolynomialValues<F>>; NUM_TABLES] = Default::default();
+            for &table in &tables {
+                let k = group_size(table);
+                let mut trace = (0..k)
+                    .map(|_| PolynomialValues::new(F::rand_vec(n)))
+                    .collect::<Vec<_>>();
+                trace.push(PolynomialValues::new(
+                    (0..n).map(|i| F::from_bool(i % 3 != 0)).collect(),
+                ));
+                trace_poly_values[table as usize] = trace;
+            }
+
+            let mut next_column = [0; NUM_TABLES];
+            let looking_tables = tables
+                .iter()
+                .map(|&table| {
+                    let i = next_column[table as usize];
+                    next_column[table as usize] += 1;
+                    let filter =
+                        (i % 2 == 1).then(|| Filter::new_simple(Column::single(group_size(table))));
+                    TableWithColumns::new(table, vec![Column::single(i)], filter)
+                })
+                .collect::<Vec<_>>();
+
+            // The looked table holds every filtered-in value, padded to a power of two.
+            let mut looked_values = vec![];
+            for twc in &looking_tables {
+                let trace = &trace_poly_values[twc.table as usize];
+                for row in 0..n {
+                    if twc
+                        .filter
+                        .as_ref()
+                        .map_or(true, |filter| filter.eval_table(trace, row).is_one())
+                    {
+                        looked_values.push(twc.columns[0].eval_table(trace, row));
+                    }
+                }
+            }
+            let num_looked = looked_values.len();
+            let looked_len = num_looked.next_power_of_two();
+            looked_values.resize(looked_len, F::ZERO);
+            trace_poly_values[Table::Logic as usize] = vec![
+                PolynomialValues::new(looked_values),
+                PolynomialValues::new(
+                    (0..looked_len)
+                        .map(|i| F::from_bool(i < num_looked))
+                        .collect(),
+                ),
+            ];
+            for table in tables.iter().copied().chain([Table::Logic]) {
+                let trace = std::mem::take(&mut trace_poly_values[table as usize]);
+                trace_poly_values[table as usize] = pad(trace);
+            }
+
+            let looked = TableWithColumns::new(
+                Table::Logic,
+                vec![Column::single(0)],
+                Some(Filter::new_simple(Column::single(1))),
+            );
+            let ctls = vec![CrossTableLookup::new(looking_tables, looked)?];
+            assert_eq!(verify_ctl_consistency(&trace_poly_values, &ctls), Ok(()));
+
+            let ctl_challenges = GrandProductChallengeSet {
+                challenges: vec![GrandProductChallenge {
+                    beta: F::rand(),
+                    gamma: F::rand(),
+                }],
+            };
+            let ctl_data = cross_table_lookup_data::<F, D>(
+                &trace_poly_values,
+                &ctls,
+                &ctl_challenges,
+                CONSTRAINT_DEGREE,
+            );
+
+            let num_helpers = num_ctl_helper_columns_by_table(&ctls, CONSTRAINT_DEGREE);
+            for &table in &tables {
+                let k = group_size(table);
+                let data = &ctl_data[table as usize];
+                // All the looking tables of a `Table` share a single Z polynomial.
+                assert_eq!(data.len(), 1, "{tables:?}");
+                let expected = if k > 1 {
+                    ceil_div_usize(k, CONSTRAINT_DEGREE - 1)
+                } else {
+                    0
+                };
+                assert_eq!(data.zs_columns[0].helper_columns.len(), expected);
+                assert_eq!(num_helpers[0][table as usize], expected);
+                assert_eq!(data.zs_columns[0].columns.len(), k);
+            }
+
+            for table in tables.iter().copied().chain([Table::Logic]) {
+                assert_ctl_constraints_hold::<F, S>(
+                    &trace_poly_values[table as usize],
+                    &ctl_data[table as usize],
+                );
+                assert_ctl_circuit_matches_native::<S>(&ctl_data[table as usize])?;
+            }
+
+            let ctl_zs_first = ctl_data
+                .each_ref()
+                .map(|data| data.zs_columns.iter().map(|zs| zs.z.values[0]).collect());
+            let config = StarkConfig::standard_fast_config();
+            verify_cross_table_lookups::<F, D>(&ctls, ctl_zs_first, &config)?;
+        }
+
+        Ok(())
+    }
+
+    /// The rows of a CPU looking table are split between two looked tables, Logic and
+    /// Arithmetic: the sum of the looking Zs must equal the sum of the looked Zs.
+    #[test]
+    fn test_multiple_looked_tables() -> Result<()> {
+        use crate::logic::LogicStark;
+
+        const D: usize = 2;
+        type F = GoldilocksField;
+        type S = LogicStark<F, D>;
+        const CONSTRAINT_DEGREE: usize = 3;
+
+        let n = 8;
+        let pad = |mut trace: Vec<PolynomialValues<F>>| {
+            let len = trace[0].len();
+            trace.resize(S::COLUMNS, PolynomialValues::zero(len));
+            trace
+        };
+        let with_filter = |values: Vec<F>, num_active: usize| {
+            pad(vec![
+                PolynomialValues::new(values),
+                PolynomialValues::new((0..n).map(|i| F::from_bool(i < num_active)).collect()),
+            ])
+        };
+
+        // The CPU looks up 6 values: the first 4 are in the Logic table, the last 2 in the
+        // Arithmetic table.
+        let values = F::rand_vec(6);
+        let mut cpu_values = values.clone();
+        cpu_values.resize(n, F::ZERO);
+        let mut logic_values = values[..4].to_vec();
+        logic_values.resize(n, F::ZERO);
+        let mut arithmetic_values = values[4..].to_vec();
+        arithmetic_values.resize(n, F::ZERO);
+
+        let mut trace_poly_values: [Vec<PolynomialValues<F>>; NUM_TABLES] = Default::default();
+        trace_poly_values[Table::Cpu as usize] = with_filter(cpu_values, 6);
+        trace_poly_values[Table::Logic as usize] = with_filter(logic_values, 4);
+        trace_poly_values[Table::Arithmetic as usize] = with_filter(arithmetic_values, 2);
+
+        let twc = |table| {
+            TableWithColumns::new(
+                table,
+                vec![Column::single(0)],
+                Some(Filter::new_simple(Column::single(1))),
+            )
+        };
+        let ctls = vec![CrossTableLookup::new_multi_looked(
+            vec![twc(Table::Cpu)],
+            vec![twc(Table::Logic), twc(Table::Arithmetic)],
+        )?];
+        assert_eq!(verify_ctl_consistency(&trace_poly_values, &ctls), Ok(()));
+
+        let ctl_challenges = GrandProductChallengeSet {
+            challenges: vec![GrandProductChallenge {
+                beta: F::rand(),
+                gamma: F::rand(),
+            }],
+        };
+        let ctl_data = cross_table_lookup_data::<F, D>(
+            &trace_poly_values,
+            &ctls,
+            &ctl_challenges,
+            CONSTRAINT_DEGREE,
+        );
+        for table in [Table::Cpu, Table::Logic, Table::Arithmetic] {
+            assert_eq!(ctl_data[table as usize].len(), 1);
+            assert_ctl_constraints_hold::<F, S>(
+                &trace_poly_values[table as usize],
+                &ctl_data[table as usize],
+            );
+            assert_ctl_circuit_matches_native::<S>(&ctl_data[table as usize])?;
+        }
+
+        let config = StarkConfig::standard_fast_config();
+        let ctl_zs_first = |ctl_data: &[CtlData<F>; NUM_TABLES]| {
+            ctl_data
+                .each_ref()
+                .map(|data| data.zs_columns.iter().map(|zs| zs.z.values[0]).collect())
+        };
+        verify_cross_table_lookups::<F, D>(&ctls, ctl_zs_first(&ctl_data), &config)?;
+
+        // With only one of the two looked tables, the lookup fails.
+        let partial_ctls = vec![CrossTableLookup::new(
+            vec![twc(Table::Cpu)],
+            twc(Table::Logic),
+        )?];
+        assert!(verify_ctl_consistency(&trace_poly_values, &partial_ctls).is_err());
+        let partial_data = cross_table_lookup_data::<F, D>(
+            &trace_poly_values,
+            &partial_ctls,
+            &ctl_challenges,
+            CONSTRAINT_DEGREE,
+        );
+        assert!(verify_cross_table_lookups::<F, D>(
+            &partial_ctls,
+            ctl_zs_first(&partial_data),
+            &config
+        )
+        .is_err());
+
+        assert_eq!(
+            CrossTableLookup::new_multi_looked(vec![twc(Table::Cpu)], vec![]),
+            Err(CtlError::NoLookedTable)
+        );
+        Ok(())
+    }
+
+    /// Interleaved looking tables are rejected, as the looking tables of a `Table` share their Z
+    /// polynomials.
+    #[test]
+    fn test_looking_table_order() {
+        type F = GoldilocksField;
+        let twc = |table, col| TableWithColumns::<F>::new(table, vec![Column::single(col)], None);
+
+        let grouped = vec![
+            twc(Table::Cpu, 0),
+            twc(Table::Cpu, 1),
+            twc(Table::Memory, 0),
+        ];
+        assert_eq!(
+            looking_table_order(&grouped),
+            vec![Table::Cpu as usize, Table::Memory as usize]
+        );
+        assert!(CrossTableLookup::new(grouped, twc(Table::Logic, 0)).is_ok());
+
+        let interleaved = vec![
+            twc(Table::Cpu, 0),
+            twc(Table::Memory, 0),
+            twc(Table::Cpu, 1),
+        ];
+        assert_eq!(
+            CrossTableLookup::new(interleaved.clone(), twc(Table::Logic, 0)),
+            Err(CtlError::UngroupedLookingTables { table: Table::Cpu })
+        );
+        assert_eq!(
+            CrossTableLookup::new_logup(
+                interleaved,
+                twc(Table::Logic, 0).with_multiplicity(Column::single(1))
+            ),
+            Err(CtlError::UngroupedLookingTables { table: Table::Cpu })
+        );
+    }
+
+    #[test]
+    fn test_logup_logic_cpu() {
+        use crate::cpu::columns::{COL_MAP, NUM_CPU_COLUMNS};
+        use crate::cpu::cpu_stark::{self, CpuStark};
+        use crate::logic::{self, columns, LogicStark, Op, Operation};
+        use crate::util::trace_rows_to_poly_values;
+
+        const D: usize = 2;
+        type F = <PoseidonGoldilocksConfig as GenericConfig<D>>::F;
+
+        // The CPU performs the same AND three times, but the Logic table only proves it once.
+        let ops = [
+            (Op::And, 0x1234, 0xff00),
+            (Op::Xor, 0xdead, 0xbeef),
+            (Op::And, 0x1234, 0xff00),
+            (Op::And, 0x1234, 0xff00),
+        ];
+        let mut cpu_rows = vec![[F::ZERO; NUM_CPU_COLUMNS]; 8];
+        for (row, &(op, input0, input1)) in cpu_rows.iter_mut().zip(&ops) {
+            let func = match op {
+                Op::And => 0b100100,
+                Op::Xor => 0b100110,
+                _ => unreachable!(),
+            };
+            row[COL_MAP.op.logic_op] = F::ONE;
+            for (i, &col) in COL_MAP.func_bits.iter().enumerate() {
+                row[col] = F::from_canonical_u32((func >> i) & 1);
+            }
+            row[COL_MAP.mem_channels[0].value] = F::from_canonical_u32(input0);
+            row[COL_MAP.mem_channels[1].value] = F::from_canonical_u32(input1);
+            row[COL_MAP.mem_channels[2].value] = F::from_canonical_u32(op.result(input0, input1));
+        }
+
+        let logic_stark = LogicStark::<F, D> {
+            f: Default::default(),
+        };
+        let mut trace_poly_values: [Vec<PolynomialValues<F>>; NUM_TABLES] = Default::default();
+        trace_poly_values[Table::Cpu as usize] = trace_rows_to_poly_values(cpu_rows);
+        trace_poly_values[Table::Logic as usize] = logic_stark.generate_trace(
+            ops[..2]
+                .iter()
+                .map(|&(op, input0, input1)| Operation::new(op, input0, input1))
+                .collect(),
+            4,
+        );
+
+        let ctl_with_multiplicity = |and_multiplicity: F| {
+            let looking = TableWithColumns::new(
+                Table::Cpu,
+                cpu_stark::ctl_data_logic(),
+                Some(cpu_stark::ctl_filter_logic()),
+            );
+            let looked =
+                TableWithColumns::new(Table::Logic, logic::ctl_data(), Some(logic::ctl_filter()))
+                    .with_multiplicity(Column::linear_combination([
+                        (columns::IS_AND, and_multiplicity),
+                        (columns::IS_XOR, F::ONE),
+                    ]));
+            vec![CrossTableLookup::new_logup(vec![looking], looked).unwrap()]
+        };
+        let ctl_challenges = GrandProductChallengeSet {
+            challenges: (0..2)
+                .map(|_| GrandProductChallenge {
+                    beta: F::rand(),
+                    gamma: F::rand(),
+                })
+                .collect(),
+        };
+
+        // Prover side.
+        let ctls = ctl_with_multiplicity(F::from_canonical_u32(3));
+        assert_eq!(verify_ctl_consistency(&trace_poly_values, &ctls), Ok(()));
+        let ctl_data =
+            cross_table_lookup_data::<F, D>(&trace_poly_values, &ctls, &ctl_challenges, 3);
+
+        // Verifier side: the per-row constraints hold and the final sums match.
+        assert_ctl_constraints_hold::<F, CpuStark<F, D>>(
+            &trace_poly_values[Table::Cpu as usize],
+            &ctl_data[Table::Cpu as usize],
+        );
+        assert_ctl_constraints_hold::<F, LogicStark<F, D>>(
+            &trace_poly_values[Table::Logic as usize],
+            &ctl_data[Table::Logic as usize],
+        );
+        let ctl_zs_first = ctl_data
+            .each_ref()
+            .map(|data| data.zs_columns.iter().map(|zs| zs.z.values[0]).collect());
+        let config = StarkConfig::standard_fast_config();
+        assert!(verify_cross_table_lookups::<F, D>(&ctls, ctl_zs_first, &config).is_ok());
+
+        // An off-by-one multiplicity is caught by both the consistency check and the verifier.
+        let ctls = ctl_with_multiplicity(F::TWO);
+        assert!(matches!(
+            verify_ctl_consistency(&trace_poly_values, &ctls),
+            Err(CtlError::MultiplicityMismatch { .. })
+        ));
+        let ctl_data =
+            cross_table_lookup_data::<F, D>(&trace_poly_values, &ctls, &ctl_challenges, 3);
+        let ctl_zs_first = ctl_data
+            .each_ref()
+            .map(|data| data.zs_columns.iter().map(|zs| zs.z.values[0]).collect());
+        assert!(verify_cross_table_lookups::<F, D>(&ctls, ctl_zs_first, &config).is_err());
+
+        // A multiplicity of -1 is reported rather than counted p - 1 times.
+        let ctls = ctl_with_multiplicity(F::NEG_ONE);
+        assert!(matches!(
+            verify_ctl_consistency(&trace_poly_values, &ctls),
+            Err(CtlError::MultiplicityTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn test_cross_table_lookup_data_matches_serial() {
+        const D: usize = 2;
+        type F = <PoseidonGoldilocksConfig as GenericConfig<D>>::F;
+        const CONSTRAINT_DEGREE: usize = 3;
+
+        // Columns 0 to 2 hold values, column 3 a binary filter and column 4 a multiplicity.
+        let n = 16;
+        let mut trace_poly_values: [Vec<PolynomialValues<F>>; NUM_TABLES] = Default::default();
+        for table in [Table::Arithmetic, Table::Cpu, Table::Logic] {
+            let mut columns = (0..3)
+                .map(|_| PolynomialValues::new(F::rand_vec(n)))
+                .collect::<Vec<_>>();
+            columns.push(PolynomialValues::new(
+                (0..n).map(|i| F::from_bool(i % 3 != 0)).collect(),
+            ));
+            columns.push(PolynomialValues::new(
+                (0..n).map(|i| F::from_canonical_usize(i % 4)).collect(),
+            ));
+            trace_poly_values[table as usize] = columns;
+        }
+        let filter = || Some(Filter::new_simple(Column::single(3)));
+        let looking = |table| {
+            vec![
+                TableWithColumns::new(table, vec![Column::single(0), Column::single(1)], filter()),
+                TableWithColumns::new(table, vec![Column::single(1), Column::single(2)], None),
+                TableWithColumns::new(table, vec![Column::single(2), Column::single(0)], filter()),
+            ]
+        };
+        let looked = TableWithColumns::new(
+            Table::Logic,
+            vec![Column::single(0), Column::single(1)],
+            filter(),
+        );
+        let ctls = vec![
+            CrossTableLookup::new(
+                [looking(Table::Arithmetic), looking(Table::Cpu)].concat(),
+                looked.clone(),
+            )
+            .unwrap(),
+            CrossTableLookup::new_logup(
+                looking(Table::Cpu),
+                looked.with_multiplicity(Column::single(4)),
+            )
+            .unwrap(),
+        ];
+        let ctl_challenges = GrandProductChallengeSet {
+            challenges: (0..3)
+                .map(|_| GrandProductChallenge {
+                    beta: F::rand(),
+                    gamma: F::rand(),
+                })
+                .collect(),
+        };
+
+        let parallel = cross_table_lookup_data::<F, D>(
+            &trace_poly_values,
+            &ctls,
+            &ctl_challenges,
+            CONSTRAINT_DEGREE,
+        );
+        // The same data, from plain loops over the CTLs, challenges and tables.
+        let mut serial = [0; NUM_TABLES].map(|_| CtlData::default());
+        for ctl in &ctls {
+            for &challenge in &ctl_challenges.challenges {
+                let looked_groups = ctl
+                    .looked_tables
+                    .iter()
+                    .map(|looked| (looked.table, vec![looked]));
+                for (table, group) in group_by_table(&ctl.looking_tables)
+                    .into_iter()
+                    .chain(looked_groups)
+                {
+                    let filter = group
+                        .iter()
+                        .map(|twc| twc.ctl_filter(ctl.kind))
+                        .collect::<Vec<_>>();
+                    let columns = group.iter().map(|twc| &twc.columns[..]).collect::<Vec<_>>();
+                    let columns_filters = columns
+                        .iter()
+                        .zip(&filter)
+                        .map(|(&columns, filter)| (columns, filter))
+                        .collect::<Vec<_>>();
+                    let mut helpers_zs = partial_sums(
+                        &trace_poly_values[table as usize],
+                        &columns_filters,
+                        challenge,
+                        CONSTRAINT_DEGREE,
+                        ctl.kind == CtlKind::LogUp,
+                    );
+                    // A single looked table has no helper column.
+                    let z = helpers_zs.pop().unwrap();
+                    serial[table as usize].zs_columns.push(CtlZData {
+                        helper_columns: helpers_zs,
+                        z,
+                        challenge,
+                        columns,
+                        filter,
+                    });
+                }
+            }
+        }
+
+        for (parallel, serial) in parallel.iter().zip(&serial) {
+            assert_eq!(parallel.len(), serial.len());
+            for (p, s) in parallel.zs_columns.iter().zip(&serial.zs_columns) {
+                assert_eq!(p.helper_columns, s.helper_columns);
+                assert_eq!(p.z, s.z);
+                assert_eq!(p.challenge, s.challenge);
+                assert_eq!(p.columns, s.columns);
+                assert_eq!(p.filter, s.filter);
+            }
+        }
+    }
+
+    #[test]
+    fn test_le_bytes() {
+        type F = <PoseidonGoldilocksConfig as GenericConfig<2>>::F;
+        let value = 0xdeadbeef_u32;
+        let trace = value
+            .to_le_bytes()
+            .map(|byte| PolynomialValues::new(vec![F::from_canonical_u8(byte)]));
+
+        let column = Column::<F>::le_bytes(0..4);
+        assert_eq!(column.eval_table(&trace, 0), F::from_canonical_u32(value));
+
+        let trace = [3u8, 1, 2].map(|limb| PolynomialValues::new(vec![F::from_canonical_u8(limb)]));
+        let column = Column::<F>::le_base(0..3, 32);
+        assert_eq!(
+            column.eval_table(&trace, 0),
+            F::from_canonical_u32(3 + 32 + 2 * 32 * 32)
+        );
+    }
+
+    /// Two layouts of the same fields: columns derived from a view's column map follow its
+    /// layout, while a hard-coded range of the first layout reads the wrong columns of the
+    /// second one, which a CTL on these columns catches.
+    #[test]
+    fn test_columns_from_field() {
+        use crate::util::indices_arr;
+
+        type F = GoldilocksField;
+
+        #[repr(C)]
+        struct ViewAB<T> {
+            a: [T; 2],
+            b: [T; 3],
+        }
+        #[repr(C)]
+        struct ViewBA<T> {
+            b: [T; 3],
+            a: [T; 2],
+        }
+        let map_ab: ViewAB<usize> = unsafe { core::mem::transmute(indices_arr::<5>()) };
+        let map_ba: ViewBA<usize> = unsafe { core::mem::transmute(indices_arr::<5>()) };
+        assert_eq!(field_range(map_ab.a), 0..2);
+        assert_eq!(field_range(map_ab.b), 2..5);
+        assert_eq!(field_range(map_ba.b), 0..3);
+        assert_eq!(field_range(map_ba.a), 3..5);
+
+        let a = [F::from_canonical_u8(1), F::from_canonical_u8(2)];
+        let b = [3, 4, 5].map(F::from_canonical_u8);
+        let row_ab: [F; 5] = unsafe { core::mem::transmute(ViewAB { a, b }) };
+        let row_ba: [F; 5] = unsafe { core::mem::transmute(ViewBA { b, a }) };
+        let trace_ab = row_ab.map(|v| PolynomialValues::new(vec![v])).to_vec();
+        let trace_ba = row_ba.map(|v| PolynomialValues::new(vec![v])).to_vec();
+
+        let eval = |columns: &[Column<F>], trace: &[PolynomialValues<F>]| {
+            columns
+                .iter()
+                .map(|c| c.eval_table(trace, 0))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(eval(&Column::columns_from_field(map_ab.b), &trace_ab), b);
+        assert_eq!(eval(&Column::columns_from_field(map_ba.b), &trace_ba), b);
+        let hard_coded = Column::columns_from_range(2..5);
+        assert_ne!(eval(&hard_coded, &trace_ba), b);
+
+        let looking = TableWithColumns::new(Table::Cpu, hard_coded, None);
+        let looked =
+            TableWithColumns::new(Table::Logic, Column::columns_from_field(map_ab.b), None);
+        let mut traces = vec![vec![]; NUM_TABLES];
+        traces[Table::Logic as usize] = trace_ab;
+        traces[Table::Cpu as usize] = trace_ba;
+        let ctl = CrossTableLookup::new(vec![looking], looked).unwrap();
+        assert!(verify_ctl_consistency(&traces, &[ctl]).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "aren't consecutive")]
+    fn test_field_range_not_consecutive() {
+        field_range([3, 4, 6]);
+    }
+
+    #[test]
+    fn test_column_combinators() {
+        type F = <PoseidonGoldilocksConfig as GenericConfig<2>>::F;
+        let n = 4;
+        let trace = (0..4)
+            .map(|_| PolynomialValues::new(F::rand_vec(n)))
+            .collect::<Vec<_>>();
+        let two = F::TWO;
+        let three = F::from_canonical_u8(3);
+
+        let lhs = Column::linear_combination_and_next_row_with_constant(
+            [(0, F::ONE), (1, two)],
+            [(2, three)],
+            F::ONE,
+        );
+        let rhs = Column::linear_combination_and_next_row_with_constant(
+            [(1, three), (3, F::ONE)],
+            [(2, three)],
+            two,
+        );
+        let column = lhs.add(&rhs).scale(two).add_constant(three);
+        assert_eq!(column.linear_combination.len(), 3);
+        assert_eq!(column.next_row_linear_combination.len(), 1);
+
+        for row in 0..n {
+            let v = |c: usize| trace[c].values[row];
+            let next = if row < n - 1 {
+                trace[2].values[row + 1] * (three + three)
+            } else {
+                F::ZERO
+            };
+            let expected = (v(0) + v(1) * (two + three) + v(3) + next + F::ONE + two) * two + three;
+            assert_eq!(column.eval_table(&trace, row), expected);
+            assert_eq!(
+                column.eval_table(&trace, row),
+                (lhs.eval_table(&trace, row) + rhs.eval_table(&trace, row)) * two + three
+            );
+        }
+    }
+
+    #[test]
+    fn test_filter_combinators() -> Result<()> {
+        use plonky2::iop::witness::PartialWitness;
+        use plonky2::plonk::circuit_data::CircuitConfig;
+
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        // Both columns run through all four combinations of bits.
+        let trace = [[0, 1, 0, 1], [0, 0, 1, 1]]
+            .map(|col| PolynomialValues::new(col.map(F::from_canonical_u8).to_vec()));
+        let (a, b) = (Column::single(0), Column::single(1));
+        let cases = [
+            (Filter::not(a.clone()), [1, 0, 1, 0]),
+            (Filter::and(a.clone(), b.clone()), [0, 0, 0, 1]),
+            (Filter::or(a, b), [0, 1, 1, 1]),
+        ];
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        for (filter, expected) in &cases {
+            for (row, &expected) in expected.iter().enumerate() {
+                let expected = F::from_canonical_u8(expected);
+                let v = trace.iter().map(|col| col.values[row]).collect::<Vec<_>>();
+                assert_eq!(filter.eval_table(&trace, row), expected);
+                assert_eq!(filter.eval_filter::<F, F, 1>(&v, &v), expected);
+
+                let v_t = v
+                    .iter()
+                    .map(|&x| builder.constant_extension(x.into()))
+                    .collect::<Vec<_>>();
+                let eval_t = filter.eval_filter_circuit(&mut builder, &v_t, &v_t);
+                let expected_t = builder.constant_extension(expected.into());
+                builder.connect_extension(eval_t, expected_t);
+            }
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(PartialWitness::new())?;
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_eval_all_rows() {
+        type F = <PoseidonGoldilocksConfig as GenericConfig<2>>::F;
+        let n = 8;
+        let trace = (0..3)
+            .map(|_| PolynomialValues::new(F::rand_vec(n)))
+            .collect::<Vec<_>>();
+        let column = Column::linear_combination_and_next_row_with_constant(
+            [(0, F::rand()), (1, F::rand())],
+            [(2, F::rand())],
+            F::rand(),
+        );
+
+        let expected = (0..n)
+            .map(|row| column.eval_table(&trace, row))
+            .collect::<Vec<_>>();
+        assert_eq!(column.eval_all_rows(&trace), expected);
+    }
+
+    #[test]
+    fn test_try_eval_table_out_of_range() {
+        type F = <PoseidonGoldilocksConfig as GenericConfig<2>>::F;
+        let trace = (0..3)
+            .map(|_| PolynomialValues::new(F::rand_vec(4)))
+            .collect::<Vec<_>>();
+
+        let column = Column::linear_combination([(0, F::ONE), (2, F::TWO)]);
+        assert_eq!(
+            column.try_eval_table(&trace, 1).unwrap(),
+            column.eval_table(&trace, 1)
+        );
+        assert!(column.try_eval_table(&trace, 4).is_err());
+
+        let column = Column::single(0).add(&Column::single_next_row(5));
+        let err = column.try_eval_table(&trace, 0).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Column index 5 is out of a table of width 3"
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Column index 3 is out of a table of width 3")]
+    fn test_eval_table_out_of_range() {
+        type F = <PoseidonGoldilocksConfig as GenericConfig<2>>::F;
+        let trace = (0..3)
+            .map(|_| PolynomialValues::new(F::rand_vec(4)))
+            .collect::<Vec<_>>();
+        Column::single(3).eval_table(&trace, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "can't read the row after the next one")]
+    fn test_next_next_row_column_in_ctl() {
+        type F = <PoseidonGoldilocksConfig as GenericConfig<2>>::F;
+        TableWithColumns::<F>::new(
+            Table::Arithmetic,
+            vec![Column::single(0).add(&Column::single_next_next_row(1))],
+            None,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "can't read the row after the next one")]
+    fn test_next_next_row_filter() {
+        type F = <PoseidonGoldilocksConfig as GenericConfig<2>>::F;
+        Filter::<F>::new(vec![], vec![Column::single_next_next_row(0)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "can't read the row after the next one")]
+    fn test_next_next_row_filter_and() {
+        type F = <PoseidonGoldilocksConfig as GenericConfig<2>>::F;
+        Filter::<F>::and(Column::single(0), Column::single_next_next_row(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "can't read the row after the next one")]
+    fn test_next_next_row_filter_or() {
+        type F = <PoseidonGoldilocksConfig as GenericConfig<2>>::F;
+        Filter::<F>::or(Column::single_next_next_row(0), Column::single(1));
+    }
+
+    #[test]
+    fn test_next_next_row_column() {
+        type F = <PoseidonGoldilocksConfig as GenericConfig<2>>::F;
+        let n = 4;
+        let trace = (0..2)
+            .map(|_| PolynomialValues::new(F::rand_vec(n)))
+            .collect::<Vec<_>>();
+        let column = Column::single(0)
+            .add(&Column::single_next_row(1))
+            .add(&Column::single_next_next_row(0).scale(F::TWO));
+        assert_eq!(column.max_index(), Some(1));
+
+        let v = |c: usize, row: usize| trace[c].values.get(row).copied().unwrap_or(F::ZERO);
+        for row in 0..n {
+            let expected = v(0, row) + v(1, row + 1) + v(0, row + 2) * F::TWO;
+            assert_eq!(column.eval_table(&trace, row), expected);
+        }
+
+        let rows = (0..3)
+            .map(|row| trace.iter().map(|col| col.values[row]).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            column.eval_with_next_next::<F, F, 1>(&rows[0], &rows[1], &rows[2]),
+            column.eval_table(&trace, 0)
+        );
+    }
+
+    #[test]
+    fn test_cross_table_lookup_serialization() -> IoResult<()> {
+        type F = <PoseidonGoldilocksConfig as GenericConfig<2>>::F;
+        let ctls = crate::all_stark::all_cross_table_lookups::<F>();
+
+        let mut bytes = vec![];
+        for ctl in &ctls {
+            ctl.to_buffer(&mut bytes)?;
+        }
+        let mut buffer = Buffer::new(&bytes);
+        for ctl in &ctls {
+            assert_eq!(&CrossTableLookup::from_buffer(&mut buffer)?, ctl);
+        }
+
+        bytes[0] = CTL_FORMAT_VERSION + 1;
+        assert!(CrossTableLookup::<F>::from_buffer(&mut Buffer::new(&bytes)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_grand_product_challenge_set_serialization() -> IoResult<()> {
+        type F = <PoseidonGoldilocksConfig as GenericConfig<2>>::F;
+        let challenges = GrandProductChallengeSet {
+            challenges: (0..2)
+                .map(|_| GrandProductChallenge {
+                    beta: F::rand(),
+                    gamma: F::rand(),
+                })
+                .collect(),
+        };
+
+        let mut bytes = vec![];
+        challenges.to_buffer(&mut bytes)?;
+        let mut buffer = Buffer::new(&bytes);
+        assert_eq!(
+            GrandProductChallengeSet::from_buffer(&mut buffer)?,
+            challenges
+        );
+        assert!(buffer.unread_bytes().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_combine_with_powers() {
+        type F = GoldilocksField;
+        let challenge = GrandProductChallenge {
+            beta: F::rand(),
+            gamma: F::rand(),
+        };
+        let powers = challenge.beta.powers().take(8).collect_vec();
+        for n in 0..=8 {
+            let terms = F::rand_vec(n);
+            assert_eq!(
+                challenge.combine_with_powers(terms.iter(), &powers),
+                challenge.combine(terms.iter())
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Fewer powers than terms")]
+    fn test_combine_with_too_few_powers() {
+        type F = GoldilocksField;
+        let challenge = GrandProductChallenge {
+            beta: F::rand(),
+            gamma: F::rand(),
+        };
+        let powers = challenge.beta.powers().take(3).collect_vec();
+        challenge.combine_with_powers(F::rand_vec(4).iter(), &powers);
+    }
+
+    /// Compares `get_helper_cols`, which combines rows with precomputed powers of `beta`, with
+    /// combining each row with `combine`, on a 2^16-row trace.
+    #[test]
+    #[ignore]
+    fn test_helper_cols_combine_benchmark() {
+        type F = GoldilocksField;
+        let n = 1 << 16;
+        let trace = (0..8)
+            .map(|_| PolynomialValues::new(F::rand_vec(n)))
+            .collect::<Vec<_>>();
+        let columns = Column::singles(0..8).collect::<Vec<_>>();
+        let no_filter = None;
+        let columns_filters: Vec<ColumnFilter<F>> =
+            vec![(&columns[..4], &no_filter), (&columns[4..], &no_filter)];
+        let challenge = GrandProductChallenge {
+            beta: F::rand(),
+            gamma: F::rand(),
+        };
+
+        let start = std::time::Instant::now();
+        let helper_columns = get_helper_cols(&trace, n, &columns_filters, challenge, 3, false);
+        let with_powers = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let mut expected = vec![F::ZERO; n];
+        for (columns, _) in &columns_filters {
+            let combined = (0..n)
+                .map(|row| {
+                    let evals = columns
+                        .iter()
+                        .map(|c| c.eval_table(&trace, row))
+                        .collect::<Vec<F>>();
+                    challenge.combine(evals.iter())
+                })
+                .collect::<Vec<F>>();
+            batch_add_inplace(&mut expected, &F::batch_multiplicative_inverse(&combined));
+        }
+        let with_combine = start.elapsed();
+
+        assert_eq!(helper_columns, vec![PolynomialValues::new(expected)]);
+        log::info!("get_helper_cols: {with_powers:?}, per-row combine: {with_combine:?}");
+    }
+
+    #[test]
+    fn test_tables_touched() {
+        type F = <PoseidonGoldilocksConfig as GenericConfig<2>>::F;
+        let ctls = crate::all_stark::all_cross_table_lookups::<F>();
+        assert_eq!(CrossTableLookup::tables_touched(&ctls), [true; NUM_TABLES]);
+
+        let ctl = CrossTableLookup::new_unchecked(
+            vec![TableWithColumns::new(
+                Table::Cpu,
+                vec![Column::single(0)],
+                None,
+            )],
+            TableWithColumns::new(Table::Memory, vec![Column::single(1)], None),
+        );
+        let touched = CrossTableLookup::tables_touched(&[ctl]);
+        for table in Table::all() {
+            assert_eq!(
+                touched[table as usize],
+                matches!(table, Table::Cpu | Table::Memory),
+                "{table:?}"
+            );
+        }
+        assert_eq!(
+            CrossTableLookup::<F>::tables_touched(&[]),
+            [false; NUM_TABLES]
+        );
+    }
+
+    fn self_referencing_ctl_data<F: RichField>(
+        trace_poly_values: &[Vec<PolynomialValues<F>>; NUM_TABLES],
+        ctls: &[CrossTableLookup<F>],
+        num_challenges: usize,
+    ) -> [CtlData<'_, F>; NUM_TABLES] {
+        let ctl_challenges = GrandProductChallengeSet {
+            challenges: (0..num_challenges)
+                .map(|_| GrandProductChallenge {
+                    beta: F::rand(),
+                    gamma: F::rand(),
+                })
+                .collect(),
+        };
+        cross_table_lookup_data::<F, 2>(trace_poly_values, ctls, &ctl_challenges, 3)
+    }
+
+    /// A CTL where the Logic table is looked and looking at the same time, so that it has two Z
+    /// polynomials per challenge, and only the looking ones have helper columns.
+    fn self_referencing_ctls<F: Field>() -> Vec<CrossTableLookup<F>> {
+        let twc = |table, col| TableWithColumns::new(table, vec![Column::single(col)], None);
+        vec![CrossTableLookup::new(
+            vec![
+                twc(Table::Logic, 0),
+                twc(Table::Arithmetic, 0),
+                twc(Table::Logic, 1),
+                twc(Table::Logic, 2),
+            ],
+            twc(Table::Logic, 3),
+        )
+        .unwrap()]
+    }
+
+    #[test]
+    fn test_ctl_data_sizes() {
+        type F = <PoseidonGoldilocksConfig as GenericConfig<2>>::F;
+
+        let mut trace_poly_values: [Vec<PolynomialValues<F>>; NUM_TABLES] = Default::default();
+        for table in [Table::Arithmetic, Table::Logic] {
+            trace_poly_values[table as usize] = (0..4)
+                .map(|_| PolynomialValues::new(F::rand_vec(8)))
+                .collect();
+        }
+        let ctls = self_referencing_ctls();
+        let ctl_data = self_referencing_ctl_data(&trace_poly_values, &ctls, 2);
+
+        let logic = &ctl_data[Table::Logic as usize];
+        assert_eq!(logic.total_helper_cols(), 2 * 2);
+        assert_eq!(logic.total_z_polys(), 2 * 2);
+        let arithmetic = &ctl_data[Table::Arithmetic as usize];
+        assert_eq!(arithmetic.total_helper_cols(), 0);
+        assert_eq!(arithmetic.total_z_polys(), 2);
+        assert_eq!(
+            CrossTableLookup::num_ctl_helpers_zs_all(&ctls, Table::Logic, 2, 3),
+            (4, 4, vec![2])
+        );
+        assert_eq!(CrossTableLookup::num_ctl_zs(&ctls, Table::Cpu, 2), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Table Logic: unexpected number of CTL")]
+    fn test_ctl_data_mis_sized() {
+        type F = <PoseidonGoldilocksConfig as GenericConfig<2>>::F;
+
+        let mut trace_poly_values: [Vec<PolynomialValues<F>>; NUM_TABLES] = Default::default();
+        for table in [Table::Arithmetic, Table::Logic] {
+            trace_poly_values[table as usize] = (0..4)
+                .map(|_| PolynomialValues::new(F::rand_vec(8)))
+                .collect();
+        }
+        let ctls = self_referencing_ctls();
+        let ctl_data = self_referencing_ctl_data(&trace_poly_values, &ctls, 1);
+
+        // With constraint degree 5, the three looking Logic tables fit in a single helper column.
+        check_ctl_data_sizes(&ctl_data, &ctls, 1, 5);
+    }
+
+    #[test]
+    fn test_max_column_index() {
+        type F = <PoseidonGoldilocksConfig as GenericConfig<2>>::F;
+        assert_eq!(Column::<F>::constant(F::TWO).max_index(), None);
+        assert_eq!(
+            Column::<F>::linear_combination_and_next_row_with_constant(
+                [(3, F::ONE)],
+                [(7, F::ONE)],
+                F::ZERO
+            )
+            .max_index(),
+            Some(7)
+        );
+
+        let table = TableWithColumns::<F>::new(
+            Table::Arithmetic,
+            vec![Column::single(2), Column::le_bits([0, 1])],
+            Some(Filter::new(
+                vec![(Column::single(4), Column::single(9))],
+                vec![],
+            )),
+        );
+        assert_eq!(table.max_column_index(), Some(9));
+
+        let table = TableWithColumns::<F>::new(Table::Arithmetic, vec![Column::one()], None);
+        assert_eq!(table.max_column_index(), None);
+    }
+
+    #[test]
+    fn test_assert_columns_zero_where_filter_zero() {
+        type F = <PoseidonGoldilocksConfig as GenericConfig<2>>::F;
+        let trace = vec![
+            PolynomialValues::<F>::new(vec![F::ONE, F::ZERO]),
+            PolynomialValues::<F>::new(vec![F::TWO, F::ZERO]),
+        ];
+        let table = TableWithColumns::<F>::new(
+            Table::Arithmetic,
+            vec![Column::single(1)],
+            Some(Filter::new_simple(Column::single(0))),
+        );
+        assert_columns_zero_where_filter_zero(&trace, &table);
+    }
+
+    #[test]
+    #[should_panic(expected = "filtered-out rows [1]")]
+    fn test_assert_columns_zero_where_filter_zero_fails() {
+        type F = <PoseidonGoldilocksConfig as GenericConfig<2>>::F;
+        let trace = vec![
+            PolynomialValues::<F>::new(vec![F::ONE, F::ZERO]),
+            PolynomialValues::<F>::new(vec![F::TWO, F::TWO]),
+        ];
+        let table = TableWithColumns::<F>::new(
+            Table::Arithmetic,
+            vec![Column::single(1)],
+            Some(Filter::new_simple(Column::single(0))),
+        );
+        assert_columns_zero_where_filter_zero(&trace, &table);
     }
 }
@@ -1,32 +1,45 @@
-use core::mem::{self, MaybeUninit};
+use core::mem::size_of;
 use std::collections::BTreeMap;
+use std::fmt;
 use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use hashbrown::HashMap;
 use itertools::{zip_eq, Itertools};
+use once_cell::sync::OnceCell;
 use plonky2::field::extension::Extendable;
 use plonky2::fri::FriParams;
 use plonky2::gates::constant::ConstantGate;
 use plonky2::gates::noop::NoopGate;
-use plonky2::hash::hash_types::RichField;
+use plonky2::hash::hash_types::{HashOutTarget, RichField, NUM_HASH_OUT_ELTS};
+use plonky2::hash::merkle_proofs::MerkleCapTarget;
+use plonky2::hash::poseidon::PoseidonHash;
 use plonky2::iop::challenger::RecursiveChallenger;
 use plonky2::iop::target::{BoolTarget, Target};
 use plonky2::iop::witness::{PartialWitness, WitnessWrite};
 use plonky2::plonk::circuit_builder::CircuitBuilder;
 use plonky2::plonk::circuit_data::{
-    CircuitConfig, CircuitData, CommonCircuitData, VerifierCircuitTarget,
+    CircuitConfig, CircuitData, CommonCircuitData, VerifierCircuitData, VerifierCircuitTarget,
+    VerifierOnlyCircuitData,
 };
-use plonky2::plonk::config::{AlgebraicHasher, GenericConfig};
+use plonky2::plonk::config::{AlgebraicHasher, GenericConfig, GenericHashOut, Hasher};
 use plonky2::plonk::proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget};
 use plonky2::recursion::cyclic_recursion::check_cyclic_proof_verifier_data;
 use plonky2::recursion::dummy_circuit::cyclic_base_proof;
+use plonky2::timed;
 use plonky2::util::serialization::{
-    Buffer, GateSerializer, IoResult, Read, WitnessGeneratorSerializer, Write,
+    Buffer, GateSerializer, IoError, IoResult, Read, WitnessGeneratorSerializer, Write,
 };
 use plonky2::util::timing::TimingTree;
 use plonky2_util::log2_ceil;
 
-use crate::all_stark::{all_cross_table_lookups, AllStark, Table, NUM_TABLES};
+use crate::aggregation::AggregationAccumulator;
+use crate::all_stark::{
+    all_cross_table_lookups, AllStark, Table, ALL_TABLES_ACTIVE, NUM_PUBLIC_INPUT_USERDATA,
+    NUM_TABLES,
+};
+pub use crate::block_verifier::verify_block_with_vk;
 use crate::config::StarkConfig;
 use crate::cpu::kernel::assembler::Kernel;
 use crate::cross_table_lookup::{
@@ -34,22 +47,34 @@ use crate::cross_table_lookup::{
     GrandProductChallengeSet,
 };
 use crate::generation::state::{
-    AssumptionReceipt, AssumptionReceipts, CompositeReceipt, InnerReceipt, Receipt, ReceiptClaim,
+    check_assumptions, merge_assumptions, AssumptionReceipt, AssumptionReceipts, CompositeReceipt,
+    InnerReceipt, Receipt, ReceiptClaim, DEFAULT_MAX_ASSUMPTIONS,
 };
+use crate::generation::{generate_traces, generate_traces_with_assumptions};
 use crate::get_challenges::observe_public_values_target;
-use crate::proof::{MemRootsTarget, PublicValues, PublicValuesTarget, StarkProofWithMetadata};
-use crate::prover::{prove_with_output_and_assumptions, prove_with_outputs};
+use crate::proof::{
+    AllProof, MemRootsTarget, PublicValues, PublicValuesTarget, StarkProofWithMetadata,
+};
+use crate::prover::{prove_with_traces, prove_with_traces_with_progress, ProvePhase, ProveTimings};
 use crate::recursive_verifier::{
     add_common_recursion_gates, add_virtual_public_values, recursive_stark_circuit,
     set_public_value_targets, PlonkWrapperCircuit, PublicInputs, StarkWrapperCircuit,
 };
 use crate::stark::Stark;
-use crate::util::u32_array_to_u8_vec;
-use crate::verifier::verify_proof;
+use crate::util::{u32_array_to_u8_vec, CountingWriter};
+use crate::verifier::verify_proof_with_active_tables;
 //use crate::util::h256_limbs;
 
 /// The recursion threshold. We end a chain of recursive proofs once we reach this size.
-const THRESHOLD_DEGREE_BITS: usize = 13;
+pub const THRESHOLD_DEGREE_BITS: usize = 13;
+
+/// The version of the `RootCircuitData::to_buffer` format, written after the circuit data. To be
+/// bumped on any layout change, e.g. of the active-table flags that follow it.
+pub const ROOT_CIRCUIT_FORMAT_VERSION: u8 = 2;
+
+/// The version of the `AllRecursiveCircuits::to_bytes` format, written first. To be bumped on any
+/// layout change of the header or of the circuits following it, e.g. of the CTLs.
+pub const ALL_CIRCUITS_FORMAT_VERSION: u8 = 2;
 
 pub const RANGE_TABLES: [&str; 12] = [
     "ARITHMETIC",
@@ -66,6 +91,88 @@ pub const RANGE_TABLES: [&str; 12] = [
     "MEMORY",
 ];
 
+/// Checks that every table has at least one size to build recursive circuits for.
+///
+/// Each size is built once per table, since the circuits are keyed by size, so empty or
+/// reversed ranges are the only invalid inputs.
+pub fn validate_degree_bits_ranges(
+    degree_bits_ranges: &[Range<usize>; NUM_TABLES],
+) -> anyhow::Result<()> {
+    for (table, range) in degree_bits_ranges.iter().enumerate() {
+        anyhow::ensure!(
+            range.start < range.end,
+            "Empty degree bits range {:?} for {:?} table. To set it, run: export {}=\"x..y\" with x < y",
+            range,
+            Table::all()[table],
+            RANGE_TABLES[table],
+        );
+    }
+    Ok(())
+}
+
+/// An error proving with the recursive circuits. The prove methods of `AllRecursiveCircuits`
+/// return it as an `anyhow::Error`, from which it can be recovered with `downcast_ref`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RecursionError {
+    /// No shrinking circuits were built, or loaded, for STARK proofs of `table` with
+    /// `degree_bits`.
+    MissingCircuit { table: Table, degree_bits: usize },
+    /// The degree of a STARK proof of `table` can't be recovered from the proof.
+    MalformedProof { table: Table },
+    /// A STARK proof of `table` has a degree the circuits weren't built for.
+    DegreeOutOfRange {
+        table: Table,
+        degree_bits: usize,
+        range: Range<usize>,
+    },
+    /// Public values can't be set in the targets of a circuit.
+    PublicValuesConversion,
+    /// The userdata has more than `NUM_PUBLIC_INPUT_USERDATA` bytes.
+    UserdataTooLong { len: usize },
+    /// The shrinking circuits of `table` for `degree_bits` were built for STARK proofs without
+    /// FRI query rounds, so their rate can't be checked against an inner config.
+    NoQueryRounds { table: Table, degree_bits: usize },
+}
+
+impl fmt::Display for RecursionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingCircuit { table, degree_bits } => write!(
+                f,
+                "Missing preprocessed circuits for {table:?} STARK proofs of degree bits {degree_bits}"
+            ),
+            Self::MalformedProof { table } => write!(
+                f,
+                "Malformed {table:?} STARK proof: its degree can't be recovered"
+            ),
+            Self::DegreeOutOfRange {
+                table,
+                degree_bits,
+                range,
+            } => write!(
+                f,
+                "Proof degree out of configured range: the {table:?} STARK proof has degree bits {degree_bits}, but circuits were only built for {range:?}. To set it, run: export {}=\"{}..{}\"",
+                RANGE_TABLES[*table as usize],
+                range.start.min(*degree_bits),
+                range.end.max(degree_bits + 1),
+            ),
+            Self::PublicValuesConversion => {
+                write!(f, "Invalid conversion when setting public values targets.")
+            }
+            Self::UserdataTooLong { len } => write!(
+                f,
+                "Userdata is too long: {len} bytes, at most {NUM_PUBLIC_INPUT_USERDATA} are supported"
+            ),
+            Self::NoQueryRounds { table, degree_bits } => write!(
+                f,
+                "The {table:?} circuits for degree bits {degree_bits} were built for no FRI query rounds"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RecursionError {}
+
 /// Contains all recursive circuits used in the system.
 ///
 /// For each STARK and each initial `degree_bits`, this contains a chain of
@@ -86,6 +193,12 @@ where
     pub block: BlockCircuitData<F, C, D>,
     /// Holds chains of circuits for each table and for each initial `degree_bits`.
     by_table: [RecursiveCircuitsForTable<F, C, D>; NUM_TABLES],
+    /// A chain of circuits shrinking root proofs down to `THRESHOLD_DEGREE_BITS`, used by
+    /// `compress`. It is only built on first use, see `compress_wrappers`.
+    compress_wrappers: OnceCell<Vec<PlonkWrapperCircuit<F, C, D>>>,
+    /// The maximum number of assumptions of a receipt, see `check_assumptions`. It isn't part of
+    /// the circuits, so it isn't serialized.
+    max_assumptions: usize,
 }
 
 /// Data for the ZKVM root circuit, which is used to combine each STARK's shrunk wrapper proof
@@ -97,12 +210,16 @@ where
     C: GenericConfig<D, F = F>,
 {
     pub circuit: CircuitData<F, C, D>,
-    proof_with_pis: [ProofWithPublicInputsTarget<D>; NUM_TABLES],
+    /// The shrunk proof of each table, or `None` if the table is inactive.
+    proof_with_pis: [Option<ProofWithPublicInputsTarget<D>>; NUM_TABLES],
     /// For each table, various inner circuits may be used depending on the initial table size.
     /// This target holds the index of the circuit (within `final_circuits()`) that was used.
-    index_verifier_data: [Target; NUM_TABLES],
+    index_verifier_data: [Option<Target>; NUM_TABLES],
     /// Public inputs containing public values.
     public_values: PublicValuesTarget,
+    /// Public inputs holding the block circuit's verifier data, see `add_block_vk_public_inputs`.
+    /// They aren't used by ZKVM root proofs either.
+    block_vk: VerifierCircuitTarget,
     /// Public inputs used for cyclic verification. These aren't actually used for ZKVM root
     /// proofs; the circuit has them just to match the structure of aggregation proofs.
     cyclic_vk: VerifierCircuitTarget,
@@ -113,20 +230,25 @@ where
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
 {
-    pub fn to_buffer(
+    pub fn to_buffer<W: Write>(
         &self,
-        buffer: &mut Vec<u8>,
+        buffer: &mut W,
         gate_serializer: &dyn GateSerializer<F, D>,
         generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
     ) -> IoResult<()> {
         buffer.write_circuit_data(&self.circuit, gate_serializer, generator_serializer)?;
-        for proof in &self.proof_with_pis {
+        buffer.write_u8(ROOT_CIRCUIT_FORMAT_VERSION)?;
+        for active in self.active_tables() {
+            buffer.write_bool(active)?;
+        }
+        for proof in self.proof_with_pis.iter().flatten() {
             buffer.write_target_proof_with_public_inputs(proof)?;
         }
-        for index in self.index_verifier_data {
+        for &index in self.index_verifier_data.iter().flatten() {
             buffer.write_target(index)?;
         }
         self.public_values.to_buffer(buffer)?;
+        buffer.write_target_verifier_circuit(&self.block_vk)?;
         buffer.write_target_verifier_circuit(&self.cyclic_vk)?;
         Ok(())
     }
@@ -137,15 +259,31 @@ where
         generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
     ) -> IoResult<Self> {
         let circuit = buffer.read_circuit_data(gate_serializer, generator_serializer)?;
+        if buffer.read_u8()? != ROOT_CIRCUIT_FORMAT_VERSION {
+            return Err(IoError);
+        }
+        let mut active_tables = [false; NUM_TABLES];
+        for active in active_tables.iter_mut() {
+            *active = buffer.read_bool()?;
+        }
         let mut proof_with_pis = Vec::with_capacity(NUM_TABLES);
-        for _ in 0..NUM_TABLES {
-            proof_with_pis.push(buffer.read_target_proof_with_public_inputs()?);
+        for &active in &active_tables {
+            proof_with_pis.push(if active {
+                Some(buffer.read_target_proof_with_public_inputs()?)
+            } else {
+                None
+            });
         }
         let mut index_verifier_data = Vec::with_capacity(NUM_TABLES);
-        for _ in 0..NUM_TABLES {
-            index_verifier_data.push(buffer.read_target()?);
+        for &active in &active_tables {
+            index_verifier_data.push(if active {
+                Some(buffer.read_target()?)
+            } else {
+                None
+            });
         }
         let public_values = PublicValuesTarget::from_buffer(buffer)?;
+        let block_vk = buffer.read_target_verifier_circuit()?;
         let cyclic_vk = buffer.read_target_verifier_circuit()?;
 
         Ok(Self {
@@ -153,13 +291,19 @@ where
             proof_with_pis: proof_with_pis.try_into().unwrap(),
             index_verifier_data: index_verifier_data.try_into().unwrap(),
             public_values,
+            block_vk,
             cyclic_vk,
         })
     }
+
+    /// The tables whose proofs are verified by the root circuit.
+    pub fn active_tables(&self) -> [bool; NUM_TABLES] {
+        core::array::from_fn(|i| self.proof_with_pis[i].is_some())
+    }
 }
 
 /// Data for the aggregation circuit, which is used to compress two proofs into one. Each inner
-/// proof can be either an ZKVM root proof or another aggregation proof.
+/// proof can be either an ZKVM root proof, another aggregation proof or a block proof.
 #[derive(Eq, PartialEq, Debug)]
 pub struct AggregationCircuitData<F, C, const D: usize>
 where
@@ -170,6 +314,8 @@ where
     lhs: AggregationChildTarget<D>,
     rhs: AggregationChildTarget<D>,
     public_values: PublicValuesTarget,
+    /// The verifier data of block children, see `add_block_vk_public_inputs`.
+    block_vk: VerifierCircuitTarget,
     cyclic_vk: VerifierCircuitTarget,
 }
 
@@ -178,14 +324,15 @@ where
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
 {
-    pub fn to_buffer(
+    pub fn to_buffer<W: Write>(
         &self,
-        buffer: &mut Vec<u8>,
+        buffer: &mut W,
         gate_serializer: &dyn GateSerializer<F, D>,
         generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
     ) -> IoResult<()> {
         buffer.write_circuit_data(&self.circuit, gate_serializer, generator_serializer)?;
         buffer.write_target_verifier_circuit(&self.cyclic_vk)?;
+        buffer.write_target_verifier_circuit(&self.block_vk)?;
         self.public_values.to_buffer(buffer)?;
         self.lhs.to_buffer(buffer)?;
         self.rhs.to_buffer(buffer)?;
@@ -199,6 +346,7 @@ where
     ) -> IoResult<Self> {
         let circuit = buffer.read_circuit_data(gate_serializer, generator_serializer)?;
         let cyclic_vk = buffer.read_target_verifier_circuit()?;
+        let block_vk = buffer.read_target_verifier_circuit()?;
         let public_values = PublicValuesTarget::from_buffer(buffer)?;
         let lhs = AggregationChildTarget::from_buffer(buffer)?;
         let rhs = AggregationChildTarget::from_buffer(buffer)?;
@@ -207,21 +355,48 @@ where
             lhs,
             rhs,
             public_values,
+            block_vk,
             cyclic_vk,
         })
     }
 }
 
+/// The kind of proof verified by an `AggregationChildTarget`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AggregationChildKind {
+    Root,
+    Aggregation,
+    Block,
+}
+
+impl AggregationChildKind {
+    /// The kind of a child of `prove_aggregation`, which only takes root and aggregation proofs.
+    pub fn from_is_agg(is_agg: bool) -> Self {
+        if is_agg {
+            Self::Aggregation
+        } else {
+            Self::Root
+        }
+    }
+}
+
+/// A proof verified by the aggregation circuit. Aggregation proofs go through `agg_proof`, and are
+/// verified against the aggregation circuit itself. Root and block proofs go through `evm_proof`,
+/// and are verified against the root circuit or, if `is_block`, against the block verifier data
+/// of the aggregation proof. The root, aggregation and block circuits share their
+/// `CommonCircuitData`, so all three kinds of proofs have the same shape.
 #[derive(Eq, PartialEq, Debug)]
 pub struct AggregationChildTarget<const D: usize> {
     is_agg: BoolTarget,
+    is_block: BoolTarget,
     agg_proof: ProofWithPublicInputsTarget<D>,
     evm_proof: ProofWithPublicInputsTarget<D>,
 }
 
 impl<const D: usize> AggregationChildTarget<D> {
-    pub fn to_buffer(&self, buffer: &mut Vec<u8>) -> IoResult<()> {
+    pub fn to_buffer<W: Write>(&self, buffer: &mut W) -> IoResult<()> {
         buffer.write_target_bool(self.is_agg)?;
+        buffer.write_target_bool(self.is_block)?;
         buffer.write_target_proof_with_public_inputs(&self.agg_proof)?;
         buffer.write_target_proof_with_public_inputs(&self.evm_proof)?;
         Ok(())
@@ -229,23 +404,44 @@ impl<const D: usize> AggregationChildTarget<D> {
 
     pub fn from_buffer(buffer: &mut Buffer) -> IoResult<Self> {
         let is_agg = buffer.read_target_bool()?;
+        let is_block = buffer.read_target_bool()?;
         let agg_proof = buffer.read_target_proof_with_public_inputs()?;
         let evm_proof = buffer.read_target_proof_with_public_inputs()?;
         Ok(Self {
             is_agg,
+            is_block,
             agg_proof,
             evm_proof,
         })
     }
 
+    /// The public values of the child, whichever of the three kinds of proof it is.
     pub fn public_values<F: RichField + Extendable<D>>(
         &self,
         builder: &mut CircuitBuilder<F, D>,
     ) -> PublicValuesTarget {
-        let agg_pv = PublicValuesTarget::from_public_inputs(&self.agg_proof.public_inputs);
-        let evm_pv = PublicValuesTarget::from_public_inputs(&self.evm_proof.public_inputs);
+        // All children are proofs of circuits built to start with public values.
+        let agg_pv = PublicValuesTarget::from_public_inputs(&self.agg_proof.public_inputs)
+            .expect("Aggregation proofs start with public values");
+        // Root and block proofs share `evm_proof`, and the same layout of public values.
+        let evm_pv = PublicValuesTarget::from_public_inputs(&self.evm_proof.public_inputs)
+            .expect("Root and block proofs start with public values");
         PublicValuesTarget::select(builder, self.is_agg, agg_pv, evm_pv)
     }
+
+    fn set<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>>(
+        &self,
+        witness: &mut PartialWitness<F>,
+        kind: AggregationChildKind,
+        proof: &ProofWithPublicInputs<F, C, D>,
+    ) where
+        C::Hasher: AlgebraicHasher<F>,
+    {
+        witness.set_bool_target(self.is_agg, kind == AggregationChildKind::Aggregation);
+        witness.set_bool_target(self.is_block, kind == AggregationChildKind::Block);
+        witness.set_proof_with_pis_target(&self.agg_proof, proof);
+        witness.set_proof_with_pis_target(&self.evm_proof, proof);
+    }
 }
 
 #[derive(Eq, PartialEq, Debug)]
@@ -259,6 +455,8 @@ where
     parent_block_proof: ProofWithPublicInputsTarget<D>,
     agg_root_proof: ProofWithPublicInputsTarget<D>,
     public_values: PublicValuesTarget,
+    /// The block circuit's own verifier data, as in `cyclic_vk`, see `add_block_vk_public_inputs`.
+    block_vk: VerifierCircuitTarget,
     cyclic_vk: VerifierCircuitTarget,
 }
 
@@ -267,9 +465,9 @@ where
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
 {
-    pub fn to_buffer(
+    pub fn to_buffer<W: Write>(
         &self,
-        buffer: &mut Vec<u8>,
+        buffer: &mut W,
         gate_serializer: &dyn GateSerializer<F, D>,
         generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
     ) -> IoResult<()> {
@@ -278,6 +476,7 @@ where
         buffer.write_target_proof_with_public_inputs(&self.parent_block_proof)?;
         buffer.write_target_proof_with_public_inputs(&self.agg_root_proof)?;
         self.public_values.to_buffer(buffer)?;
+        buffer.write_target_verifier_circuit(&self.block_vk)?;
         buffer.write_target_verifier_circuit(&self.cyclic_vk)?;
         Ok(())
     }
@@ -292,6 +491,7 @@ where
         let parent_block_proof = buffer.read_target_proof_with_public_inputs()?;
         let agg_root_proof = buffer.read_target_proof_with_public_inputs()?;
         let public_values = PublicValuesTarget::from_buffer(buffer)?;
+        let block_vk = buffer.read_target_verifier_circuit()?;
         let cyclic_vk = buffer.read_target_verifier_circuit()?;
         Ok(Self {
             circuit,
@@ -299,11 +499,72 @@ where
             parent_block_proof,
             agg_root_proof,
             public_values,
+            block_vk,
             cyclic_vk,
         })
     }
 }
 
+/// Size of a preprocessed circuit, as reported by `AllRecursiveCircuits::size_report`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CircuitSize {
+    pub degree_bits: usize,
+    /// The number of rows of the circuit, `1 << degree_bits`, each holding at most one gate.
+    pub num_rows: usize,
+    /// The length of the serialized circuit data, including its targets.
+    pub serialized_bytes: usize,
+}
+
+/// Size of the shrinking chain of a table for one initial STARK `degree_bits`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TableCircuitSize {
+    pub stark_degree_bits: usize,
+    /// The number of shrinking wrappers following the initial STARK wrapper.
+    pub chain_length: usize,
+    /// The final circuit of the chain, the one verified by the root circuit.
+    pub final_circuit: CircuitSize,
+    /// The length of the serialized chain as a whole.
+    pub serialized_bytes: usize,
+}
+
+/// Sizes of all the circuits of an `AllRecursiveCircuits`, e.g. to size a circuit cache.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SizeReport {
+    pub root: CircuitSize,
+    pub aggregation: CircuitSize,
+    pub block: CircuitSize,
+    /// For each table, the chains ordered by initial STARK `degree_bits`.
+    pub by_table: [Vec<TableCircuitSize>; NUM_TABLES],
+}
+
+impl SizeReport {
+    /// The serialized length of all circuits, excluding the `to_bytes` header.
+    pub fn total_serialized_bytes(&self) -> usize {
+        self.root.serialized_bytes
+            + self.aggregation.serialized_bytes
+            + self.block.serialized_bytes
+            + self
+                .by_table
+                .iter()
+                .flatten()
+                .map(|table_size| table_size.serialized_bytes)
+                .sum::<usize>()
+    }
+}
+
+impl CircuitSize {
+    fn new<F: RichField + Extendable<D>, const D: usize>(
+        common: &CommonCircuitData<F, D>,
+        serialized_bytes: usize,
+    ) -> Self {
+        Self {
+            degree_bits: common.degree_bits(),
+            num_rows: common.degree(),
+            serialized_bytes,
+        }
+    }
+}
+
 impl<F, C, const D: usize> AllRecursiveCircuits<F, C, D>
 where
     F: RichField + Extendable<D>,
@@ -315,27 +576,66 @@ where
         gate_serializer: &dyn GateSerializer<F, D>,
         generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
     ) -> IoResult<Vec<u8>> {
-        // TODO: would be better to initialize it dynamically based on the supported max degree.
-        let mut buffer = Vec::with_capacity(1 << 34);
+        let mut buffer =
+            Vec::with_capacity(self.serialized_size(gate_serializer, generator_serializer)?);
+        // Header with the format version and the offset of each table's circuits, so that they
+        // can be loaded on their own with `from_bytes_table`. The offsets are filled in once known.
+        buffer.write_u8(ALL_CIRCUITS_FORMAT_VERSION)?;
+        buffer.write_usize(NUM_TABLES)?;
+        let header_start = buffer.len();
+        for _ in 0..NUM_TABLES {
+            buffer.write_usize(0)?;
+        }
         self.root
             .to_buffer(&mut buffer, gate_serializer, generator_serializer)?;
         self.aggregation
             .to_buffer(&mut buffer, gate_serializer, generator_serializer)?;
         self.block
             .to_buffer(&mut buffer, gate_serializer, generator_serializer)?;
-        for table in &self.by_table {
+        self.compress_wrappers_to_buffer(&mut buffer, gate_serializer, generator_serializer)?;
+        for (i, table) in self.by_table.iter().enumerate() {
+            let offset_pos = header_start + i * size_of::<u64>();
+            let offset = (buffer.len() as u64).to_le_bytes();
+            buffer[offset_pos..offset_pos + offset.len()].copy_from_slice(&offset);
             table.to_buffer(&mut buffer, gate_serializer, generator_serializer)?;
         }
         Ok(buffer)
     }
 
-    #[allow(clippy::missing_transmute_annotations)]
+    /// The length of `to_bytes`, measured with a `CountingWriter` so that nothing is allocated.
+    pub fn serialized_size(
+        &self,
+        gate_serializer: &dyn GateSerializer<F, D>,
+        generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
+    ) -> IoResult<usize> {
+        let mut writer = CountingWriter::new();
+        writer.write_u8(ALL_CIRCUITS_FORMAT_VERSION)?;
+        writer.write_usize(NUM_TABLES)?;
+        for _ in 0..NUM_TABLES {
+            writer.write_usize(0)?;
+        }
+        self.root
+            .to_buffer(&mut writer, gate_serializer, generator_serializer)?;
+        self.aggregation
+            .to_buffer(&mut writer, gate_serializer, generator_serializer)?;
+        self.block
+            .to_buffer(&mut writer, gate_serializer, generator_serializer)?;
+        self.compress_wrappers_to_buffer(&mut writer, gate_serializer, generator_serializer)?;
+        for table in &self.by_table {
+            table.to_buffer(&mut writer, gate_serializer, generator_serializer)?;
+        }
+        Ok(writer.bytes_written())
+    }
+
     pub fn from_bytes(
         bytes: &[u8],
         gate_serializer: &dyn GateSerializer<F, D>,
         generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
     ) -> IoResult<Self> {
         let mut buffer = Buffer::new(bytes);
+        // The tables are stored right after the compression circuits, so the offsets aren't
+        // needed here.
+        Self::read_header(&mut buffer)?;
         let root =
             RootCircuitData::from_buffer(&mut buffer, gate_serializer, generator_serializer)?;
         let aggregation = AggregationCircuitData::from_buffer(
@@ -345,178 +645,460 @@ where
         )?;
         let block =
             BlockCircuitData::from_buffer(&mut buffer, gate_serializer, generator_serializer)?;
+        let compress_wrappers = OnceCell::new();
+        if buffer.read_bool()? {
+            let wrappers =
+                shrinking_wrappers_from_buffer(&mut buffer, gate_serializer, generator_serializer)?;
+            compress_wrappers.set(wrappers).map_err(|_| IoError)?;
+        }
 
-        // Tricky use of MaybeUninit to remove the need for implementing Debug
-        // for all underlying types, necessary to convert a by_table Vec to an array.
-        let by_table = {
-            let mut by_table: [MaybeUninit<RecursiveCircuitsForTable<F, C, D>>; NUM_TABLES] =
-                unsafe { MaybeUninit::uninit().assume_init() };
-            for table in &mut by_table[..] {
-                let value = RecursiveCircuitsForTable::from_buffer(
-                    &mut buffer,
-                    gate_serializer,
-                    generator_serializer,
-                )?;
-                *table = MaybeUninit::new(value);
-            }
-            unsafe {
-                #[allow(clippy::missing_transmute_annotations)]
-                mem::transmute::<_, [RecursiveCircuitsForTable<F, C, D>; NUM_TABLES]>(by_table)
-            }
-        };
+        let by_table =
+            Self::by_table_from_buffer(&mut buffer, gate_serializer, generator_serializer)?;
 
         Ok(Self {
             root,
             aggregation,
             block,
             by_table,
+            compress_wrappers,
+            max_assumptions: DEFAULT_MAX_ASSUMPTIONS,
+        })
+    }
+
+    /// Loads the circuits of a single table from bytes written by `to_bytes`, without
+    /// deserializing the other circuits.
+    pub fn from_bytes_table(
+        bytes: &[u8],
+        table: Table,
+        gate_serializer: &dyn GateSerializer<F, D>,
+        generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
+    ) -> IoResult<RecursiveCircuitsForTable<F, C, D>> {
+        let offsets = Self::read_header(&mut Buffer::new(bytes))?;
+        let table_bytes = bytes.get(offsets[table as usize]..).ok_or(IoError)?;
+        RecursiveCircuitsForTable::from_buffer(
+            &mut Buffer::new(table_bytes),
+            gate_serializer,
+            generator_serializer,
+        )
+    }
+
+    /// Reports the degree, row count and serialized length of every circuit. Lengths are
+    /// measured with a `CountingWriter`, so nothing is actually serialized.
+    pub fn size_report(
+        &self,
+        gate_serializer: &dyn GateSerializer<F, D>,
+        generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
+    ) -> IoResult<SizeReport> {
+        let mut writer = CountingWriter::new();
+        self.root
+            .to_buffer(&mut writer, gate_serializer, generator_serializer)?;
+        let root = CircuitSize::new(&self.root.circuit.common, writer.bytes_written());
+
+        let mut writer = CountingWriter::new();
+        self.aggregation
+            .to_buffer(&mut writer, gate_serializer, generator_serializer)?;
+        let aggregation =
+            CircuitSize::new(&self.aggregation.circuit.common, writer.bytes_written());
+
+        let mut writer = CountingWriter::new();
+        self.block
+            .to_buffer(&mut writer, gate_serializer, generator_serializer)?;
+        let block = CircuitSize::new(&self.block.circuit.common, writer.bytes_written());
+
+        let by_table = self
+            .by_table
+            .iter()
+            .map(|table_circuits| table_circuits.size_report(gate_serializer, generator_serializer))
+            .collect::<IoResult<Vec<_>>>()?
+            .try_into()
+            .map_err(|_| IoError)?;
+
+        Ok(SizeReport {
+            root,
+            aggregation,
+            block,
+            by_table,
         })
     }
 
+    /// Reads the header written by `to_bytes`, returning the offset of each table's circuits.
+    /// Fails on bytes written in another format version.
+    fn read_header(buffer: &mut Buffer) -> IoResult<[usize; NUM_TABLES]> {
+        if buffer.read_u8()? != ALL_CIRCUITS_FORMAT_VERSION {
+            return Err(IoError);
+        }
+        if buffer.read_usize()? != NUM_TABLES {
+            return Err(IoError);
+        }
+        let mut offsets = [0; NUM_TABLES];
+        for offset in offsets.iter_mut() {
+            *offset = buffer.read_usize()?;
+        }
+        Ok(offsets)
+    }
+
+    /// Writes the compression chain if it was built, preceded by a flag saying whether it was.
+    fn compress_wrappers_to_buffer<W: Write>(
+        &self,
+        buffer: &mut W,
+        gate_serializer: &dyn GateSerializer<F, D>,
+        generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
+    ) -> IoResult<()> {
+        buffer.write_bool(self.compress_wrappers.get().is_some())?;
+        if let Some(compress_wrappers) = self.compress_wrappers.get() {
+            shrinking_wrappers_to_buffer(
+                compress_wrappers,
+                buffer,
+                gate_serializer,
+                generator_serializer,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Builds the shrinking chains of `table` with the STARK of `all_stark` it is generated for,
+    /// returning them along with the time it took.
+    fn build_table_circuits(
+        all_stark: &AllStark<F, D>,
+        table: Table,
+        degree_bits_ranges: &[Range<usize>; NUM_TABLES],
+        stark_config: &StarkConfig,
+        shrinking_config: &CircuitConfig,
+    ) -> (RecursiveCircuitsForTable<F, C, D>, Duration) {
+        let start = Instant::now();
+        let degree_bits_range = degree_bits_ranges[table as usize].clone();
+        let ctls = &all_stark.cross_table_lookups;
+        let circuits = match table {
+            Table::Arithmetic => RecursiveCircuitsForTable::new(
+                table,
+                &all_stark.arithmetic_stark,
+                degree_bits_range,
+                ctls,
+                stark_config,
+                shrinking_config,
+            ),
+            Table::Cpu => RecursiveCircuitsForTable::new(
+                table,
+                &all_stark.cpu_stark,
+                degree_bits_range,
+                ctls,
+                stark_config,
+                shrinking_config,
+            ),
+            Table::Poseidon => RecursiveCircuitsForTable::new(
+                table,
+                &all_stark.poseidon_stark,
+                degree_bits_range,
+                ctls,
+                stark_config,
+                shrinking_config,
+            ),
+            Table::PoseidonSponge => RecursiveCircuitsForTable::new(
+                table,
+                &all_stark.poseidon_sponge_stark,
+                degree_bits_range,
+                ctls,
+                stark_config,
+                shrinking_config,
+            ),
+            Table::Keccak => RecursiveCircuitsForTable::new(
+                table,
+                &all_stark.keccak_stark,
+                degree_bits_range,
+                ctls,
+                stark_config,
+                shrinking_config,
+            ),
+            Table::KeccakSponge => RecursiveCircuitsForTable::new(
+                table,
+                &all_stark.keccak_sponge_stark,
+                degree_bits_range,
+                ctls,
+                stark_config,
+                shrinking_config,
+            ),
+            Table::ShaExtend => RecursiveCircuitsForTable::new(
+                table,
+                &all_stark.sha_extend_stark,
+                degree_bits_range,
+                ctls,
+                stark_config,
+                shrinking_config,
+            ),
+            Table::ShaExtendSponge => RecursiveCircuitsForTable::new(
+                table,
+                &all_stark.sha_extend_sponge_stark,
+                degree_bits_range,
+                ctls,
+                stark_config,
+                shrinking_config,
+            ),
+            Table::ShaCompress => RecursiveCircuitsForTable::new(
+                table,
+                &all_stark.sha_compress_stark,
+                degree_bits_range,
+                ctls,
+                stark_config,
+                shrinking_config,
+            ),
+            Table::ShaCompressSponge => RecursiveCircuitsForTable::new(
+                table,
+                &all_stark.sha_compress_sponge_stark,
+                degree_bits_range,
+                ctls,
+                stark_config,
+                shrinking_config,
+            ),
+            Table::Logic => RecursiveCircuitsForTable::new(
+                table,
+                &all_stark.logic_stark,
+                degree_bits_range,
+                ctls,
+                stark_config,
+                shrinking_config,
+            ),
+            Table::Memory => RecursiveCircuitsForTable::new(
+                table,
+                &all_stark.memory_stark,
+                degree_bits_range,
+                ctls,
+                stark_config,
+                shrinking_config,
+            ),
+        };
+        let time = start.elapsed();
+        log::debug!("Built the {:?} recursion circuits in {:?}", table, time);
+        (circuits, time)
+    }
+
+    fn by_table_from_buffer(
+        buffer: &mut Buffer,
+        gate_serializer: &dyn GateSerializer<F, D>,
+        generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
+    ) -> IoResult<[RecursiveCircuitsForTable<F, C, D>; NUM_TABLES]> {
+        let by_table = (0..NUM_TABLES)
+            .map(|_| {
+                RecursiveCircuitsForTable::from_buffer(
+                    buffer,
+                    gate_serializer,
+                    generator_serializer,
+                )
+            })
+            .collect::<IoResult<Vec<_>>>()?;
+        // Going through a `Vec` avoids requiring `Debug` for all underlying types.
+        by_table.try_into().map_err(|_| IoError)
+    }
+
     /// Preprocess all recursive circuits used by the system.
     pub fn new(
         all_stark: &AllStark<F, D>,
         degree_bits_ranges: &[Range<usize>; NUM_TABLES],
         stark_config: &StarkConfig,
     ) -> Self {
-        let arithmetic = RecursiveCircuitsForTable::new(
-            Table::Arithmetic,
-            &all_stark.arithmetic_stark,
-            degree_bits_ranges[Table::Arithmetic as usize].clone(),
-            &all_stark.cross_table_lookups,
-            stark_config,
-        );
-        let cpu = RecursiveCircuitsForTable::new(
-            Table::Cpu,
-            &all_stark.cpu_stark,
-            degree_bits_ranges[Table::Cpu as usize].clone(),
-            &all_stark.cross_table_lookups,
-            stark_config,
-        );
-        let poseidon = RecursiveCircuitsForTable::new(
-            Table::Poseidon,
-            &all_stark.poseidon_stark,
-            degree_bits_ranges[Table::Poseidon as usize].clone(),
-            &all_stark.cross_table_lookups,
-            stark_config,
-        );
-        let poseidon_sponge = RecursiveCircuitsForTable::new(
-            Table::PoseidonSponge,
-            &all_stark.poseidon_sponge_stark,
-            degree_bits_ranges[Table::PoseidonSponge as usize].clone(),
-            &all_stark.cross_table_lookups,
-            stark_config,
-        );
-        let keccak = RecursiveCircuitsForTable::new(
-            Table::Keccak,
-            &all_stark.keccak_stark,
-            degree_bits_ranges[Table::Keccak as usize].clone(),
-            &all_stark.cross_table_lookups,
-            stark_config,
-        );
-        let keccak_sponge = RecursiveCircuitsForTable::new(
-            Table::KeccakSponge,
-            &all_stark.keccak_sponge_stark,
-            degree_bits_ranges[Table::KeccakSponge as usize].clone(),
-            &all_stark.cross_table_lookups,
-            stark_config,
-        );
-
-        let sha_extend = RecursiveCircuitsForTable::new(
-            Table::ShaExtend,
-            &all_stark.sha_extend_stark,
-            degree_bits_ranges[Table::ShaExtend as usize].clone(),
-            &all_stark.cross_table_lookups,
+        Self::new_with_active(
+            all_stark,
+            degree_bits_ranges,
             stark_config,
-        );
+            &ALL_TABLES_ACTIVE,
+        )
+    }
 
-        let sha_extend_sponge = RecursiveCircuitsForTable::new(
-            Table::ShaExtendSponge,
-            &all_stark.sha_extend_sponge_stark,
-            degree_bits_ranges[Table::ShaExtendSponge as usize].clone(),
-            &all_stark.cross_table_lookups,
+    /// Preprocess the recursive circuits of `active_tables` only, e.g. leaving out the SHA and
+    /// Keccak tables for programs which never call these precompiles. The root circuit doesn't
+    /// verify the proofs of inactive tables and counts them as empty in cross-table lookups, so
+    /// segments using an inactive table can't be proven. The CPU table must be active.
+    pub fn new_with_active(
+        all_stark: &AllStark<F, D>,
+        degree_bits_ranges: &[Range<usize>; NUM_TABLES],
+        stark_config: &StarkConfig,
+        active_tables: &[bool; NUM_TABLES],
+    ) -> Self {
+        Self::new_with_security(
+            all_stark,
+            degree_bits_ranges,
             stark_config,
-        );
+            active_tables,
+            false,
+        )
+    }
 
-        let sha_compress = RecursiveCircuitsForTable::new(
-            Table::ShaCompress,
-            &all_stark.sha_compress_stark,
-            degree_bits_ranges[Table::ShaCompress as usize].clone(),
-            &all_stark.cross_table_lookups,
+    /// Preprocess all recursive circuits with only `INSECURE_TEST_NUM_QUERY_ROUNDS` FRI query
+    /// rounds, so that tests of the root, aggregation and block flow run in seconds.
+    ///
+    /// WARNING: proofs generated with these circuits are NOT SOUND. This must never be used
+    /// outside of tests, hence it is only available with the `insecure-test` feature.
+    #[cfg(feature = "insecure-test")]
+    pub fn new_test_only_reduced_security(
+        all_stark: &AllStark<F, D>,
+        degree_bits_ranges: &[Range<usize>; NUM_TABLES],
+        stark_config: &StarkConfig,
+    ) -> Self {
+        log::warn!("Building recursive circuits with reduced security; proofs are NOT sound.");
+        Self::new_with_security(
+            all_stark,
+            degree_bits_ranges,
             stark_config,
-        );
+            &ALL_TABLES_ACTIVE,
+            true,
+        )
+    }
 
-        let sha_compress_sponge = RecursiveCircuitsForTable::new(
-            Table::ShaCompressSponge,
-            &all_stark.sha_compress_sponge_stark,
-            degree_bits_ranges[Table::ShaCompressSponge as usize].clone(),
-            &all_stark.cross_table_lookups,
-            stark_config,
+    fn new_with_security(
+        all_stark: &AllStark<F, D>,
+        degree_bits_ranges: &[Range<usize>; NUM_TABLES],
+        stark_config: &StarkConfig,
+        active_tables: &[bool; NUM_TABLES],
+        test_only_reduced_security: bool,
+    ) -> Self {
+        // Fail before spending minutes building circuits.
+        if let Err(e) = validate_degree_bits_ranges(degree_bits_ranges) {
+            panic!("{e}");
+        }
+        if let Err(e) = all_stark.check_constraint_degrees() {
+            panic!("{e}");
+        }
+        assert!(
+            active_tables[Table::Cpu as usize],
+            "The CPU table must be active."
         );
-
-        let logic = RecursiveCircuitsForTable::new(
-            Table::Logic,
-            &all_stark.logic_stark,
-            degree_bits_ranges[Table::Logic as usize].clone(),
-            &all_stark.cross_table_lookups,
-            stark_config,
+        let touched = CrossTableLookup::tables_touched(&all_stark.cross_table_lookups);
+        for table in Table::all() {
+            let i = table as usize;
+            if active_tables[i] && !touched[i] && !degree_bits_ranges[i].is_empty() {
+                log::warn!(
+                    "Building {:?} circuits for degree bits {:?}, but no cross-table lookup \
+                     references this table.",
+                    table,
+                    degree_bits_ranges[i]
+                );
+            }
+        }
+        // No circuits are needed for inactive tables.
+        let degree_bits_ranges = &core::array::from_fn(|i| {
+            if active_tables[i] {
+                degree_bits_ranges[i].clone()
+            } else {
+                0..0
+            }
+        });
+        let recursion_config = recursion_config(test_only_reduced_security);
+        let shrinking_config = shrinking_config(test_only_reduced_security);
+        // The shrinking chains of the tables are independent, and the bulk of the setup cost, so
+        // they are built on separate threads. Circuit building is deterministic, so the result
+        // doesn't depend on scheduling.
+        let start = Instant::now();
+        let by_table_timed = std::thread::scope(|scope| {
+            let shrinking_config = &shrinking_config;
+            // All threads are spawned before the first one is joined. A failed build panics with
+            // its original payload.
+            Table::all()
+                .map(|table| {
+                    scope.spawn(move || {
+                        Self::build_table_circuits(
+                            all_stark,
+                            table,
+                            degree_bits_ranges,
+                            stark_config,
+                            shrinking_config,
+                        )
+                    })
+                })
+                .map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|payload| std::panic::resume_unwind(payload))
+                })
+        });
+        let serial_time = by_table_timed
+            .iter()
+            .map(|(_, time)| *time)
+            .sum::<Duration>();
+        log::info!(
+            "Built the table recursion circuits in {:?}, {:?} if built serially",
+            start.elapsed(),
+            serial_time
         );
-        let memory = RecursiveCircuitsForTable::new(
-            Table::Memory,
-            &all_stark.memory_stark,
-            degree_bits_ranges[Table::Memory as usize].clone(),
-            &all_stark.cross_table_lookups,
+        let by_table = by_table_timed.map(|(circuits, _)| circuits);
+        let (root, aggregation, block) = Self::create_segment_circuits(
+            &by_table,
+            active_tables,
             stark_config,
+            &recursion_config,
         );
-
-        let by_table = [
-            arithmetic,
-            cpu,
-            poseidon,
-            poseidon_sponge,
-            keccak,
-            keccak_sponge,
-            sha_extend,
-            sha_extend_sponge,
-            sha_compress,
-            sha_compress_sponge,
-            logic,
-            memory,
-        ];
-        let root = Self::create_root_circuit(&by_table, stark_config);
-        let aggregation = Self::create_aggregation_circuit(&root);
-        let block = Self::create_block_circuit(&aggregation);
         Self {
             root,
             aggregation,
             block,
             by_table,
+            compress_wrappers: OnceCell::new(),
+            max_assumptions: DEFAULT_MAX_ASSUMPTIONS,
+        }
+    }
+
+    /// Builds the root, aggregation and block circuits with the same degree, so that they share
+    /// their `CommonCircuitData` and the aggregation circuit can verify any of them. The block
+    /// circuit is the largest, so the root circuit is rebuilt with its degree until they agree.
+    fn create_segment_circuits(
+        by_table: &[RecursiveCircuitsForTable<F, C, D>; NUM_TABLES],
+        active_tables: &[bool; NUM_TABLES],
+        stark_config: &StarkConfig,
+        recursion_config: &CircuitConfig,
+    ) -> (
+        RootCircuitData<F, C, D>,
+        AggregationCircuitData<F, C, D>,
+        BlockCircuitData<F, C, D>,
+    ) {
+        let mut min_degree_bits = 0;
+        loop {
+            let root = Self::create_root_circuit(
+                by_table,
+                active_tables,
+                stark_config,
+                recursion_config,
+                min_degree_bits,
+            );
+            let aggregation = Self::create_aggregation_circuit(&root);
+            let block = Self::create_block_circuit(&aggregation, recursion_config, &[]);
+            let block_degree_bits = block.circuit.common.degree_bits();
+            if block_degree_bits == root.circuit.common.degree_bits() {
+                return (root, aggregation, block);
+            }
+            min_degree_bits = block_degree_bits;
         }
     }
 
     fn create_root_circuit(
         by_table: &[RecursiveCircuitsForTable<F, C, D>; NUM_TABLES],
+        active_tables: &[bool; NUM_TABLES],
         stark_config: &StarkConfig,
+        recursion_config: &CircuitConfig,
+        min_degree_bits: usize,
     ) -> RootCircuitData<F, C, D> {
-        let inner_common_data: [_; NUM_TABLES] =
-            core::array::from_fn(|i| &by_table[i].final_circuits()[0].common);
+        let inner_common_data: [_; NUM_TABLES] = core::array::from_fn(|i| {
+            active_tables[i].then(|| &by_table[i].final_circuits()[0].common)
+        });
 
-        let mut builder = CircuitBuilder::new(CircuitConfig::standard_recursion_config());
+        let mut builder = CircuitBuilder::new(recursion_config.clone());
 
         let public_values = add_virtual_public_values(&mut builder);
+        public_values.check_userdata_len(&mut builder);
 
-        let recursive_proofs =
-            core::array::from_fn(|i| builder.add_virtual_proof_with_pis(inner_common_data[i]));
+        let recursive_proofs: [_; NUM_TABLES] = core::array::from_fn(|i| {
+            inner_common_data[i].map(|common_data| builder.add_virtual_proof_with_pis(common_data))
+        });
         let pis: [_; NUM_TABLES] = core::array::from_fn(|i| {
-            PublicInputs::<Target, <C::Hasher as AlgebraicHasher<F>>::AlgebraicPermutation>::from_vec(
-                &recursive_proofs[i].public_inputs,
-                stark_config,
-            )
+            recursive_proofs[i].as_ref().map(|proof| {
+                PublicInputs::<Target, <C::Hasher as AlgebraicHasher<F>>::AlgebraicPermutation>::from_vec(
+                    &proof.public_inputs,
+                    stark_config,
+                )
+            })
         });
-        let index_verifier_data = core::array::from_fn(|_i| builder.add_virtual_target());
+        let index_verifier_data =
+            core::array::from_fn(|i| active_tables[i].then(|| builder.add_virtual_target()));
 
         let mut challenger = RecursiveChallenger::<F, C::Hasher, D>::new(&mut builder);
-        for pi in &pis {
+        for pi in pis.iter().flatten() {
             for h in &pi.trace_cap {
                 challenger.observe_elements(h);
             }
@@ -530,7 +1112,7 @@ where
             stark_config.num_challenges,
         );
         // Check that the correct CTL challenges are used in every proof.
-        for pi in &pis {
+        for pi in pis.iter().flatten() {
             for i in 0..stark_config.num_challenges {
                 builder.connect(
                     ctl_challenges.challenges[i].beta,
@@ -543,33 +1125,47 @@ where
             }
         }
 
-        let state = challenger.compact(&mut builder);
-        for (&before, &s) in zip_eq(state.as_ref(), pis[0].challenger_state_before.as_ref()) {
-            builder.connect(before, s);
-        }
-        // Check that the challenger state is consistent between proofs.
-        for i in 1..NUM_TABLES {
-            for (&before, &after) in zip_eq(
-                pis[i].challenger_state_before.as_ref(),
-                pis[i - 1].challenger_state_after.as_ref(),
-            ) {
-                builder.connect(before, after);
+        // Check that the challenger state is consistent between the proofs of active tables.
+        let mut state = challenger.compact(&mut builder);
+        for pi in pis.iter().flatten() {
+            for (&before, &s) in zip_eq(pi.challenger_state_before.as_ref(), state.as_ref()) {
+                builder.connect(before, s);
             }
+            state = pi.challenger_state_after.clone();
         }
 
-        // Verify the CTL checks.
+        // Verify the CTL checks. Inactive tables count as empty: the lookups of active tables
+        // into them, and from them, must sum to zero.
+        let all_ctls = all_cross_table_lookups();
+        let zero = builder.zero();
+        let ctl_zs_first = core::array::from_fn(|i| match &pis[i] {
+            Some(pi) => pi.ctl_zs_first.clone(),
+            None => vec![
+                zero;
+                CrossTableLookup::num_ctl_zs(
+                    &all_ctls,
+                    Table::all()[i],
+                    stark_config.num_challenges
+                )
+            ],
+        });
         verify_cross_table_lookups_circuit::<F, D>(
             &mut builder,
-            all_cross_table_lookups(),
-            pis.map(|p| p.ctl_zs_first),
+            all_ctls,
+            ctl_zs_first,
             stark_config,
         );
 
         for (i, table_circuits) in by_table.iter().enumerate() {
+            let (Some(inner_common_data), Some(recursive_proof)) =
+                (inner_common_data[i], &recursive_proofs[i])
+            else {
+                continue;
+            };
             let final_circuits = table_circuits.final_circuits();
             for final_circuit in &final_circuits {
                 assert_eq!(
-                    &final_circuit.common, inner_common_data[i],
+                    &final_circuit.common, inner_common_data,
                     "common_data mismatch"
                 );
             }
@@ -582,30 +1178,38 @@ where
             while !possible_vks.len().is_power_of_two() {
                 possible_vks.push(possible_vks[0].clone());
             }
-            let inner_verifier_data =
-                builder.random_access_verifier_data(index_verifier_data[i], possible_vks);
-
-            builder.verify_proof::<C>(
-                &recursive_proofs[i],
-                &inner_verifier_data,
-                inner_common_data[i],
+            let inner_verifier_data = builder.random_access_verifier_data(
+                index_verifier_data[i].expect("Active tables have a verifier data index"),
+                possible_vks,
             );
+
+            builder.verify_proof::<C>(recursive_proof, &inner_verifier_data, inner_common_data);
         }
 
         // We want ZKVM root proofs to have the exact same structure as aggregation proofs, so we add
         // public inputs for cyclic verification, even though they'll be ignored.
+        let block_vk = add_block_vk_public_inputs(&mut builder);
         let cyclic_vk = builder.add_verifier_data_public_inputs();
 
-        builder.add_gate(
-            ConstantGate::new(inner_common_data[0].config.num_constants),
-            vec![],
-        );
+        let num_constants = inner_common_data
+            .iter()
+            .flatten()
+            .next()
+            .expect("The CPU table is active")
+            .config
+            .num_constants;
+        builder.add_gate(ConstantGate::new(num_constants), vec![]);
+
+        while log2_ceil(builder.num_gates()) < min_degree_bits {
+            builder.add_gate(NoopGate, vec![]);
+        }
 
         RootCircuitData {
             circuit: builder.build::<C>(),
             proof_with_pis: recursive_proofs,
             index_verifier_data,
             public_values,
+            block_vk,
             cyclic_vk,
         }
     }
@@ -615,9 +1219,10 @@ where
     ) -> AggregationCircuitData<F, C, D> {
         let mut builder = CircuitBuilder::<F, D>::new(root.circuit.common.config.clone());
         let public_values = add_virtual_public_values(&mut builder);
+        let block_vk = add_block_vk_public_inputs(&mut builder);
         let cyclic_vk = builder.add_verifier_data_public_inputs();
-        let lhs = Self::add_agg_child(&mut builder, root);
-        let rhs = Self::add_agg_child(&mut builder, root);
+        let lhs = Self::add_agg_child(&mut builder, root, &block_vk);
+        let rhs = Self::add_agg_child(&mut builder, root, &block_vk);
 
         let lhs_public_values = lhs.public_values(&mut builder);
         let rhs_public_values = rhs.public_values(&mut builder);
@@ -640,35 +1245,30 @@ where
             rhs_public_values.roots_before,
         );
 
-        // Connect agg `userdata` with lhs `userdata`.
-        for (limb0, limb1) in public_values
-            .userdata
-            .iter()
-            .zip_eq(&lhs_public_values.userdata)
-        {
-            builder.connect(*limb0, *limb1);
-        }
-
-        // Connect agg `userdata` with rhs `userdata`.
-        for (limb0, limb1) in public_values
-            .userdata
-            .iter()
-            .zip_eq(&rhs_public_values.userdata)
-        {
-            builder.connect(*limb0, *limb1);
-        }
+        // Connect agg `userdata` with lhs and rhs `userdata`.
+        PublicValuesTarget::connect_userdata(&mut builder, &public_values, &lhs_public_values);
+        PublicValuesTarget::connect_userdata(&mut builder, &public_values, &rhs_public_values);
 
-        // Pad to match the root circuit's degree.
+        // Pad to match the root circuit's degree. Both children are verified against the root
+        // circuit's `CommonCircuitData`, including when they are aggregation proofs, so the
+        // aggregation circuit must end up with exactly the same degree as the root circuit.
+        // The block circuit derives its own degree from this one, see `create_block_circuit`.
         while log2_ceil(builder.num_gates()) < root.circuit.common.degree_bits() {
             builder.add_gate(NoopGate, vec![]);
         }
 
         let circuit = builder.build::<C>();
+        assert_eq!(
+            circuit.common.degree_bits(),
+            root.circuit.common.degree_bits(),
+            "The aggregation circuit must have the same degree as the root circuit."
+        );
         AggregationCircuitData {
             circuit,
             lhs,
             rhs,
             public_values,
+            block_vk,
             cyclic_vk,
         }
     }
@@ -676,43 +1276,90 @@ where
     fn add_agg_child(
         builder: &mut CircuitBuilder<F, D>,
         root: &RootCircuitData<F, C, D>,
+        block_vk: &VerifierCircuitTarget,
     ) -> AggregationChildTarget<D> {
         let common = &root.circuit.common;
         let root_vk = builder.constant_verifier_data(&root.circuit.verifier_only);
         let is_agg = builder.add_virtual_bool_target_safe();
+        let is_block = builder.add_virtual_bool_target_safe();
+        let is_agg_and_block = builder.and(is_agg, is_block);
+        builder.assert_zero(is_agg_and_block.target);
         let agg_proof = builder.add_virtual_proof_with_pis(common);
         let evm_proof = builder.add_virtual_proof_with_pis(common);
+        let evm_vk = select_verifier_data(builder, is_block, block_vk, &root_vk);
         builder
-            .conditionally_verify_cyclic_proof::<C>(
-                is_agg, &agg_proof, &evm_proof, &root_vk, common,
-            )
+            .conditionally_verify_cyclic_proof::<C>(is_agg, &agg_proof, &evm_proof, &evm_vk, common)
             .expect("Failed to build cyclic recursion circuit");
+        // An aggregation child must have verified its own block children against the same
+        // verifier data.
+        let child_block_vk = block_vk_from_public_inputs(
+            &agg_proof.public_inputs,
+            common.config.fri_config.cap_height,
+        );
+        conditionally_connect_verifier_data(builder, is_agg, &child_block_vk, block_vk);
         AggregationChildTarget {
             is_agg,
+            is_block,
             agg_proof,
             evm_proof,
         }
     }
 
-    fn create_block_circuit(agg: &AggregationCircuitData<F, C, D>) -> BlockCircuitData<F, C, D> {
+    fn create_block_circuit(
+        agg: &AggregationCircuitData<F, C, D>,
+        recursion_config: &CircuitConfig,
+        counter_limbs: &[usize],
+    ) -> BlockCircuitData<F, C, D> {
+        // The block circuit verifies proofs of itself, so it has to know its own degree before
+        // being built. It is at least as large as the aggregation circuit, since both verify two
+        // inner proofs, so start from the aggregation degree and rebuild with the degree actually
+        // reached until the two agree. This usually takes one or two builds.
+        let mut degree_bits = agg.circuit.common.degree_bits();
+        loop {
+            let block = Self::create_block_circuit_with_degree(
+                agg,
+                recursion_config,
+                counter_limbs,
+                degree_bits,
+            );
+            let block_degree_bits = block.circuit.common.degree_bits();
+            if block_degree_bits == degree_bits {
+                return block;
+            }
+            // Padding ensures the circuit is never smaller than expected.
+            assert!(block_degree_bits > degree_bits);
+            degree_bits = block_degree_bits;
+        }
+    }
+
+    /// Builds the block circuit, assuming parent block proofs have `degree_bits`.
+    fn create_block_circuit_with_degree(
+        agg: &AggregationCircuitData<F, C, D>,
+        recursion_config: &CircuitConfig,
+        counter_limbs: &[usize],
+        degree_bits: usize,
+    ) -> BlockCircuitData<F, C, D> {
         // The block circuit is similar to the agg circuit; both verify two inner proofs.
         // We need to adjust a few things, but it's easier than making a new CommonCircuitData.
         let expected_common_data = CommonCircuitData {
             fri_params: FriParams {
-                degree_bits: 14,
+                degree_bits,
                 ..agg.circuit.common.fri_params.clone()
             },
             ..agg.circuit.common.clone()
         };
 
-        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let mut builder = CircuitBuilder::<F, D>::new(recursion_config.clone());
         let public_values = add_virtual_public_values(&mut builder);
+        let block_vk = add_block_vk_public_inputs(&mut builder);
         let has_parent_block = builder.add_virtual_bool_target_safe();
         let parent_block_proof = builder.add_virtual_proof_with_pis(&expected_common_data);
         let agg_root_proof = builder.add_virtual_proof_with_pis(&agg.circuit.common);
 
-        let parent_pv = PublicValuesTarget::from_public_inputs(&parent_block_proof.public_inputs);
-        let agg_pv = PublicValuesTarget::from_public_inputs(&agg_root_proof.public_inputs);
+        let parent_pv = PublicValuesTarget::from_public_inputs(&parent_block_proof.public_inputs)
+            .expect("Block proofs start with public values");
+        let agg_pv = PublicValuesTarget::from_public_inputs(&agg_root_proof.public_inputs)
+            .expect("Aggregation proofs start with public values");
 
         // Connect block `trie_roots_before` with parent_pv `trie_roots_before`.
         MemRootsTarget::connect(
@@ -722,13 +1369,18 @@ where
         );
         // Connect the rest of block `public_values` with agg_pv.
         MemRootsTarget::connect(&mut builder, public_values.roots_after, agg_pv.roots_after);
+        // The parent userdata is the one of its own aggregation proof, so that counters are
+        // checked against what the parent block actually proved.
+        PublicValuesTarget::connect_userdata(&mut builder, &public_values, &agg_pv);
 
         // Make connections between block proofs, and check initial and final block values.
-        Self::connect_block_proof(&mut builder, has_parent_block, &parent_pv, &agg_pv);
-
-        for (&limb0, &limb1) in parent_pv.userdata.iter().zip_eq(&agg_pv.userdata) {
-            builder.connect(limb0, limb1);
-        }
+        Self::connect_block_proof(
+            &mut builder,
+            has_parent_block,
+            &parent_pv,
+            &agg_pv,
+            counter_limbs,
+        );
 
         let cyclic_vk = builder.add_verifier_data_public_inputs();
         builder
@@ -742,6 +1394,20 @@ where
         let agg_verifier_data = builder.constant_verifier_data(&agg.circuit.verifier_only);
         builder.verify_proof::<C>(&agg_root_proof, &agg_verifier_data, &agg.circuit.common);
 
+        // Block proofs aggregated into the aggregation proof must be proofs of this circuit.
+        let agg_block_vk = block_vk_from_public_inputs(
+            &agg_root_proof.public_inputs,
+            agg.circuit.common.config.fri_config.cap_height,
+        );
+        connect_verifier_data(&mut builder, &agg_block_vk, &cyclic_vk);
+        connect_verifier_data(&mut builder, &block_vk, &cyclic_vk);
+
+        // Pad up to the expected cyclic degree, e.g. with fewer query rounds the circuit may be
+        // smaller than a previous build.
+        while log2_ceil(builder.num_gates()) < expected_common_data.degree_bits() {
+            builder.add_gate(NoopGate, vec![]);
+        }
+
         let circuit = builder.build::<C>();
         BlockCircuitData {
             circuit,
@@ -749,23 +1415,81 @@ where
             parent_block_proof,
             agg_root_proof,
             public_values,
+            block_vk,
             cyclic_vk,
         }
     }
 
     fn connect_block_proof(
         builder: &mut CircuitBuilder<F, D>,
-        _has_parent_block: BoolTarget,
+        has_parent_block: BoolTarget,
         lhs: &PublicValuesTarget,
         rhs: &PublicValuesTarget,
+        counter_limbs: &[usize],
     ) {
         // Between blocks, we only connect state tries and userdata.
         for (&limb0, limb1) in lhs.roots_after.root.iter().zip(rhs.roots_before.root) {
             builder.connect(limb0, limb1);
         }
+
+        // Without a parent block, the dummy parent proof must start where the aggregation proof
+        // starts, so that the genesis `roots_before` can't be chosen freely.
+        let has_no_parent_block = builder.not(has_parent_block);
+        for (&limb0, &limb1) in lhs.roots_before.root.iter().zip(&rhs.roots_before.root) {
+            let diff = builder.sub(limb0, limb1);
+            let constr = builder.mul(has_no_parent_block.target, diff);
+            builder.assert_zero(constr);
+        }
+
+        connect_block_userdata(builder, has_parent_block, lhs, rhs, counter_limbs);
+    }
+
+    /// Rebuilds the block circuit so that the userdata limbs at `counter_limbs` increment by one
+    /// from each block to the next, e.g. for a block number, instead of being equal. The other
+    /// limbs and the userdata length still have to match. Limbs are bytes, so a counter can't
+    /// increment past 255. Block proofs of the previous block circuit can't be continued, and
+    /// root and aggregation proofs must be proven again to be aggregated with new block proofs.
+    /// If the counters make the block circuit larger than the aggregation circuit, block
+    /// receipts can no longer be aggregated, see `prove_aggregation_with_kinds`.
+    pub fn set_block_counter_limbs(&mut self, counter_limbs: &[usize]) -> anyhow::Result<()> {
+        if let Some(&limb) = counter_limbs
+            .iter()
+            .find(|&&limb| limb >= NUM_PUBLIC_INPUT_USERDATA)
+        {
+            anyhow::bail!(
+                "Counter limb {limb} is out of the {NUM_PUBLIC_INPUT_USERDATA} userdata limbs"
+            );
+        }
+        let recursion_config = self.block.circuit.common.config.clone();
+        self.block =
+            Self::create_block_circuit(&self.aggregation, &recursion_config, counter_limbs);
+        Ok(())
+    }
+
+    /// Sets the maximum number of assumptions a receipt can use, `DEFAULT_MAX_ASSUMPTIONS` by
+    /// default. Proving or verifying a receipt with more assumptions fails.
+    pub fn set_max_assumptions(&mut self, max_assumptions: usize) {
+        self.max_assumptions = max_assumptions;
+    }
+
+    /// Checks that STARK proofs generated with `inner_config` can be verified by these circuits.
+    ///
+    /// The inner config, used to prove the tables, is the one these circuits were built with. It
+    /// is distinct from the config of the recursion circuits, so it can be tuned on its own, e.g.
+    /// with fewer FRI queries and more proof-of-work bits. The proof-of-work bits can't be
+    /// recovered from the circuits and aren't checked.
+    pub fn check_inner_config(&self, inner_config: &StarkConfig) -> anyhow::Result<()> {
+        for (table, table_circuits) in Table::all().iter().zip(&self.by_table) {
+            table_circuits
+                .check_inner_config(*table, inner_config)
+                .map_err(|e| anyhow::anyhow!("{:?} circuits: {e}", table))?;
+        }
+        Ok(())
     }
 
     /// Create a proof for each STARK, then combine them, eventually culminating in a root proof.
+    /// `config` must be the inner config these circuits were built with, see
+    /// `check_inner_config`.
     pub fn prove_root(
         &self,
         all_stark: &AllStark<F, D>,
@@ -773,54 +1497,119 @@ where
         config: &StarkConfig,
         timing: &mut TimingTree,
     ) -> anyhow::Result<Receipt<F, C, D>> {
-        let (all_proof, output) = prove_with_outputs::<F, C, D>(all_stark, kernel, config, timing)?;
-        verify_proof(all_stark, all_proof.clone(), config).unwrap();
-        let mut root_inputs = PartialWitness::new();
-        for table in 0..NUM_TABLES {
-            let stark_proof = &all_proof.stark_proofs[table];
-            let original_degree_bits = stark_proof.proof.recover_degree_bits(config);
-            let table_circuits = &self.by_table[table];
-            let shrunk_proof = table_circuits
-                .by_stark_size
-                .get(&original_degree_bits)
-                .ok_or_else(|| {
-                    anyhow::Error::msg(format!(
-                        "Missing preprocessed circuits for {:?} table with size {}. To set it, run: export {}=\"{}..{}\" ",
-                        Table::all()[table],
-                        original_degree_bits,
-                        RANGE_TABLES[table],
-                        original_degree_bits,
-                        original_degree_bits + 1,
-                    ))
-                })?
-                .shrink(stark_proof, &all_proof.ctl_challenges)?;
-            let index_verifier_data = table_circuits
-                .by_stark_size
-                .keys()
-                .position(|&size| size == original_degree_bits)
-                .unwrap();
-            root_inputs.set_target(
-                self.root.index_verifier_data[table],
-                F::from_canonical_usize(index_verifier_data),
-            );
-            root_inputs.set_proof_with_pis_target(&self.root.proof_with_pis[table], &shrunk_proof);
-        }
+        self.prove_root_with_progress(all_stark, kernel, config, timing, &mut |_| {})
+    }
 
-        root_inputs.set_verifier_data_target(
-            &self.root.cyclic_vk,
-            &self.aggregation.circuit.verifier_only,
-        );
+    /// Same as `prove_root`, without natively verifying the STARK proofs before recursing on them.
+    ///
+    /// The native check catches prover bugs early with a clear error, but the root proof can't be
+    /// generated from invalid STARK proofs anyway, so it's redundant once the prover is trusted.
+    /// Skipping it saves a full native verification, and a copy of the `AllProof`, per proof.
+    pub fn prove_root_unchecked(
+        &self,
+        all_stark: &AllStark<F, D>,
+        kernel: &Kernel,
+        config: &StarkConfig,
+        timing: &mut TimingTree,
+    ) -> anyhow::Result<Receipt<F, C, D>> {
+        self.prove_root_inner(all_stark, kernel, config, timing, &mut |_| {}, false, None)
+    }
 
-        set_public_value_targets(
-            &mut root_inputs,
-            &self.root.public_values,
-            &all_proof.public_values,
-        )
-        .map_err(|_| {
-            anyhow::Error::msg("Invalid conversion when setting public values targets.")
-        })?;
+    /// Same as `prove_root`, calling `progress` at each milestone: once the traces are
+    /// generated, once per table proven and shrunk, and once the root proof is generated.
+    pub fn prove_root_with_progress(
+        &self,
+        all_stark: &AllStark<F, D>,
+        kernel: &Kernel,
+        config: &StarkConfig,
+        timing: &mut TimingTree,
+        progress: &mut dyn FnMut(ProvePhase),
+    ) -> anyhow::Result<Receipt<F, C, D>> {
+        self.prove_root_inner(all_stark, kernel, config, timing, progress, true, None)
+    }
+
+    /// Same as `prove_root`, returning the time spent in each phase, e.g. to export as metrics,
+    /// rather than only recording it in a `TimingTree`.
+    pub fn prove_root_with_timings(
+        &self,
+        all_stark: &AllStark<F, D>,
+        kernel: &Kernel,
+        config: &StarkConfig,
+    ) -> anyhow::Result<(Receipt<F, C, D>, ProveTimings)> {
+        let mut timing = TimingTree::default();
+        let mut timings = ProveTimings::default();
+        let receipt =
+            self.prove_root_with_progress(all_stark, kernel, config, &mut timing, &mut |phase| {
+                timings.record(phase)
+            })?;
+        Ok((receipt, timings))
+    }
+
+    /// Same as `prove_root`, also returning the proofs of every circuit of each table's shrinking
+    /// chain, see `RecursiveCircuitsForTableSize::shrink_with_trace`, e.g. to find which one of
+    /// them fails to verify. Inactive tables have no proofs. Meant for debugging only.
+    pub fn prove_root_debug(
+        &self,
+        all_stark: &AllStark<F, D>,
+        kernel: &Kernel,
+        config: &StarkConfig,
+        timing: &mut TimingTree,
+    ) -> anyhow::Result<(Receipt<F, C, D>, [ShrinkTrace<F, C, D>; NUM_TABLES])> {
+        let mut shrink_traces = core::array::from_fn(|_| vec![]);
+        let receipt = self.prove_root_inner(
+            all_stark,
+            kernel,
+            config,
+            timing,
+            &mut |_| {},
+            true,
+            Some(&mut shrink_traces),
+        )?;
+        Ok((receipt, shrink_traces))
+    }
 
+    /// Shared by `prove_root_with_progress`, `prove_root_unchecked` and `prove_root_debug`. If
+    /// `verify_native`, the STARK proofs are verified natively before being recursively verified.
+    /// If `shrink_traces` is given, it receives the proofs of each table's shrinking chain.
+    fn prove_root_inner(
+        &self,
+        all_stark: &AllStark<F, D>,
+        kernel: &Kernel,
+        config: &StarkConfig,
+        timing: &mut TimingTree,
+        progress: &mut dyn FnMut(ProvePhase),
+        verify_native: bool,
+        shrink_traces: Option<&mut [ShrinkTrace<F, C, D>; NUM_TABLES]>,
+    ) -> anyhow::Result<Receipt<F, C, D>> {
+        self.check_inner_config(config)?;
+        let active_tables = self.root.active_tables();
+        let start = Instant::now();
+        let (traces, public_values, output) = timed!(
+            timing,
+            "generate all traces",
+            generate_traces::<F, C, D>(all_stark, kernel, config, timing)?
+        );
+        progress(ProvePhase::TraceGeneration {
+            elapsed: start.elapsed(),
+        });
+        let all_proof = prove_with_traces_with_progress(
+            all_stark,
+            config,
+            traces,
+            public_values,
+            &active_tables,
+            timing,
+            progress,
+        )?;
+        if verify_native {
+            verify_proof_with_active_tables(all_stark, all_proof.clone(), config, &active_tables)?;
+        }
+        let root_inputs = self.root_inputs(&all_proof, config, progress, shrink_traces)?;
+        let start = Instant::now();
         let root_proof = self.root.circuit.prove(root_inputs)?;
+        progress(ProvePhase::Root {
+            elapsed: start.elapsed(),
+        });
 
         Ok(Receipt::Segments(InnerReceipt {
             proof: root_proof,
@@ -843,46 +1632,134 @@ where
         if assumptions.is_empty() {
             return self.prove_root(all_stark, kernel, config, timing);
         }
-        let (all_proof, output, assumption_used) = prove_with_output_and_assumptions::<F, C, D>(
+        self.check_inner_config(config)?;
+        let active_tables = self.root.active_tables();
+        let (traces, public_values, output, assumption_used) = timed!(
+            timing,
+            "generate all traces",
+            generate_traces_with_assumptions::<F, C, D>(
+                all_stark,
+                kernel,
+                config,
+                timing,
+                assumptions
+            )?
+        );
+        let all_proof = prove_with_traces(
             all_stark,
-            kernel,
             config,
+            traces,
+            public_values,
+            &active_tables,
             timing,
-            assumptions,
         )?;
-        verify_proof(all_stark, all_proof.clone(), config).unwrap();
-        let mut root_inputs = PartialWitness::new();
+        verify_proof_with_active_tables(all_stark, all_proof.clone(), config, &active_tables)?;
+        let root_inputs = self.root_inputs(&all_proof, config, &mut |_| {}, None)?;
+        let root_proof = self.root.circuit.prove(root_inputs)?;
+
+        let program_receipt = InnerReceipt {
+            proof: root_proof,
+            values: all_proof.public_values.clone(),
+            claim: ReceiptClaim {
+                elf_id: u32_array_to_u8_vec(&all_proof.public_values.roots_before.root),
+                commit: output.output.clone(),
+            },
+        };
+        check_assumptions(
+            program_receipt.claim_digest(),
+            &assumption_used.lock().unwrap(),
+            self.max_assumptions,
+        )?;
+        Ok(Receipt::Composite(CompositeReceipt {
+            program_receipt,
+            assumption_used,
+        }))
+    }
+
+    /// Split the execution of `kernel` at the given step `segment_boundaries` (see
+    /// `Kernel::split_at_steps`) and prove each segment with `prove_root`, for executions too
+    /// long to fit in the largest tables of a single proof.
+    ///
+    /// The returned receipts are in execution order. The memory root a segment ends with is the
+    /// one the next segment starts from, i.e. `roots_after` of receipt `i` equals `roots_before`
+    /// of receipt `i + 1`, so they can be folded with `prove_aggregation_all`, which checks
+    /// this chaining, into a receipt going from the `roots_before` to the `roots_after` of
+    /// `kernel`.
+    pub fn prove_segments(
+        &self,
+        all_stark: &AllStark<F, D>,
+        kernel: &Kernel,
+        config: &StarkConfig,
+        segment_boundaries: &[usize],
+        timing: &mut TimingTree,
+    ) -> anyhow::Result<Vec<Receipt<F, C, D>>> {
+        let segments = timed!(
+            timing,
+            "split into segments",
+            kernel.split_at_steps(segment_boundaries)?
+        );
+        segments
+            .iter()
+            .map(|segment| self.prove_root(all_stark, segment, config, timing))
+            .collect()
+    }
 
+    /// Set the witness of the root circuit: the shrunk proofs of the active tables, the
+    /// aggregation verifier key and the public values. A `ProvePhase::Shrink` is reported to
+    /// `progress` for every shrunk proof, and its shrinking chain is kept in `shrink_traces` if
+    /// given.
+    fn root_inputs(
+        &self,
+        all_proof: &AllProof<F, C, D>,
+        config: &StarkConfig,
+        progress: &mut dyn FnMut(ProvePhase),
+        mut shrink_traces: Option<&mut [ShrinkTrace<F, C, D>; NUM_TABLES]>,
+    ) -> anyhow::Result<PartialWitness<F>> {
+        let mut root_inputs = PartialWitness::new();
         for table in 0..NUM_TABLES {
+            let (Some(proof_with_pis), Some(index_verifier_data_target)) = (
+                &self.root.proof_with_pis[table],
+                self.root.index_verifier_data[table],
+            ) else {
+                continue;
+            };
             let stark_proof = &all_proof.stark_proofs[table];
-            let original_degree_bits = stark_proof.proof.recover_degree_bits(config);
             let table_circuits = &self.by_table[table];
-            let shrunk_proof = table_circuits
-                .by_stark_size
-                .get(&original_degree_bits)
-                .ok_or_else(|| {
-                    anyhow::Error::msg(format!(
-                        "Missing preprocessed circuits for {:?} table with size {}. To set it, run: export {}=\"{}..{}\" ",
-                        Table::all()[table],
-                        original_degree_bits,
-                        RANGE_TABLES[table],
-                        original_degree_bits,
-                        original_degree_bits + 1,
-                    ))
-                })?
-                .shrink(stark_proof, &all_proof.ctl_challenges)?;
+            let original_degree_bits = check_proof_degree_bits(
+                Table::all()[table],
+                stark_proof.proof.try_recover_degree_bits(config),
+                table_circuits.degree_bits_range(),
+            )?;
+            let start = Instant::now();
+            let table_circuits_for_size =
+                table_circuits.for_degree_bits(Table::all()[table], original_degree_bits)?;
+            let shrunk_proof = match shrink_traces.as_deref_mut() {
+                Some(shrink_traces) => {
+                    let (shrunk_proof, shrink_trace) = table_circuits_for_size
+                        .shrink_with_trace(stark_proof, &all_proof.ctl_challenges)?;
+                    shrink_traces[table] = shrink_trace;
+                    shrunk_proof
+                }
+                None => table_circuits_for_size.shrink(stark_proof, &all_proof.ctl_challenges)?,
+            };
+            progress(ProvePhase::Shrink {
+                table: Table::all()[table],
+                elapsed: start.elapsed(),
+            });
             let index_verifier_data = table_circuits
                 .by_stark_size
                 .keys()
                 .position(|&size| size == original_degree_bits)
                 .unwrap();
             root_inputs.set_target(
-                self.root.index_verifier_data[table],
+                index_verifier_data_target,
                 F::from_canonical_usize(index_verifier_data),
             );
-            root_inputs.set_proof_with_pis_target(&self.root.proof_with_pis[table], &shrunk_proof);
+            root_inputs.set_proof_with_pis_target(proof_with_pis, &shrunk_proof);
         }
 
+        root_inputs
+            .set_verifier_data_target(&self.root.block_vk, &self.block.circuit.verifier_only);
         root_inputs.set_verifier_data_target(
             &self.root.cyclic_vk,
             &self.aggregation.circuit.verifier_only,
@@ -893,30 +1770,81 @@ where
             &self.root.public_values,
             &all_proof.public_values,
         )
-        .map_err(|_| {
-            anyhow::Error::msg("Invalid conversion when setting public values targets.")
-        })?;
-
-        let root_proof = self.root.circuit.prove(root_inputs)?;
+        .map_err(|_| RecursionError::PublicValuesConversion)?;
 
-        let program_receipt = InnerReceipt {
-            proof: root_proof,
-            values: all_proof.public_values.clone(),
-            claim: ReceiptClaim {
-                elf_id: u32_array_to_u8_vec(&all_proof.public_values.roots_before.root),
-                commit: output.output.clone(),
-            },
-        };
-        Ok(Receipt::Composite(CompositeReceipt {
-            program_receipt,
-            assumption_used,
-        }))
+        Ok(root_inputs)
     }
 
     pub fn verify_root(&self, agg_receipt: Receipt<F, C, D>) -> anyhow::Result<()> {
         self.root.circuit.verify(agg_receipt.proof())
     }
 
+    /// Verifies the shrunk proof of a single table, e.g. to check the output of a worker
+    /// shrinking it before assembling the root proof. See
+    /// `RecursiveCircuitsForTable::verify_shrunk`.
+    pub fn verify_shrunk(
+        &self,
+        table: Table,
+        degree_bits: usize,
+        proof: ProofWithPublicInputs<F, C, D>,
+    ) -> anyhow::Result<()> {
+        self.by_table[table as usize].verify_shrunk(table, degree_bits, proof)
+    }
+
+    /// Verifies a root receipt and re-proves it through a chain of single-input wrapper circuits,
+    /// down to a proof of degree `THRESHOLD_DEGREE_BITS`. The public values, claim and assumptions
+    /// of the receipt are preserved. Compressed receipts are checked with `verify_compressed`.
+    ///
+    /// Unlike `prove_aggregation`, which always verifies two children and combines their public
+    /// values, each wrapper only verifies the previous proof and carries its public inputs
+    /// forward, so it can shrink a single receipt.
+    pub fn compress(&self, receipt: &Receipt<F, C, D>) -> anyhow::Result<Receipt<F, C, D>> {
+        self.verify_root(receipt.clone())?;
+        let mut proof = receipt.proof();
+        for wrapper in self.compress_wrappers() {
+            proof = wrapper.prove(&proof)?;
+        }
+        let inner = InnerReceipt {
+            proof,
+            values: receipt.values(),
+            claim: receipt.claim(),
+        };
+        match receipt {
+            Receipt::Segments(_receipt) => Ok(Receipt::Segments(inner)),
+            Receipt::Composite(receipt) => Ok(Receipt::Composite(CompositeReceipt {
+                program_receipt: inner,
+                assumption_used: receipt.assumption_used.clone(),
+            })),
+        }
+    }
+
+    /// The chain of wrappers used by `compress`, built on first use since most callers never
+    /// compress receipts. The root circuit is built with the recursion config, from which the
+    /// shrinking config is derived.
+    fn compress_wrappers(&self) -> &[PlonkWrapperCircuit<F, C, D>] {
+        self.compress_wrappers.get_or_init(|| {
+            let shrinking_config = shrinking_config_from(&self.root.circuit.common.config);
+            build_shrinking_wrappers(&self.root.circuit, &shrinking_config)
+        })
+    }
+
+    /// Verifies a receipt produced by `compress`.
+    pub fn verify_compressed(&self, receipt: &Receipt<F, C, D>) -> anyhow::Result<()> {
+        self.compressed_verifier_data().verify(receipt.proof())
+    }
+
+    /// The data needed to verify compressed proofs, without the prover data.
+    pub fn compressed_verifier_data(&self) -> VerifierCircuitData<F, C, D> {
+        self.compress_wrappers()
+            .last()
+            .map(|wrapper| &wrapper.circuit)
+            .unwrap_or(&self.root.circuit)
+            .verifier_data()
+    }
+
+    /// Aggregates `lhs_receipt` and `rhs_receipt`, of consecutive executions. Fails natively, see
+    /// `check_receipts_chain`, if the rhs receipt doesn't continue the lhs one, rather than with
+    /// an unsatisfied circuit constraint.
     pub fn prove_aggregation(
         &self,
         lhs_is_agg: bool,
@@ -924,6 +1852,39 @@ where
         rhs_is_agg: bool,
         rhs_receipt: &Receipt<F, C, D>,
     ) -> anyhow::Result<Receipt<F, C, D>> {
+        self.prove_aggregation_with_kinds(
+            AggregationChildKind::from_is_agg(lhs_is_agg),
+            lhs_receipt,
+            AggregationChildKind::from_is_agg(rhs_is_agg),
+            rhs_receipt,
+        )
+    }
+
+    /// Like `prove_aggregation`, but each receipt may also be a block receipt, e.g. to aggregate
+    /// the blocks of a rollup into a super-block, whose aggregation receipt can in turn be proven
+    /// as a block.
+    pub fn prove_aggregation_with_kinds(
+        &self,
+        lhs_kind: AggregationChildKind,
+        lhs_receipt: &Receipt<F, C, D>,
+        rhs_kind: AggregationChildKind,
+        rhs_receipt: &Receipt<F, C, D>,
+    ) -> anyhow::Result<Receipt<F, C, D>> {
+        for (kind, receipt) in [(lhs_kind, lhs_receipt), (rhs_kind, rhs_receipt)] {
+            if kind == AggregationChildKind::Block {
+                anyhow::ensure!(
+                    self.block.circuit.common == self.aggregation.circuit.common,
+                    "The block circuit doesn't have the shape of the aggregation circuit, see \
+                     `set_block_counter_limbs`, so block receipts can't be aggregated."
+                );
+                verify_block_with_vk(
+                    receipt.proof(),
+                    &self.block.circuit.verifier_only,
+                    &self.block.circuit.common,
+                )?;
+            }
+        }
+        check_receipts_chain(&lhs_receipt.values(), &rhs_receipt.values())?;
         let mut agg_inputs = PartialWitness::new();
 
         let public_values = PublicValues {
@@ -932,14 +1893,17 @@ where
             userdata: rhs_receipt.values().userdata,
         };
 
-        agg_inputs.set_bool_target(self.aggregation.lhs.is_agg, lhs_is_agg);
-        agg_inputs.set_proof_with_pis_target(&self.aggregation.lhs.agg_proof, &lhs_receipt.proof());
-        agg_inputs.set_proof_with_pis_target(&self.aggregation.lhs.evm_proof, &lhs_receipt.proof());
-
-        agg_inputs.set_bool_target(self.aggregation.rhs.is_agg, rhs_is_agg);
-        agg_inputs.set_proof_with_pis_target(&self.aggregation.rhs.agg_proof, &rhs_receipt.proof());
-        agg_inputs.set_proof_with_pis_target(&self.aggregation.rhs.evm_proof, &rhs_receipt.proof());
+        self.aggregation
+            .lhs
+            .set(&mut agg_inputs, lhs_kind, &lhs_receipt.proof());
+        self.aggregation
+            .rhs
+            .set(&mut agg_inputs, rhs_kind, &rhs_receipt.proof());
 
+        agg_inputs.set_verifier_data_target(
+            &self.aggregation.block_vk,
+            &self.block.circuit.verifier_only,
+        );
         agg_inputs.set_verifier_data_target(
             &self.aggregation.cyclic_vk,
             &self.aggregation.circuit.verifier_only,
@@ -950,26 +1914,32 @@ where
             &self.aggregation.public_values,
             &public_values,
         )
-        .map_err(|_| {
-            anyhow::Error::msg("Invalid conversion when setting public values targets.")
-        })?;
+        .map_err(|_| RecursionError::PublicValuesConversion)?;
+
+        let claim = ReceiptClaim {
+            elf_id: lhs_receipt.claim().clone().elf_id,
+            commit: rhs_receipt.claim().clone().commit,
+        };
+
+        // Each assumption is kept once, see `merge_assumptions`. The merged assumptions are
+        // checked before being stored, so that a failure leaves `lhs_receipt` untouched.
+        let assumptions = lhs_receipt.assumptions();
+        let rhs_assumptions = rhs_receipt.assumptions();
+        let mut merged = assumptions.lock().unwrap().clone();
+        if !Arc::ptr_eq(&assumptions, &rhs_assumptions) {
+            merge_assumptions(&mut merged, &rhs_assumptions.lock().unwrap());
+        }
+        check_assumptions(claim.digest(), &merged, self.max_assumptions)?;
 
         let aggregation_proof = self.aggregation.circuit.prove(agg_inputs)?;
+        *assumptions.lock().unwrap() = merged;
         let inner = InnerReceipt {
             proof: aggregation_proof,
             values: public_values,
-            claim: ReceiptClaim {
-                elf_id: lhs_receipt.claim().clone().elf_id,
-                commit: rhs_receipt.claim().clone().commit,
-            },
+            claim,
         };
 
-        let assumptions = lhs_receipt.assumptions();
-        for assumption in rhs_receipt.assumptions().borrow().iter() {
-            assumptions.borrow_mut().insert(0, assumption.clone());
-        }
-
-        if assumptions.borrow().is_empty() {
+        if assumptions.lock().unwrap().is_empty() {
             Ok(Receipt::Segments(inner))
         } else {
             Ok(Receipt::Composite(CompositeReceipt {
@@ -979,8 +1949,34 @@ where
         }
     }
 
+    /// Aggregates the root receipts of consecutive segments, in execution order, into a balanced
+    /// binary tree of `prove_aggregation` calls, with the `AggregationAccumulator` also used by
+    /// `AggregationCoordinator`. A single receipt is returned unchanged.
+    pub fn prove_aggregation_all(
+        &self,
+        receipts: &[Receipt<F, C, D>],
+    ) -> anyhow::Result<Receipt<F, C, D>> {
+        let mut accumulator = AggregationAccumulator::new();
+        for receipt in receipts {
+            // `prove_aggregation` extends the assumptions of its left input in place, so give each
+            // receipt its own copy to leave `receipts` untouched.
+            let receipt = match receipt {
+                Receipt::Composite(composite) => Receipt::Composite(CompositeReceipt {
+                    program_receipt: composite.program_receipt.clone(),
+                    assumption_used: Arc::new(Mutex::new(
+                        composite.assumption_used.lock().unwrap().clone(),
+                    )),
+                }),
+                receipt => receipt.clone(),
+            };
+            accumulator.push(self, receipt)?;
+        }
+        accumulator.finish(self)
+    }
+
     pub fn verify_aggregation(&self, receipt: &Receipt<F, C, D>) -> anyhow::Result<()> {
         self.aggregation.circuit.verify(receipt.proof())?;
+        check_block_verifier_data(&receipt.proof(), &self.block.circuit.verifier_only)?;
         check_cyclic_proof_verifier_data(
             &receipt.proof(),
             &self.aggregation.circuit.verifier_only,
@@ -1021,10 +2017,21 @@ where
                 nonzero_pis.insert(key, F::from_canonical_u32(value));
             }
 
-            let userdata_keys = 16..16 + agg_root_receipt.values().userdata.len();
-            for (key, &value) in userdata_keys.zip_eq(&agg_root_receipt.values().userdata) {
+            let values = agg_root_receipt.values();
+            let userdata = values
+                .padded_userdata()
+                .ok_or(RecursionError::UserdataTooLong {
+                    len: values.userdata.len(),
+                })?;
+            let userdata_keys =
+                PublicValuesTarget::USERDATA_OFFSET..PublicValuesTarget::USERDATA_LEN_OFFSET;
+            for (key, &value) in userdata_keys.zip_eq(&userdata) {
                 nonzero_pis.insert(key, F::from_canonical_u8(value));
             }
+            nonzero_pis.insert(
+                PublicValuesTarget::USERDATA_LEN_OFFSET,
+                F::from_canonical_usize(values.userdata.len()),
+            );
 
             block_inputs.set_proof_with_pis_target(
                 &self.block.parent_block_proof,
@@ -1039,6 +2046,8 @@ where
         block_inputs
             .set_proof_with_pis_target(&self.block.agg_root_proof, &agg_root_receipt.proof());
 
+        block_inputs
+            .set_verifier_data_target(&self.block.block_vk, &self.block.circuit.verifier_only);
         block_inputs
             .set_verifier_data_target(&self.block.cyclic_vk, &self.block.circuit.verifier_only);
 
@@ -1047,9 +2056,7 @@ where
             &self.block.public_values,
             &agg_root_receipt.values(),
         )
-        .map_err(|_| {
-            anyhow::Error::msg("Invalid conversion when setting public values targets.")
-        })?;
+        .map_err(|_| RecursionError::PublicValuesConversion)?;
 
         let block_proof = self.block.circuit.prove(block_inputs)?;
         let inner = InnerReceipt {
@@ -1066,29 +2073,79 @@ where
         }
     }
 
+    /// Proves a chain of blocks, one per aggregation receipt, in order, each block proof
+    /// verifying its predecessor. The last block receipt attests to the whole chain. Independent
+    /// block receipts can instead be aggregated with `prove_aggregation_with_kinds`.
+    pub fn prove_block_chain(
+        &self,
+        agg_receipts: &[Receipt<F, C, D>],
+    ) -> anyhow::Result<Receipt<F, C, D>> {
+        let (first, rest) = agg_receipts
+            .split_first()
+            .ok_or_else(|| anyhow::anyhow!("No receipts to chain."))?;
+        let mut block_receipt = self.prove_block(None, first)?;
+        for agg_receipt in rest {
+            block_receipt = self.prove_block(Some(&block_receipt), agg_receipt)?;
+        }
+        Ok(block_receipt)
+    }
+
     pub fn verify_block(&self, block_receipt: &Receipt<F, C, D>) -> anyhow::Result<()> {
-        self.block.circuit.verify(block_receipt.proof())?;
+        verify_block_with_vk(
+            block_receipt.proof(),
+            &self.block.circuit.verifier_only,
+            &self.block.circuit.common,
+        )?;
         match block_receipt {
             Receipt::Segments(_receipt) => (),
             Receipt::Composite(receipt) => {
-                for assumption in receipt.assumption_used.borrow_mut().iter_mut() {
+                check_assumptions(
+                    receipt.claim_digest(),
+                    &receipt.assumption_used.lock().unwrap(),
+                    self.max_assumptions,
+                )?;
+                for assumption in receipt.assumption_used.lock().unwrap().iter_mut() {
                     let receipt = assumption.1.clone();
                     match receipt {
                         AssumptionReceipt::<F, C, D>::Proven(inner) => {
                             self.verify_root(Receipt::Segments(*inner))?;
                         }
                         AssumptionReceipt::Unresolved(assumpt) => {
-                            log::error!("unresolved assumption: {:X?}", assumpt);
+                            anyhow::bail!(
+                                "Unresolved assumption {:X?}, see `Receipt::resolve_assumption`.",
+                                assumpt.claim
+                            );
                         }
                     }
                 }
             }
         };
-        check_cyclic_proof_verifier_data(
-            &block_receipt.proof(),
-            &self.block.circuit.verifier_only,
-            &self.block.circuit.common,
-        )
+        Ok(())
+    }
+
+    /// The data needed to verify root proofs, without the prover data.
+    pub fn root_verifier_data(&self) -> VerifierCircuitData<F, C, D> {
+        self.root.circuit.verifier_data()
+    }
+
+    /// The data needed to verify aggregation proofs, without the prover data.
+    pub fn aggregation_verifier_data(&self) -> VerifierCircuitData<F, C, D> {
+        self.aggregation.circuit.verifier_data()
+    }
+
+    /// The data needed to verify block proofs, without the prover data. Block proofs are checked
+    /// against it with `verify_block_with_vk`.
+    pub fn block_verifier_data(&self) -> VerifierCircuitData<F, C, D> {
+        self.block.circuit.verifier_data()
+    }
+
+    /// A digest of the block circuit's verifier-only data, i.e. its constants and sigmas cap and
+    /// circuit digest, identifying the verification key of block proofs.
+    pub fn vk_digest(&self) -> [F; 4] {
+        let verifier_only = &self.block.circuit.verifier_only;
+        let mut elements = verifier_only.constants_sigmas_cap.flatten();
+        elements.extend(verifier_only.circuit_digest.to_vec());
+        PoseidonHash::hash_no_pad(&elements).elements
     }
 }
 
@@ -1100,7 +2157,7 @@ where
     C::Hasher: AlgebraicHasher<F>,
 {
     /// A map from `log_2(height)` to a chain of shrinking recursion circuits starting at that
-    /// height.
+    /// height. Its keys are contiguous, as they're built from a range.
     by_stark_size: BTreeMap<usize, RecursiveCircuitsForTableSize<F, C, D>>,
 }
 
@@ -1110,9 +2167,9 @@ where
     C: GenericConfig<D, F = F>,
     C::Hasher: AlgebraicHasher<F>,
 {
-    pub fn to_buffer(
+    pub fn to_buffer<W: Write>(
         &self,
-        buffer: &mut Vec<u8>,
+        buffer: &mut W,
         gate_serializer: &dyn GateSerializer<F, D>,
         generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
     ) -> IoResult<()> {
@@ -1133,6 +2190,12 @@ where
         let mut by_stark_size = BTreeMap::new();
         for _ in 0..length {
             let key = buffer.read_usize()?;
+            // Sizes are written in increasing order, without gaps.
+            if let Some((&last, _)) = by_stark_size.last_key_value() {
+                if key != last + 1 {
+                    return Err(IoError);
+                }
+            }
             let table = RecursiveCircuitsForTableSize::from_buffer(
                 buffer,
                 gate_serializer,
@@ -1149,6 +2212,7 @@ where
         degree_bits_range: Range<usize>,
         all_ctls: &[CrossTableLookup<F>],
         stark_config: &StarkConfig,
+        shrinking_config: &CircuitConfig,
     ) -> Self {
         let by_stark_size = degree_bits_range
             .map(|degree_bits| {
@@ -1160,6 +2224,7 @@ where
                         degree_bits,
                         all_ctls,
                         stark_config,
+                        shrinking_config,
                     ),
                 )
             })
@@ -1167,34 +2232,138 @@ where
         Self { by_stark_size }
     }
 
-    /// For each initial `degree_bits`, get the final circuit at the end of that shrinking chain.
-    /// Each of these final circuits should have degree `THRESHOLD_DEGREE_BITS`.
-    fn final_circuits(&self) -> Vec<&CircuitData<F, C, D>> {
+    /// The shrinking chain of STARK proofs of `table` with `degree_bits`.
+    fn for_degree_bits(
+        &self,
+        table: Table,
+        degree_bits: usize,
+    ) -> Result<&RecursiveCircuitsForTableSize<F, C, D>, RecursionError> {
         self.by_stark_size
-            .values()
-            .map(|chain| {
-                chain
-                    .shrinking_wrappers
-                    .last()
-                    .map(|wrapper| &wrapper.circuit)
-                    .unwrap_or(&chain.initial_wrapper.circuit)
-            })
-            .collect()
+            .get(&degree_bits)
+            .ok_or(RecursionError::MissingCircuit { table, degree_bits })
     }
-}
 
-/// A chain of shrinking wrapper circuits, ending with a final circuit with `degree_bits`
-/// `THRESHOLD_DEGREE_BITS`.
-#[derive(Eq, PartialEq, Debug)]
-struct RecursiveCircuitsForTableSize<F, C, const D: usize>
-where
-    F: RichField + Extendable<D>,
-    C: GenericConfig<D, F = F>,
-    C::Hasher: AlgebraicHasher<F>,
-{
-    initial_wrapper: StarkWrapperCircuit<F, C, D>,
-    shrinking_wrappers: Vec<PlonkWrapperCircuit<F, C, D>>,
-}
+    /// Verifies a shrunk proof of a STARK proof of `table` with `degree_bits`, i.e. a proof of the
+    /// last circuit of its shrinking chain, as the root circuit expects it.
+    pub fn verify_shrunk(
+        &self,
+        table: Table,
+        degree_bits: usize,
+        proof: ProofWithPublicInputs<F, C, D>,
+    ) -> anyhow::Result<()> {
+        self.for_degree_bits(table, degree_bits)?
+            .final_circuit()
+            .verify(proof)
+    }
+
+    /// The sizes, as `log_2(height)`, these circuits were built for. This relies on the keys of
+    /// `by_stark_size` being contiguous.
+    fn degree_bits_range(&self) -> Range<usize> {
+        match (
+            self.by_stark_size.keys().next(),
+            self.by_stark_size.keys().next_back(),
+        ) {
+            (Some(&first), Some(&last)) => first..last + 1,
+            _ => 0..0,
+        }
+    }
+
+    fn size_report(
+        &self,
+        gate_serializer: &dyn GateSerializer<F, D>,
+        generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
+    ) -> IoResult<Vec<TableCircuitSize>> {
+        self.by_stark_size
+            .iter()
+            .map(|(&stark_degree_bits, chain)| {
+                let mut writer = CountingWriter::new();
+                chain.to_buffer(&mut writer, gate_serializer, generator_serializer)?;
+                let final_circuit = chain.final_circuit();
+                let mut final_writer = CountingWriter::new();
+                final_writer.write_circuit_data(
+                    final_circuit,
+                    gate_serializer,
+                    generator_serializer,
+                )?;
+                Ok(TableCircuitSize {
+                    stark_degree_bits,
+                    chain_length: chain.shrinking_wrappers.len(),
+                    final_circuit: CircuitSize::new(
+                        &final_circuit.common,
+                        final_writer.bytes_written(),
+                    ),
+                    serialized_bytes: writer.bytes_written(),
+                })
+            })
+            .collect()
+    }
+
+    /// Checks the shape of the STARK proofs verified by the initial wrapper of every chain
+    /// against `inner_config`.
+    fn check_inner_config(&self, table: Table, inner_config: &StarkConfig) -> anyhow::Result<()> {
+        let fri_config = &inner_config.fri_config;
+        for (&degree_bits, chain) in &self.by_stark_size {
+            let wrapper = &chain.initial_wrapper;
+            let proof_target = &wrapper.stark_proof_target;
+            let query_rounds = &proof_target.opening_proof.query_round_proofs;
+            anyhow::ensure!(
+                query_rounds.len() == fri_config.num_query_rounds,
+                "Built for {} FRI query rounds, got {}.",
+                query_rounds.len(),
+                fri_config.num_query_rounds
+            );
+            anyhow::ensure!(
+                proof_target.trace_cap.0.len() == 1 << fri_config.cap_height,
+                "Built for a Merkle cap of {} hashes, got a cap height of {}.",
+                proof_target.trace_cap.0.len(),
+                fri_config.cap_height
+            );
+            let first_round = query_rounds
+                .first()
+                .ok_or(RecursionError::NoQueryRounds { table, degree_bits })?;
+            let num_siblings = first_round.initial_trees_proof.evals_proofs[0]
+                .1
+                .siblings
+                .len();
+            anyhow::ensure!(
+                num_siblings + fri_config.cap_height == degree_bits + fri_config.rate_bits,
+                "Built for a rate of {} bits, got {}.",
+                num_siblings + fri_config.cap_height - degree_bits,
+                fri_config.rate_bits
+            );
+            let num_challenges = wrapper.ctl_challenges_target.challenges.len();
+            anyhow::ensure!(
+                num_challenges == inner_config.num_challenges,
+                "Built for {} challenges, got {}.",
+                num_challenges,
+                inner_config.num_challenges
+            );
+        }
+        Ok(())
+    }
+
+    /// For each initial `degree_bits`, get the final circuit at the end of that shrinking chain.
+    /// Each of these final circuits should have degree `THRESHOLD_DEGREE_BITS`.
+    fn final_circuits(&self) -> Vec<&CircuitData<F, C, D>> {
+        self.by_stark_size
+            .values()
+            .map(|chain| chain.final_circuit())
+            .collect()
+    }
+}
+
+/// A chain of shrinking wrapper circuits, ending with a final circuit with `degree_bits`
+/// `THRESHOLD_DEGREE_BITS`.
+#[derive(Eq, PartialEq, Debug)]
+struct RecursiveCircuitsForTableSize<F, C, const D: usize>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    C::Hasher: AlgebraicHasher<F>,
+{
+    initial_wrapper: StarkWrapperCircuit<F, C, D>,
+    shrinking_wrappers: Vec<PlonkWrapperCircuit<F, C, D>>,
+}
 
 impl<F, C, const D: usize> RecursiveCircuitsForTableSize<F, C, D>
 where
@@ -1202,28 +2371,18 @@ where
     C: GenericConfig<D, F = F>,
     C::Hasher: AlgebraicHasher<F>,
 {
-    pub fn to_buffer(
+    pub fn to_buffer<W: Write>(
         &self,
-        buffer: &mut Vec<u8>,
+        buffer: &mut W,
         gate_serializer: &dyn GateSerializer<F, D>,
         generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
     ) -> IoResult<()> {
-        buffer.write_usize(self.shrinking_wrappers.len())?;
-        if !self.shrinking_wrappers.is_empty() {
-            buffer.write_common_circuit_data(
-                &self.shrinking_wrappers[0].circuit.common,
-                gate_serializer,
-            )?;
-        }
-        for wrapper in &self.shrinking_wrappers {
-            buffer.write_prover_only_circuit_data(
-                &wrapper.circuit.prover_only,
-                generator_serializer,
-                &wrapper.circuit.common,
-            )?;
-            buffer.write_verifier_only_circuit_data(&wrapper.circuit.verifier_only)?;
-            buffer.write_target_proof_with_public_inputs(&wrapper.proof_with_pis_target)?;
-        }
+        shrinking_wrappers_to_buffer(
+            &self.shrinking_wrappers,
+            buffer,
+            gate_serializer,
+            generator_serializer,
+        )?;
         self.initial_wrapper
             .to_buffer(buffer, gate_serializer, generator_serializer)?;
         Ok(())
@@ -1234,26 +2393,8 @@ where
         gate_serializer: &dyn GateSerializer<F, D>,
         generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
     ) -> IoResult<Self> {
-        let length = buffer.read_usize()?;
-        let mut shrinking_wrappers = Vec::with_capacity(length);
-        if length != 0 {
-            let common = buffer.read_common_circuit_data(gate_serializer)?;
-
-            for _ in 0..length {
-                let prover_only =
-                    buffer.read_prover_only_circuit_data(generator_serializer, &common)?;
-                let verifier_only = buffer.read_verifier_only_circuit_data()?;
-                let proof_with_pis_target = buffer.read_target_proof_with_public_inputs()?;
-                shrinking_wrappers.push(PlonkWrapperCircuit {
-                    circuit: CircuitData {
-                        common: common.clone(),
-                        prover_only,
-                        verifier_only,
-                    },
-                    proof_with_pis_target,
-                })
-            }
-        };
+        let shrinking_wrappers =
+            shrinking_wrappers_from_buffer(buffer, gate_serializer, generator_serializer)?;
 
         let initial_wrapper =
             StarkWrapperCircuit::from_buffer(buffer, gate_serializer, generator_serializer)?;
@@ -1264,12 +2405,21 @@ where
         })
     }
 
+    /// The last circuit of the chain, or the initial wrapper if there is no shrinking wrapper.
+    fn final_circuit(&self) -> &CircuitData<F, C, D> {
+        self.shrinking_wrappers
+            .last()
+            .map(|wrapper| &wrapper.circuit)
+            .unwrap_or(&self.initial_wrapper.circuit)
+    }
+
     fn new<S: Stark<F, D>>(
         table: Table,
         stark: &S,
         degree_bits: usize,
         all_ctls: &[CrossTableLookup<F>],
         stark_config: &StarkConfig,
+        shrinking_config: &CircuitConfig,
     ) -> Self {
         let initial_wrapper = recursive_stark_circuit(
             table,
@@ -1277,42 +2427,11 @@ where
             degree_bits,
             all_ctls,
             stark_config,
-            &shrinking_config(),
+            shrinking_config,
             THRESHOLD_DEGREE_BITS,
         );
-        let mut shrinking_wrappers = vec![];
-
-        // Shrinking recursion loop.
-        loop {
-            let last = shrinking_wrappers
-                .last()
-                .map(|wrapper: &PlonkWrapperCircuit<F, C, D>| &wrapper.circuit)
-                .unwrap_or(&initial_wrapper.circuit);
-            let last_degree_bits = last.common.degree_bits();
-            assert!(last_degree_bits >= THRESHOLD_DEGREE_BITS);
-            if last_degree_bits == THRESHOLD_DEGREE_BITS {
-                break;
-            }
-
-            let mut builder = CircuitBuilder::new(shrinking_config());
-            let proof_with_pis_target = builder.add_virtual_proof_with_pis(&last.common);
-            let last_vk = builder.constant_verifier_data(&last.verifier_only);
-            builder.verify_proof::<C>(&proof_with_pis_target, &last_vk, &last.common);
-            builder.register_public_inputs(&proof_with_pis_target.public_inputs); // carry PIs forward
-            add_common_recursion_gates(&mut builder);
-            let circuit = builder.build::<C>();
-
-            assert!(
-                circuit.common.degree_bits() < last_degree_bits,
-                "Couldn't shrink to expected recursion threshold of 2^{}; stalled at 2^{}",
-                THRESHOLD_DEGREE_BITS,
-                circuit.common.degree_bits()
-            );
-            shrinking_wrappers.push(PlonkWrapperCircuit {
-                circuit,
-                proof_with_pis_target,
-            });
-        }
+        let shrinking_wrappers =
+            build_shrinking_wrappers(&initial_wrapper.circuit, shrinking_config);
 
         Self {
             initial_wrapper,
@@ -1333,14 +2452,883 @@ where
         }
         Ok(proof)
     }
+
+    /// Same as `shrink`, also returning the proof of every circuit of the chain in order, starting
+    /// with the initial wrapper's and ending with the shrunk proof.
+    fn shrink_with_trace(
+        &self,
+        stark_proof_with_metadata: &StarkProofWithMetadata<F, C, D>,
+        ctl_challenges: &GrandProductChallengeSet<F>,
+    ) -> anyhow::Result<(ProofWithPublicInputs<F, C, D>, ShrinkTrace<F, C, D>)> {
+        let mut proof = self
+            .initial_wrapper
+            .prove(stark_proof_with_metadata, ctl_challenges)?;
+        let mut trace = Vec::with_capacity(self.shrinking_wrappers.len() + 1);
+        for wrapper_circuit in &self.shrinking_wrappers {
+            let next_proof = wrapper_circuit.prove(&proof)?;
+            trace.push(proof);
+            proof = next_proof;
+        }
+        trace.push(proof.clone());
+        Ok((proof, trace))
+    }
+}
+
+/// The proofs of a table's shrinking chain, see `AllRecursiveCircuits::prove_root_debug`.
+pub type ShrinkTrace<F, C, const D: usize> = Vec<ProofWithPublicInputs<F, C, D>>;
+
+/// Builds a chain of wrapper circuits, each verifying a proof of the previous one and carrying
+/// its public inputs forward, from `initial` down to a final circuit of degree
+/// `THRESHOLD_DEGREE_BITS`. The chain is empty if `initial` is already at the threshold.
+fn build_shrinking_wrappers<F, C, const D: usize>(
+    initial: &CircuitData<F, C, D>,
+    shrinking_config: &CircuitConfig,
+) -> Vec<PlonkWrapperCircuit<F, C, D>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F> + 'static,
+    C::Hasher: AlgebraicHasher<F>,
+{
+    let mut shrinking_wrappers = vec![];
+
+    // Shrinking recursion loop.
+    loop {
+        let last = shrinking_wrappers
+            .last()
+            .map(|wrapper: &PlonkWrapperCircuit<F, C, D>| &wrapper.circuit)
+            .unwrap_or(initial);
+        let last_degree_bits = last.common.degree_bits();
+        assert!(last_degree_bits >= THRESHOLD_DEGREE_BITS);
+        if last_degree_bits == THRESHOLD_DEGREE_BITS {
+            break;
+        }
+
+        let mut builder = CircuitBuilder::new(shrinking_config.clone());
+        let proof_with_pis_target = builder.add_virtual_proof_with_pis(&last.common);
+        let last_vk = builder.constant_verifier_data(&last.verifier_only);
+        builder.verify_proof::<C>(&proof_with_pis_target, &last_vk, &last.common);
+        builder.register_public_inputs(&proof_with_pis_target.public_inputs); // carry PIs forward
+        add_common_recursion_gates(&mut builder);
+        // With reduced query rounds, the verifier may fit below the threshold; pad up to it.
+        while log2_ceil(builder.num_gates()) < THRESHOLD_DEGREE_BITS {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let circuit = builder.build::<C>();
+
+        assert!(
+            circuit.common.degree_bits() < last_degree_bits,
+            "Couldn't shrink to expected recursion threshold of 2^{}; stalled at 2^{}",
+            THRESHOLD_DEGREE_BITS,
+            circuit.common.degree_bits()
+        );
+        shrinking_wrappers.push(PlonkWrapperCircuit {
+            circuit,
+            proof_with_pis_target,
+        });
+    }
+    shrinking_wrappers
+}
+
+/// Writes a chain of shrinking wrappers. All wrappers share the same common data, which is only
+/// written once.
+fn shrinking_wrappers_to_buffer<W: Write, F, C, const D: usize>(
+    shrinking_wrappers: &[PlonkWrapperCircuit<F, C, D>],
+    buffer: &mut W,
+    gate_serializer: &dyn GateSerializer<F, D>,
+    generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
+) -> IoResult<()>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    buffer.write_usize(shrinking_wrappers.len())?;
+    if !shrinking_wrappers.is_empty() {
+        buffer.write_common_circuit_data(&shrinking_wrappers[0].circuit.common, gate_serializer)?;
+    }
+    for wrapper in shrinking_wrappers {
+        buffer.write_prover_only_circuit_data(
+            &wrapper.circuit.prover_only,
+            generator_serializer,
+            &wrapper.circuit.common,
+        )?;
+        buffer.write_verifier_only_circuit_data(&wrapper.circuit.verifier_only)?;
+        buffer.write_target_proof_with_public_inputs(&wrapper.proof_with_pis_target)?;
+    }
+    Ok(())
+}
+
+/// Reads a chain of shrinking wrappers written by `shrinking_wrappers_to_buffer`.
+fn shrinking_wrappers_from_buffer<F, C, const D: usize>(
+    buffer: &mut Buffer,
+    gate_serializer: &dyn GateSerializer<F, D>,
+    generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
+) -> IoResult<Vec<PlonkWrapperCircuit<F, C, D>>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let length = buffer.read_usize()?;
+    let mut shrinking_wrappers = Vec::with_capacity(length);
+    if length != 0 {
+        let common = buffer.read_common_circuit_data(gate_serializer)?;
+
+        for _ in 0..length {
+            let prover_only =
+                buffer.read_prover_only_circuit_data(generator_serializer, &common)?;
+            let verifier_only = buffer.read_verifier_only_circuit_data()?;
+            let proof_with_pis_target = buffer.read_target_proof_with_public_inputs()?;
+            shrinking_wrappers.push(PlonkWrapperCircuit {
+                circuit: CircuitData {
+                    common: common.clone(),
+                    prover_only,
+                    verifier_only,
+                },
+                proof_with_pis_target,
+            })
+        }
+    };
+    Ok(shrinking_wrappers)
+}
+
+/// Checks the degree bits recovered from a STARK proof of `table`, `None` if the proof is
+/// malformed, against the sizes the table's recursive circuits were built for.
+fn check_proof_degree_bits(
+    table: Table,
+    degree_bits: Option<usize>,
+    degree_bits_range: Range<usize>,
+) -> Result<usize, RecursionError> {
+    let degree_bits = degree_bits.ok_or(RecursionError::MalformedProof { table })?;
+    if !degree_bits_range.contains(&degree_bits) {
+        return Err(RecursionError::DegreeOutOfRange {
+            table,
+            degree_bits,
+            range: degree_bits_range,
+        });
+    }
+    Ok(degree_bits)
+}
+
+/// Checks natively what the aggregation circuit enforces on its children's public values: `rhs`
+/// must start from the memory root `lhs` ends with, and both must have the same userdata. A
+/// mismatch would otherwise only show up as an unsatisfied circuit constraint.
+fn check_receipts_chain(lhs: &PublicValues, rhs: &PublicValues) -> anyhow::Result<()> {
+    // The public values `rhs` must have to continue `lhs`, up to its `roots_after`.
+    let expected = PublicValues {
+        roots_before: lhs.roots_after.clone(),
+        roots_after: rhs.roots_after.clone(),
+        userdata: lhs.userdata.clone(),
+    };
+    let diffs = expected.diff(rhs);
+    anyhow::ensure!(
+        diffs.is_empty(),
+        "The rhs receipt doesn't continue the lhs one, expected vs rhs public values: {}",
+        diffs.iter().join(", ")
+    );
+    Ok(())
+}
+
+/// Connects the userdata of a parent block with the userdata of the aggregation proof of its
+/// child. Limbs at `counter_limbs` must increment by one instead, except without a parent block,
+/// where the dummy parent proof carries the aggregation userdata as is.
+fn connect_block_userdata<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    has_parent_block: BoolTarget,
+    parent: &PublicValuesTarget,
+    agg: &PublicValuesTarget,
+    counter_limbs: &[usize],
+) {
+    for (i, (&limb0, &limb1)) in parent.userdata.iter().zip(&agg.userdata).enumerate() {
+        if counter_limbs.contains(&i) {
+            let incremented = builder.add(limb0, has_parent_block.target);
+            builder.connect(incremented, limb1);
+        } else {
+            builder.connect(limb0, limb1);
+        }
+    }
+    builder.connect(parent.userdata_len, agg.userdata_len);
+}
+
+/// Adds public inputs holding the block circuit's verifier data, after the public values and
+/// before the cyclic verifier data, which must come last. The root, aggregation and block
+/// circuits all have them, so that they share their `CommonCircuitData`. Aggregation proofs
+/// verify their block children against them, and the block circuit connects them to its own
+/// verifier data, so that any proof eventually verified by a block proof, or by
+/// `verify_aggregation`, only aggregates genuine block proofs.
+fn add_block_vk_public_inputs<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+) -> VerifierCircuitTarget {
+    let cap_height = builder.config.fri_config.cap_height;
+    let block_vk = builder.add_virtual_verifier_data(cap_height);
+    builder.register_public_inputs(&verifier_data_elements(&block_vk));
+    block_vk
+}
+
+/// The block verifier data in the public inputs of a root, aggregation or block proof, see
+/// `add_block_vk_public_inputs`.
+fn block_vk_from_public_inputs(pis: &[Target], cap_height: usize) -> VerifierCircuitTarget {
+    let start = PublicValuesTarget::SIZE;
+    verifier_data_from_elements(&pis[start..start + NUM_HASH_OUT_ELTS * (1 + (1 << cap_height))])
+}
+
+/// The inverse of `verifier_data_elements`.
+fn verifier_data_from_elements(elements: &[Target]) -> VerifierCircuitTarget {
+    let hashes = elements
+        .chunks(NUM_HASH_OUT_ELTS)
+        .map(|hash| HashOutTarget::from_vec(hash.to_vec()))
+        .collect_vec();
+    VerifierCircuitTarget {
+        circuit_digest: hashes[0],
+        constants_sigmas_cap: MerkleCapTarget(hashes[1..].to_vec()),
+    }
+}
+
+/// The targets of `vk`, in the order of its public inputs: the circuit digest, then the cap.
+fn verifier_data_elements(vk: &VerifierCircuitTarget) -> Vec<Target> {
+    let mut elements = vk.circuit_digest.elements.to_vec();
+    for hash in &vk.constants_sigmas_cap.0 {
+        elements.extend(hash.elements);
+    }
+    elements
+}
+
+fn connect_verifier_data<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    x: &VerifierCircuitTarget,
+    y: &VerifierCircuitTarget,
+) {
+    for (x, y) in zip_eq(verifier_data_elements(x), verifier_data_elements(y)) {
+        builder.connect(x, y);
+    }
+}
+
+/// Connects `x` and `y` if `condition` is true.
+fn conditionally_connect_verifier_data<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    condition: BoolTarget,
+    x: &VerifierCircuitTarget,
+    y: &VerifierCircuitTarget,
+) {
+    for (x, y) in zip_eq(verifier_data_elements(x), verifier_data_elements(y)) {
+        let diff = builder.sub(x, y);
+        let constr = builder.mul(condition.target, diff);
+        builder.assert_zero(constr);
+    }
+}
+
+/// `if b { x } else { y }`.
+fn select_verifier_data<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    b: BoolTarget,
+    x: &VerifierCircuitTarget,
+    y: &VerifierCircuitTarget,
+) -> VerifierCircuitTarget {
+    let elements = zip_eq(verifier_data_elements(x), verifier_data_elements(y))
+        .map(|(x, y)| builder.select(b, x, y))
+        .collect_vec();
+    verifier_data_from_elements(&elements)
+}
+
+/// Checks that the block verifier data in the public inputs of `proof` are `block_verifier_only`,
+/// see `add_block_vk_public_inputs`.
+fn check_block_verifier_data<F, C, const D: usize>(
+    proof: &ProofWithPublicInputs<F, C, D>,
+    block_verifier_only: &VerifierOnlyCircuitData<C, D>,
+) -> anyhow::Result<()>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let mut expected = block_verifier_only.circuit_digest.to_vec();
+    expected.extend(block_verifier_only.constants_sigmas_cap.flatten());
+    let start = PublicValuesTarget::SIZE;
+    anyhow::ensure!(
+        proof.public_inputs.get(start..start + expected.len()) == Some(&expected[..]),
+        "The proof aggregates block proofs of another block circuit."
+    );
+    Ok(())
+}
+
+/// Number of FRI query rounds used by circuits built with `test_only_reduced_security`.
+/// This is far too few for soundness.
+const INSECURE_TEST_NUM_QUERY_ROUNDS: usize = 2;
+
+/// The config of the root, aggregation and block circuits.
+fn recursion_config(test_only_reduced_security: bool) -> CircuitConfig {
+    let mut config = CircuitConfig::standard_recursion_config();
+    if test_only_reduced_security {
+        config.fri_config.num_query_rounds = INSECURE_TEST_NUM_QUERY_ROUNDS;
+    }
+    config
 }
 
 /// Our usual recursion threshold is 2^12 gates, but for these shrinking circuits, we use a few more
 /// gates for a constant inner VK and for public inputs. This pushes us over the threshold to 2^13.
 /// As long as we're at 2^13 gates, we might as well use a narrower witness.
-fn shrinking_config() -> CircuitConfig {
+fn shrinking_config(test_only_reduced_security: bool) -> CircuitConfig {
+    shrinking_config_from(&recursion_config(test_only_reduced_security))
+}
+
+fn shrinking_config_from(recursion_config: &CircuitConfig) -> CircuitConfig {
     CircuitConfig {
         num_routed_wires: 40,
-        ..CircuitConfig::standard_recursion_config()
+        ..recursion_config.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::marker::PhantomData;
+
+    use plonky2::field::types::Field;
+    use plonky2::plonk::config::PoseidonGoldilocksConfig;
+    use plonky2::util::serialization::{DefaultGateSerializer, DefaultGeneratorSerializer};
+    use plonky2_util::log2_strict;
+
+    use super::*;
+    use crate::all_stark::NUM_PUBLIC_INPUT_USERDATA;
+    use crate::proof::MemRoots;
+    use crate::prover::testutils::exit_kernel;
+    use crate::recursive_verifier::set_trie_roots_target;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_validate_degree_bits_ranges() {
+        let mut ranges = [0; NUM_TABLES].map(|_| 10..12);
+        assert!(validate_degree_bits_ranges(&ranges).is_ok());
+
+        ranges[Table::Logic as usize] = 12..12;
+        let err = validate_degree_bits_ranges(&ranges).unwrap_err();
+        assert!(err.to_string().contains("export LOGIC="));
+
+        ranges[Table::Logic as usize] = Range { start: 13, end: 12 };
+        assert!(validate_degree_bits_ranges(&ranges).is_err());
+    }
+
+    #[test]
+    fn test_check_proof_degree_bits() {
+        assert_eq!(
+            check_proof_degree_bits(Table::Cpu, Some(12), 10..16).unwrap(),
+            12
+        );
+        assert_eq!(
+            check_proof_degree_bits(Table::Cpu, Some(15), 10..16).unwrap(),
+            15
+        );
+
+        let err = check_proof_degree_bits(Table::Cpu, Some(18), 10..16).unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.starts_with("Proof degree out of configured range"));
+        assert!(msg.contains("export CPU=\"10..19\""));
+        let err = check_proof_degree_bits(Table::Memory, Some(4), 10..16).unwrap_err();
+        assert!(err.to_string().contains("export MEMORY=\"4..16\""));
+
+        assert_eq!(
+            check_proof_degree_bits(Table::Cpu, Some(18), 10..16),
+            Err(RecursionError::DegreeOutOfRange {
+                table: Table::Cpu,
+                degree_bits: 18,
+                range: 10..16
+            })
+        );
+
+        let err = check_proof_degree_bits(Table::Cpu, None, 10..16).unwrap_err();
+        assert!(err.to_string().starts_with("Malformed Cpu STARK proof"));
+    }
+
+    #[test]
+    fn test_prove_root_debug() -> anyhow::Result<()> {
+        let kernel = exit_kernel()?;
+        let all_stark = AllStark::<F, D>::default();
+        let config = StarkConfig::standard_fast_config();
+        let num_rows = all_stark.profile::<C>(&kernel, &config)?;
+        let ranges = all_stark.recommend_degree_ranges(&num_rows.into());
+        // The program never calls the precompiles, so their tables are left out, and the
+        // circuits are built with reduced security to keep the test fast.
+        let mut active_tables = ALL_TABLES_ACTIVE;
+        for table in [
+            Table::Keccak,
+            Table::KeccakSponge,
+            Table::ShaExtend,
+            Table::ShaExtendSponge,
+            Table::ShaCompress,
+            Table::ShaCompressSponge,
+        ] {
+            active_tables[table as usize] = false;
+        }
+        let all_circuits = AllRecursiveCircuits::<F, C, D>::new_with_security(
+            &all_stark,
+            &ranges,
+            &config,
+            &active_tables,
+            true,
+        );
+
+        let mut timing = TimingTree::default();
+        let (receipt, shrink_traces) =
+            all_circuits.prove_root_debug(&all_stark, &kernel, &config, &mut timing)?;
+        all_circuits.verify_root(receipt)?;
+
+        // Each proof of a chain verifies against the circuit of the chain that produced it.
+        for (table, shrink_trace) in Table::all().into_iter().zip(shrink_traces) {
+            if !active_tables[table as usize] {
+                assert!(shrink_trace.is_empty(), "{table:?}");
+                continue;
+            }
+            let degree_bits = log2_strict(num_rows[table as usize]);
+            let chain = &all_circuits.by_table[table as usize].by_stark_size[&degree_bits];
+            assert_eq!(
+                shrink_trace.len(),
+                chain.shrinking_wrappers.len() + 1,
+                "{table:?}"
+            );
+            let circuits = [&chain.initial_wrapper.circuit].into_iter().chain(
+                chain
+                    .shrinking_wrappers
+                    .iter()
+                    .map(|wrapper| &wrapper.circuit),
+            );
+            for (circuit, proof) in circuits.zip(shrink_trace) {
+                circuit.verify(proof)?;
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_shrunk_memory() -> anyhow::Result<()> {
+        let kernel = exit_kernel()?;
+        let all_stark = AllStark::<F, D>::default();
+        let config = StarkConfig::standard_fast_config();
+        let num_rows = all_stark.profile::<C>(&kernel, &config)?;
+        let ranges = all_stark.recommend_degree_ranges(&num_rows.into());
+        let table = Table::Memory;
+        // Only the memory table's circuits are needed, not the root circuit.
+        let (table_circuits, _) = AllRecursiveCircuits::<F, C, D>::build_table_circuits(
+            &all_stark,
+            table,
+            &ranges,
+            &config,
+            &shrinking_config(true),
+        );
+
+        let mut timing = TimingTree::default();
+        let all_proof = crate::prover::prove::<F, C, D>(&all_stark, &kernel, &config, &mut timing)?;
+        let degree_bits = all_proof.degree_bits(&config)[table as usize];
+        let shrunk_proof = table_circuits.for_degree_bits(table, degree_bits)?.shrink(
+            &all_proof.stark_proofs[table as usize],
+            &all_proof.ctl_challenges,
+        )?;
+
+        table_circuits.verify_shrunk(table, degree_bits, shrunk_proof.clone())?;
+        let err = table_circuits
+            .verify_shrunk(table, ranges[table as usize].end, shrunk_proof)
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<RecursionError>(),
+            Some(RecursionError::MissingCircuit { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_circuit_error() {
+        let circuits = RecursiveCircuitsForTable::<F, C, D> {
+            by_stark_size: BTreeMap::new(),
+        };
+        let err = circuits.for_degree_bits(Table::Memory, 14).unwrap_err();
+        assert!(matches!(
+            err,
+            RecursionError::MissingCircuit {
+                table: Table::Memory,
+                degree_bits: 14
+            }
+        ));
+
+        // Prove methods return it as an `anyhow::Error`.
+        let err = anyhow::Error::from(err);
+        assert_eq!(
+            err.downcast_ref::<RecursionError>(),
+            Some(&RecursionError::MissingCircuit {
+                table: Table::Memory,
+                degree_bits: 14
+            })
+        );
+        assert!(err.to_string().contains("degree bits 14"));
+    }
+
+    #[test]
+    fn test_check_inner_config_without_query_rounds() {
+        let all_stark = AllStark::<F, D>::default();
+        let mut config = StarkConfig::standard_fast_config();
+        config.fri_config.num_query_rounds = 0;
+        let table = Table::Logic;
+        let mut ranges = [0; NUM_TABLES].map(|_| 0..0);
+        ranges[table as usize] = 8..9;
+        let (circuits, _) = AllRecursiveCircuits::<F, C, D>::build_table_circuits(
+            &all_stark,
+            table,
+            &ranges,
+            &config,
+            &shrinking_config(true),
+        );
+
+        let err = circuits.check_inner_config(table, &config).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<RecursionError>(),
+            Some(&RecursionError::NoQueryRounds {
+                table,
+                degree_bits: 8
+            })
+        );
+    }
+
+    #[test]
+    fn test_by_table_from_truncated_buffer() {
+        // Three empty tables, then the buffer ends.
+        let mut bytes = vec![];
+        for _ in 0..3 {
+            bytes.write_usize(0).unwrap();
+        }
+
+        let gate_serializer = DefaultGateSerializer;
+        let generator_serializer = DefaultGeneratorSerializer::<C, D> {
+            _phantom: PhantomData,
+        };
+        let res = AllRecursiveCircuits::<F, C, D>::by_table_from_buffer(
+            &mut Buffer::new(&bytes),
+            &gate_serializer,
+            &generator_serializer,
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_from_bytes_table() {
+        // A header pointing at `NUM_TABLES` empty tables, in place of a full serialization.
+        let header_len = 1 + (NUM_TABLES + 1) * size_of::<u64>();
+        let mut bytes = vec![];
+        bytes.write_u8(ALL_CIRCUITS_FORMAT_VERSION).unwrap();
+        bytes.write_usize(NUM_TABLES).unwrap();
+        for i in 0..NUM_TABLES {
+            bytes
+                .write_usize(header_len + i * size_of::<u64>())
+                .unwrap();
+        }
+        for _ in 0..NUM_TABLES {
+            bytes.write_usize(0).unwrap();
+        }
+
+        let gate_serializer = DefaultGateSerializer;
+        let generator_serializer = DefaultGeneratorSerializer::<C, D> {
+            _phantom: PhantomData,
+        };
+        for table in Table::all() {
+            let circuits = AllRecursiveCircuits::<F, C, D>::from_bytes_table(
+                &bytes,
+                table,
+                &gate_serializer,
+                &generator_serializer,
+            )
+            .unwrap();
+            assert!(circuits.by_stark_size.is_empty());
+        }
+
+        // Bytes written in another format version are an error.
+        let mut other_version = bytes.clone();
+        other_version[0] = ALL_CIRCUITS_FORMAT_VERSION + 1;
+        assert!(AllRecursiveCircuits::<F, C, D>::from_bytes_table(
+            &other_version,
+            Table::Arithmetic,
+            &gate_serializer,
+            &generator_serializer,
+        )
+        .is_err());
+
+        // An offset past the end of the bytes is an error.
+        let offset_pos = 1 + size_of::<u64>();
+        bytes[offset_pos..offset_pos + size_of::<u64>()].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(AllRecursiveCircuits::<F, C, D>::from_bytes_table(
+            &bytes,
+            Table::Arithmetic,
+            &gate_serializer,
+            &generator_serializer,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_counting_writer_matches_buffer() {
+        let gate_serializer = DefaultGateSerializer;
+        let generator_serializer = DefaultGeneratorSerializer::<C, D> {
+            _phantom: PhantomData,
+        };
+        let circuits = RecursiveCircuitsForTable::<F, C, D> {
+            by_stark_size: BTreeMap::new(),
+        };
+
+        let mut bytes = vec![];
+        circuits
+            .to_buffer(&mut bytes, &gate_serializer, &generator_serializer)
+            .unwrap();
+        bytes.write_field(F::ONE).unwrap();
+        let mut writer = CountingWriter::new();
+        circuits
+            .to_buffer(&mut writer, &gate_serializer, &generator_serializer)
+            .unwrap();
+        writer.write_field(F::ONE).unwrap();
+        assert_eq!(writer.bytes_written(), bytes.len());
+
+        let report = circuits
+            .size_report(&gate_serializer, &generator_serializer)
+            .unwrap();
+        assert!(report.is_empty());
+    }
+
+    #[test]
+    fn test_circuit_size_counts_rows() {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        for _ in 0..100 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+        let size = CircuitSize::new(&data.common, 0);
+        assert_eq!(size.num_rows, 1 << size.degree_bits);
+        assert!(size.num_rows >= 100);
+    }
+
+    #[test]
+    fn test_connect_padded_userdata() -> anyhow::Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let agg_pv = add_virtual_public_values(&mut builder);
+        let lhs_pv = add_virtual_public_values(&mut builder);
+        let rhs_pv = add_virtual_public_values(&mut builder);
+        PublicValuesTarget::connect_userdata(&mut builder, &agg_pv, &lhs_pv);
+        PublicValuesTarget::connect_userdata(&mut builder, &agg_pv, &rhs_pv);
+        let data = builder.build::<C>();
+
+        // Shorter than the maximum, both padded with zeros.
+        let public_values = PublicValues {
+            userdata: vec![1, 2, 3],
+            ..Default::default()
+        };
+        let mut inputs = PartialWitness::new();
+        for pv_target in [&agg_pv, &lhs_pv, &rhs_pv] {
+            set_public_value_targets(&mut inputs, pv_target, &public_values)
+                .map_err(|_| RecursionError::PublicValuesConversion)?;
+        }
+        let proof = data.prove(inputs)?;
+        data.verify(proof.clone())?;
+
+        let pis = &proof.public_inputs;
+        let userdata =
+            &pis[PublicValuesTarget::USERDATA_OFFSET..PublicValuesTarget::USERDATA_LEN_OFFSET];
+        assert_eq!(userdata[..3], [1, 2, 3].map(F::from_canonical_u8));
+        assert!(userdata[3..].iter().all(|limb| limb.is_zero()));
+        assert_eq!(
+            pis[PublicValuesTarget::USERDATA_LEN_OFFSET],
+            F::from_canonical_usize(3)
+        );
+
+        let too_long = PublicValues {
+            userdata: vec![0; NUM_PUBLIC_INPUT_USERDATA + 1],
+            ..Default::default()
+        };
+        assert!(too_long.padded_userdata().is_none());
+        Ok(())
+    }
+
+    /// Prove a circuit connecting the userdata of two checked segment public values, as the
+    /// aggregation circuit does for its children. Each side is given as its zero-padded limbs
+    /// and its claimed length.
+    fn prove_checked_userdata(lhs: (&[u8], usize), rhs: (&[u8], usize)) {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let lhs_pv = add_virtual_public_values(&mut builder);
+        let rhs_pv = add_virtual_public_values(&mut builder);
+        lhs_pv.check_userdata_len(&mut builder);
+        rhs_pv.check_userdata_len(&mut builder);
+        PublicValuesTarget::connect_userdata(&mut builder, &lhs_pv, &rhs_pv);
+        let data = builder.build::<C>();
+
+        let mut inputs = PartialWitness::new();
+        for (pv_target, (userdata, len)) in [(&lhs_pv, lhs), (&rhs_pv, rhs)] {
+            let roots = MemRoots::default();
+            set_trie_roots_target(&mut inputs, &pv_target.roots_before, &roots);
+            set_trie_roots_target(&mut inputs, &pv_target.roots_after, &roots);
+            for (i, &limb) in pv_target.userdata.iter().enumerate() {
+                let byte = userdata.get(i).copied().unwrap_or(0);
+                inputs.set_target(limb, F::from_canonical_u8(byte));
+            }
+            inputs.set_target(pv_target.userdata_len, F::from_canonical_usize(len));
+        }
+        let proof = data.prove(inputs).unwrap();
+        data.verify(proof).unwrap();
+    }
+
+    #[test]
+    fn test_checked_userdata_len() {
+        // Shorter than the maximum, empty and full.
+        prove_checked_userdata((&[1, 2, 3], 3), (&[1, 2, 3], 3));
+        prove_checked_userdata((&[], 0), (&[], 0));
+        let full = [5; NUM_PUBLIC_INPUT_USERDATA];
+        prove_checked_userdata(
+            (&full, NUM_PUBLIC_INPUT_USERDATA),
+            (&full, NUM_PUBLIC_INPUT_USERDATA),
+        );
+        // Trailing zeros may still count as userdata.
+        prove_checked_userdata((&[1, 2, 3], 4), (&[1, 2, 3], 4));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_checked_userdata_differing() {
+        prove_checked_userdata((&[1, 2, 3], 3), (&[1, 2, 4], 3));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_checked_userdata_differing_len() {
+        prove_checked_userdata((&[1, 2], 2), (&[1, 2], 3));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_checked_userdata_nonzero_padding() {
+        prove_checked_userdata((&[1, 2, 3], 2), (&[1, 2, 3], 2));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_checked_userdata_len_too_large() {
+        let len = NUM_PUBLIC_INPUT_USERDATA + 1;
+        prove_checked_userdata((&[], len), (&[], len));
+    }
+
+    /// Prove a circuit connecting a parent block's public values to an aggregation proof's,
+    /// where the aggregation proof starts from `[2; 8]`.
+    fn prove_block_connection(has_parent_block: bool, parent_roots_before: u32) {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let has_parent_block_target = builder.add_virtual_bool_target_safe();
+        let parent_pv = add_virtual_public_values(&mut builder);
+        let agg_pv = add_virtual_public_values(&mut builder);
+        AllRecursiveCircuits::<F, C, D>::connect_block_proof(
+            &mut builder,
+            has_parent_block_target,
+            &parent_pv,
+            &agg_pv,
+            &[],
+        );
+        let data = builder.build::<C>();
+
+        let parent_values = PublicValues {
+            roots_before: MemRoots {
+                root: [parent_roots_before; 8],
+            },
+            roots_after: MemRoots { root: [2; 8] },
+            userdata: vec![],
+        };
+        let agg_values = PublicValues {
+            roots_before: MemRoots { root: [2; 8] },
+            roots_after: MemRoots { root: [3; 8] },
+            userdata: vec![],
+        };
+        let mut inputs = PartialWitness::new();
+        inputs.set_bool_target(has_parent_block_target, has_parent_block);
+        set_public_value_targets(&mut inputs, &parent_pv, &parent_values).unwrap();
+        set_public_value_targets(&mut inputs, &agg_pv, &agg_values).unwrap();
+        let proof = data.prove(inputs).unwrap();
+        data.verify(proof).unwrap();
+    }
+
+    #[test]
+    fn test_connect_block_proof() {
+        // Genesis block starting from the aggregation proof's initial state.
+        prove_block_connection(false, 2);
+        // A parent block may start anywhere.
+        prove_block_connection(true, 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_connect_block_proof_mismatched_genesis() {
+        prove_block_connection(false, 1);
+    }
+
+    /// Prove a circuit connecting a parent block's userdata to an aggregation proof's, where the
+    /// userdata limb 0 is a counter.
+    fn prove_block_userdata(has_parent_block: bool, parent_userdata: &[u8], agg_userdata: &[u8]) {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let has_parent_block_target = builder.add_virtual_bool_target_safe();
+        let parent_pv = add_virtual_public_values(&mut builder);
+        let agg_pv = add_virtual_public_values(&mut builder);
+        connect_block_userdata(
+            &mut builder,
+            has_parent_block_target,
+            &parent_pv,
+            &agg_pv,
+            &[0],
+        );
+        let data = builder.build::<C>();
+
+        let values = |userdata: &[u8]| PublicValues {
+            roots_before: MemRoots { root: [0; 8] },
+            roots_after: MemRoots { root: [0; 8] },
+            userdata: userdata.to_vec(),
+        };
+        let mut inputs = PartialWitness::new();
+        inputs.set_bool_target(has_parent_block_target, has_parent_block);
+        set_public_value_targets(&mut inputs, &parent_pv, &values(parent_userdata)).unwrap();
+        set_public_value_targets(&mut inputs, &agg_pv, &values(agg_userdata)).unwrap();
+        let proof = data.prove(inputs).unwrap();
+        data.verify(proof).unwrap();
+    }
+
+    #[test]
+    fn test_connect_block_userdata_counter() {
+        // A genesis block, then its child: the block number increments, the rest is unchanged.
+        prove_block_userdata(false, &[7, 1, 2], &[7, 1, 2]);
+        prove_block_userdata(true, &[7, 1, 2], &[8, 1, 2]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_connect_block_userdata_counter_not_incremented() {
+        prove_block_userdata(true, &[7, 1, 2], &[7, 1, 2]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_connect_block_userdata_other_limb_changed() {
+        prove_block_userdata(true, &[7, 1, 2], &[8, 1, 3]);
+    }
+
+    #[test]
+    fn test_check_receipts_chain() {
+        let segment = |before, after| PublicValues {
+            roots_before: MemRoots { root: [before; 8] },
+            roots_after: MemRoots { root: [after; 8] },
+            userdata: vec![1, 2, 3],
+        };
+        assert!(check_receipts_chain(&segment(0, 1), &segment(1, 2)).is_ok());
+
+        // The rhs starts from another root: every limb is reported.
+        let err = check_receipts_chain(&segment(0, 1), &segment(5, 2)).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("roots_before[0]: 0x00000001 != 0x00000005"));
+        assert!(message.contains("roots_before[7]"));
+        assert!(!message.contains("roots_after"));
+
+        // Same for a single mismatched limb and different userdata.
+        let mut rhs = segment(1, 2);
+        rhs.roots_before.root[4] = 0xdead;
+        rhs.userdata[2] = 4;
+        let message = check_receipts_chain(&segment(0, 1), &rhs)
+            .unwrap_err()
+            .to_string();
+        assert!(message.contains("roots_before[4]: 0x00000001 != 0x0000dead"));
+        assert!(!message.contains("roots_before[3]"));
+        assert!(message.contains("userdata[2]: 0x03 != 0x04"));
     }
 }
@@ -20,7 +20,8 @@ use crate::generation::outputs::{get_outputs, GenerationOutputs};
 use crate::generation::state::GenerationState;
 use crate::witness::transition::transition;
 
-use std::{cell::RefCell, rc::Rc};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
 
 pub fn generate_traces<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
     all_stark: &AllStark<F, D>,
@@ -32,31 +33,16 @@ pub fn generate_traces<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>,
     PublicValues,
     GenerationOutputs,
 )> {
-    // Decode the trace record
-    // 1. Decode instruction and fill in cpu columns
-    // 2. Decode memory and fill in memory columns
-    let mut state = GenerationState::<F, C, D>::new(kernel.program.step, kernel).unwrap();
-    generate_bootstrap_kernel::<F, C, D>(&mut state, kernel);
-
-    timed!(timing, "simulate CPU", simulate_cpu(&mut state, kernel)?);
-
-    log::info!(
-        "Trace lengths (before padding): {:?}",
-        state.traces.get_lengths()
-    );
+    let mut state = run_kernel::<F, C, D>(all_stark, kernel, false, timing)?;
 
     let outputs = get_outputs(&mut state)
         .map_err(|err| anyhow!("Failed to generate post-state info: {:?}", err))?;
 
     // Execute the trace record
 
-    // Generate the public values and outputs
-    // let mut userdata = kernel.read_public_inputs();
-    // assert!(userdata.len() <= NUM_PUBLIC_INPUT_USERDATA);
-    // userdata.resize(NUM_PUBLIC_INPUT_USERDATA, 0u8);
+    // Generate the public values and outputs. Circuits pad `userdata` with zeros.
     let userdata = kernel.read_public_inputs();
-
-    assert!(userdata.len() == NUM_PUBLIC_INPUT_USERDATA);
+    assert!(userdata.len() <= NUM_PUBLIC_INPUT_USERDATA);
 
     let public_values = PublicValues {
         roots_before: MemRoots {
@@ -70,11 +56,43 @@ pub fn generate_traces<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>,
     let tables = timed!(
         timing,
         "convert trace data to tables",
-        state.traces.into_tables(all_stark, config, timing)
+        state.traces.into_tables(all_stark, config, timing)?
     );
     Ok((tables, public_values, outputs))
 }
 
+/// Runs `kernel` on the CPU, leaving the operations of every table in the traces of the
+/// returned state, before they are converted to tables. With `count_opcodes`, the executed
+/// instructions are also counted in `opcode_counts`.
+pub(crate) fn run_kernel<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    all_stark: &AllStark<F, D>,
+    kernel: &Kernel,
+    count_opcodes: bool,
+    timing: &mut TimingTree,
+) -> anyhow::Result<GenerationState<F, C, D>> {
+    // Decode the trace record
+    // 1. Decode instruction and fill in cpu columns
+    // 2. Decode memory and fill in memory columns
+    let mut state = GenerationState::<F, C, D>::new(kernel.program.step, kernel).unwrap();
+    state.alignment = all_stark.cpu_stark.alignment;
+    if count_opcodes {
+        state.traces.opcode_counts = Some(BTreeMap::new());
+    }
+    generate_bootstrap_kernel::<F, C, D>(&mut state, kernel);
+
+    timed!(timing, "simulate CPU", simulate_cpu(&mut state, kernel)?);
+
+    log::info!(
+        "Trace lengths (before padding): {:?}",
+        state.traces.get_lengths()
+    );
+    Ok(state)
+}
+
 pub fn generate_traces_with_assumptions<
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
@@ -89,12 +107,13 @@ pub fn generate_traces_with_assumptions<
     [Vec<PolynomialValues<F>>; NUM_TABLES],
     PublicValues,
     GenerationOutputs,
-    Rc<RefCell<AssumptionUsage<F, C, D>>>,
+    Arc<Mutex<AssumptionUsage<F, C, D>>>,
 )> {
     // Decode the trace record
     // 1. Decode instruction and fill in cpu columns
     // 2. Decode memory and fill in memory columns
     let mut state = GenerationState::<F, C, D>::new(kernel.program.step, kernel).unwrap();
+    state.alignment = all_stark.cpu_stark.alignment;
     for assumption in assumptions.iter() {
         state.add_assumption(assumption.clone());
     }
@@ -112,13 +131,9 @@ pub fn generate_traces_with_assumptions<
 
     // Execute the trace record
 
-    // Generate the public values and outputs
-    // let mut userdata = kernel.read_public_inputs();
-    // assert!(userdata.len() <= NUM_PUBLIC_INPUT_USERDATA);
-    // userdata.resize(NUM_PUBLIC_INPUT_USERDATA, 0u8);
+    // Generate the public values and outputs. Circuits pad `userdata` with zeros.
     let userdata = kernel.read_public_inputs();
-
-    assert!(userdata.len() == NUM_PUBLIC_INPUT_USERDATA);
+    assert!(userdata.len() <= NUM_PUBLIC_INPUT_USERDATA);
 
     let public_values = PublicValues {
         roots_before: MemRoots {
@@ -132,7 +147,7 @@ pub fn generate_traces_with_assumptions<
     let tables = timed!(
         timing,
         "convert trace data to tables",
-        state.traces.into_tables(all_stark, config, timing)
+        state.traces.into_tables(all_stark, config, timing)?
     );
     Ok((tables, public_values, outputs, state.assumptions_used))
 }
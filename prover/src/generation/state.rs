@@ -1,17 +1,20 @@
 // use keccak_hash::keccak;
 use crate::cpu::kernel::assembler::Kernel;
+use crate::cpu::memio::AlignmentMode;
 use crate::proof::PublicValues;
 use crate::witness::errors::ProgramError;
 use crate::witness::memory::MemoryState;
 use crate::witness::state::RegistersState;
 use crate::witness::traces::{TraceCheckpoint, Traces};
+use anyhow::{anyhow, bail, ensure};
+use hashbrown::HashMap;
 use plonky2::field::extension::Extendable;
 use plonky2::hash::hash_types::RichField;
 use plonky2::plonk::config::GenericConfig;
 use plonky2::plonk::proof::ProofWithPublicInputs;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::{cell::RefCell, rc::Rc};
+use std::sync::{Arc, Mutex};
 
 pub const ZERO: [u8; 32] = [0u8; 32];
 
@@ -25,13 +28,24 @@ pub struct Assumption {
     pub claim: [u8; 32],
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 pub struct ReceiptClaim {
     pub elf_id: Vec<u8>, // pre image id
     pub commit: Vec<u8>, // commit info
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+impl ReceiptClaim {
+    /// The digest identifying this claim, e.g. in an [Assumption].
+    pub fn digest(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+
+        hasher.update(self.elf_id.clone());
+        hasher.update(self.commit.clone());
+        hasher.finalize().into()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 #[serde(bound = "")]
 pub struct InnerReceipt<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> {
     pub proof: ProofWithPublicInputs<F, C, D>,
@@ -45,16 +59,11 @@ where
     C: GenericConfig<D, F = F>,
 {
     pub fn claim_digest(&self) -> [u8; 32] {
-        let mut hasher = Sha256::new();
-
-        hasher.update(self.claim.elf_id.clone());
-        hasher.update(self.claim.commit.clone());
-        let digest: [u8; 32] = hasher.finalize().into();
-        digest
+        self.claim.digest()
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 #[serde(bound = "")]
 pub enum AssumptionReceipt<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
 {
@@ -83,6 +92,69 @@ where
 pub type AssumptionReceipts<F, C, const D: usize> = Vec<AssumptionReceipt<F, C, D>>;
 pub type AssumptionUsage<F, C, const D: usize> = Vec<(Assumption, AssumptionReceipt<F, C, D>)>;
 
+/// The default maximum number of assumptions a receipt can use, see `check_assumptions`.
+pub const DEFAULT_MAX_ASSUMPTIONS: usize = 1 << 10;
+
+/// Check the assumptions used by a receipt with claim digest `claim_digest`: there can be at most
+/// `max_assumptions` of them, and none of them can be the claim of the receipt itself, which
+/// would make the receipt prove its own claim.
+pub fn check_assumptions<F, C, const D: usize>(
+    claim_digest: [u8; 32],
+    assumptions: &AssumptionUsage<F, C, D>,
+    max_assumptions: usize,
+) -> anyhow::Result<()>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    ensure!(
+        assumptions.len() <= max_assumptions,
+        "Too many assumptions: {} > {}.",
+        assumptions.len(),
+        max_assumptions
+    );
+    for (assumption, receipt) in assumptions {
+        ensure!(
+            assumption.claim != claim_digest && receipt.claim_digest() != claim_digest,
+            "Cyclic assumption {:X?}: the receipt assumes its own claim.",
+            claim_digest
+        );
+    }
+    Ok(())
+}
+
+/// Append the assumptions of `rhs` which aren't in `lhs` yet, keyed on their claim digest, so
+/// that each assumption appears once, in first-seen order. If an assumption is unresolved in
+/// `lhs` but proven in `rhs`, the proof is kept.
+pub fn merge_assumptions<F, C, const D: usize>(
+    lhs: &mut AssumptionUsage<F, C, D>,
+    rhs: &AssumptionUsage<F, C, D>,
+) where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let mut positions: HashMap<[u8; 32], usize> = lhs
+        .iter()
+        .enumerate()
+        .map(|(i, (assumption, _))| (assumption.claim, i))
+        .collect();
+    for (assumption, receipt) in rhs {
+        match positions.get(&assumption.claim) {
+            Some(&i) => {
+                if matches!(lhs[i].1, AssumptionReceipt::Unresolved(_))
+                    && matches!(receipt, AssumptionReceipt::Proven(_))
+                {
+                    lhs[i].1 = receipt.clone();
+                }
+            }
+            None => {
+                positions.insert(assumption.claim, lhs.len());
+                lhs.push((assumption.clone(), receipt.clone()));
+            }
+        }
+    }
+}
+
 impl<F, C, const D: usize> From<InnerReceipt<F, C, D>> for AssumptionReceipt<F, C, D>
 where
     F: RichField + Extendable<D>,
@@ -113,7 +185,19 @@ pub struct CompositeReceipt<
     const D: usize,
 > {
     pub program_receipt: InnerReceipt<F, C, D>,
-    pub assumption_used: Rc<RefCell<AssumptionUsage<F, C, D>>>,
+    pub assumption_used: Arc<Mutex<AssumptionUsage<F, C, D>>>,
+}
+
+impl<F, C, const D: usize> PartialEq for CompositeReceipt<F, C, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.program_receipt == other.program_receipt
+            && (Arc::ptr_eq(&self.assumption_used, &other.assumption_used)
+                || *self.assumption_used.lock().unwrap() == *other.assumption_used.lock().unwrap())
+    }
 }
 
 impl<F, C, const D: usize> CompositeReceipt<F, C, D>
@@ -122,16 +206,11 @@ where
     C: GenericConfig<D, F = F>,
 {
     pub fn claim_digest(&self) -> [u8; 32] {
-        let mut hasher = Sha256::new();
-
-        hasher.update(self.program_receipt.claim.elf_id.clone());
-        hasher.update(self.program_receipt.claim.commit.clone());
-        let digest: [u8; 32] = hasher.finalize().into();
-        digest
+        self.program_receipt.claim.digest()
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 #[serde(bound = "")]
 pub enum Receipt<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> {
     Segments(InnerReceipt<F, C, D>),
@@ -171,12 +250,40 @@ where
         }
     }
 
-    pub fn assumptions(&self) -> Rc<RefCell<AssumptionUsage<F, C, D>>> {
+    pub fn assumptions(&self) -> Arc<Mutex<AssumptionUsage<F, C, D>>> {
         match self {
-            Self::Segments(_receipt) => Rc::new(RefCell::new(Vec::new())),
+            Self::Segments(_receipt) => Arc::new(Mutex::new(Vec::new())),
             Self::Composite(receipt) => receipt.assumption_used.clone(),
         }
     }
+
+    /// Replace the unresolved assumption with digest `claim_digest` by `proof`, whose claim must
+    /// have the same digest. The assumptions are shared with the receipts this one was derived
+    /// from, e.g. the root receipt of a block receipt.
+    pub fn resolve_assumption(
+        &mut self,
+        claim_digest: [u8; 32],
+        proof: Receipt<F, C, D>,
+    ) -> anyhow::Result<()> {
+        ensure!(
+            proof.claim_digest() == claim_digest,
+            "The proof claim doesn't match the assumption {:X?}.",
+            claim_digest
+        );
+        let Self::Composite(receipt) = self else {
+            bail!("Receipt has no assumptions.");
+        };
+        let mut assumption_used = receipt.assumption_used.lock().unwrap();
+        let (_, assumption_receipt) = assumption_used
+            .iter_mut()
+            .find(|(assumption, assumption_receipt)| {
+                assumption.claim == claim_digest
+                    && matches!(assumption_receipt, AssumptionReceipt::Unresolved(_))
+            })
+            .ok_or_else(|| anyhow!("No unresolved assumption {:X?}.", claim_digest))?;
+        *assumption_receipt = proof.into();
+        Ok(())
+    }
 }
 
 impl<F, C, const D: usize> From<Receipt<F, C, D>> for InnerReceipt<F, C, D>
@@ -218,9 +325,10 @@ pub(crate) struct GenerationState<
     pub(crate) public_values_stream: Vec<u8>,
     pub(crate) public_values_stream_ptr: usize,
     pub(crate) traces: Traces<F>,
-    pub(crate) assumptions: Rc<RefCell<AssumptionReceipts<F, C, D>>>,
-    pub(crate) assumptions_used: Rc<RefCell<AssumptionUsage<F, C, D>>>,
+    pub(crate) assumptions: Arc<Mutex<AssumptionReceipts<F, C, D>>>,
+    pub(crate) assumptions_used: Arc<Mutex<AssumptionUsage<F, C, D>>>,
     pub(crate) step: usize,
+    pub(crate) alignment: AlignmentMode,
 }
 
 impl<F, C, const D: usize> GenerationState<F, C, D>
@@ -237,9 +345,10 @@ where
             input_stream_ptr: kernel.program.input_stream_ptr,
             public_values_stream: kernel.program.public_values_stream.clone(),
             public_values_stream_ptr: kernel.program.public_values_stream_ptr,
-            assumptions: Rc::new(RefCell::new(Vec::new())),
-            assumptions_used: Rc::new(RefCell::new(Vec::new())),
+            assumptions: Arc::new(Mutex::new(Vec::new())),
+            assumptions_used: Arc::new(Mutex::new(Vec::new())),
             step,
+            alignment: AlignmentMode::default(),
         })
     }
 
@@ -249,7 +358,7 @@ where
     ) -> &mut Self {
         let receipt: AssumptionReceipt<F, C, D> = assumption.into();
         log::info!("add assumption {:?}", receipt.claim_digest());
-        self.assumptions.borrow_mut().push(receipt);
+        self.assumptions.lock().unwrap().push(receipt);
         self
     }
 
@@ -257,7 +366,7 @@ where
         &self,
         claim_digest: &[u8; 32],
     ) -> Option<(Assumption, AssumptionReceipt<F, C, D>)> {
-        for assumption_receipt in self.assumptions.borrow().iter() {
+        for assumption_receipt in self.assumptions.lock().unwrap().iter() {
             let cached_claim_digest = assumption_receipt.claim_digest();
 
             if cached_claim_digest != *claim_digest {
@@ -298,3 +407,150 @@ where
         self.registers.next_pc = dst;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use plonky2::field::types::Field;
+    use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+    use plonky2::plonk::circuit_builder::CircuitBuilder;
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    use super::*;
+    use crate::proof::MemRoots;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn dummy_receipt(value: u64) -> anyhow::Result<InnerReceipt<F, C, D>> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_target();
+        builder.register_public_input(x);
+        let circuit = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(value));
+
+        Ok(InnerReceipt {
+            proof: circuit.prove(pw)?,
+            values: PublicValues {
+                roots_before: MemRoots { root: [1; 8] },
+                roots_after: MemRoots { root: [2; 8] },
+                userdata: vec![3; 32],
+            },
+            claim: ReceiptClaim {
+                elf_id: vec![4; 32],
+                commit: vec![value as u8; 5],
+            },
+        })
+    }
+
+    #[test]
+    fn test_resolve_assumption() -> anyhow::Result<()> {
+        let assumption = dummy_receipt(7)?;
+        let claim = assumption.claim_digest();
+        let mut receipt = Receipt::Composite(CompositeReceipt {
+            program_receipt: dummy_receipt(8)?,
+            assumption_used: Arc::new(Mutex::new(vec![(
+                Assumption { claim },
+                Assumption { claim }.into(),
+            )])),
+        });
+        let other = Receipt::Segments(dummy_receipt(9)?);
+
+        assert!(receipt.resolve_assumption(claim, other.clone()).is_err());
+        assert!(receipt
+            .resolve_assumption(other.claim_digest(), other)
+            .is_err());
+        receipt.resolve_assumption(claim, Receipt::Segments(assumption.clone()))?;
+        assert!(matches!(
+            receipt.assumptions().lock().unwrap()[0].1,
+            AssumptionReceipt::Proven(_)
+        ));
+        // Already resolved.
+        assert!(receipt
+            .resolve_assumption(claim, Receipt::Segments(assumption))
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_shared_assumption() -> anyhow::Result<()> {
+        let shared = dummy_receipt(7)?;
+        let claim = shared.claim_digest();
+        let other = dummy_receipt(9)?;
+        let mut lhs: AssumptionUsage<F, C, D> =
+            vec![(Assumption { claim }, Assumption { claim }.into())];
+        let rhs = vec![
+            (
+                Assumption {
+                    claim: other.claim_digest(),
+                },
+                other.clone().into(),
+            ),
+            (Assumption { claim }, shared.into()),
+        ];
+
+        merge_assumptions(&mut lhs, &rhs);
+        merge_assumptions(&mut lhs, &rhs);
+
+        assert_eq!(lhs.len(), 2);
+        assert_eq!(lhs[0].0.claim, claim);
+        assert!(matches!(lhs[0].1, AssumptionReceipt::Proven(_)));
+        assert_eq!(lhs[1].0.claim, other.claim_digest());
+        Ok(())
+    }
+
+    #[test]
+    fn test_composite_receipt_serde_roundtrip() -> anyhow::Result<()> {
+        let assumption = dummy_receipt(7)?;
+        let assumption_used = vec![(
+            Assumption {
+                claim: assumption.claim_digest(),
+            },
+            assumption.into(),
+        )];
+        let receipt = Receipt::Composite(CompositeReceipt {
+            program_receipt: dummy_receipt(8)?,
+            assumption_used: Arc::new(Mutex::new(assumption_used)),
+        });
+
+        let bytes = serde_json::to_vec(&receipt)?;
+        let decoded: Receipt<F, C, D> = serde_json::from_slice(&bytes)?;
+
+        assert_eq!(decoded, receipt);
+        assert_eq!(decoded.assumptions().lock().unwrap().len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_assumptions() -> anyhow::Result<()> {
+        let program_receipt = dummy_receipt(8)?;
+        let claim = program_receipt.claim_digest();
+        let other = dummy_receipt(9)?;
+        let other_claim = other.claim_digest();
+        let assumptions: AssumptionUsage<F, C, D> =
+            vec![(Assumption { claim: other_claim }, other.into())];
+
+        check_assumptions(claim, &assumptions, 1)?;
+        let err = check_assumptions(claim, &assumptions, 0).unwrap_err();
+        assert!(err.to_string().contains("Too many assumptions"));
+
+        // A receipt assuming its own claim.
+        let receipt = Receipt::Composite(CompositeReceipt {
+            program_receipt,
+            assumption_used: Arc::new(Mutex::new(vec![(
+                Assumption { claim },
+                Assumption { claim }.into(),
+            )])),
+        });
+        let err = check_assumptions(
+            receipt.claim_digest(),
+            &receipt.assumptions().lock().unwrap(),
+            DEFAULT_MAX_ASSUMPTIONS,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Cyclic assumption"));
+        Ok(())
+    }
+}
@@ -3,7 +3,7 @@ use plonky2::fri::proof::{FriProof, FriProofTarget};
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::challenger::{Challenger, RecursiveChallenger};
 use plonky2::plonk::circuit_builder::CircuitBuilder;
-use plonky2::plonk::config::{AlgebraicHasher, GenericConfig};
+use plonky2::plonk::config::{AlgebraicHasher, GenericConfig, Hasher};
 
 use crate::all_stark::{AllStark, NUM_TABLES};
 use crate::config::StarkConfig;
@@ -88,6 +88,17 @@ fn observe_extra_block_data_target<
 }
 */
 
+/// A challenger which has absorbed `seed`, so that all the Fiat-Shamir challenges of a proof are
+/// drawn from a fixed initial sponge state of the caller's choosing.
+///
+/// Only meant for tests and debugging. A seeded proof is only accepted by a verifier seeded the
+/// same way, and letting the prover pick the seed gives it more freedom over the challenges.
+pub fn seeded_challenger<F: RichField, H: Hasher<F>>(seed: [u64; 4]) -> Challenger<F, H> {
+    let mut challenger = Challenger::new();
+    challenger.observe_elements(&seed.map(F::from_noncanonical_u64));
+    challenger
+}
+
 pub(crate) fn observe_public_values<
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
@@ -98,9 +109,13 @@ pub(crate) fn observe_public_values<
 ) -> Result<(), ProgramError> {
     observe_trie_roots::<F, C, D>(challenger, &public_values.roots_before);
     observe_trie_roots::<F, C, D>(challenger, &public_values.roots_after);
-    for elem in &public_values.userdata {
-        challenger.observe_element(F::from_canonical_u8(*elem));
+    let userdata = public_values
+        .padded_userdata()
+        .ok_or(ProgramError::IntegerTooLarge)?;
+    for elem in userdata {
+        challenger.observe_element(F::from_canonical_u8(elem));
     }
+    challenger.observe_element(F::from_canonical_usize(public_values.userdata.len()));
     Ok(())
 }
 
@@ -117,18 +132,23 @@ pub(crate) fn observe_public_values_target<
     observe_trie_roots_target::<F, C, D>(challenger, &public_values.roots_before);
     observe_trie_roots_target::<F, C, D>(challenger, &public_values.roots_after);
     challenger.observe_elements(&public_values.userdata);
+    challenger.observe_element(public_values.userdata_len);
 }
 
 impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> AllProof<F, C, D> {
-    /// Computes all Fiat-Shamir challenges used in the STARK proof.
+    /// Computes all Fiat-Shamir challenges used in the STARK proof, starting from `challenger`,
+    /// which is empty unless the proof was seeded. As when proving, inactive tables are left out
+    /// of the challenger chain and get no challenges.
     pub(crate) fn get_challenges(
         &self,
         config: &StarkConfig,
+        active_tables: &[bool; NUM_TABLES],
+        mut challenger: Challenger<F, C::Hasher>,
     ) -> Result<AllProofChallenges<F, D>, ProgramError> {
-        let mut challenger = Challenger::<F, C::Hasher>::new();
-
-        for proof in &self.stark_proofs {
-            challenger.observe_cap(&proof.proof.trace_cap);
+        for (proof, &active) in self.stark_proofs.iter().zip(active_tables) {
+            if active {
+                challenger.observe_cap(&proof.proof.trace_cap);
+            }
         }
 
         observe_public_values::<F, C, D>(&mut challenger, &self.public_values)?;
@@ -138,10 +158,12 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> A
 
         Ok(AllProofChallenges {
             stark_challenges: core::array::from_fn(|i| {
-                challenger.compact();
-                self.stark_proofs[i]
-                    .proof
-                    .get_challenges(&mut challenger, config)
+                active_tables[i].then(|| {
+                    challenger.compact();
+                    self.stark_proofs[i]
+                        .proof
+                        .get_challenges(&mut challenger, config)
+                })
             }),
             ctl_challenges,
         })
@@ -40,7 +40,7 @@ pub const fn reg_output_limb(i: usize) -> usize {
     reg_a_prime_prime_prime(x, y) + is_high_limb
 }
 
-const R: [[u8; 5]; 5] = [
+pub(crate) const R: [[u8; 5]; 5] = [
     [0, 36, 3, 41, 18],
     [1, 44, 10, 45, 2],
     [62, 6, 43, 15, 61],
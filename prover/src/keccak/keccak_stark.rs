@@ -10,9 +10,11 @@ use plonky2::iop::ext_target::ExtensionTarget;
 use plonky2::plonk::plonk_common::reduce_with_powers_ext_circuit;
 
 use super::columns::reg_input_limb;
+use crate::all_stark::MIN_TRACE_LEN;
 use crate::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
 use crate::cross_table_lookup::{Column, Filter};
 use crate::evaluation_frame::{StarkEvaluationFrame, StarkFrame};
+use crate::keccak::columns::R;
 use crate::keccak::columns::{
     reg_a, reg_a_prime, reg_a_prime_prime, reg_a_prime_prime_0_0_bit, reg_a_prime_prime_prime,
     reg_b, reg_c, reg_c_prime, reg_output_limb, reg_step, NUM_COLUMNS, TIMESTAMP,
@@ -51,6 +53,54 @@ pub fn ctl_filter_outputs<F: Field>() -> Filter<F> {
     Filter::new_simple(Column::single(reg_step(NUM_ROUNDS - 1)))
 }
 
+/// Applies the Keccak-f[1600] permutation to `state`, whose lanes are
+/// indexed as `state[5 * y + x]` like the inputs of the Keccak STARK.
+pub fn keccak_f(state: &mut [u64; NUM_INPUTS]) {
+    for round in 0..NUM_ROUNDS {
+        // Theta.
+        let c: [u64; 5] = core::array::from_fn(|x| (0..5).fold(0, |acc, y| acc ^ state[5 * y + x]));
+        for x in 0..5 {
+            let d = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                state[5 * y + x] ^= d;
+            }
+        }
+
+        // Rho and pi: B[y, 2x + 3y] = ROT(A[x, y], r[x, y]).
+        let mut b = [0u64; NUM_INPUTS];
+        for x in 0..5 {
+            for y in 0..5 {
+                b[5 * ((2 * x + 3 * y) % 5) + y] = state[5 * y + x].rotate_left(R[x][y] as u32);
+            }
+        }
+
+        // Chi.
+        for x in 0..5 {
+            for y in 0..5 {
+                state[5 * y + x] =
+                    b[5 * y + x] ^ (!b[5 * y + (x + 1) % 5] & b[5 * y + (x + 2) % 5]);
+            }
+        }
+
+        // Iota.
+        state[0] ^= rc_value(round);
+    }
+}
+
+/// Generates the Keccak STARK trace of one permutation per state,
+/// outside of any sponge. The permutations are timestamped with their
+/// index in `states`.
+pub fn keccak_f_trace<F: RichField + Extendable<D>, const D: usize>(
+    states: Vec<[u64; NUM_INPUTS]>,
+) -> Vec<PolynomialValues<F>> {
+    let inputs = states
+        .into_iter()
+        .enumerate()
+        .map(|(timestamp, state)| (state, timestamp))
+        .collect();
+    KeccakStark::<F, D>::default().generate_trace(inputs, MIN_TRACE_LEN)
+}
+
 #[derive(Copy, Clone, Default)]
 pub struct KeccakStark<F, const D: usize> {
     pub(crate) f: PhantomData<F>,
@@ -607,22 +657,17 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for KeccakStark<F
 mod tests {
     use anyhow::Result;
     use env_logger::{try_init_from_env, Env, DEFAULT_FILTER_ENV};
-    use plonky2::field::polynomial::PolynomialValues;
     use plonky2::field::types::{Field, PrimeField64};
-    use plonky2::fri::oracle::PolynomialBatch;
-    use plonky2::iop::challenger::Challenger;
     use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
-    use plonky2::timed;
     use plonky2::util::timing::TimingTree;
     use tiny_keccak::keccakf;
 
     use crate::config::StarkConfig;
-    use crate::cross_table_lookup::{
-        Column, CtlData, CtlZData, Filter, GrandProductChallenge, GrandProductChallengeSet,
-    };
     use crate::keccak::columns::reg_output_limb;
-    use crate::keccak::keccak_stark::{KeccakStark, NUM_INPUTS, NUM_ROUNDS};
-    use crate::prover::prove_single_table;
+    use crate::keccak::keccak_stark::{
+        keccak_f, keccak_f_trace, KeccakStark, NUM_INPUTS, NUM_ROUNDS,
+    };
+    use crate::prover::testutils::prove_single_table_standalone;
     use crate::stark_testing::{test_stark_circuit_constraints, test_stark_low_degree};
 
     #[test]
@@ -685,6 +730,29 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn keccak_f_matches_tiny_keccak() {
+        let input: [u64; NUM_INPUTS] = rand::random();
+
+        let mut state = input;
+        keccak_f(&mut state);
+        let mut expected = input;
+        keccakf(&mut expected);
+        assert_eq!(state, expected);
+
+        const D: usize = 2;
+        type F = <PoseidonGoldilocksConfig as GenericConfig<D>>::F;
+
+        let trace = keccak_f_trace::<F, D>(vec![[0; NUM_INPUTS], input]);
+        let output = (0..NUM_INPUTS)
+            .map(|i| {
+                let limb = |j: usize| trace[reg_output_limb(j)].values[2 * NUM_ROUNDS - 1];
+                (limb(2 * i + 1).to_canonical_u64() << 32) | limb(2 * i).to_canonical_u64()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(output, expected);
+    }
+
     #[test]
     fn keccak_benchmark() -> Result<()> {
         const NUM_PERMS: usize = 85;
@@ -703,49 +771,10 @@ mod tests {
         let mut timing = TimingTree::new("prove", log::Level::Debug);
         let trace_poly_values = stark.generate_trace(input, 8);
 
-        // TODO: Cloning this isn't great; consider having `from_values` accept a reference,
-        // or having `compute_permutation_z_polys` read trace values from the `PolynomialBatch`.
-        let cloned_trace_poly_values = timed!(timing, "clone", trace_poly_values.clone());
-
-        let trace_commitments = timed!(
-            timing,
-            "compute trace commitment",
-            PolynomialBatch::<F, C, D>::from_values(
-                cloned_trace_poly_values,
-                config.fri_config.rate_bits,
-                false,
-                config.fri_config.cap_height,
-                &mut timing,
-                None,
-            )
-        );
-        let degree = 1 << trace_commitments.degree_log;
-
-        // Fake CTL data.
-        let ctl_z_data = CtlZData {
-            helper_columns: vec![PolynomialValues::zero(degree)],
-            z: PolynomialValues::zero(degree),
-            challenge: GrandProductChallenge {
-                beta: F::ZERO,
-                gamma: F::ZERO,
-            },
-            columns: vec![],
-            filter: vec![Some(Filter::new_simple(Column::constant(F::ZERO)))],
-        };
-        let ctl_data = CtlData {
-            zs_columns: vec![ctl_z_data.clone(); config.num_challenges],
-        };
-
-        prove_single_table(
+        prove_single_table_standalone::<F, C, S, D>(
             &stark,
             &config,
             &trace_poly_values,
-            &trace_commitments,
-            &ctl_data,
-            &GrandProductChallengeSet {
-                challenges: vec![ctl_z_data.challenge; config.num_challenges],
-            },
-            &mut Challenger::new(),
             &mut timing,
         )?;
 
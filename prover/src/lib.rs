@@ -8,33 +8,68 @@
 #![feature(decl_macro)]
 #![feature(generic_arg_infer)]
 #![allow(dead_code)]
+#[cfg(feature = "std")]
+pub mod aggregation;
+#[cfg(feature = "std")]
 pub mod all_stark;
+#[cfg(feature = "std")]
 pub mod arithmetic;
+pub mod block_verifier;
+#[cfg(feature = "std")]
 pub mod config;
+#[cfg(feature = "std")]
 pub mod constraint_consumer;
+#[cfg(feature = "std")]
 pub mod cpu;
+#[cfg(feature = "std")]
 pub mod cross_table_lookup;
+#[cfg(feature = "std")]
 pub mod evaluation_frame;
+#[cfg(feature = "std")]
 pub mod fixed_recursive_verifier;
+#[cfg(feature = "std")]
 pub mod generation;
+#[cfg(feature = "std")]
 pub mod get_challenges;
+#[cfg(feature = "std")]
 pub mod keccak;
+#[cfg(feature = "std")]
 pub mod keccak_sponge;
+#[cfg(feature = "std")]
 pub mod logic;
+#[cfg(feature = "std")]
 pub mod lookup;
+#[cfg(feature = "std")]
 pub mod memory;
+#[cfg(feature = "std")]
 pub mod poseidon;
+#[cfg(feature = "std")]
 pub mod poseidon_sponge;
+#[cfg(feature = "std")]
 pub mod proof;
+#[cfg(feature = "std")]
 pub mod prover;
+#[cfg(feature = "std")]
 pub mod recursive_verifier;
+#[cfg(feature = "std")]
+pub mod sha512_extend_sponge;
+#[cfg(feature = "std")]
 pub mod sha_compress;
+#[cfg(feature = "std")]
 pub mod sha_compress_sponge;
+#[cfg(feature = "std")]
 pub mod sha_extend;
+#[cfg(feature = "std")]
 pub mod sha_extend_sponge;
+#[cfg(feature = "std")]
 pub mod stark;
+#[cfg(feature = "std")]
 pub mod stark_testing;
+#[cfg(feature = "std")]
 pub mod util;
+#[cfg(feature = "std")]
 pub mod vanishing_poly;
+#[cfg(feature = "std")]
 pub mod verifier;
+#[cfg(feature = "std")]
 pub mod witness;
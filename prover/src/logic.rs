@@ -7,6 +7,7 @@ use plonky2::field::polynomial::PolynomialValues;
 use plonky2::field::types::Field;
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::ext_target::ExtensionTarget;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
 use plonky2_util::ceil_div_usize;
 
 use crate::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
@@ -75,6 +76,44 @@ pub fn ctl_filter<F: Field>() -> Filter<F> {
     ]))
 }
 
+/// Rotates the little-endian bit columns `bits` right by `amount`. The rotation is a permutation
+/// of the columns, so it needs no constraint.
+pub fn rotate_right_packed<P: PackedField>(bits: &[P], amount: usize) -> Vec<P> {
+    let n = bits.len();
+    (0..n).map(|i| bits[(i + amount) % n]).collect()
+}
+
+/// Shifts the little-endian bit columns `bits` right by `amount`. The top `amount` bits are the
+/// constant zero, so no constraint is needed either.
+pub fn shift_right_packed<P: PackedField>(bits: &[P], amount: usize) -> Vec<P> {
+    (0..bits.len())
+        .map(|i| bits.get(i + amount).copied().unwrap_or(P::ZEROS))
+        .collect()
+}
+
+/// Circuit version of `rotate_right_packed`. The builder is unused, as the rotation adds no gate,
+/// but is taken for symmetry with `constrain_shift_right`.
+pub fn constrain_rotate_right<F: RichField + Extendable<D>, const D: usize>(
+    _builder: &mut CircuitBuilder<F, D>,
+    bits: &[ExtensionTarget<D>],
+    amount: usize,
+) -> Vec<ExtensionTarget<D>> {
+    let n = bits.len();
+    (0..n).map(|i| bits[(i + amount) % n]).collect()
+}
+
+/// Circuit version of `shift_right_packed`, forcing the top `amount` bits to zero.
+pub fn constrain_shift_right<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    bits: &[ExtensionTarget<D>],
+    amount: usize,
+) -> Vec<ExtensionTarget<D>> {
+    let zero = builder.zero_extension();
+    (0..bits.len())
+        .map(|i| bits.get(i + amount).copied().unwrap_or(zero))
+        .collect()
+}
+
 #[derive(Copy, Clone, Default)]
 pub struct LogicStark<F, const D: usize> {
     pub f: PhantomData<F>,
@@ -322,7 +361,16 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for LogicStark<F,
 #[cfg(test)]
 mod tests {
 
-    use crate::logic::{LogicStark, Op, Operation};
+    use plonky2::field::types::Field;
+
+    use crate::all_stark::Table;
+    use crate::cross_table_lookup::testutils::assert_columns_zero_where_filter_zero;
+    use crate::cross_table_lookup::TableWithColumns;
+    use crate::logic::{
+        columns, constrain_rotate_right, constrain_shift_right, ctl_data, ctl_filter,
+        rotate_right_packed, shift_right_packed, LogicStark, Op, Operation,
+    };
+    use crate::util::{limb_from_bits_le, limb_from_bits_le_recursive, trace_rows_to_poly_values};
 
     use crate::stark_testing::{
         test_stark_check_constraints, test_stark_circuit_constraints, test_stark_low_degree,
@@ -330,6 +378,9 @@ mod tests {
 
     use anyhow::Result;
 
+    use plonky2::iop::witness::PartialWitness;
+    use plonky2::plonk::circuit_builder::CircuitBuilder;
+    use plonky2::plonk::circuit_data::CircuitConfig;
     use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
 
     #[test]
@@ -402,5 +453,80 @@ mod tests {
         for i in 0..(vals.len() - 1) {
             test_stark_check_constraints::<F, C, S, D>(stark, &vals[i], &vals[i + 1]);
         }
+
+        // Padding rows don't feed the CPU lookup.
+        let table = TableWithColumns::new(Table::Logic, ctl_data(), Some(ctl_filter()));
+        assert_columns_zero_where_filter_zero(&trace_rows_to_poly_values(vals), &table);
+    }
+
+    #[test]
+    fn test_nor() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type S = LogicStark<F, D>;
+
+        let stark = S {
+            f: Default::default(),
+        };
+
+        assert_eq!(Operation::new(Op::Nor, 0, 0).result, u32::MAX);
+        assert_eq!(Operation::new(Op::Nor, u32::MAX, 0).result, 0);
+
+        let inputs = [(0, 0), (u32::MAX, u32::MAX)]
+            .into_iter()
+            .chain((0..16).map(|_| (rand::random::<u32>(), rand::random::<u32>())))
+            .collect::<Vec<_>>();
+        let ops = inputs
+            .iter()
+            .map(|&(a, b)| Operation::new(Op::Nor, a, b))
+            .collect();
+        let vals = stark.generate_trace_rows(ops, 1 << 5);
+
+        for (row, &(a, b)) in vals.iter().zip(&inputs) {
+            assert_eq!(row[columns::RESULT.start], F::from_canonical_u32(!(a | b)));
+        }
+        for i in 0..(vals.len() - 1) {
+            test_stark_check_constraints::<F, C, S, D>(stark, &vals[i], &vals[i + 1]);
+        }
+    }
+
+    #[test]
+    fn test_rotate_and_shift_right() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let x = 0x9abc_def1u32;
+        let bits = (0..32)
+            .map(|i| F::from_bool((x >> i) & 1 == 1))
+            .collect::<Vec<_>>();
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let bit_targets = bits
+            .iter()
+            .map(|&bit| builder.constant_extension(bit.into()))
+            .collect::<Vec<_>>();
+        for amount in [0, 1, 3, 7, 10, 17, 18, 19, 31] {
+            let rotated = limb_from_bits_le(rotate_right_packed(&bits, amount));
+            assert_eq!(
+                rotated,
+                F::from_canonical_u32(x.rotate_right(amount as u32))
+            );
+            let shifted = limb_from_bits_le(shift_right_packed(&bits, amount));
+            assert_eq!(shifted, F::from_canonical_u32(x >> amount));
+
+            let rotated = constrain_rotate_right(&mut builder, &bit_targets, amount);
+            let rotated = limb_from_bits_le_recursive(&mut builder, rotated);
+            let expected = builder
+                .constant_extension(F::from_canonical_u32(x.rotate_right(amount as u32)).into());
+            builder.connect_extension(rotated, expected);
+            let shifted = constrain_shift_right(&mut builder, &bit_targets, amount);
+            let shifted = limb_from_bits_le_recursive(&mut builder, shifted);
+            let expected = builder.constant_extension(F::from_canonical_u32(x >> amount).into());
+            builder.connect_extension(shifted, expected);
+        }
+        let circuit = builder.build::<C>();
+        circuit.verify(circuit.prove(PartialWitness::new())?)
     }
 }
@@ -92,6 +92,7 @@ pub(crate) fn lookup_helper_columns<F: Field>(
         &columns_filters,
         grand_challenge,
         constraint_degree,
+        false,
     );
 
     // Add `1/(table+challenge)` to the helper columns.
@@ -59,12 +59,12 @@ impl MemoryOp {
             virt,
         } = self.address;
         row[ADDR_CONTEXT] = F::from_canonical_usize(context);
-        row[ADDR_SEGMENT] = F::from_canonical_usize(segment);
+        row[ADDR_SEGMENT] = F::from_canonical_u32(segment);
         row[ADDR_VIRTUAL] = F::from_canonical_usize(virt);
         // The value written to R0 register should be ignored as 0
         let value = if (self.kind == Write)
             && (context == 0)
-            && (segment == (Segment::RegisterFile as usize))
+            && (segment == Segment::REGISTER_FILE)
             && (virt == 0)
         {
             0_u32
@@ -18,7 +18,29 @@ pub enum Segment {
 impl Segment {
     pub(crate) const COUNT: usize = 5;
 
-    pub(crate) fn all() -> [Self; Self::COUNT] {
+    /// Numeric identifiers of the built-in segments, as stored in `MemoryAddress::segment`.
+    pub const CODE: u32 = Segment::Code as u32;
+    pub const KERNEL_GENERAL: u32 = Segment::KernelGeneral as u32;
+    pub const KERNEL_GENERAL_2: u32 = Segment::KernelGeneral2 as u32;
+    pub const SHIFT_TABLE: u32 = Segment::ShiftTable as u32;
+    pub const REGISTER_FILE: u32 = Segment::RegisterFile as u32;
+
+    /// The first identifier available to program-defined segments, e.g. a scratchpad region.
+    /// The memory STARK range checks the delta between consecutive segments, so custom
+    /// identifiers should be allocated contiguously from here.
+    pub const FIRST_CUSTOM: u32 = Self::COUNT as u32;
+
+    pub fn id(&self) -> u32 {
+        *self as u32
+    }
+
+    /// The number of built-in segments.
+    pub const fn count() -> usize {
+        Self::COUNT
+    }
+
+    /// All built-in segments, in order of their identifiers.
+    pub fn all() -> [Self; Self::COUNT] {
         [
             Self::Code,
             Self::KernelGeneral,
@@ -50,3 +72,9 @@ impl Segment {
         }
     }
 }
+
+impl From<Segment> for u32 {
+    fn from(segment: Segment) -> Self {
+        segment.id()
+    }
+}
@@ -678,22 +678,15 @@ mod tests {
     use anyhow::Result;
     use env_logger::{try_init_from_env, Env, DEFAULT_FILTER_ENV};
     use plonky2::field::goldilocks_field::GoldilocksField;
-    use plonky2::field::polynomial::PolynomialValues;
     use plonky2::field::types::{Field, Sample};
-    use plonky2::fri::oracle::PolynomialBatch;
-    use plonky2::iop::challenger::Challenger;
     use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
-    use plonky2::timed;
     use plonky2::util::timing::TimingTree;
 
     use crate::config::StarkConfig;
     use crate::constraint_consumer::ConstraintConsumer;
-    use crate::cross_table_lookup::{
-        Column, CtlData, CtlZData, Filter, GrandProductChallenge, GrandProductChallengeSet,
-    };
     use crate::poseidon::constants::SPONGE_WIDTH;
     use crate::poseidon::poseidon_stark::{eval_packed_generic, PoseidonStark};
-    use crate::prover::prove_single_table;
+    use crate::prover::testutils::prove_single_table_standalone;
     use crate::stark_testing::{test_stark_circuit_constraints, test_stark_low_degree};
 
     #[test]
@@ -765,49 +758,10 @@ mod tests {
         let mut timing = TimingTree::new("prove", log::Level::Debug);
         let trace_poly_values = stark.generate_trace(&input, 8);
 
-        // TODO: Cloning this isn't great; consider having `from_values` accept a reference,
-        // or having `compute_permutation_z_polys` read trace values from the `PolynomialBatch`.
-        let cloned_trace_poly_values = timed!(timing, "clone", trace_poly_values.clone());
-
-        let trace_commitments = timed!(
-            timing,
-            "compute trace commitment",
-            PolynomialBatch::<F, C, D>::from_values(
-                cloned_trace_poly_values,
-                config.fri_config.rate_bits,
-                false,
-                config.fri_config.cap_height,
-                &mut timing,
-                None,
-            )
-        );
-        let degree = 1 << trace_commitments.degree_log;
-
-        // Fake CTL data.
-        let ctl_z_data = CtlZData {
-            helper_columns: vec![PolynomialValues::zero(degree)],
-            z: PolynomialValues::zero(degree),
-            challenge: GrandProductChallenge {
-                beta: F::ZERO,
-                gamma: F::ZERO,
-            },
-            columns: vec![],
-            filter: vec![Some(Filter::new_simple(Column::constant(F::ZERO)))],
-        };
-        let ctl_data = CtlData {
-            zs_columns: vec![ctl_z_data.clone(); config.num_challenges],
-        };
-
-        prove_single_table(
+        prove_single_table_standalone::<F, C, S, D>(
             &stark,
             &config,
             &trace_poly_values,
-            &trace_commitments,
-            &ctl_data,
-            &GrandProductChallengeSet {
-                challenges: vec![ctl_z_data.challenge; config.num_challenges],
-            },
-            &mut Challenger::new(),
             &mut timing,
         )?;
 
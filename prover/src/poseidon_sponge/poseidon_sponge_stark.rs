@@ -14,7 +14,6 @@ use plonky2::iop::ext_target::ExtensionTarget;
 use crate::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
 use crate::cross_table_lookup::{Column, Filter};
 use crate::evaluation_frame::{StarkEvaluationFrame, StarkFrame};
-use crate::memory::segments::Segment;
 use crate::poseidon::constants::{SPONGE_RATE, SPONGE_WIDTH};
 use crate::poseidon::poseidon_stark::poseidon_with_witness;
 use crate::poseidon_sponge::columns::*;
@@ -317,7 +316,7 @@ impl<F: RichField + Extendable<D>, const D: usize> PoseidonSpongeStark<F, D> {
         let virt: [usize; SPONGE_RATE] = virt.try_into().unwrap();
 
         row.context = F::from_canonical_usize(op.base_address[0].context);
-        row.segment = F::from_canonical_usize(op.base_address[Segment::Code as usize].segment);
+        row.segment = F::from_canonical_u32(op.base_address[0].segment);
         row.virt = virt.map(F::from_canonical_usize);
         row.timestamp = F::from_canonical_usize(op.timestamp);
         row.len = F::from_canonical_usize(op.input.len());
@@ -669,7 +668,7 @@ mod tests {
         let op = PoseidonSpongeOp {
             base_address: vec![MemoryAddress {
                 context: 0,
-                segment: Segment::Code as usize,
+                segment: Segment::CODE,
                 virt: 0,
             }],
             timestamp: 0,
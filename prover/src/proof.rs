@@ -1,24 +1,33 @@
-use crate::all_stark::NUM_PUBLIC_INPUT_USERDATA;
+use crate::all_stark::{Table, NUM_PUBLIC_INPUT_USERDATA};
+use core::fmt;
 use itertools::Itertools;
 use plonky2::field::extension::{Extendable, FieldExtension};
+use plonky2::field::polynomial::PolynomialCoeffs;
+use plonky2::field::types::Field;
 use plonky2::fri::oracle::PolynomialBatch;
 use plonky2::fri::proof::{FriChallenges, FriChallengesTarget, FriProof, FriProofTarget};
 use plonky2::fri::structure::{
     FriOpeningBatch, FriOpeningBatchTarget, FriOpenings, FriOpeningsTarget,
 };
 use plonky2::hash::hash_types::{MerkleCapTarget, RichField};
+use plonky2::hash::hashing::PlonkyPermutation;
 use plonky2::hash::merkle_tree::MerkleCap;
 use plonky2::iop::ext_target::ExtensionTarget;
 use plonky2::iop::target::{BoolTarget, Target};
 use plonky2::plonk::circuit_builder::CircuitBuilder;
 use plonky2::plonk::config::{GenericConfig, Hasher};
-use plonky2::util::serialization::{Buffer, IoResult, Read, Write};
+use plonky2::util::serialization::{Buffer, IoError, IoResult, Read, Write};
 use plonky2_maybe_rayon::*;
+use plonky2_util::log2_strict;
 use serde::{Deserialize, Serialize};
 
-use crate::all_stark::NUM_TABLES;
+use crate::all_stark::{AllStark, NUM_TABLES};
 use crate::config::StarkConfig;
 use crate::cross_table_lookup::GrandProductChallengeSet;
+use crate::verifier::verify_table_proof;
+
+/// The version of the `AllProof::to_bytes` format, to be bumped on any layout change.
+pub const ALL_PROOF_FORMAT_VERSION: u8 = 1;
 
 /// A STARK proof for each table, plus some metadata used to create recursive wrapper proofs.
 #[derive(Debug, Clone)]
@@ -29,13 +38,232 @@ pub struct AllProof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, co
 }
 
 impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> AllProof<F, C, D> {
+    /// The log2 of the trace length of every table, 0 for the empty proofs of inactive tables.
     pub fn degree_bits(&self, config: &StarkConfig) -> [usize; NUM_TABLES] {
-        core::array::from_fn(|i| self.stark_proofs[i].proof.recover_degree_bits(config))
+        core::array::from_fn(|i| {
+            let proof = &self.stark_proofs[i].proof;
+            if proof.is_empty() {
+                0
+            } else {
+                proof.recover_degree_bits(config)
+            }
+        })
+    }
+
+    /// Whether this proof has the public values `other`, e.g. those of a receipt. Unlike
+    /// `PublicValues::diff`, userdata of different lengths never compares equal.
+    pub fn public_values_eq(&self, other: &PublicValues) -> bool {
+        self.public_values == *other
+    }
+
+    /// Verifies the proof of `table` on its own, e.g. to isolate the table at fault when a proof
+    /// fails to verify. Its constraints, including its side of the cross-table lookups, are checked
+    /// against the CTL challenges of this proof, but the CTL sums, which involve all tables, aren't.
+    /// `active_tables` are the tables the proof was made with, and `table` must be one of them.
+    pub fn verify_table(
+        &self,
+        all_stark: &AllStark<F, D>,
+        table: Table,
+        config: &StarkConfig,
+        active_tables: &[bool; NUM_TABLES],
+    ) -> anyhow::Result<()> {
+        verify_table_proof(all_stark, self, table, config, active_tables)
+    }
+
+    /// Returns the structural fingerprint of this proof, meant to be logged when a proof
+    /// doesn't match the circuits verifying it.
+    pub fn summary(&self) -> ProofSummary {
+        ProofSummary {
+            tables: core::array::from_fn(|i| self.stark_proofs[i].proof.summary()),
+            num_ctl_challenges: self.ctl_challenges.challenges.len(),
+            roots_before: self.public_values.roots_before.clone(),
+            roots_after: self.public_values.roots_after.clone(),
+        }
+    }
+
+    /// Serializes the proof, e.g. to cache it before recursion. The first byte is
+    /// `ALL_PROOF_FORMAT_VERSION`.
+    ///
+    /// The STARK proofs are encoded with `bincode`: plonky2's FRI proof deserialization needs the
+    /// `CommonCircuitData` of a circuit, which STARK proofs don't have.
+    pub fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let mut buffer = vec![ALL_PROOF_FORMAT_VERSION];
+        for stark_proof in &self.stark_proofs {
+            buffer.write_field_vec(stark_proof.init_challenger_state.as_ref())?;
+            let proof_bytes = bincode::serialize(&stark_proof.proof)?;
+            buffer.write_usize(proof_bytes.len())?;
+            buffer.write_all(&proof_bytes)?;
+        }
+        self.ctl_challenges.to_buffer(&mut buffer)?;
+        let public_values_bytes = bincode::serialize(&self.public_values)?;
+        buffer.write_usize(public_values_bytes.len())?;
+        buffer.write_all(&public_values_bytes)?;
+        Ok(buffer)
+    }
+
+    /// Deserializes a proof written by `to_bytes`, failing if it was written in another format
+    /// version.
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let Some((&version, bytes)) = bytes.split_first() else {
+            anyhow::bail!("Empty AllProof bytes");
+        };
+        anyhow::ensure!(
+            version == ALL_PROOF_FORMAT_VERSION,
+            "Unsupported AllProof format version {version}, expected {ALL_PROOF_FORMAT_VERSION}"
+        );
+
+        let mut buffer = Buffer::new(bytes);
+        let mut stark_proofs = Vec::with_capacity(NUM_TABLES);
+        for _ in 0..NUM_TABLES {
+            let state = buffer.read_field_vec(<C::Hasher as Hasher<F>>::Permutation::WIDTH)?;
+            let init_challenger_state = <C::Hasher as Hasher<F>>::Permutation::new(state);
+            let proof = bincode::deserialize(&read_length_prefixed(&mut buffer)?)?;
+            stark_proofs.push(StarkProofWithMetadata {
+                init_challenger_state,
+                proof,
+            });
+        }
+        let ctl_challenges = GrandProductChallengeSet::from_buffer(&mut buffer)?;
+        let public_values = bincode::deserialize(&read_length_prefixed(&mut buffer)?)?;
+        anyhow::ensure!(
+            buffer.unread_bytes().is_empty(),
+            "Trailing bytes after AllProof"
+        );
+
+        Ok(Self {
+            stark_proofs: stark_proofs
+                .try_into()
+                .unwrap_or_else(|_| unreachable!("one proof per table")),
+            ctl_challenges,
+            public_values,
+        })
+    }
+
+    /// Returns the size of every table's STARK proof, to see where proving time and proof size
+    /// go.
+    pub fn stats(&self, config: &StarkConfig) -> ProofStats {
+        let degree_bits = self.degree_bits(config);
+        let tables: [TableProofStats; NUM_TABLES] = core::array::from_fn(|i| {
+            let proof = &self.stark_proofs[i].proof;
+            TableProofStats {
+                degree_bits: degree_bits[i],
+                num_trace_columns: proof.openings.local_values.len(),
+                num_auxiliary_polys: proof.openings.auxiliary_polys.len(),
+                num_bytes: bincode::serialized_size(proof).expect("STARK proofs are serializable")
+                    as usize,
+            }
+        });
+        ProofStats {
+            total_trace_columns: tables.iter().map(|t| t.num_trace_columns).sum(),
+            total_auxiliary_polys: tables.iter().map(|t| t.num_auxiliary_polys).sum(),
+            total_bytes: tables.iter().map(|t| t.num_bytes).sum(),
+            tables,
+        }
+    }
+}
+
+/// Reads bytes written as their length followed by the bytes themselves.
+fn read_length_prefixed(buffer: &mut Buffer) -> IoResult<Vec<u8>> {
+    let len = buffer.read_usize()?;
+    if len > buffer.unread_bytes().len() {
+        return Err(IoError);
+    }
+    let mut bytes = vec![0; len];
+    buffer.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Size of a single table's STARK proof, see `AllProof::stats`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableProofStats {
+    /// Log2 of the trace length.
+    pub degree_bits: usize,
+    /// Number of trace columns.
+    pub num_trace_columns: usize,
+    /// Number of lookup helper, CTL helper and CTL `Z` polynomials.
+    pub num_auxiliary_polys: usize,
+    /// Length of the proof serialized with `bincode`.
+    pub num_bytes: usize,
+}
+
+/// Sizes of the STARK proofs of an `AllProof`, per table and in total.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofStats {
+    pub tables: [TableProofStats; NUM_TABLES],
+    pub total_trace_columns: usize,
+    pub total_auxiliary_polys: usize,
+    pub total_bytes: usize,
+}
+
+impl fmt::Display for ProofStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (table, stats) in Table::all().iter().zip(self.tables.iter()) {
+            writeln!(
+                f,
+                "{:?}: degree_bits={}, trace_columns={}, auxiliary_polys={}, bytes={}",
+                table,
+                stats.degree_bits,
+                stats.num_trace_columns,
+                stats.num_auxiliary_polys,
+                stats.num_bytes,
+            )?;
+        }
+        writeln!(
+            f,
+            "Total: trace_columns={}, auxiliary_polys={}, bytes={}",
+            self.total_trace_columns, self.total_auxiliary_polys, self.total_bytes,
+        )
+    }
+}
+
+/// Shape of a single table's STARK proof, see `AllProof::summary`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableProofSummary {
+    /// Log2 of the LDE size of the trace. The trace degree is `lde_bits - rate_bits`.
+    pub lde_bits: usize,
+    /// Number of trace columns opened.
+    pub num_columns: usize,
+    /// Number of lookup helper, CTL helper and CTL `Z` polynomials opened.
+    pub num_auxiliary_polys: usize,
+    /// Number of CTL `Z` polynomials opened at `1`.
+    pub num_ctl_zs: usize,
+    /// Number of quotient polynomial chunks opened.
+    pub num_quotient_polys: usize,
+}
+
+/// Structural summary of an `AllProof`.
+#[derive(Debug, Clone)]
+pub struct ProofSummary {
+    pub tables: [TableProofSummary; NUM_TABLES],
+    pub num_ctl_challenges: usize,
+    pub roots_before: MemRoots,
+    pub roots_after: MemRoots,
+}
+
+impl fmt::Display for ProofSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "ctl challenges: {}", self.num_ctl_challenges)?;
+        writeln!(f, "roots before: {:?}", self.roots_before.root)?;
+        writeln!(f, "roots after: {:?}", self.roots_after.root)?;
+        for (table, summary) in Table::all().iter().zip(self.tables.iter()) {
+            writeln!(
+                f,
+                "{:?}: lde_bits={}, columns={}, auxiliary_polys={}, ctl_zs={}, quotient_polys={}",
+                table,
+                summary.lde_bits,
+                summary.num_columns,
+                summary.num_auxiliary_polys,
+                summary.num_ctl_zs,
+                summary.num_quotient_polys,
+            )?;
+        }
+        Ok(())
     }
 }
 
 pub(crate) struct AllProofChallenges<F: RichField + Extendable<D>, const D: usize> {
-    pub stark_challenges: [StarkProofChallenges<F, D>; NUM_TABLES],
+    /// The challenges of every active table, `None` for inactive ones.
+    pub stark_challenges: [Option<StarkProofChallenges<F, D>>; NUM_TABLES],
     pub ctl_challenges: GrandProductChallengeSet<F>,
 }
 
@@ -48,14 +276,111 @@ pub(crate) struct AllChallengerState<F: RichField + Extendable<D>, H: Hasher<F>,
 }
 
 /// Memory values which are public.
-#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
 pub struct PublicValues {
     pub roots_before: MemRoots,
     pub roots_after: MemRoots,
+    /// At most `NUM_PUBLIC_INPUT_USERDATA` bytes, zero-padded to that length in circuits.
     pub userdata: Vec<u8>,
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+impl PublicValues {
+    /// `userdata` zero-padded to `NUM_PUBLIC_INPUT_USERDATA` bytes, or `None` if it is longer.
+    pub fn padded_userdata(&self) -> Option<[u8; NUM_PUBLIC_INPUT_USERDATA]> {
+        if self.userdata.len() > NUM_PUBLIC_INPUT_USERDATA {
+            return None;
+        }
+        let mut padded = [0; NUM_PUBLIC_INPUT_USERDATA];
+        padded[..self.userdata.len()].copy_from_slice(&self.userdata);
+        Some(padded)
+    }
+
+    /// Lists the limbs in which `self` and `other` differ, as the circuits see them, i.e. with
+    /// `userdata` zero-padded. Userdata too long for the circuits is compared unpadded.
+    pub fn diff(&self, other: &PublicValues) -> Vec<FieldDiff> {
+        let roots_diff = |lhs: &MemRoots, rhs: &MemRoots| {
+            (0..lhs.root.len())
+                .filter(|&limb| lhs.root[limb] != rhs.root[limb])
+                .map(|limb| (limb, lhs.root[limb], rhs.root[limb]))
+                .collect::<Vec<_>>()
+        };
+        let mut diffs = roots_diff(&self.roots_before, &other.roots_before)
+            .into_iter()
+            .map(|(limb, lhs, rhs)| FieldDiff::RootsBefore { limb, lhs, rhs })
+            .collect::<Vec<_>>();
+        diffs.extend(
+            roots_diff(&self.roots_after, &other.roots_after)
+                .into_iter()
+                .map(|(limb, lhs, rhs)| FieldDiff::RootsAfter { limb, lhs, rhs }),
+        );
+
+        let pad = |userdata: &[u8]| {
+            let mut padded = userdata.to_vec();
+            padded.resize(userdata.len().max(NUM_PUBLIC_INPUT_USERDATA), 0);
+            padded
+        };
+        let (lhs_userdata, rhs_userdata) = (pad(&self.userdata), pad(&other.userdata));
+        let len = lhs_userdata.len().max(rhs_userdata.len());
+        for index in 0..len {
+            let lhs = lhs_userdata.get(index).copied().unwrap_or(0);
+            let rhs = rhs_userdata.get(index).copied().unwrap_or(0);
+            if lhs != rhs {
+                diffs.push(FieldDiff::Userdata { index, lhs, rhs });
+            }
+        }
+        if self.userdata.len() != other.userdata.len() {
+            diffs.push(FieldDiff::UserdataLen {
+                lhs: self.userdata.len(),
+                rhs: other.userdata.len(),
+            });
+        }
+        diffs
+    }
+}
+
+/// A limb of `PublicValues` which differs between two of them, see `PublicValues::diff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldDiff {
+    RootsBefore {
+        limb: usize,
+        lhs: u32,
+        rhs: u32,
+    },
+    RootsAfter {
+        limb: usize,
+        lhs: u32,
+        rhs: u32,
+    },
+    /// A byte of the userdata, zero-padded to `NUM_PUBLIC_INPUT_USERDATA` bytes.
+    Userdata {
+        index: usize,
+        lhs: u8,
+        rhs: u8,
+    },
+    UserdataLen {
+        lhs: usize,
+        rhs: usize,
+    },
+}
+
+impl fmt::Display for FieldDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RootsBefore { limb, lhs, rhs } => {
+                write!(f, "roots_before[{limb}]: {lhs:#010x} != {rhs:#010x}")
+            }
+            Self::RootsAfter { limb, lhs, rhs } => {
+                write!(f, "roots_after[{limb}]: {lhs:#010x} != {rhs:#010x}")
+            }
+            Self::Userdata { index, lhs, rhs } => {
+                write!(f, "userdata[{index}]: {lhs:#04x} != {rhs:#04x}")
+            }
+            Self::UserdataLen { lhs, rhs } => write!(f, "userdata length: {lhs} != {rhs}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MemRoots {
     pub root: [u32; 8],
 }
@@ -66,11 +391,21 @@ pub struct MemRoots {
 pub struct PublicValuesTarget {
     pub roots_before: MemRootsTarget,
     pub roots_after: MemRootsTarget,
+    /// `userdata` padded with zeros up to `NUM_PUBLIC_INPUT_USERDATA` limbs.
     pub userdata: [Target; NUM_PUBLIC_INPUT_USERDATA],
+    /// The number of meaningful limbs of `userdata`.
+    pub userdata_len: Target,
 }
 
 impl PublicValuesTarget {
-    pub fn to_buffer(&self, buffer: &mut Vec<u8>) -> IoResult<()> {
+    /// Index of `userdata` within the public inputs.
+    pub const USERDATA_OFFSET: usize = 16;
+    /// Index of `userdata_len` within the public inputs.
+    pub const USERDATA_LEN_OFFSET: usize = Self::USERDATA_OFFSET + NUM_PUBLIC_INPUT_USERDATA;
+    /// The number of public inputs taken by public values.
+    pub const SIZE: usize = Self::USERDATA_LEN_OFFSET + 1;
+
+    pub fn to_buffer<W: Write>(&self, buffer: &mut W) -> IoResult<()> {
         let MemRootsTarget {
             root: state_root_before,
         } = self.roots_before;
@@ -84,6 +419,7 @@ impl PublicValuesTarget {
         buffer.write_target_array(&state_root_after)?;
 
         buffer.write_target_array(&self.userdata)?;
+        buffer.write_target(self.userdata_len)?;
         Ok(())
     }
 
@@ -97,20 +433,33 @@ impl PublicValuesTarget {
         };
 
         let userdata = buffer.read_target_array()?;
+        let userdata_len = buffer.read_target()?;
 
         Ok(Self {
             roots_before,
             roots_after,
             userdata,
+            userdata_len,
         })
     }
 
-    pub fn from_public_inputs(pis: &[Target]) -> Self {
-        Self {
+    /// Reads the public values from the first `SIZE` public inputs of a proof, which may have
+    /// more public inputs after them.
+    pub fn from_public_inputs(pis: &[Target]) -> Result<Self, PublicValuesError> {
+        if pis.len() < Self::SIZE {
+            return Err(PublicValuesError::TooFewPublicInputs {
+                len: pis.len(),
+                expected: Self::SIZE,
+            });
+        }
+        Ok(Self {
             roots_before: MemRootsTarget::from_public_inputs(&pis[0..8]),
             roots_after: MemRootsTarget::from_public_inputs(&pis[8..16]),
-            userdata: pis[16..16 + NUM_PUBLIC_INPUT_USERDATA].try_into().unwrap(),
-        }
+            userdata: pis[Self::USERDATA_OFFSET..Self::USERDATA_LEN_OFFSET]
+                .try_into()
+                .unwrap(),
+            userdata_len: pis[Self::USERDATA_LEN_OFFSET],
+        })
     }
 
     pub fn select<F: RichField + Extendable<D>, const D: usize>(
@@ -135,7 +484,42 @@ impl PublicValuesTarget {
             userdata: core::array::from_fn(|i| {
                 builder.select(condition, pv0.userdata[i], pv1.userdata[i])
             }),
+            userdata_len: builder.select(condition, pv0.userdata_len, pv1.userdata_len),
+        }
+    }
+
+    /// Connect the `userdata` of two public values, including its length. Both are padded to
+    /// `NUM_PUBLIC_INPUT_USERDATA` limbs, so userdata of different lengths can't be connected.
+    pub fn connect_userdata<F: RichField + Extendable<D>, const D: usize>(
+        builder: &mut CircuitBuilder<F, D>,
+        pv0: &Self,
+        pv1: &Self,
+    ) {
+        for (&limb0, &limb1) in pv0.userdata.iter().zip(&pv1.userdata) {
+            builder.connect(limb0, limb1);
+        }
+        builder.connect(pv0.userdata_len, pv1.userdata_len);
+    }
+
+    /// Checks that `userdata_len` is at most `NUM_PUBLIC_INPUT_USERDATA` and that the limbs past
+    /// it are zero, so that a given userdata has a single padded encoding.
+    pub fn check_userdata_len<F: RichField + Extendable<D>, const D: usize>(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+    ) {
+        // `past_end` is 1 from the limb at index `userdata_len` on, and 0 before it.
+        let mut past_end = builder.zero();
+        for i in 0..=NUM_PUBLIC_INPUT_USERDATA {
+            let index = builder.constant(F::from_canonical_usize(i));
+            let is_len = builder.is_equal(self.userdata_len, index);
+            past_end = builder.add(past_end, is_len.target);
+            if let Some(&limb) = self.userdata.get(i) {
+                let padding = builder.mul(limb, past_end);
+                builder.assert_zero(padding);
+            }
         }
+        // Otherwise `userdata_len` matched no index.
+        builder.assert_one(past_end);
     }
 }
 
@@ -144,6 +528,27 @@ pub struct MemRootsTarget {
     pub root: [Target; 8],
 }
 
+/// An error when reading public values from a proof's public inputs, see
+/// `PublicValuesTarget::from_public_inputs`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PublicValuesError {
+    /// The proof has fewer public inputs than public values take.
+    TooFewPublicInputs { len: usize, expected: usize },
+}
+
+impl fmt::Display for PublicValuesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooFewPublicInputs { len, expected } => write!(
+                f,
+                "Proof has {len} public inputs, but public values take at least {expected}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PublicValuesError {}
+
 impl MemRootsTarget {
     pub const SIZE: usize = 24;
 
@@ -174,7 +579,8 @@ impl MemRootsTarget {
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct StarkProof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> {
     /// Merkle cap of LDEs of trace values.
     pub trace_cap: MerkleCap<F, C::Hasher>,
@@ -200,7 +606,45 @@ where
     pub proof: StarkProof<F, C, D>,
 }
 
+impl<F, C, const D: usize> StarkProofWithMetadata<F, C, D>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    /// The placeholder proof of a table left out of proving, see
+    /// `AllRecursiveCircuits::new_with_active`. It commits to nothing and doesn't verify.
+    pub fn empty() -> Self {
+        Self {
+            init_challenger_state: <C::Hasher as Hasher<F>>::Permutation::new(core::iter::empty()),
+            proof: StarkProof {
+                trace_cap: MerkleCap(vec![]),
+                auxiliary_polys_cap: MerkleCap(vec![]),
+                quotient_polys_cap: MerkleCap(vec![]),
+                openings: StarkOpeningSet {
+                    local_values: vec![],
+                    next_values: vec![],
+                    auxiliary_polys: vec![],
+                    auxiliary_polys_next: vec![],
+                    ctl_zs_first: vec![],
+                    quotient_polys: vec![],
+                },
+                opening_proof: FriProof {
+                    commit_phase_merkle_caps: vec![],
+                    query_round_proofs: vec![],
+                    final_poly: PolynomialCoeffs::empty(),
+                    pow_witness: F::ZERO,
+                },
+            },
+        }
+    }
+}
+
 impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> StarkProof<F, C, D> {
+    /// Whether this is the proof of `StarkProofWithMetadata::empty`.
+    pub fn is_empty(&self) -> bool {
+        self.trace_cap.0.is_empty() && self.opening_proof.query_round_proofs.is_empty()
+    }
+
     /// Recover the length of the trace from a STARK proof and a STARK config.
     pub fn recover_degree_bits(&self, config: &StarkConfig) -> usize {
         let initial_merkle_proof = &self.opening_proof.query_round_proofs[0]
@@ -211,9 +655,46 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize> S
         lde_bits - config.fri_config.rate_bits
     }
 
+    /// Like `recover_degree_bits`, but returns `None` rather than panicking or returning a
+    /// nonsensical value on a malformed proof: one without query rounds, or whose LDE would be
+    /// smaller than the blowup or larger than the field's two-adic subgroup.
+    pub fn try_recover_degree_bits(&self, config: &StarkConfig) -> Option<usize> {
+        let query_round = self.opening_proof.query_round_proofs.first()?;
+        let (_, initial_merkle_proof) = query_round.initial_trees_proof.evals_proofs.first()?;
+        let lde_bits = config.fri_config.cap_height + initial_merkle_proof.siblings.len();
+        if lde_bits > F::TWO_ADICITY {
+            return None;
+        }
+        lde_bits.checked_sub(config.fri_config.rate_bits)
+    }
+
     pub fn num_ctl_zs(&self) -> usize {
         self.openings.ctl_zs_first.len()
     }
+
+    /// Recover the LDE size of the trace without a STARK config, using the cap height
+    /// from the trace cap.
+    pub fn recover_lde_bits(&self) -> usize {
+        let initial_merkle_proof = &self.opening_proof.query_round_proofs[0]
+            .initial_trees_proof
+            .evals_proofs[0]
+            .1;
+        log2_strict(self.trace_cap.0.len()) + initial_merkle_proof.siblings.len()
+    }
+
+    pub fn summary(&self) -> TableProofSummary {
+        TableProofSummary {
+            lde_bits: if self.is_empty() {
+                0
+            } else {
+                self.recover_lde_bits()
+            },
+            num_columns: self.openings.local_values.len(),
+            num_auxiliary_polys: self.openings.auxiliary_polys.len(),
+            num_ctl_zs: self.num_ctl_zs(),
+            num_quotient_polys: self.openings.quotient_polys.len(),
+        }
+    }
 }
 
 #[derive(Eq, PartialEq, Debug)]
@@ -226,7 +707,7 @@ pub struct StarkProofTarget<const D: usize> {
 }
 
 impl<const D: usize> StarkProofTarget<D> {
-    pub fn to_buffer(&self, buffer: &mut Vec<u8>) -> IoResult<()> {
+    pub fn to_buffer<W: Write>(&self, buffer: &mut W) -> IoResult<()> {
         buffer.write_target_merkle_cap(&self.trace_cap)?;
         buffer.write_target_merkle_cap(&self.auxiliary_polys_cap)?;
         buffer.write_target_merkle_cap(&self.quotient_polys_cap)?;
@@ -279,7 +760,8 @@ pub(crate) struct StarkProofChallengesTarget<const D: usize> {
 }
 
 /// Purported values of each polynomial at the challenge point.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct StarkOpeningSet<F: RichField + Extendable<D>, const D: usize> {
     /// Openings of trace polynomials at `zeta`.
     pub local_values: Vec<F::Extension>,
@@ -378,7 +860,7 @@ pub struct StarkOpeningSetTarget<const D: usize> {
 }
 
 impl<const D: usize> StarkOpeningSetTarget<D> {
-    pub fn to_buffer(&self, buffer: &mut Vec<u8>) -> IoResult<()> {
+    pub fn to_buffer<W: Write>(&self, buffer: &mut W) -> IoResult<()> {
         buffer.write_target_ext_vec(&self.local_values)?;
         buffer.write_target_ext_vec(&self.next_values)?;
         buffer.write_target_ext_vec(&self.auxiliary_polys)?;
@@ -455,3 +937,179 @@ pub struct StarkProofWithPublicInputs<
     // TODO: Maybe make it generic over a `S: Stark` and replace with `[F; S::PUBLIC_INPUTS]`.
     pub public_inputs: Vec<F>,
 }
+
+#[cfg(test)]
+mod tests {
+    use plonky2::plonk::circuit_builder::CircuitBuilder;
+    use plonky2::plonk::circuit_data::CircuitConfig;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    use crate::all_stark::Table;
+    use crate::arithmetic::arithmetic_stark::ArithmeticStark;
+    use crate::cpu::cpu_stark::CpuStark;
+    use crate::keccak::keccak_stark::KeccakStark;
+    use crate::keccak_sponge::keccak_sponge_stark::KeccakSpongeStark;
+    use crate::logic::LogicStark;
+    use crate::memory::memory_stark::MemoryStark;
+    use crate::poseidon::poseidon_stark::PoseidonStark;
+    use crate::poseidon_sponge::poseidon_sponge_stark::PoseidonSpongeStark;
+    use crate::proof::{
+        AllProof, FieldDiff, MemRoots, PublicValues, PublicValuesError, PublicValuesTarget,
+        ALL_PROOF_FORMAT_VERSION,
+    };
+    use crate::prover::testutils::prove_exit_kernel;
+    use crate::sha_compress::sha_compress_stark::ShaCompressStark;
+    use crate::sha_compress_sponge::sha_compress_sponge_stark::ShaCompressSpongeStark;
+    use crate::sha_extend::sha_extend_stark::ShaExtendStark;
+    use crate::sha_extend_sponge::sha_extend_sponge_stark::ShaExtendSpongeStark;
+    use crate::stark::Stark;
+    use crate::verifier::verify_proof;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    #[test]
+    fn test_public_values_target_from_public_inputs() {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let pis = builder.add_virtual_targets(PublicValuesTarget::SIZE + 4);
+
+        let pv = PublicValuesTarget::from_public_inputs(&pis).unwrap();
+        assert_eq!(pv.roots_before.root, pis[0..8]);
+        assert_eq!(
+            pv.userdata_len,
+            pis[PublicValuesTarget::USERDATA_LEN_OFFSET]
+        );
+        assert!(PublicValuesTarget::from_public_inputs(&pis[..PublicValuesTarget::SIZE]).is_ok());
+
+        let err = PublicValuesTarget::from_public_inputs(&pis[..10]).unwrap_err();
+        assert_eq!(
+            err,
+            PublicValuesError::TooFewPublicInputs {
+                len: 10,
+                expected: PublicValuesTarget::SIZE,
+            }
+        );
+        assert!(err.to_string().contains("10 public inputs"));
+    }
+
+    #[test]
+    fn test_all_proof_bytes_round_trip() -> anyhow::Result<()> {
+        let (all_stark, config, proof) = prove_exit_kernel::<F, C, D>()?;
+
+        let bytes = proof.to_bytes()?;
+        assert_eq!(bytes[0], ALL_PROOF_FORMAT_VERSION);
+        let decoded = AllProof::<F, C, D>::from_bytes(&bytes)?;
+        assert_eq!(decoded.to_bytes()?, bytes);
+        assert!(decoded.public_values_eq(&proof.public_values));
+        verify_proof(&all_stark, decoded, &config)?;
+
+        let mut truncated = bytes.clone();
+        truncated.pop();
+        assert!(AllProof::<F, C, D>::from_bytes(&truncated).is_err());
+        let mut trailing = bytes;
+        trailing.push(0);
+        assert!(AllProof::<F, C, D>::from_bytes(&trailing).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_public_values_diff() {
+        let values = PublicValues {
+            roots_before: MemRoots { root: [1; 8] },
+            roots_after: MemRoots { root: [2; 8] },
+            userdata: vec![1, 2, 3],
+        };
+        assert_eq!(values.diff(&values.clone()), vec![]);
+        assert_eq!(values, values.clone());
+
+        let mut other = values.clone();
+        other.roots_before.root[3] = 7;
+        other.roots_after.root[0] = 0;
+        other.userdata = vec![1, 2, 3, 0];
+        assert_ne!(values, other);
+        assert_eq!(
+            values.diff(&other),
+            vec![
+                FieldDiff::RootsBefore {
+                    limb: 3,
+                    lhs: 1,
+                    rhs: 7
+                },
+                FieldDiff::RootsAfter {
+                    limb: 0,
+                    lhs: 2,
+                    rhs: 0
+                },
+                FieldDiff::UserdataLen { lhs: 3, rhs: 4 },
+            ]
+        );
+
+        other.userdata[1] = 9;
+        assert!(values.diff(&other).contains(&FieldDiff::Userdata {
+            index: 1,
+            lhs: 2,
+            rhs: 9
+        }));
+        assert_eq!(
+            FieldDiff::RootsBefore {
+                limb: 3,
+                lhs: 1,
+                rhs: 7
+            }
+            .to_string(),
+            "roots_before[3]: 0x00000001 != 0x00000007"
+        );
+    }
+
+    #[test]
+    fn test_all_proof_bytes_version_mismatch() {
+        let err =
+            AllProof::<F, C, D>::from_bytes(&[ALL_PROOF_FORMAT_VERSION + 1, 0, 0]).unwrap_err();
+        assert!(err.to_string().contains("format version"));
+        assert!(AllProof::<F, C, D>::from_bytes(&[]).is_err());
+    }
+
+    #[test]
+    fn test_proof_stats() -> anyhow::Result<()> {
+        let (all_stark, config, proof) = prove_exit_kernel::<F, C, D>()?;
+        let stats = proof.stats(&config);
+
+        let columns = [
+            ArithmeticStark::<F, D>::COLUMNS,
+            CpuStark::<F, D>::COLUMNS,
+            PoseidonStark::<F, D>::COLUMNS,
+            PoseidonSpongeStark::<F, D>::COLUMNS,
+            KeccakStark::<F, D>::COLUMNS,
+            KeccakSpongeStark::<F, D>::COLUMNS,
+            ShaExtendStark::<F, D>::COLUMNS,
+            ShaExtendSpongeStark::<F, D>::COLUMNS,
+            ShaCompressStark::<F, D>::COLUMNS,
+            ShaCompressSpongeStark::<F, D>::COLUMNS,
+            LogicStark::<F, D>::COLUMNS,
+            MemoryStark::<F, D>::COLUMNS,
+        ];
+        let lookup_helpers = all_stark.num_lookups_helper_columns(&config);
+        for table in Table::all() {
+            let i = table as usize;
+            let table_stats = stats.tables[i];
+            let proof = &proof.stark_proofs[i].proof;
+            assert_eq!(table_stats.num_trace_columns, columns[i], "{table:?}");
+            assert!(
+                table_stats.num_auxiliary_polys >= lookup_helpers[i] + proof.num_ctl_zs(),
+                "{table:?}"
+            );
+            assert_eq!(table_stats.degree_bits, proof.recover_degree_bits(&config));
+            assert_eq!(
+                table_stats.num_bytes as u64,
+                bincode::serialized_size(proof)?
+            );
+        }
+        assert_eq!(stats.total_trace_columns, columns.iter().sum::<usize>());
+        assert_eq!(
+            stats.total_bytes,
+            stats.tables.iter().map(|t| t.num_bytes).sum::<usize>()
+        );
+        Ok(())
+    }
+}
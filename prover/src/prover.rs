@@ -18,7 +18,7 @@ use plonky2::util::transpose;
 use plonky2_maybe_rayon::*;
 use plonky2_util::{log2_ceil, log2_strict};
 
-use crate::all_stark::{AllStark, Table, NUM_TABLES};
+use crate::all_stark::{AllStark, Table, ALL_TABLES_ACTIVE, NUM_TABLES};
 use crate::config::StarkConfig;
 use crate::constraint_consumer::ConstraintConsumer;
 use crate::cpu::kernel::assembler::Kernel;
@@ -30,16 +30,65 @@ use crate::evaluation_frame::StarkEvaluationFrame;
 use crate::generation::outputs::GenerationOutputs;
 use crate::generation::state::{AssumptionReceipts, AssumptionUsage};
 use crate::generation::{generate_traces, generate_traces_with_assumptions};
-use crate::get_challenges::observe_public_values;
+use crate::get_challenges::{observe_public_values, seeded_challenger};
 use crate::lookup::{lookup_helper_columns, Lookup, LookupCheckVars};
 use crate::proof::{AllProof, PublicValues, StarkOpeningSet, StarkProof, StarkProofWithMetadata};
 use crate::stark::Stark;
 use crate::vanishing_poly::eval_vanishing_poly;
-use std::{cell::RefCell, rc::Rc};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 #[cfg(any(feature = "test", test))]
 use crate::cross_table_lookup::testutils::check_ctls;
 
+/// A milestone of proving, reported by `AllRecursiveCircuits::prove_root_with_progress` along
+/// with the time it took, e.g. to drive a progress bar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProvePhase {
+    /// The traces of all tables were generated.
+    TraceGeneration { elapsed: Duration },
+    /// The STARK proof of `table` was generated.
+    Stark { table: Table, elapsed: Duration },
+    /// The STARK proof of `table` was shrunk by its recursive circuits.
+    Shrink { table: Table, elapsed: Duration },
+    /// The root proof was generated.
+    Root { elapsed: Duration },
+}
+
+/// The time spent in each phase of proving a root proof, see
+/// `AllRecursiveCircuits::prove_root_with_timings`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProveTimings {
+    pub trace_gen: Duration,
+    /// Indexed by `Table`.
+    pub per_table_stark: [Duration; NUM_TABLES],
+    /// Indexed by `Table`.
+    pub per_table_shrink: [Duration; NUM_TABLES],
+    pub root: Duration,
+}
+
+impl ProveTimings {
+    /// Adds the time `phase` took to the corresponding duration.
+    pub fn record(&mut self, phase: ProvePhase) {
+        match phase {
+            ProvePhase::TraceGeneration { elapsed } => self.trace_gen += elapsed,
+            ProvePhase::Stark { table, elapsed } => self.per_table_stark[table as usize] += elapsed,
+            ProvePhase::Shrink { table, elapsed } => {
+                self.per_table_shrink[table as usize] += elapsed
+            }
+            ProvePhase::Root { elapsed } => self.root += elapsed,
+        }
+    }
+
+    /// The sum of all durations.
+    pub fn total(&self) -> Duration {
+        self.trace_gen
+            + self.per_table_stark.iter().sum::<Duration>()
+            + self.per_table_shrink.iter().sum::<Duration>()
+            + self.root
+    }
+}
+
 /// Generate traces, then create all STARK proofs.
 pub fn prove<F, C, const D: usize>(
     all_stark: &AllStark<F, D>,
@@ -61,7 +110,7 @@ pub fn prove_with_assumptions<F, C, const D: usize>(
     config: &StarkConfig,
     timing: &mut TimingTree,
     assumptions: AssumptionReceipts<F, C, D>,
-) -> Result<(AllProof<F, C, D>, Rc<RefCell<AssumptionUsage<F, C, D>>>)>
+) -> Result<(AllProof<F, C, D>, Arc<Mutex<AssumptionUsage<F, C, D>>>)>
 where
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
@@ -89,7 +138,49 @@ where
         generate_traces::<F, C, D>(all_stark, kernel, config, timing)?
     );
 
-    let proof = prove_with_traces(all_stark, config, traces, public_values, timing)?;
+    let proof = prove_with_traces(
+        all_stark,
+        config,
+        traces,
+        public_values,
+        &ALL_TABLES_ACTIVE,
+        timing,
+    )?;
+    Ok((proof, outputs))
+}
+
+/// Like `prove_with_outputs`, but with all the Fiat-Shamir challenges drawn from a challenger
+/// which has absorbed `seed`, see `seeded_challenger`.
+///
+/// Only meant for tests and debugging, e.g. to compare proofs against golden files: the proof is
+/// only accepted by `verify_proof_seeded` with the same seed.
+pub fn prove_with_outputs_seeded<F, C, const D: usize>(
+    all_stark: &AllStark<F, D>,
+    kernel: &Kernel,
+    config: &StarkConfig,
+    timing: &mut TimingTree,
+    seed: [u64; 4],
+) -> Result<(AllProof<F, C, D>, GenerationOutputs)>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    let (traces, public_values, outputs) = timed!(
+        timing,
+        "generate all traces",
+        generate_traces::<F, C, D>(all_stark, kernel, config, timing)?
+    );
+
+    let proof = prove_with_traces_and_challenger(
+        all_stark,
+        config,
+        traces,
+        public_values,
+        &ALL_TABLES_ACTIVE,
+        seeded_challenger(seed),
+        timing,
+        &mut |_| {},
+    )?;
     Ok((proof, outputs))
 }
 
@@ -104,7 +195,7 @@ pub fn prove_with_output_and_assumptions<F, C, const D: usize>(
 ) -> Result<(
     AllProof<F, C, D>,
     GenerationOutputs,
-    Rc<RefCell<AssumptionUsage<F, C, D>>>,
+    Arc<Mutex<AssumptionUsage<F, C, D>>>,
 )>
 where
     F: RichField + Extendable<D>,
@@ -122,18 +213,82 @@ where
         )?
     );
 
-    let proof = prove_with_traces(all_stark, config, traces, public_values, timing)?;
+    let proof = prove_with_traces(
+        all_stark,
+        config,
+        traces,
+        public_values,
+        &ALL_TABLES_ACTIVE,
+        timing,
+    )?;
     Ok((proof, outputs, receipts))
 }
 
 /// Compute all STARK proofs.
+///
+/// Only `active_tables` are proven, the others get an empty proof (see
+/// `StarkProofWithMetadata::empty`) and must not take part in any cross-table lookup.
 pub(crate) fn prove_with_traces<F, C, const D: usize>(
     all_stark: &AllStark<F, D>,
     config: &StarkConfig,
     trace_poly_values: [Vec<PolynomialValues<F>>; NUM_TABLES],
     public_values: PublicValues,
+    active_tables: &[bool; NUM_TABLES],
     timing: &mut TimingTree,
 ) -> Result<AllProof<F, C, D>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    prove_with_traces_with_progress(
+        all_stark,
+        config,
+        trace_poly_values,
+        public_values,
+        active_tables,
+        timing,
+        &mut |_| {},
+    )
+}
+
+/// Same as `prove_with_traces`, reporting a `ProvePhase::Stark` to `progress` once each active
+/// table is proven.
+pub(crate) fn prove_with_traces_with_progress<F, C, const D: usize>(
+    all_stark: &AllStark<F, D>,
+    config: &StarkConfig,
+    trace_poly_values: [Vec<PolynomialValues<F>>; NUM_TABLES],
+    public_values: PublicValues,
+    active_tables: &[bool; NUM_TABLES],
+    timing: &mut TimingTree,
+    progress: &mut dyn FnMut(ProvePhase),
+) -> Result<AllProof<F, C, D>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    prove_with_traces_and_challenger(
+        all_stark,
+        config,
+        trace_poly_values,
+        public_values,
+        active_tables,
+        Challenger::new(),
+        timing,
+        progress,
+    )
+}
+
+/// Compute all STARK proofs, starting from `challenger` rather than from an empty one.
+fn prove_with_traces_and_challenger<F, C, const D: usize>(
+    all_stark: &AllStark<F, D>,
+    config: &StarkConfig,
+    trace_poly_values: [Vec<PolynomialValues<F>>; NUM_TABLES],
+    public_values: PublicValues,
+    active_tables: &[bool; NUM_TABLES],
+    mut challenger: Challenger<F, C::Hasher>,
+    timing: &mut TimingTree,
+    progress: &mut dyn FnMut(ProvePhase),
+) -> Result<AllProof<F, C, D>>
 where
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
@@ -179,13 +334,14 @@ where
         .iter()
         .map(|c| c.merkle_tree.cap.clone())
         .collect::<Vec<_>>();
-    let mut challenger = Challenger::<F, C::Hasher>::new();
-    for cap in &trace_caps {
-        challenger.observe_cap(cap);
+    for (cap, &active) in trace_caps.iter().zip_eq(active_tables) {
+        if active {
+            challenger.observe_cap(cap);
+        }
     }
 
     observe_public_values::<F, C, D>(&mut challenger, &public_values)
-        .map_err(|_| anyhow::Error::msg("Invalid conversion of public values."))?;
+        .map_err(|err| anyhow::anyhow!("Invalid conversion of public values: {err:?}"))?;
 
     let ctl_challenges = get_grand_product_challenge_set(&mut challenger, config.num_challenges);
     let ctl_data_per_table = timed!(
@@ -210,7 +366,9 @@ where
             ctl_data_per_table,
             &mut challenger,
             &ctl_challenges,
-            timing
+            active_tables,
+            timing,
+            progress
         )?
     );
 
@@ -239,202 +397,187 @@ fn prove_with_commitments<F, C, const D: usize>(
     ctl_data_per_table: [CtlData<F>; NUM_TABLES],
     challenger: &mut Challenger<F, C::Hasher>,
     ctl_challenges: &GrandProductChallengeSet<F>,
+    active_tables: &[bool; NUM_TABLES],
     timing: &mut TimingTree,
+    progress: &mut dyn FnMut(ProvePhase),
 ) -> Result<[StarkProofWithMetadata<F, C, D>; NUM_TABLES]>
 where
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
 {
-    let arithmetic_proof = timed!(
-        timing,
-        "prove Arithmetic STARK",
-        prove_single_table(
+    let mut stark_proofs = Vec::with_capacity(NUM_TABLES);
+    for table in Table::all() {
+        // Inactive tables aren't verified, so they aren't proven either.
+        if !active_tables[table as usize] {
+            stark_proofs.push(StarkProofWithMetadata::empty());
+            continue;
+        }
+        let start = Instant::now();
+        let proof = timed!(
+            timing,
+            &format!("prove {table:?} STARK"),
+            prove_table(
+                all_stark,
+                table,
+                config,
+                &trace_poly_values[table as usize],
+                &trace_commitments[table as usize],
+                &ctl_data_per_table[table as usize],
+                ctl_challenges,
+                challenger,
+                timing,
+            )?
+        );
+        progress(ProvePhase::Stark {
+            table,
+            elapsed: start.elapsed(),
+        });
+        stark_proofs.push(proof);
+    }
+
+    Ok(stark_proofs
+        .try_into()
+        .unwrap_or_else(|_| unreachable!("one proof per table")))
+}
+
+/// Prove `table` with the STARK of `all_stark` it is generated for.
+fn prove_table<F, C, const D: usize>(
+    all_stark: &AllStark<F, D>,
+    table: Table,
+    config: &StarkConfig,
+    trace_poly_values: &[PolynomialValues<F>],
+    trace_commitment: &PolynomialBatch<F, C, D>,
+    ctl_data: &CtlData<F>,
+    ctl_challenges: &GrandProductChallengeSet<F>,
+    challenger: &mut Challenger<F, C::Hasher>,
+    timing: &mut TimingTree,
+) -> Result<StarkProofWithMetadata<F, C, D>>
+where
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+{
+    match table {
+        Table::Arithmetic => prove_single_table(
             &all_stark.arithmetic_stark,
             config,
-            &trace_poly_values[Table::Arithmetic as usize],
-            &trace_commitments[Table::Arithmetic as usize],
-            &ctl_data_per_table[Table::Arithmetic as usize],
+            trace_poly_values,
+            trace_commitment,
+            ctl_data,
             ctl_challenges,
             challenger,
             timing,
-        )?
-    );
-    let cpu_proof = timed!(
-        timing,
-        "prove CPU STARK",
-        prove_single_table(
+        ),
+        Table::Cpu => prove_single_table(
             &all_stark.cpu_stark,
             config,
-            &trace_poly_values[Table::Cpu as usize],
-            &trace_commitments[Table::Cpu as usize],
-            &ctl_data_per_table[Table::Cpu as usize],
+            trace_poly_values,
+            trace_commitment,
+            ctl_data,
             ctl_challenges,
             challenger,
             timing,
-        )?
-    );
-
-    let poseidon_proof = timed!(
-        timing,
-        "prove Poseidon STARK",
-        prove_single_table(
+        ),
+        Table::Poseidon => prove_single_table(
             &all_stark.poseidon_stark,
             config,
-            &trace_poly_values[Table::Poseidon as usize],
-            &trace_commitments[Table::Poseidon as usize],
-            &ctl_data_per_table[Table::Poseidon as usize],
+            trace_poly_values,
+            trace_commitment,
+            ctl_data,
             ctl_challenges,
             challenger,
             timing,
-        )?
-    );
-    let poseidon_sponge_proof = timed!(
-        timing,
-        "prove Poseidon sponge STARK",
-        prove_single_table(
+        ),
+        Table::PoseidonSponge => prove_single_table(
             &all_stark.poseidon_sponge_stark,
             config,
-            &trace_poly_values[Table::PoseidonSponge as usize],
-            &trace_commitments[Table::PoseidonSponge as usize],
-            &ctl_data_per_table[Table::PoseidonSponge as usize],
+            trace_poly_values,
+            trace_commitment,
+            ctl_data,
             ctl_challenges,
             challenger,
             timing,
-        )?
-    );
-
-    let keccak_proof = timed!(
-        timing,
-        "prove Keccak STARK",
-        prove_single_table(
+        ),
+        Table::Keccak => prove_single_table(
             &all_stark.keccak_stark,
             config,
-            &trace_poly_values[Table::Keccak as usize],
-            &trace_commitments[Table::Keccak as usize],
-            &ctl_data_per_table[Table::Keccak as usize],
+            trace_poly_values,
+            trace_commitment,
+            ctl_data,
             ctl_challenges,
             challenger,
             timing,
-        )?
-    );
-    let keccak_sponge_proof = timed!(
-        timing,
-        "prove Keccak sponge STARK",
-        prove_single_table(
+        ),
+        Table::KeccakSponge => prove_single_table(
             &all_stark.keccak_sponge_stark,
             config,
-            &trace_poly_values[Table::KeccakSponge as usize],
-            &trace_commitments[Table::KeccakSponge as usize],
-            &ctl_data_per_table[Table::KeccakSponge as usize],
+            trace_poly_values,
+            trace_commitment,
+            ctl_data,
             ctl_challenges,
             challenger,
             timing,
-        )?
-    );
-
-    let sha_extend_proof = timed!(
-        timing,
-        "prove SHA Extend STARK",
-        prove_single_table(
+        ),
+        Table::ShaExtend => prove_single_table(
             &all_stark.sha_extend_stark,
             config,
-            &trace_poly_values[Table::ShaExtend as usize],
-            &trace_commitments[Table::ShaExtend as usize],
-            &ctl_data_per_table[Table::ShaExtend as usize],
+            trace_poly_values,
+            trace_commitment,
+            ctl_data,
             ctl_challenges,
             challenger,
             timing,
-        )?
-    );
-
-    let sha_extend_sponge_proof = timed!(
-        timing,
-        "prove SHA Extend sponge STARK",
-        prove_single_table(
+        ),
+        Table::ShaExtendSponge => prove_single_table(
             &all_stark.sha_extend_sponge_stark,
             config,
-            &trace_poly_values[Table::ShaExtendSponge as usize],
-            &trace_commitments[Table::ShaExtendSponge as usize],
-            &ctl_data_per_table[Table::ShaExtendSponge as usize],
+            trace_poly_values,
+            trace_commitment,
+            ctl_data,
             ctl_challenges,
             challenger,
             timing,
-        )?
-    );
-
-    let sha_compress_proof = timed!(
-        timing,
-        "prove SHA Compress STARK",
-        prove_single_table(
+        ),
+        Table::ShaCompress => prove_single_table(
             &all_stark.sha_compress_stark,
             config,
-            &trace_poly_values[Table::ShaCompress as usize],
-            &trace_commitments[Table::ShaCompress as usize],
-            &ctl_data_per_table[Table::ShaCompress as usize],
+            trace_poly_values,
+            trace_commitment,
+            ctl_data,
             ctl_challenges,
             challenger,
             timing,
-        )?
-    );
-
-    let sha_compress_sponge_proof = timed!(
-        timing,
-        "prove SHA Compress sponge STARK",
-        prove_single_table(
+        ),
+        Table::ShaCompressSponge => prove_single_table(
             &all_stark.sha_compress_sponge_stark,
             config,
-            &trace_poly_values[Table::ShaCompressSponge as usize],
-            &trace_commitments[Table::ShaCompressSponge as usize],
-            &ctl_data_per_table[Table::ShaCompressSponge as usize],
+            trace_poly_values,
+            trace_commitment,
+            ctl_data,
             ctl_challenges,
             challenger,
             timing,
-        )?
-    );
-
-    let logic_proof = timed!(
-        timing,
-        "prove Logic STARK",
-        prove_single_table(
+        ),
+        Table::Logic => prove_single_table(
             &all_stark.logic_stark,
             config,
-            &trace_poly_values[Table::Logic as usize],
-            &trace_commitments[Table::Logic as usize],
-            &ctl_data_per_table[Table::Logic as usize],
+            trace_poly_values,
+            trace_commitment,
+            ctl_data,
             ctl_challenges,
             challenger,
             timing,
-        )?
-    );
-    let memory_proof = timed!(
-        timing,
-        "prove Memory STARK",
-        prove_single_table(
+        ),
+        Table::Memory => prove_single_table(
             &all_stark.memory_stark,
             config,
-            &trace_poly_values[Table::Memory as usize],
-            &trace_commitments[Table::Memory as usize],
-            &ctl_data_per_table[Table::Memory as usize],
+            trace_poly_values,
+            trace_commitment,
+            ctl_data,
             ctl_challenges,
             challenger,
             timing,
-        )?
-    );
-
-    Ok([
-        arithmetic_proof,
-        cpu_proof,
-        poseidon_proof,
-        poseidon_sponge_proof,
-        keccak_proof,
-        keccak_sponge_proof,
-        sha_extend_proof,
-        sha_extend_sponge_proof,
-        sha_compress_proof,
-        sha_compress_sponge_proof,
-        logic_proof,
-        memory_proof,
-    ])
+        ),
+    }
 }
 
 /// Compute proof for a single STARK table.
@@ -908,3 +1051,99 @@ fn check_constraints<'a, F, C, S, const D: usize>(
         );
     }
 }
+
+#[cfg(any(feature = "test", test))]
+pub mod testutils {
+    use super::*;
+    use crate::cross_table_lookup::GrandProductChallenge;
+
+    /// `addiu $v0, $zero, 4246; syscall`: a program exiting right away, the smallest one that can
+    /// be proven.
+    pub fn exit_kernel() -> Result<Kernel> {
+        Kernel::from_asm(".word 0x24021096, 0x0000000c", 0x0040_0000)
+    }
+
+    /// Proves `exit_kernel` with all tables and `StarkConfig::standard_fast_config`, which are
+    /// returned along with the proof.
+    pub fn prove_exit_kernel<F, C, const D: usize>(
+    ) -> Result<(AllStark<F, D>, StarkConfig, AllProof<F, C, D>)>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+    {
+        let all_stark = AllStark::default();
+        let config = StarkConfig::standard_fast_config();
+        let mut timing = TimingTree::new("prove", log::Level::Debug);
+        let proof = prove(&all_stark, &exit_kernel()?, &config, &mut timing)?;
+        Ok((all_stark, config, proof))
+    }
+
+    /// Proves `kernel` with `all_stark` and `StarkConfig::standard_fast_config`, and verifies the
+    /// proof, which is returned for further checks.
+    pub fn prove_and_verify<F, C, const D: usize>(
+        all_stark: &AllStark<F, D>,
+        kernel: &Kernel,
+    ) -> Result<AllProof<F, C, D>>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+    {
+        let config = StarkConfig::standard_fast_config();
+        let mut timing = TimingTree::new("prove", log::Level::Debug);
+        let proof = prove(all_stark, kernel, &config, &mut timing)?;
+        crate::verifier::verify_proof(all_stark, proof.clone(), &config)?;
+        Ok(proof)
+    }
+
+    /// Proves a single STARK table on its own, without any cross-table lookup, as in the
+    /// per-table benchmarks. The CTL data is `CtlData::fake` and the challenger starts empty.
+    pub fn prove_single_table_standalone<F, C, S, const D: usize>(
+        stark: &S,
+        config: &StarkConfig,
+        trace_poly_values: &[PolynomialValues<F>],
+        timing: &mut TimingTree,
+    ) -> Result<StarkProofWithMetadata<F, C, D>>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        S: Stark<F, D>,
+    {
+        // TODO: Cloning this isn't great; consider having `from_values` accept a reference,
+        // or having `compute_permutation_z_polys` read trace values from the `PolynomialBatch`.
+        let cloned_trace_poly_values = timed!(timing, "clone", trace_poly_values.to_vec());
+
+        let trace_commitment = timed!(
+            timing,
+            "compute trace commitment",
+            PolynomialBatch::<F, C, D>::from_values(
+                cloned_trace_poly_values,
+                config.fri_config.rate_bits,
+                false,
+                config.fri_config.cap_height,
+                timing,
+                None,
+            )
+        );
+        let degree = 1 << trace_commitment.degree_log;
+
+        let ctl_challenges = GrandProductChallengeSet {
+            challenges: vec![
+                GrandProductChallenge {
+                    beta: F::ZERO,
+                    gamma: F::ZERO,
+                };
+                config.num_challenges
+            ],
+        };
+        prove_single_table(
+            stark,
+            config,
+            trace_poly_values,
+            &trace_commitment,
+            &CtlData::fake(degree, config.num_challenges),
+            &ctl_challenges,
+            &mut Challenger::new(),
+            timing,
+        )
+    }
+}
@@ -104,9 +104,9 @@ where
     C: GenericConfig<D, F = F>,
     C::Hasher: AlgebraicHasher<F>,
 {
-    pub fn to_buffer(
+    pub fn to_buffer<W: Write>(
         &self,
-        buffer: &mut Vec<u8>,
+        buffer: &mut W,
         gate_serializer: &dyn GateSerializer<F, D>,
         generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
     ) -> IoResult<()> {
@@ -458,10 +458,12 @@ pub(crate) fn add_virtual_public_values<F: RichField + Extendable<D>, const D: u
     let roots_before = add_virtual_trie_roots(builder);
     let roots_after = add_virtual_trie_roots(builder);
     let userdata = builder.add_virtual_public_input_arr();
+    let userdata_len = builder.add_virtual_public_input();
     PublicValuesTarget {
         roots_before,
         roots_after,
         userdata,
+        userdata_len,
     }
 }
 
@@ -578,18 +580,22 @@ where
         &public_values_target.roots_after,
         &public_values.roots_after,
     );
-    // setup userdata
-    for (i, limb) in public_values.userdata.iter().enumerate() {
+    // setup userdata, zero-padded
+    let userdata = public_values
+        .padded_userdata()
+        .ok_or(ProgramError::IntegerTooLarge)?;
+    for (i, limb) in userdata.into_iter().enumerate() {
         log::trace!(
             "set userdata target: {:?} => {:?}",
             public_values_target.userdata[i],
-            F::from_canonical_u8(*limb),
-        );
-        witness.set_target(
-            public_values_target.userdata[i],
-            F::from_canonical_u8(*limb),
+            F::from_canonical_u8(limb),
         );
+        witness.set_target(public_values_target.userdata[i], F::from_canonical_u8(limb));
     }
+    witness.set_target(
+        public_values_target.userdata_len,
+        F::from_canonical_usize(public_values.userdata.len()),
+    );
     Ok(())
 }
 
@@ -0,0 +1,101 @@
+use crate::sha512_extend_sponge::sha512_extend_sponge_stark::NUM_ROUNDS;
+use crate::util::{indices_arr, transmute_no_compile_time_size_checks};
+use std::borrow::{Borrow, BorrowMut};
+use std::mem::transmute;
+
+pub(crate) const NUM_EXTEND_INPUT: usize = 4;
+pub(crate) const WORD_BITS: usize = 64;
+pub(crate) const SHA512_EXTEND_SPONGE_READ_BYTES: usize = NUM_EXTEND_INPUT * WORD_BITS / 8;
+pub(crate) struct Sha512ExtendSpongeColumnsView<T: Copy> {
+    /// round
+    pub round: [T; NUM_ROUNDS],
+
+    /// Input in le bits order
+    pub w_i_minus_15: [T; WORD_BITS],
+    pub w_i_minus_2: [T; WORD_BITS],
+    pub w_i_minus_16: [T; WORD_BITS],
+    pub w_i_minus_7: [T; WORD_BITS],
+
+    /// Intermediate values in le bits order
+    pub s_0: [T; WORD_BITS],
+    pub s_1: [T; WORD_BITS],
+
+    /// Carries of the additions of the low and high 32-bit halves, as two bits each.
+    pub carry_lo: [T; 2],
+    pub carry_hi: [T; 2],
+
+    /// Output in le bits order
+    pub w_i: [T; WORD_BITS],
+
+    /// Input address
+    pub input_virt: [T; NUM_EXTEND_INPUT],
+
+    /// Output address
+    pub output_virt: T,
+
+    pub context: T,
+    pub segment: T,
+
+    /// The timestamp at which inputs should be read from memory.
+    pub timestamp: T,
+}
+
+pub const NUM_SHA512_EXTEND_SPONGE_COLUMNS: usize = size_of::<Sha512ExtendSpongeColumnsView<u8>>();
+
+impl<T: Copy> From<[T; NUM_SHA512_EXTEND_SPONGE_COLUMNS]> for Sha512ExtendSpongeColumnsView<T> {
+    fn from(value: [T; NUM_SHA512_EXTEND_SPONGE_COLUMNS]) -> Self {
+        unsafe { transmute_no_compile_time_size_checks(value) }
+    }
+}
+
+impl<T: Copy> From<Sha512ExtendSpongeColumnsView<T>> for [T; NUM_SHA512_EXTEND_SPONGE_COLUMNS] {
+    fn from(value: Sha512ExtendSpongeColumnsView<T>) -> Self {
+        unsafe { transmute_no_compile_time_size_checks(value) }
+    }
+}
+
+impl<T: Copy> Borrow<Sha512ExtendSpongeColumnsView<T>> for [T; NUM_SHA512_EXTEND_SPONGE_COLUMNS] {
+    fn borrow(&self) -> &Sha512ExtendSpongeColumnsView<T> {
+        unsafe { transmute(self) }
+    }
+}
+
+impl<T: Copy> BorrowMut<Sha512ExtendSpongeColumnsView<T>>
+    for [T; NUM_SHA512_EXTEND_SPONGE_COLUMNS]
+{
+    fn borrow_mut(&mut self) -> &mut Sha512ExtendSpongeColumnsView<T> {
+        unsafe { transmute(self) }
+    }
+}
+
+impl<T: Copy> Borrow<[T; NUM_SHA512_EXTEND_SPONGE_COLUMNS]> for Sha512ExtendSpongeColumnsView<T> {
+    fn borrow(&self) -> &[T; NUM_SHA512_EXTEND_SPONGE_COLUMNS] {
+        unsafe { transmute(self) }
+    }
+}
+
+impl<T: Copy> BorrowMut<[T; NUM_SHA512_EXTEND_SPONGE_COLUMNS]>
+    for Sha512ExtendSpongeColumnsView<T>
+{
+    fn borrow_mut(&mut self) -> &mut [T; NUM_SHA512_EXTEND_SPONGE_COLUMNS] {
+        unsafe { transmute(self) }
+    }
+}
+
+impl<T: Copy + Default> Default for Sha512ExtendSpongeColumnsView<T> {
+    fn default() -> Self {
+        [T::default(); NUM_SHA512_EXTEND_SPONGE_COLUMNS].into()
+    }
+}
+
+const fn make_col_map() -> Sha512ExtendSpongeColumnsView<usize> {
+    let indices_arr = indices_arr::<NUM_SHA512_EXTEND_SPONGE_COLUMNS>();
+    unsafe {
+        transmute::<[usize; NUM_SHA512_EXTEND_SPONGE_COLUMNS], Sha512ExtendSpongeColumnsView<usize>>(
+            indices_arr,
+        )
+    }
+}
+
+pub(crate) const SHA512_EXTEND_SPONGE_COL_MAP: Sha512ExtendSpongeColumnsView<usize> =
+    make_col_map();
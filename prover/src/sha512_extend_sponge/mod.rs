@@ -0,0 +1,2 @@
+pub mod columns;
+pub mod sha512_extend_sponge_stark;
@@ -0,0 +1,680 @@
+use crate::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
+use crate::cpu::membus::NUM_CHANNELS;
+use crate::evaluation_frame::{StarkEvaluationFrame, StarkFrame};
+use crate::keccak::logic::{xor3_gen, xor3_gen_circuit};
+use crate::sha512_extend_sponge::columns::{
+    Sha512ExtendSpongeColumnsView, NUM_EXTEND_INPUT, NUM_SHA512_EXTEND_SPONGE_COLUMNS, WORD_BITS,
+};
+use crate::sha_extend::logic::get_input_range_8;
+use crate::sha_extend_sponge::logic::{
+    diff_address_ext_circuit_constraint, round_increment_ext_circuit_constraint,
+};
+use crate::stark::Stark;
+use crate::util::trace_rows_to_poly_values;
+use crate::witness::memory::MemoryAddress;
+use itertools::Itertools;
+use plonky2::field::extension::{Extendable, FieldExtension};
+use plonky2::field::packed::PackedField;
+use plonky2::field::polynomial::PolynomialValues;
+use plonky2::field::types::Field;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::ext_target::ExtensionTarget;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use std::borrow::Borrow;
+use std::marker::PhantomData;
+
+pub const NUM_ROUNDS: usize = 64;
+
+/// Size of a word in bytes, i.e. the stride between consecutive words of the schedule.
+const WORD_BYTES: usize = WORD_BITS / 8;
+
+/// Right rotations and right shift of `s0 = sigma0(w[i-15])`.
+const S_0_ROTATIONS: [usize; 2] = [1, 8];
+const S_0_SHIFT: usize = 7;
+/// Right rotations and right shift of `s1 = sigma1(w[i-2])`.
+const S_1_ROTATIONS: [usize; 2] = [19, 61];
+const S_1_SHIFT: usize = 6;
+
+/// Computes `rotr(x, r0) ^ rotr(x, r1) ^ (x >> shift)`.
+fn sigma(x: u64, rotations: [usize; 2], shift: usize) -> u64 {
+    x.rotate_right(rotations[0] as u32) ^ x.rotate_right(rotations[1] as u32) ^ (x >> shift)
+}
+
+/// Computes the arithmetic generalization of `sigma` on the little-endian bits of `x`.
+fn sigma_packed<P: PackedField>(
+    x: [P; WORD_BITS],
+    rotations: [usize; 2],
+    shift: usize,
+) -> [P; WORD_BITS] {
+    core::array::from_fn(|i| {
+        let shifted = if i + shift < WORD_BITS {
+            x[i + shift]
+        } else {
+            P::ZEROS
+        };
+        xor3_gen(
+            x[(i + rotations[0]) % WORD_BITS],
+            x[(i + rotations[1]) % WORD_BITS],
+            shifted,
+        )
+    })
+}
+
+fn sigma_ext_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    x: [ExtensionTarget<D>; WORD_BITS],
+    rotations: [usize; 2],
+    shift: usize,
+) -> [ExtensionTarget<D>; WORD_BITS] {
+    let zero = builder.zero_extension();
+    core::array::from_fn(|i| {
+        let shifted = if i + shift < WORD_BITS {
+            x[i + shift]
+        } else {
+            zero
+        };
+        xor3_gen_circuit(
+            builder,
+            x[(i + rotations[0]) % WORD_BITS],
+            x[(i + rotations[1]) % WORD_BITS],
+            shifted,
+        )
+    })
+}
+
+/// The value of 32 little-endian bits.
+fn limb_packed<P: PackedField>(bits: &[P]) -> P {
+    bits.iter()
+        .enumerate()
+        .map(|(i, &bit)| bit * P::Scalar::from_canonical_u64(1 << i))
+        .sum()
+}
+
+fn limb_ext_circuit<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    bits: &[ExtensionTarget<D>],
+) -> ExtensionTarget<D> {
+    let zero = builder.zero_extension();
+    bits.iter().enumerate().fold(zero, |acc, (i, &bit)| {
+        builder.mul_const_add_extension(F::from_canonical_u64(1 << i), bit, acc)
+    })
+}
+
+fn u64_to_le_bits<F: Field>(x: u64) -> [F; WORD_BITS] {
+    core::array::from_fn(|i| F::from_bool((x >> i) & 1 == 1))
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Sha512ExtendSpongeOp {
+    /// The base address at which inputs are read
+    pub(crate) base_address: Vec<MemoryAddress>,
+
+    /// The timestamp at which inputs are read
+    pub(crate) timestamp: usize,
+
+    /// The input that was read.
+    /// Values: w_i_minus_15, w_i_minus_2, w_i_minus_16, w_i_minus_7 in little-endian order.
+    pub(crate) input: Vec<u8>,
+
+    /// The index of round
+    pub(crate) i: usize,
+
+    /// The base address at which the output is written.
+    pub(crate) output_address: MemoryAddress,
+}
+
+/// The SHA-512 counterpart of `ShaExtendSpongeStark`, extending a message schedule of 64-bit
+/// words over 64 rounds. Unlike the SHA-256 sponge, which looks its rounds up in the ShaExtend
+/// table, this table constrains `w_i` itself, on the bits of its inputs.
+#[derive(Copy, Clone, Default)]
+pub struct Sha512ExtendSpongeStark<F, const D: usize> {
+    f: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Sha512ExtendSpongeStark<F, D> {
+    pub(crate) fn generate_trace(
+        &self,
+        operations: Vec<Sha512ExtendSpongeOp>,
+        min_rows: usize,
+    ) -> Vec<PolynomialValues<F>> {
+        // Generate the witness row-wise.
+        let trace_rows = self.generate_trace_rows(operations, min_rows);
+
+        trace_rows_to_poly_values(trace_rows)
+    }
+
+    fn generate_trace_rows(
+        &self,
+        operations: Vec<Sha512ExtendSpongeOp>,
+        min_rows: usize,
+    ) -> Vec<[F; NUM_SHA512_EXTEND_SPONGE_COLUMNS]> {
+        let base_len = operations.len();
+        let mut rows = Vec::with_capacity(base_len.max(min_rows).next_power_of_two());
+        for op in operations {
+            rows.push(self.generate_rows_for_op(op).into());
+        }
+
+        let padded_rows = rows.len().max(min_rows).next_power_of_two();
+        for _ in rows.len()..padded_rows {
+            rows.push(Sha512ExtendSpongeColumnsView::default().into());
+        }
+
+        rows
+    }
+
+    fn generate_rows_for_op(&self, op: Sha512ExtendSpongeOp) -> Sha512ExtendSpongeColumnsView<F> {
+        let mut row = Sha512ExtendSpongeColumnsView::default();
+        row.timestamp = F::from_canonical_usize(op.timestamp);
+        row.round = [F::ZERO; NUM_ROUNDS];
+        row.round[op.i] = F::ONE;
+
+        row.context = F::from_canonical_usize(op.base_address[0].context);
+        row.segment = F::from_canonical_u32(op.base_address[0].segment);
+        let virt = (0..op.input.len() / WORD_BYTES)
+            .map(|i| op.base_address[i].virt)
+            .collect_vec();
+        let virt: [usize; NUM_EXTEND_INPUT] = virt.try_into().unwrap();
+        row.input_virt = virt.map(F::from_canonical_usize);
+        row.output_virt = F::from_canonical_usize(op.output_address.virt);
+
+        let [w_i_minus_15, w_i_minus_2, w_i_minus_16, w_i_minus_7] = core::array::from_fn(|i| {
+            u64::from_le_bytes(op.input[get_input_range_8(i)].try_into().unwrap())
+        });
+        row.w_i_minus_15 = u64_to_le_bits(w_i_minus_15);
+        row.w_i_minus_2 = u64_to_le_bits(w_i_minus_2);
+        row.w_i_minus_16 = u64_to_le_bits(w_i_minus_16);
+        row.w_i_minus_7 = u64_to_le_bits(w_i_minus_7);
+
+        let s_0 = sigma(w_i_minus_15, S_0_ROTATIONS, S_0_SHIFT);
+        let s_1 = sigma(w_i_minus_2, S_1_ROTATIONS, S_1_SHIFT);
+        row.s_0 = u64_to_le_bits(s_0);
+        row.s_1 = u64_to_le_bits(s_1);
+
+        // w_i = s_1 + w_i_minus_16 + s_0 + w_i_minus_7, added by 32-bit halves.
+        let lo = |x: u64| x & 0xffff_ffff;
+        let hi = |x: u64| x >> 32;
+        let sum_lo = lo(s_1) + lo(w_i_minus_16) + lo(s_0) + lo(w_i_minus_7);
+        let carry_lo = sum_lo >> 32;
+        let sum_hi = hi(s_1) + hi(w_i_minus_16) + hi(s_0) + hi(w_i_minus_7) + carry_lo;
+        let carry_hi = sum_hi >> 32;
+        row.carry_lo = [carry_lo & 1, carry_lo >> 1].map(F::from_canonical_u64);
+        row.carry_hi = [carry_hi & 1, carry_hi >> 1].map(F::from_canonical_u64);
+
+        let w_i = lo(sum_lo) | (lo(sum_hi) << 32);
+        debug_assert_eq!(w_i, self.compute_w_i(&op.input));
+        row.w_i = u64_to_le_bits(w_i);
+
+        row
+    }
+
+    fn compute_w_i(&self, input: &[u8]) -> u64 {
+        let w_i_minus_15 = u64::from_le_bytes(input[get_input_range_8(0)].try_into().unwrap());
+        let w_i_minus_2 = u64::from_le_bytes(input[get_input_range_8(1)].try_into().unwrap());
+        let w_i_minus_16 = u64::from_le_bytes(input[get_input_range_8(2)].try_into().unwrap());
+        let w_i_minus_7 = u64::from_le_bytes(input[get_input_range_8(3)].try_into().unwrap());
+        let s0 = sigma(w_i_minus_15, S_0_ROTATIONS, S_0_SHIFT);
+        let s1 = sigma(w_i_minus_2, S_1_ROTATIONS, S_1_SHIFT);
+        s1.wrapping_add(w_i_minus_16)
+            .wrapping_add(s0)
+            .wrapping_add(w_i_minus_7)
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for Sha512ExtendSpongeStark<F, D> {
+    type EvaluationFrame<FE, P, const D2: usize>
+        = StarkFrame<P, NUM_SHA512_EXTEND_SPONGE_COLUMNS>
+    where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>;
+
+    type EvaluationFrameTarget = StarkFrame<ExtensionTarget<D>, NUM_SHA512_EXTEND_SPONGE_COLUMNS>;
+
+    fn eval_packed_generic<FE, P, const D2: usize>(
+        &self,
+        vars: &Self::EvaluationFrame<FE, P, D2>,
+        yield_constr: &mut ConstraintConsumer<P>,
+    ) where
+        FE: FieldExtension<D2, BaseField = F>,
+        P: PackedField<Scalar = FE>,
+    {
+        let local_values: &[P; NUM_SHA512_EXTEND_SPONGE_COLUMNS] =
+            vars.get_local_values().try_into().unwrap();
+        let local_values: &Sha512ExtendSpongeColumnsView<P> = local_values.borrow();
+        let next_values: &[P; NUM_SHA512_EXTEND_SPONGE_COLUMNS] =
+            vars.get_next_values().try_into().unwrap();
+        let next_values: &Sha512ExtendSpongeColumnsView<P> = next_values.borrow();
+
+        // check the round
+        for i in 0..NUM_ROUNDS {
+            yield_constr.constraint(local_values.round[i] * (local_values.round[i] - P::ONES));
+        }
+
+        // check the bits
+        for bits in [
+            local_values.w_i_minus_15,
+            local_values.w_i_minus_2,
+            local_values.w_i_minus_16,
+            local_values.w_i_minus_7,
+            local_values.w_i,
+        ] {
+            for bit in bits {
+                yield_constr.constraint(bit * (bit - P::ONES));
+            }
+        }
+        for bit in local_values
+            .carry_lo
+            .into_iter()
+            .chain(local_values.carry_hi)
+        {
+            yield_constr.constraint(bit * (bit - P::ONES));
+        }
+
+        // check s_0 and s_1. Padding rows are all zeros, which satisfies these constraints.
+        let s_0 = sigma_packed(local_values.w_i_minus_15, S_0_ROTATIONS, S_0_SHIFT);
+        let s_1 = sigma_packed(local_values.w_i_minus_2, S_1_ROTATIONS, S_1_SHIFT);
+        for i in 0..WORD_BITS {
+            yield_constr.constraint(local_values.s_0[i] - s_0[i]);
+            yield_constr.constraint(local_values.s_1[i] - s_1[i]);
+        }
+
+        // check the wrapping add: w_i = s_1 + w_i_minus_16 + s_0 + w_i_minus_7,
+        // by 32-bit halves.
+        let two_pow_32 = FE::from_canonical_u64(1 << 32);
+        let carry_lo = local_values.carry_lo[0] + local_values.carry_lo[1].doubles();
+        let carry_hi = local_values.carry_hi[0] + local_values.carry_hi[1].doubles();
+        let summands = [
+            &local_values.s_1,
+            &local_values.w_i_minus_16,
+            &local_values.s_0,
+            &local_values.w_i_minus_7,
+        ];
+        let sum_lo = summands
+            .iter()
+            .map(|bits| limb_packed(&bits[..32]))
+            .sum::<P>();
+        let sum_hi = summands
+            .iter()
+            .map(|bits| limb_packed(&bits[32..]))
+            .sum::<P>();
+        yield_constr
+            .constraint(sum_lo - limb_packed(&local_values.w_i[..32]) - carry_lo * two_pow_32);
+        yield_constr.constraint(
+            sum_hi + carry_lo - limb_packed(&local_values.w_i[32..]) - carry_hi * two_pow_32,
+        );
+
+        // check the filter
+        let is_final = local_values.round[NUM_ROUNDS - 1];
+        yield_constr.constraint(is_final * (is_final - P::ONES));
+        let not_final = P::ONES - is_final;
+
+        let sum_round_flags = (0..NUM_ROUNDS).map(|i| local_values.round[i]).sum::<P>();
+
+        // If this is not the final step or a padding row,
+        // the timestamp must be increased by 2 * NUM_CHANNELS.
+        yield_constr.constraint(
+            sum_round_flags
+                * not_final
+                * (next_values.timestamp
+                    - local_values.timestamp
+                    - FE::from_canonical_usize(2 * NUM_CHANNELS)),
+        );
+
+        // If this is not the final step or a padding row,
+        // round index should be increased by one
+
+        let local_round_index = (0..NUM_ROUNDS)
+            .map(|i| local_values.round[i] * FE::from_canonical_u32(i as u32))
+            .sum::<P>();
+        let next_round_index = (0..NUM_ROUNDS)
+            .map(|i| next_values.round[i] * FE::from_canonical_u32(i as u32))
+            .sum::<P>();
+        yield_constr.constraint(
+            sum_round_flags * not_final * (next_round_index - local_round_index - P::ONES),
+        );
+
+        // If this is not the final step or a padding row,
+        // input and output addresses should be increased by 8 each
+        (0..NUM_EXTEND_INPUT).for_each(|i| {
+            yield_constr.constraint(
+                sum_round_flags
+                    * not_final
+                    * (next_values.input_virt[i]
+                        - local_values.input_virt[i]
+                        - FE::from_canonical_usize(WORD_BYTES)),
+            );
+        });
+        yield_constr.constraint(
+            sum_round_flags
+                * not_final
+                * (next_values.output_virt
+                    - local_values.output_virt
+                    - FE::from_canonical_usize(WORD_BYTES)),
+        );
+
+        // If it's not the padding row, check the virtual addresses
+        // The list of input addresses are: w[i-15], w[i-2], w[i-16], w[i-7]
+
+        // add_w[i-15] = add_w[i-16] + 8
+        yield_constr.constraint(
+            sum_round_flags
+                * (local_values.input_virt[0]
+                    - local_values.input_virt[2]
+                    - FE::from_canonical_usize(WORD_BYTES)),
+        );
+        // add_w[i-2] = add_w[i-16] + 112
+        yield_constr.constraint(
+            sum_round_flags
+                * (local_values.input_virt[1]
+                    - local_values.input_virt[2]
+                    - FE::from_canonical_usize(14 * WORD_BYTES)),
+        );
+        // add_w[i-7] = add_w[i-16] + 72
+        yield_constr.constraint(
+            sum_round_flags
+                * (local_values.input_virt[3]
+                    - local_values.input_virt[2]
+                    - FE::from_canonical_usize(9 * WORD_BYTES)),
+        );
+        // add_w[i] = add_w[i-16] + 128
+        yield_constr.constraint(
+            sum_round_flags
+                * (local_values.output_virt
+                    - local_values.input_virt[2]
+                    - FE::from_canonical_usize(16 * WORD_BYTES)),
+        );
+    }
+
+    fn eval_ext_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: &Self::EvaluationFrameTarget,
+        yield_constr: &mut RecursiveConstraintConsumer<F, D>,
+    ) {
+        let local_values: &[ExtensionTarget<D>; NUM_SHA512_EXTEND_SPONGE_COLUMNS] =
+            vars.get_local_values().try_into().unwrap();
+        let local_values: &Sha512ExtendSpongeColumnsView<ExtensionTarget<D>> =
+            local_values.borrow();
+        let next_values: &[ExtensionTarget<D>; NUM_SHA512_EXTEND_SPONGE_COLUMNS] =
+            vars.get_next_values().try_into().unwrap();
+        let next_values: &Sha512ExtendSpongeColumnsView<ExtensionTarget<D>> = next_values.borrow();
+
+        let one_ext = builder.one_extension();
+        let word_bytes_ext =
+            builder.constant_extension(F::Extension::from_canonical_usize(WORD_BYTES));
+        let num_channel =
+            builder.constant_extension(F::Extension::from_canonical_usize(2 * NUM_CHANNELS));
+
+        // check the round
+        for i in 0..NUM_ROUNDS {
+            let constraint = builder.mul_sub_extension(
+                local_values.round[i],
+                local_values.round[i],
+                local_values.round[i],
+            );
+            yield_constr.constraint(builder, constraint);
+        }
+
+        // check the bits
+        for bits in [
+            local_values.w_i_minus_15,
+            local_values.w_i_minus_2,
+            local_values.w_i_minus_16,
+            local_values.w_i_minus_7,
+            local_values.w_i,
+        ] {
+            for bit in bits {
+                let constraint = builder.mul_sub_extension(bit, bit, bit);
+                yield_constr.constraint(builder, constraint);
+            }
+        }
+        for bit in local_values
+            .carry_lo
+            .into_iter()
+            .chain(local_values.carry_hi)
+        {
+            let constraint = builder.mul_sub_extension(bit, bit, bit);
+            yield_constr.constraint(builder, constraint);
+        }
+
+        // check s_0 and s_1
+        let s_0 = sigma_ext_circuit(builder, local_values.w_i_minus_15, S_0_ROTATIONS, S_0_SHIFT);
+        let s_1 = sigma_ext_circuit(builder, local_values.w_i_minus_2, S_1_ROTATIONS, S_1_SHIFT);
+        for i in 0..WORD_BITS {
+            let constraint = builder.sub_extension(local_values.s_0[i], s_0[i]);
+            yield_constr.constraint(builder, constraint);
+            let constraint = builder.sub_extension(local_values.s_1[i], s_1[i]);
+            yield_constr.constraint(builder, constraint);
+        }
+
+        // check the wrapping add: w_i = s_1 + w_i_minus_16 + s_0 + w_i_minus_7,
+        // by 32-bit halves.
+        let two_pow_32 = F::from_canonical_u64(1 << 32);
+        let two = F::TWO;
+        let carry_lo = builder.mul_const_add_extension(
+            two,
+            local_values.carry_lo[1],
+            local_values.carry_lo[0],
+        );
+        let carry_hi = builder.mul_const_add_extension(
+            two,
+            local_values.carry_hi[1],
+            local_values.carry_hi[0],
+        );
+        let summands = [
+            &local_values.s_1,
+            &local_values.w_i_minus_16,
+            &local_values.s_0,
+            &local_values.w_i_minus_7,
+        ];
+        let sum_lo = summands
+            .iter()
+            .map(|bits| limb_ext_circuit(builder, &bits[..32]))
+            .collect_vec();
+        let sum_lo = builder.add_many_extension(sum_lo);
+        let sum_hi = summands
+            .iter()
+            .map(|bits| limb_ext_circuit(builder, &bits[32..]))
+            .collect_vec();
+        let sum_hi = builder.add_many_extension(sum_hi);
+
+        let w_i_lo = limb_ext_circuit(builder, &local_values.w_i[..32]);
+        let w_i_lo = builder.mul_const_add_extension(two_pow_32, carry_lo, w_i_lo);
+        let constraint = builder.sub_extension(sum_lo, w_i_lo);
+        yield_constr.constraint(builder, constraint);
+
+        let w_i_hi = limb_ext_circuit(builder, &local_values.w_i[32..]);
+        let w_i_hi = builder.mul_const_add_extension(two_pow_32, carry_hi, w_i_hi);
+        let sum_hi = builder.add_extension(sum_hi, carry_lo);
+        let constraint = builder.sub_extension(sum_hi, w_i_hi);
+        yield_constr.constraint(builder, constraint);
+
+        // check the filter
+        let is_final = local_values.round[NUM_ROUNDS - 1];
+        let constraint = builder.mul_sub_extension(is_final, is_final, is_final);
+        yield_constr.constraint(builder, constraint);
+        let not_final = builder.sub_extension(one_ext, is_final);
+
+        let sum_round_flags =
+            builder.add_many_extension((0..NUM_ROUNDS).map(|i| local_values.round[i]));
+
+        // If this is not the final step or a padding row,
+        // the timestamp must be increased by 2 * NUM_CHANNELS.
+        let diff = builder.sub_extension(next_values.timestamp, local_values.timestamp);
+        let diff = builder.sub_extension(diff, num_channel);
+        let constraint = builder.mul_many_extension([sum_round_flags, not_final, diff]);
+        yield_constr.constraint(builder, constraint);
+
+        // If this is not the final step or a padding row,
+        // round index should be increased by one
+
+        let round_increment =
+            round_increment_ext_circuit_constraint(builder, local_values.round, next_values.round);
+        let constraint = builder.mul_many_extension([sum_round_flags, not_final, round_increment]);
+        yield_constr.constraint(builder, constraint);
+
+        // If this is not the final step or a padding row,
+        // input and output addresses should be increased by 8 each
+        (0..NUM_EXTEND_INPUT).for_each(|i| {
+            let increment =
+                builder.sub_extension(next_values.input_virt[i], local_values.input_virt[i]);
+            let address_increment = builder.sub_extension(increment, word_bytes_ext);
+            let constraint =
+                builder.mul_many_extension([sum_round_flags, not_final, address_increment]);
+            yield_constr.constraint(builder, constraint);
+        });
+
+        let increment = builder.sub_extension(next_values.output_virt, local_values.output_virt);
+        let address_increment = builder.sub_extension(increment, word_bytes_ext);
+        let constraint =
+            builder.mul_many_extension([sum_round_flags, not_final, address_increment]);
+        yield_constr.constraint(builder, constraint);
+
+        // If it's not the padding row, check the virtual addresses
+        // The list of input addresses are: w[i-15], w[i-2], w[i-16], w[i-7]
+
+        // add_w[i-15] = add_w[i-16] + 8
+        let constraint = diff_address_ext_circuit_constraint(
+            builder,
+            sum_round_flags,
+            local_values.input_virt[0],
+            local_values.input_virt[2],
+            WORD_BYTES,
+        );
+        yield_constr.constraint(builder, constraint);
+
+        // add_w[i-2] = add_w[i-16] + 112
+        let constraint = diff_address_ext_circuit_constraint(
+            builder,
+            sum_round_flags,
+            local_values.input_virt[1],
+            local_values.input_virt[2],
+            14 * WORD_BYTES,
+        );
+        yield_constr.constraint(builder, constraint);
+
+        // add_w[i-7] = add_w[i-16] + 72
+        let constraint = diff_address_ext_circuit_constraint(
+            builder,
+            sum_round_flags,
+            local_values.input_virt[3],
+            local_values.input_virt[2],
+            9 * WORD_BYTES,
+        );
+        yield_constr.constraint(builder, constraint);
+
+        // add_w[i] = add_w[i-16] + 128
+        let constraint = diff_address_ext_circuit_constraint(
+            builder,
+            sum_round_flags,
+            local_values.output_virt,
+            local_values.input_virt[2],
+            16 * WORD_BYTES,
+        );
+        yield_constr.constraint(builder, constraint);
+    }
+
+    fn constraint_degree(&self) -> usize {
+        3
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::memory::segments::Segment;
+    use crate::memory::NUM_CHANNELS;
+    use crate::sha512_extend_sponge::columns::WORD_BITS;
+    use crate::sha512_extend_sponge::sha512_extend_sponge_stark::{
+        Sha512ExtendSpongeOp, Sha512ExtendSpongeStark, NUM_ROUNDS,
+    };
+    use crate::stark_testing::{test_stark_circuit_constraints, test_stark_low_degree};
+    use crate::witness::memory::MemoryAddress;
+    use plonky2::field::goldilocks_field::GoldilocksField;
+    use plonky2::field::types::Field;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    /// The ops extending the message schedule whose first 16 words are `w`.
+    fn get_ops(w: [u64; 16]) -> Vec<Sha512ExtendSpongeOp> {
+        let mut w = w.to_vec();
+        let addresses = (0..NUM_ROUNDS + 16)
+            .map(|i| MemoryAddress {
+                context: 0,
+                segment: Segment::CODE,
+                virt: i * 8,
+            })
+            .collect::<Vec<_>>();
+
+        let mut res = vec![];
+        let mut time = 0;
+        for i in 16..NUM_ROUNDS + 16 {
+            let mut input_values = vec![];
+            input_values.extend(w[i - 15].to_le_bytes());
+            input_values.extend(w[i - 2].to_le_bytes());
+            input_values.extend(w[i - 16].to_le_bytes());
+            input_values.extend(w[i - 7].to_le_bytes());
+
+            let op = Sha512ExtendSpongeOp {
+                base_address: vec![
+                    addresses[i - 15],
+                    addresses[i - 2],
+                    addresses[i - 16],
+                    addresses[i - 7],
+                ],
+                timestamp: time,
+                input: input_values.clone(),
+                i: i - 16,
+                output_address: addresses[i],
+            };
+            w.push(
+                Sha512ExtendSpongeStark::<GoldilocksField, 2>::default().compute_w_i(&input_values),
+            );
+
+            res.push(op);
+            time += 2 * NUM_CHANNELS;
+        }
+
+        res
+    }
+
+    #[test]
+    fn test_correction() {
+        type F = GoldilocksField;
+        // The padded block of the message "abc".
+        let mut w = [0u64; 16];
+        w[0] = 0x6162638000000000;
+        w[15] = 0x18;
+
+        let stark = Sha512ExtendSpongeStark::<F, 2>::default();
+        let ops = get_ops(w);
+        let expected = [(1, 0x30000000000c0), (63, 0x92aeeed1a7bcf7d2)];
+        for (round, w_i) in expected {
+            let row = stark.generate_rows_for_op(ops[round].clone());
+            let w_i_bits: [F; WORD_BITS] =
+                core::array::from_fn(|j| F::from_bool((w_i >> j) & 1 == 1));
+            assert_eq!(row.w_i, w_i_bits);
+        }
+    }
+
+    #[test]
+    fn test_stark_circuit() -> anyhow::Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type S = Sha512ExtendSpongeStark<F, D>;
+
+        let stark = S::default();
+        test_stark_circuit_constraints::<F, C, S, D>(stark)
+    }
+
+    #[test]
+    fn test_stark_degree() -> anyhow::Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type S = Sha512ExtendSpongeStark<F, D>;
+
+        let stark = S {
+            f: Default::default(),
+        };
+        test_stark_low_degree(stark)
+    }
+}
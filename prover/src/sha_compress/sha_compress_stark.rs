@@ -273,7 +273,7 @@ impl<F: RichField + Extendable<D>, const D: usize> ShaCompressStark<F, D> {
 
         let mut row = ShaCompressColumnsView::<F>::default();
         row.timestamp = F::from_canonical_usize(timestamp);
-        row.segment = F::from_canonical_usize(w_i_address.segment);
+        row.segment = F::from_canonical_u32(w_i_address.segment);
         row.context = F::from_canonical_usize(w_i_address.context);
         row.w_i_virt = F::from_canonical_usize(w_i_address.virt);
         let i = inputs[40] as usize;
@@ -892,11 +892,8 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for ShaCompressSt
 #[cfg(test)]
 mod test {
     use crate::config::StarkConfig;
-    use crate::cross_table_lookup::{
-        Column, CtlData, CtlZData, Filter, GrandProductChallenge, GrandProductChallengeSet,
-    };
     use crate::memory::segments::Segment;
-    use crate::prover::prove_single_table;
+    use crate::prover::testutils::prove_single_table_standalone;
     use crate::sha_compress::columns::ShaCompressColumnsView;
     use crate::sha_compress::sha_compress_stark::{ShaCompressStark, NUM_INPUTS};
     use crate::sha_compress_sponge::constants::{SHA_COMPRESS_K, SHA_COMPRESS_K_LE_BYTES};
@@ -905,12 +902,8 @@ mod test {
     use env_logger::{try_init_from_env, Env, DEFAULT_FILTER_ENV};
     use itertools::Itertools;
     use plonky2::field::goldilocks_field::GoldilocksField;
-    use plonky2::field::polynomial::PolynomialValues;
     use plonky2::field::types::Field;
-    use plonky2::fri::oracle::PolynomialBatch;
-    use plonky2::iop::challenger::Challenger;
     use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
-    use plonky2::timed;
     use plonky2::util::timing::TimingTree;
     use rand::Rng;
     use std::borrow::Borrow;
@@ -1078,49 +1071,10 @@ mod test {
         let mut timing = TimingTree::new("prove", log::Level::Debug);
         let trace_poly_values = stark.generate_trace(input, 8);
 
-        // TODO: Cloning this isn't great; consider having `from_values` accept a reference,
-        // or having `compute_permutation_z_polys` read trace values from the `PolynomialBatch`.
-        let cloned_trace_poly_values = timed!(timing, "clone", trace_poly_values.clone());
-
-        let trace_commitments = timed!(
-            timing,
-            "compute trace commitment",
-            PolynomialBatch::<F, C, D>::from_values(
-                cloned_trace_poly_values,
-                config.fri_config.rate_bits,
-                false,
-                config.fri_config.cap_height,
-                &mut timing,
-                None,
-            )
-        );
-        let degree = 1 << trace_commitments.degree_log;
-
-        // Fake CTL data.
-        let ctl_z_data = CtlZData {
-            helper_columns: vec![PolynomialValues::zero(degree)],
-            z: PolynomialValues::zero(degree),
-            challenge: GrandProductChallenge {
-                beta: F::ZERO,
-                gamma: F::ZERO,
-            },
-            columns: vec![],
-            filter: vec![Some(Filter::new_simple(Column::constant(F::ZERO)))],
-        };
-        let ctl_data = CtlData {
-            zs_columns: vec![ctl_z_data.clone(); config.num_challenges],
-        };
-
-        prove_single_table(
+        prove_single_table_standalone::<F, C, S, D>(
             &stark,
             &config,
             &trace_poly_values,
-            &trace_commitments,
-            &ctl_data,
-            &GrandProductChallengeSet {
-                challenges: vec![ctl_z_data.challenge; config.num_challenges],
-            },
-            &mut Challenger::new(),
             &mut timing,
         )?;
 
@@ -1,7 +1,6 @@
 use crate::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer};
 use crate::cross_table_lookup::{Column, Filter};
 use crate::evaluation_frame::{StarkEvaluationFrame, StarkFrame};
-use crate::memory::segments::Segment;
 use crate::sha_compress::wrapping_add_2::{
     wrapping_add_2_ext_circuit_constraints, wrapping_add_2_packed_constraints,
 };
@@ -152,7 +151,7 @@ impl<F: RichField + Extendable<D>, const D: usize> ShaCompressSpongeStark<F, D>
 
         row.timestamp = F::from_canonical_usize(op.timestamp);
         row.context = F::from_canonical_usize(op.base_address[0].context);
-        row.segment = F::from_canonical_usize(op.base_address[Segment::Code as usize].segment);
+        row.segment = F::from_canonical_u32(op.base_address[0].segment);
         row.is_real_round = F::ONE;
         let hx_virt: [usize; 8] = (0..8)
             .map(|i| op.base_address[i].virt)
@@ -161,7 +160,7 @@ impl<F: RichField + Extendable<D>, const D: usize> ShaCompressSpongeStark<F, D>
             .unwrap();
         row.hx_virt = hx_virt.map(F::from_canonical_usize);
         row.w_start_virt = F::from_canonical_usize(op.base_address[8].virt);
-        row.w_start_segment = F::from_canonical_usize(op.base_address[8].segment);
+        row.w_start_segment = F::from_canonical_u32(op.base_address[8].segment);
         row.w_start_context = F::from_canonical_usize(op.base_address[8].context);
         row.hx = op
             .input
@@ -332,10 +331,7 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for ShaCompressSp
 #[cfg(test)]
 mod test {
     use crate::config::StarkConfig;
-    use crate::cross_table_lookup::{
-        Column, CtlData, CtlZData, Filter, GrandProductChallenge, GrandProductChallengeSet,
-    };
-    use crate::prover::prove_single_table;
+    use crate::prover::testutils::prove_single_table_standalone;
     use crate::sha_compress_sponge::columns::ShaCompressSpongeColumnsView;
     use crate::sha_compress_sponge::sha_compress_sponge_stark::{
         ShaCompressSpongeOp, ShaCompressSpongeStark,
@@ -344,12 +340,8 @@ mod test {
     use crate::witness::memory::MemoryAddress;
     use env_logger::{try_init_from_env, Env, DEFAULT_FILTER_ENV};
     use plonky2::field::goldilocks_field::GoldilocksField;
-    use plonky2::field::polynomial::PolynomialValues;
     use plonky2::field::types::Field;
-    use plonky2::fri::oracle::PolynomialBatch;
-    use plonky2::iop::challenger::Challenger;
     use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
-    use plonky2::timed;
     use plonky2::util::timing::TimingTree;
     use rand::Rng;
     use std::borrow::Borrow;
@@ -531,49 +523,10 @@ mod test {
         let mut timing = TimingTree::new("prove", log::Level::Debug);
         let trace_poly_values = stark.generate_trace(input, 8);
 
-        // TODO: Cloning this isn't great; consider having `from_values` accept a reference,
-        // or having `compute_permutation_z_polys` read trace values from the `PolynomialBatch`.
-        let cloned_trace_poly_values = timed!(timing, "clone", trace_poly_values.clone());
-
-        let trace_commitments = timed!(
-            timing,
-            "compute trace commitment",
-            PolynomialBatch::<F, C, D>::from_values(
-                cloned_trace_poly_values,
-                config.fri_config.rate_bits,
-                false,
-                config.fri_config.cap_height,
-                &mut timing,
-                None,
-            )
-        );
-        let degree = 1 << trace_commitments.degree_log;
-
-        // Fake CTL data.
-        let ctl_z_data = CtlZData {
-            helper_columns: vec![PolynomialValues::zero(degree)],
-            z: PolynomialValues::zero(degree),
-            challenge: GrandProductChallenge {
-                beta: F::ZERO,
-                gamma: F::ZERO,
-            },
-            columns: vec![],
-            filter: vec![Some(Filter::new_simple(Column::constant(F::ZERO)))],
-        };
-        let ctl_data = CtlData {
-            zs_columns: vec![ctl_z_data.clone(); config.num_challenges],
-        };
-
-        prove_single_table(
+        prove_single_table_standalone::<F, C, S, D>(
             &stark,
             &config,
             &trace_poly_values,
-            &trace_commitments,
-            &ctl_data,
-            &GrandProductChallengeSet {
-                challenges: vec![ctl_z_data.challenge; config.num_challenges],
-            },
-            &mut Challenger::new(),
             &mut timing,
         )?;
 
@@ -1,3 +1,48 @@
 pub(crate) fn get_input_range_4(i: usize) -> std::ops::Range<usize> {
     (i * 4)..(4 + i * 4)
 }
+
+pub(crate) fn get_input_range_8(i: usize) -> std::ops::Range<usize> {
+    (i * 8)..(8 + i * 8)
+}
+
+/// The `N` bits of `value`, most significant first. `N` is the word size, e.g. 32 for SHA-256
+/// and 64 for SHA-512, and the bits of `value` above it are dropped.
+pub fn from_uint_to_be_bits<const N: usize>(value: u64) -> [u8; N] {
+    assert!(N <= 64, "Words are at most 64 bits.");
+    core::array::from_fn(|i| ((value >> (N - 1 - i)) & 1) as u8)
+}
+
+/// Inverse of `from_uint_to_be_bits`: the word whose bits, most significant first, are `bits`.
+pub fn from_be_bits_to_uint<const N: usize>(bits: &[u8; N]) -> u64 {
+    assert!(N <= 64, "Words are at most 64 bits.");
+    bits.iter()
+        .fold(0, |acc, &bit| (acc << 1) | u64::from(bit & 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_be_bits_roundtrip() {
+        let bits = from_uint_to_be_bits::<32>(0x8000_0001);
+        assert_eq!(bits[0], 1);
+        assert_eq!(bits[1..31], [0; 30]);
+        assert_eq!(bits[31], 1);
+
+        for value in [0, 1, 0x6a09_e667, u32::MAX] {
+            let bits = from_uint_to_be_bits::<32>(value.into());
+            assert_eq!(from_be_bits_to_uint(&bits), u64::from(value));
+        }
+        for value in [0, 1, 0x6a09_e667_f3bc_c908, u64::MAX] {
+            let bits = from_uint_to_be_bits::<64>(value);
+            assert_eq!(from_be_bits_to_uint(&bits), value);
+        }
+        // Bits above the word size are dropped.
+        assert_eq!(
+            from_be_bits_to_uint(&from_uint_to_be_bits::<32>(u64::MAX)),
+            u64::from(u32::MAX)
+        );
+    }
+}
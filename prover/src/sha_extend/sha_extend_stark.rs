@@ -409,21 +409,15 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for ShaExtendStar
 #[cfg(test)]
 mod test {
     use crate::config::StarkConfig;
-    use crate::cross_table_lookup::{
-        Column, CtlData, CtlZData, Filter, GrandProductChallenge, GrandProductChallengeSet,
-    };
-    use crate::prover::prove_single_table;
+    use crate::prover::testutils::prove_single_table_standalone;
     use crate::sha_extend::sha_extend_stark::ShaExtendStark;
     use crate::sha_extend_sponge::columns::NUM_EXTEND_INPUT;
+    use crate::stark::Stark;
     use crate::stark_testing::{test_stark_circuit_constraints, test_stark_low_degree};
     use env_logger::{try_init_from_env, Env, DEFAULT_FILTER_ENV};
     use plonky2::field::goldilocks_field::GoldilocksField;
-    use plonky2::field::polynomial::PolynomialValues;
     use plonky2::field::types::Field;
-    use plonky2::fri::oracle::PolynomialBatch;
-    use plonky2::iop::challenger::Challenger;
     use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
-    use plonky2::timed;
     use plonky2::util::timing::TimingTree;
 
     fn get_random_input() -> [u8; NUM_EXTEND_INPUT * 4] {
@@ -519,49 +513,10 @@ mod test {
         let mut timing = TimingTree::new("prove", log::Level::Debug);
         let trace_poly_values = stark.generate_trace(input, 8);
 
-        // TODO: Cloning this isn't great; consider having `from_values` accept a reference,
-        // or having `compute_permutation_z_polys` read trace values from the `PolynomialBatch`.
-        let cloned_trace_poly_values = timed!(timing, "clone", trace_poly_values.clone());
-
-        let trace_commitments = timed!(
-            timing,
-            "compute trace commitment",
-            PolynomialBatch::<F, C, D>::from_values(
-                cloned_trace_poly_values,
-                config.fri_config.rate_bits,
-                false,
-                config.fri_config.cap_height,
-                &mut timing,
-                None,
-            )
-        );
-        let degree = 1 << trace_commitments.degree_log;
-
-        // Fake CTL data.
-        let ctl_z_data = CtlZData {
-            helper_columns: vec![PolynomialValues::zero(degree)],
-            z: PolynomialValues::zero(degree),
-            challenge: GrandProductChallenge {
-                beta: F::ZERO,
-                gamma: F::ZERO,
-            },
-            columns: vec![],
-            filter: vec![Some(Filter::new_simple(Column::constant(F::ZERO)))],
-        };
-        let ctl_data = CtlData {
-            zs_columns: vec![ctl_z_data.clone(); config.num_challenges],
-        };
-
-        prove_single_table(
+        prove_single_table_standalone::<F, C, S, D>(
             &stark,
             &config,
             &trace_poly_values,
-            &trace_commitments,
-            &ctl_data,
-            &GrandProductChallengeSet {
-                challenges: vec![ctl_z_data.challenge; config.num_challenges],
-            },
-            &mut Challenger::new(),
             &mut timing,
         )?;
 
@@ -569,6 +524,44 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_estimate_proof_bytes() -> anyhow::Result<()> {
+        const NUM_EXTEND: usize = 48;
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type S = ShaExtendStark<F, D>;
+        let stark = S::default();
+        let config = StarkConfig::standard_fast_config();
+
+        let input: Vec<([u8; NUM_EXTEND_INPUT * 4], usize)> =
+            (0..NUM_EXTEND).map(|_| (get_random_input(), 0)).collect();
+        let trace_poly_values = stark.generate_trace(input, 8);
+        let degree_bits = trace_poly_values[0].len().trailing_zeros() as usize;
+
+        let mut timing = TimingTree::new("prove", log::Level::Debug);
+        let proof = prove_single_table_standalone::<F, C, S, D>(
+            &stark,
+            &config,
+            &trace_poly_values,
+            &mut timing,
+        )?;
+        let proof_bytes = bincode::serialize(&proof.proof)?.len();
+
+        // `CtlData::fake` has one helper column and one Z polynomial per challenge.
+        let num_polys = [
+            S::COLUMNS,
+            stark.num_lookup_helper_columns(&config) + 2 * config.num_challenges,
+            stark.num_quotient_polys(&config),
+        ];
+        let estimate = config.estimate_table_proof_bytes(degree_bits, num_polys);
+        assert!(
+            estimate.abs_diff(proof_bytes) * 10 <= proof_bytes,
+            "estimated {estimate} bytes, got {proof_bytes}"
+        );
+        Ok(())
+    }
+
     fn init_logger() {
         let _ = try_init_from_env(Env::default().filter_or(DEFAULT_FILTER_ENV, "debug"));
     }
@@ -4,10 +4,14 @@ use std::mem::transmute;
 
 pub(crate) const NUM_EXTEND_INPUT: usize = 4;
 pub(crate) const SHA_EXTEND_SPONGE_READ_BYTES: usize = NUM_EXTEND_INPUT * 4;
+pub(crate) const TIMESTAMP_DIFF_BITS: usize = 32;
 pub(crate) struct ShaExtendSpongeColumnsView<T: Copy> {
     /// round
     pub round: [T; 48],
 
+    /// 1 on padding rows and 0 on the rows of an extension round, i.e. `1 - sum(round)`.
+    pub is_padding: T,
+
     /// Input
     pub w_i_minus_15: [T; 4],
     pub w_i_minus_2: [T; 4],
@@ -23,14 +27,30 @@ pub(crate) struct ShaExtendSpongeColumnsView<T: Copy> {
     /// Output address
     pub output_virt: T,
 
+    /// The distance in bytes between consecutive words of the message schedule, which is 4 for
+    /// a contiguous `w`.
+    pub stride: T,
+
+    /// The stride argument passed to the syscall in `$a1`, bound to the CPU table through the
+    /// sponge CTL. `stride` is this argument, or 4 if it is 0.
+    pub stride_arg: T,
+
+    /// The inverse of `stride_arg`, or 0 if it is 0.
+    pub stride_arg_inv: T,
+
     pub context: T,
     pub segment: T,
 
     /// The timestamp at which inputs should be read from memory.
     pub timestamp: T,
+
+    /// In the final round of an operation followed by another one, the little-endian bits of
+    /// `next_timestamp - timestamp - 1`, which range-checks that timestamps strictly increase
+    /// across operations.
+    pub timestamp_diff_bits: [T; TIMESTAMP_DIFF_BITS],
 }
 
-pub const NUM_SHA_EXTEND_SPONGE_COLUMNS: usize = size_of::<ShaExtendSpongeColumnsView<u8>>(); //216
+pub const NUM_SHA_EXTEND_SPONGE_COLUMNS: usize = size_of::<ShaExtendSpongeColumnsView<u8>>(); //112
 
 impl<T: Copy> From<[T; NUM_SHA_EXTEND_SPONGE_COLUMNS]> for ShaExtendSpongeColumnsView<T> {
     fn from(value: [T; NUM_SHA_EXTEND_SPONGE_COLUMNS]) -> Self {
@@ -1,4 +1,3 @@
-use crate::sha_extend_sponge::sha_extend_sponge_stark::NUM_ROUNDS;
 use plonky2::field::extension::Extendable;
 use plonky2::field::types::Field;
 use plonky2::hash::hash_types::RichField;
@@ -19,10 +18,29 @@ pub(crate) fn diff_address_ext_circuit_constraint<F: RichField + Extendable<D>,
     builder.mul_extension(sum_round_flags, address_diff)
 }
 
+// Compute (x - y - multiple * stride) * sum_round_flags
+pub(crate) fn stride_address_ext_circuit_constraint<
+    F: RichField + Extendable<D>,
+    const D: usize,
+>(
+    builder: &mut CircuitBuilder<F, D>,
+    sum_round_flags: ExtensionTarget<D>,
+    x: ExtensionTarget<D>,
+    y: ExtensionTarget<D>,
+    stride: ExtensionTarget<D>,
+    multiple: usize,
+) -> ExtensionTarget<D> {
+    let inter_1 = builder.sub_extension(x, y);
+    let offset = builder.mul_const_extension(F::from_canonical_usize(multiple), stride);
+    let address_diff = builder.sub_extension(inter_1, offset);
+    builder.mul_extension(sum_round_flags, address_diff)
+}
+
 // Compute nxt_round - local_round - 1
 pub(crate) fn round_increment_ext_circuit_constraint<
     F: RichField + Extendable<D>,
     const D: usize,
+    const NUM_ROUNDS: usize,
 >(
     builder: &mut CircuitBuilder<F, D>,
     local_round: [ExtensionTarget<D>; NUM_ROUNDS],
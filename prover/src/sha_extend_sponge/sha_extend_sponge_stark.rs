@@ -2,19 +2,18 @@ use crate::constraint_consumer::{ConstraintConsumer, RecursiveConstraintConsumer
 use crate::cpu::membus::NUM_CHANNELS;
 use crate::cross_table_lookup::{Column, Filter};
 use crate::evaluation_frame::{StarkEvaluationFrame, StarkFrame};
-use crate::memory::segments::Segment;
 use crate::sha_extend::logic::get_input_range_4;
 use crate::sha_extend_sponge::columns::{
     ShaExtendSpongeColumnsView, NUM_EXTEND_INPUT, NUM_SHA_EXTEND_SPONGE_COLUMNS,
-    SHA_EXTEND_SPONGE_COL_MAP,
+    SHA_EXTEND_SPONGE_COL_MAP, TIMESTAMP_DIFF_BITS,
 };
 use crate::sha_extend_sponge::logic::{
-    diff_address_ext_circuit_constraint, round_increment_ext_circuit_constraint,
+    round_increment_ext_circuit_constraint, stride_address_ext_circuit_constraint,
 };
 use crate::stark::Stark;
 use crate::util::trace_rows_to_poly_values;
 use crate::witness::memory::MemoryAddress;
-use itertools::Itertools;
+use anyhow::{anyhow, Result};
 use plonky2::field::extension::{Extendable, FieldExtension};
 use plonky2::field::packed::PackedField;
 use plonky2::field::polynomial::PolynomialValues;
@@ -22,6 +21,7 @@ use plonky2::field::types::Field;
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::ext_target::ExtensionTarget;
 use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2_maybe_rayon::*;
 use std::borrow::Borrow;
 use std::marker::PhantomData;
 
@@ -57,7 +57,7 @@ pub(crate) fn ctl_looked_data<F: Field>() -> Vec<Column<F>> {
     let w_i = Column::le_bytes(cols.w_i);
 
     Column::singles([cols.context, cols.segment, cols.output_virt, cols.timestamp])
-        .chain([w_i])
+        .chain([w_i, Column::single(cols.stride_arg)])
         .collect()
 }
 
@@ -94,10 +94,9 @@ pub(crate) fn ctl_looking_memory<F: Field>(i: usize) -> Vec<Column<F>> {
     res
 }
 
-pub(crate) fn ctl_looking_sha_extend_filter<F: Field>() -> Filter<F> {
-    let cols = SHA_EXTEND_SPONGE_COL_MAP;
-    // not the padding rows.
-    Filter::new_simple(Column::sum(cols.round))
+/// A filter which is 1 on the rows of an extension round and 0 on padding rows.
+pub(crate) fn sponge_active_filter<F: Field>() -> Filter<F> {
+    Filter::not(Column::single(SHA_EXTEND_SPONGE_COL_MAP.is_padding))
 }
 
 #[derive(Clone, Debug)]
@@ -117,6 +116,24 @@ pub(crate) struct ShaExtendSpongeOp {
 
     /// The base address at which the output is written.
     pub(crate) output_address: MemoryAddress,
+
+    /// The stride argument of the syscall, from which the distance between the words of `w` is
+    /// derived: 0 means 4.
+    pub(crate) stride_arg: u32,
+}
+
+impl ShaExtendSpongeOp {
+    /// Returns the address at which the `index`-th input word is read.
+    fn input_address(&self, index: usize) -> Result<MemoryAddress> {
+        self.base_address.get(index).copied().ok_or_else(|| {
+            anyhow!(
+                "SHA extend sponge operation at timestamp {} has {} input addresses, expected {}",
+                self.timestamp,
+                self.base_address.len(),
+                NUM_EXTEND_INPUT
+            )
+        })
+    }
 }
 
 #[derive(Copy, Clone, Default)]
@@ -129,46 +146,102 @@ impl<F: RichField + Extendable<D>, const D: usize> ShaExtendSpongeStark<F, D> {
         &self,
         operations: Vec<ShaExtendSpongeOp>,
         min_rows: usize,
-    ) -> Vec<PolynomialValues<F>> {
+    ) -> Result<Vec<PolynomialValues<F>>> {
         // Generate the witness row-wise.
-        let trace_rows = self.generate_trace_rows(operations, min_rows);
+        let trace_rows = self.generate_trace_rows(operations, min_rows)?;
 
-        trace_rows_to_poly_values(trace_rows)
+        Ok(trace_rows_to_poly_values(trace_rows))
     }
 
     fn generate_trace_rows(
         &self,
         operations: Vec<ShaExtendSpongeOp>,
         min_rows: usize,
-    ) -> Vec<[F; NUM_SHA_EXTEND_SPONGE_COLUMNS]> {
+    ) -> Result<Vec<[F; NUM_SHA_EXTEND_SPONGE_COLUMNS]>> {
         let base_len = operations.len();
-        let mut rows = Vec::with_capacity(base_len.max(min_rows).next_power_of_two());
-        for op in operations {
-            rows.push(self.generate_rows_for_op(op).into());
-        }
+        let timestamp_diffs = Self::timestamp_diffs(&operations)?;
+        let mut rows: Vec<_> = operations
+            .into_par_iter()
+            .zip(timestamp_diffs)
+            .map(|(op, timestamp_diff)| self.generate_row(op, timestamp_diff))
+            .collect::<Result<_>>()?;
+        rows.reserve(base_len.max(min_rows).next_power_of_two() - base_len);
 
         let padded_rows = rows.len().max(min_rows).next_power_of_two();
         for _ in rows.len()..padded_rows {
-            rows.push(ShaExtendSpongeColumnsView::default().into());
+            rows.push(self.generate_padding_row());
         }
 
-        rows
+        Ok(rows)
+    }
+
+    fn generate_padding_row(&self) -> [F; NUM_SHA_EXTEND_SPONGE_COLUMNS] {
+        // All round flags are 0 in a padding row.
+        let mut row = ShaExtendSpongeColumnsView::default();
+        row.is_padding = F::ONE;
+        row.into()
+    }
+
+    /// For each op, `next_timestamp - timestamp - 1` if it is the final round of an operation
+    /// followed by another one. Fails unless the timestamps of consecutive operations strictly
+    /// increase, by at most `2^TIMESTAMP_DIFF_BITS`.
+    fn timestamp_diffs(operations: &[ShaExtendSpongeOp]) -> Result<Vec<Option<usize>>> {
+        operations
+            .iter()
+            .enumerate()
+            .map(|(k, op)| {
+                let Some(next_op) = operations.get(k + 1).filter(|_| op.i == NUM_ROUNDS - 1)
+                else {
+                    return Ok(None);
+                };
+                let diff = next_op
+                    .timestamp
+                    .checked_sub(op.timestamp + 1)
+                    .filter(|&diff| diff < 1 << TIMESTAMP_DIFF_BITS)
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "SHA extend sponge operation at timestamp {} follows one at timestamp {}, \
+                             but sponge operations must have increasing timestamps",
+                            next_op.timestamp,
+                            op.timestamp
+                        )
+                    })?;
+                Ok(Some(diff))
+            })
+            .collect()
+    }
+
+    fn generate_row(
+        &self,
+        op: ShaExtendSpongeOp,
+        timestamp_diff: Option<usize>,
+    ) -> Result<[F; NUM_SHA_EXTEND_SPONGE_COLUMNS]> {
+        let mut row = self.generate_rows_for_op(op)?;
+        if let Some(diff) = timestamp_diff {
+            row.timestamp_diff_bits = core::array::from_fn(|i| F::from_bool((diff >> i) & 1 == 1));
+        }
+        Ok(row.into())
     }
 
-    fn generate_rows_for_op(&self, op: ShaExtendSpongeOp) -> ShaExtendSpongeColumnsView<F> {
+    fn generate_rows_for_op(&self, op: ShaExtendSpongeOp) -> Result<ShaExtendSpongeColumnsView<F>> {
         let mut row = ShaExtendSpongeColumnsView::default();
         row.timestamp = F::from_canonical_usize(op.timestamp);
         row.round = [F::ZEROS; 48];
         row.round[op.i] = F::ONE;
 
-        row.context = F::from_canonical_usize(op.base_address[0].context);
-        row.segment = F::from_canonical_usize(op.base_address[Segment::Code as usize].segment);
-        let virt = (0..op.input.len() / 4)
-            .map(|i| op.base_address[i].virt)
-            .collect_vec();
-        let virt: [usize; 4] = virt.try_into().unwrap();
+        let base_address = op.input_address(0)?;
+        row.context = F::from_canonical_usize(base_address.context);
+        row.segment = F::from_canonical_u32(base_address.segment);
+        let mut virt = [0; NUM_EXTEND_INPUT];
+        for (i, virt_i) in virt.iter_mut().enumerate() {
+            *virt_i = op.input_address(i)?.virt;
+        }
         row.input_virt = virt.map(F::from_canonical_usize);
         row.output_virt = F::from_canonical_usize(op.output_address.virt);
+        // The distance between w[i-15] and w[i-16].
+        row.stride = F::from_canonical_usize(virt[0] - virt[2]);
+        row.stride_arg = F::from_canonical_u32(op.stride_arg);
+        row.stride_arg_inv = row.stride_arg.try_inverse().unwrap_or(F::ZERO);
 
         let input = op.input.clone();
         row.w_i = self.compute_w_i(input);
@@ -198,7 +271,7 @@ impl<F: RichField + Extendable<D>, const D: usize> ShaExtendSpongeStark<F, D> {
             .try_into()
             .unwrap();
 
-        row
+        Ok(row)
     }
 
     fn compute_w_i(&self, input: Vec<u8>) -> [F; 4] {
@@ -241,6 +314,9 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for ShaExtendSpon
             vars.get_next_values().try_into().unwrap();
         let next_values: &ShaExtendSpongeColumnsView<P> = next_values.borrow();
 
+        // `w_i` isn't constrained here: the inputs and `w_i` of each round are looked up in the
+        // ShaExtend table, which checks the rotations, shifts and the wrapping additions.
+
         // check the round
         for i in 0..NUM_ROUNDS {
             yield_constr.constraint(local_values.round[i] * (local_values.round[i] - P::ONES));
@@ -253,6 +329,11 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for ShaExtendSpon
 
         let sum_round_flags = (0..NUM_ROUNDS).map(|i| local_values.round[i]).sum::<P>();
 
+        // A row is either a padding row or has exactly one round flag set.
+        let is_padding = local_values.is_padding;
+        yield_constr.constraint(is_padding * (is_padding - P::ONES));
+        yield_constr.constraint(is_padding + sum_round_flags - P::ONES);
+
         // If this is not the final step or a padding row,
         // the timestamp must be increased by 2 * NUM_CHANNELS.
         yield_constr.constraint(
@@ -263,6 +344,24 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for ShaExtendSpon
                     - FE::from_canonical_usize(2 * NUM_CHANNELS)),
         );
 
+        // If this is the final step and another operation follows, its timestamp must be
+        // greater: the difference minus one must fit in `TIMESTAMP_DIFF_BITS` bits.
+        for bit in local_values.timestamp_diff_bits {
+            yield_constr.constraint(bit * (bit - P::ONES));
+        }
+        let timestamp_diff = local_values
+            .timestamp_diff_bits
+            .iter()
+            .enumerate()
+            .map(|(i, &bit)| bit * FE::from_canonical_u64(1 << i))
+            .sum::<P>();
+        let next_sum_round_flags = (0..NUM_ROUNDS).map(|i| next_values.round[i]).sum::<P>();
+        yield_constr.constraint_transition(
+            is_final
+                * next_sum_round_flags
+                * (next_values.timestamp - local_values.timestamp - P::ONES - timestamp_diff),
+        );
+
         // If this is not the final step or a padding row,
         // round index should be increased by one
 
@@ -276,53 +375,62 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for ShaExtendSpon
             sum_round_flags * not_final * (next_round_index - local_round_index - P::ONES),
         );
 
+        // The stride is the stride argument, or 4 if the argument is 0.
+        let stride = local_values.stride;
+        let stride_arg = local_values.stride_arg;
+        let stride_arg_is_zero = P::ONES - stride_arg * local_values.stride_arg_inv;
+        yield_constr.constraint(stride_arg * stride_arg_is_zero);
+        yield_constr.constraint(
+            sum_round_flags
+                * (stride - stride_arg - stride_arg_is_zero * FE::from_canonical_u32(4)),
+        );
+
+        // If this is not the final step or a padding row,
+        // the stride should be the same in the next round
+        yield_constr.constraint(sum_round_flags * not_final * (next_values.stride - stride));
+
         // If this is not the final step or a padding row,
-        // input and output addresses should be increased by 4 each
+        // input and output addresses should be increased by the stride each
         (0..NUM_EXTEND_INPUT).for_each(|i| {
             yield_constr.constraint(
                 sum_round_flags
                     * not_final
-                    * (next_values.input_virt[i]
-                        - local_values.input_virt[i]
-                        - FE::from_canonical_u32(4)),
+                    * (next_values.input_virt[i] - local_values.input_virt[i] - stride),
             );
         });
         yield_constr.constraint(
             sum_round_flags
                 * not_final
-                * (next_values.output_virt - local_values.output_virt - FE::from_canonical_u32(4)),
+                * (next_values.output_virt - local_values.output_virt - stride),
         );
 
         // If it's not the padding row, check the virtual addresses
         // The list of input addresses are: w[i-15], w[i-2], w[i-16], w[i-7]
 
-        // add_w[i-15] = add_w[i-16] + 4
+        // add_w[i-15] = add_w[i-16] + stride
         yield_constr.constraint(
-            sum_round_flags
-                * (local_values.input_virt[0]
-                    - local_values.input_virt[2]
-                    - FE::from_canonical_u32(4)),
+            sum_round_flags * (local_values.input_virt[0] - local_values.input_virt[2] - stride),
         );
-        // add_w[i-2] = add_w[i-16] + 56
+        // add_w[i-2] = add_w[i-16] + 14 * stride
         yield_constr.constraint(
             sum_round_flags
                 * (local_values.input_virt[1]
                     - local_values.input_virt[2]
-                    - FE::from_canonical_u32(56)),
+                    - stride * FE::from_canonical_u32(14)),
         );
-        // add_w[i-7] = add_w[i-16] + 36
+        // add_w[i-7] = add_w[i-16] + 9 * stride
         yield_constr.constraint(
             sum_round_flags
                 * (local_values.input_virt[3]
                     - local_values.input_virt[2]
-                    - FE::from_canonical_u32(36)),
+                    - stride * FE::from_canonical_u32(9)),
         );
-        // add_w[i] = add_w[i-16] + 64
+        // add_w[i] = add_w[i-16] + 16 * stride
         yield_constr.constraint(
             sum_round_flags
                 * (local_values.output_virt
                     - local_values.input_virt[2]
-                    - FE::from_canonical_u32(64)),
+                    - stride * FE::from_canonical_u32(16)),
         );
     }
 
@@ -339,8 +447,9 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for ShaExtendSpon
             vars.get_next_values().try_into().unwrap();
         let next_values: &ShaExtendSpongeColumnsView<ExtensionTarget<D>> = next_values.borrow();
 
+        // `w_i` is constrained in the ShaExtend table, see `eval_packed_generic`.
+
         let one_ext = builder.one_extension();
-        let four_ext = builder.constant_extension(F::Extension::from_canonical_u32(4));
         let num_channel =
             builder.constant_extension(F::Extension::from_canonical_usize(2 * NUM_CHANNELS));
 
@@ -363,6 +472,14 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for ShaExtendSpon
         let sum_round_flags =
             builder.add_many_extension((0..NUM_ROUNDS).map(|i| local_values.round[i]));
 
+        // A row is either a padding row or has exactly one round flag set.
+        let is_padding = local_values.is_padding;
+        let constraint = builder.mul_sub_extension(is_padding, is_padding, is_padding);
+        yield_constr.constraint(builder, constraint);
+        let constraint = builder.add_extension(is_padding, sum_round_flags);
+        let constraint = builder.sub_extension(constraint, one_ext);
+        yield_constr.constraint(builder, constraint);
+
         // If this is not the final step or a padding row,
         // the timestamp must be increased by 2 * NUM_CHANNELS.
         let diff = builder.sub_extension(next_values.timestamp, local_values.timestamp);
@@ -370,6 +487,27 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for ShaExtendSpon
         let constraint = builder.mul_many_extension([sum_round_flags, not_final, diff]);
         yield_constr.constraint(builder, constraint);
 
+        // If this is the final step and another operation follows, its timestamp must be
+        // greater: the difference minus one must fit in `TIMESTAMP_DIFF_BITS` bits.
+        for bit in local_values.timestamp_diff_bits {
+            let constraint = builder.mul_sub_extension(bit, bit, bit);
+            yield_constr.constraint(builder, constraint);
+        }
+        let timestamp_diff =
+            local_values
+                .timestamp_diff_bits
+                .iter()
+                .enumerate()
+                .fold(one_ext, |acc, (i, &bit)| {
+                    builder.mul_const_add_extension(F::from_canonical_u64(1 << i), bit, acc)
+                });
+        let next_sum_round_flags =
+            builder.add_many_extension((0..NUM_ROUNDS).map(|i| next_values.round[i]));
+        let diff = builder.sub_extension(next_values.timestamp, local_values.timestamp);
+        let diff = builder.sub_extension(diff, timestamp_diff);
+        let constraint = builder.mul_many_extension([is_final, next_sum_round_flags, diff]);
+        yield_constr.constraint_transition(builder, constraint);
+
         // If this is not the final step or a padding row,
         // round index should be increased by one
 
@@ -378,19 +516,39 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for ShaExtendSpon
         let constraint = builder.mul_many_extension([sum_round_flags, not_final, round_increment]);
         yield_constr.constraint(builder, constraint);
 
+        // The stride is the stride argument, or 4 if the argument is 0.
+        let stride = local_values.stride;
+        let stride_arg = local_values.stride_arg;
+        let arg_times_inv = builder.mul_extension(stride_arg, local_values.stride_arg_inv);
+        let stride_arg_is_zero = builder.sub_extension(one_ext, arg_times_inv);
+        let constraint = builder.mul_extension(stride_arg, stride_arg_is_zero);
+        yield_constr.constraint(builder, constraint);
+        let default_stride =
+            builder.mul_const_extension(F::from_canonical_u32(4), stride_arg_is_zero);
+        let stride_diff = builder.sub_extension(stride, stride_arg);
+        let stride_diff = builder.sub_extension(stride_diff, default_stride);
+        let constraint = builder.mul_extension(sum_round_flags, stride_diff);
+        yield_constr.constraint(builder, constraint);
+
         // If this is not the final step or a padding row,
-        // input and output addresses should be increased by 4 each
+        // the stride should be the same in the next round
+        let stride_diff = builder.sub_extension(next_values.stride, stride);
+        let constraint = builder.mul_many_extension([sum_round_flags, not_final, stride_diff]);
+        yield_constr.constraint(builder, constraint);
+
+        // If this is not the final step or a padding row,
+        // input and output addresses should be increased by the stride each
         (0..NUM_EXTEND_INPUT).for_each(|i| {
             let increment =
                 builder.sub_extension(next_values.input_virt[i], local_values.input_virt[i]);
-            let address_increment = builder.sub_extension(increment, four_ext);
+            let address_increment = builder.sub_extension(increment, stride);
             let constraint =
                 builder.mul_many_extension([sum_round_flags, not_final, address_increment]);
             yield_constr.constraint(builder, constraint);
         });
 
         let increment = builder.sub_extension(next_values.output_virt, local_values.output_virt);
-        let address_increment = builder.sub_extension(increment, four_ext);
+        let address_increment = builder.sub_extension(increment, stride);
         let constraint =
             builder.mul_many_extension([sum_round_flags, not_final, address_increment]);
         yield_constr.constraint(builder, constraint);
@@ -398,43 +556,47 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for ShaExtendSpon
         // If it's not the padding row, check the virtual addresses
         // The list of input addresses are: w[i-15], w[i-2], w[i-16], w[i-7]
 
-        // add_w[i-15] = add_w[i-16] + 4
-        let constraint = diff_address_ext_circuit_constraint(
+        // add_w[i-15] = add_w[i-16] + stride
+        let constraint = stride_address_ext_circuit_constraint(
             builder,
             sum_round_flags,
             local_values.input_virt[0],
             local_values.input_virt[2],
-            4,
+            stride,
+            1,
         );
         yield_constr.constraint(builder, constraint);
 
-        // add_w[i-2] = add_w[i-16] + 56
-        let constraint = diff_address_ext_circuit_constraint(
+        // add_w[i-2] = add_w[i-16] + 14 * stride
+        let constraint = stride_address_ext_circuit_constraint(
             builder,
             sum_round_flags,
             local_values.input_virt[1],
             local_values.input_virt[2],
-            56,
+            stride,
+            14,
         );
         yield_constr.constraint(builder, constraint);
 
-        // add_w[i-7] = add_w[i-16] + 36
-        let constraint = diff_address_ext_circuit_constraint(
+        // add_w[i-7] = add_w[i-16] + 9 * stride
+        let constraint = stride_address_ext_circuit_constraint(
             builder,
             sum_round_flags,
             local_values.input_virt[3],
             local_values.input_virt[2],
-            36,
+            stride,
+            9,
         );
         yield_constr.constraint(builder, constraint);
 
-        // add_w[i] = add_w[i-16] + 64
-        let constraint = diff_address_ext_circuit_constraint(
+        // add_w[i] = add_w[i-16] + 16 * stride
+        let constraint = stride_address_ext_circuit_constraint(
             builder,
             sum_round_flags,
             local_values.output_virt,
             local_values.input_virt[2],
-            64,
+            stride,
+            16,
         );
         yield_constr.constraint(builder, constraint);
     }
@@ -446,26 +608,28 @@ impl<F: RichField + Extendable<D>, const D: usize> Stark<F, D> for ShaExtendSpon
 
 #[cfg(test)]
 mod test {
+    use crate::all_stark::{ctl_sha_extend_inputs, ctl_sha_extend_outputs, Table, NUM_TABLES};
     use crate::config::StarkConfig;
-    use crate::cross_table_lookup::{
-        Column, CtlData, CtlZData, Filter, GrandProductChallenge, GrandProductChallengeSet,
-    };
+    use crate::cross_table_lookup::verify_ctl_consistency;
     use crate::memory::segments::Segment;
     use crate::memory::NUM_CHANNELS;
-    use crate::prover::prove_single_table;
+    use crate::prover::testutils::prove_single_table_standalone;
+    use crate::sha_extend::sha_extend_stark::ShaExtendStark;
+    use crate::sha_extend_sponge::columns::{
+        NUM_SHA_EXTEND_SPONGE_COLUMNS, SHA_EXTEND_SPONGE_COL_MAP,
+    };
     use crate::sha_extend_sponge::sha_extend_sponge_stark::{
-        ShaExtendSpongeOp, ShaExtendSpongeStark,
+        sponge_active_filter, ShaExtendSpongeOp, ShaExtendSpongeStark, NUM_ROUNDS,
+    };
+    use crate::stark_testing::{
+        test_stark_check_constraints, test_stark_circuit_constraints, test_stark_low_degree,
     };
-    use crate::stark_testing::{test_stark_circuit_constraints, test_stark_low_degree};
+    use crate::util::trace_rows_to_poly_values;
     use crate::witness::memory::MemoryAddress;
     use env_logger::{try_init_from_env, Env, DEFAULT_FILTER_ENV};
     use plonky2::field::goldilocks_field::GoldilocksField;
-    use plonky2::field::polynomial::PolynomialValues;
     use plonky2::field::types::Field;
-    use plonky2::fri::oracle::PolynomialBatch;
-    use plonky2::iop::challenger::Challenger;
     use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
-    use plonky2::timed;
     use plonky2::util::timing::TimingTree;
 
     #[test]
@@ -483,22 +647,22 @@ mod test {
             base_address: vec![
                 MemoryAddress {
                     context: 0,
-                    segment: Segment::Code as usize,
+                    segment: Segment::CODE,
                     virt: 4,
                 },
                 MemoryAddress {
                     context: 0,
-                    segment: Segment::Code as usize,
+                    segment: Segment::CODE,
                     virt: 56,
                 },
                 MemoryAddress {
                     context: 0,
-                    segment: Segment::Code as usize,
+                    segment: Segment::CODE,
                     virt: 0,
                 },
                 MemoryAddress {
                     context: 0,
-                    segment: Segment::Code as usize,
+                    segment: Segment::CODE,
                     virt: 36,
                 },
             ],
@@ -507,13 +671,14 @@ mod test {
             i: 0,
             output_address: MemoryAddress {
                 context: 0,
-                segment: Segment::Code as usize,
+                segment: Segment::CODE,
                 virt: 64,
             },
+            stride_arg: 0,
         };
 
         let stark = S::default();
-        let row = stark.generate_rows_for_op(op);
+        let row = stark.generate_rows_for_op(op).map_err(|e| e.to_string())?;
 
         let w_i_bin = 40965_u32.to_le_bytes();
         assert_eq!(row.w_i, w_i_bin.map(F::from_canonical_u8));
@@ -545,7 +710,196 @@ mod test {
         test_stark_low_degree(stark)
     }
 
-    fn get_random_input() -> Vec<ShaExtendSpongeOp> {
+    #[test]
+    fn test_short_base_address() {
+        let mut op = get_random_input(4).remove(0);
+        op.base_address.truncate(2);
+        let stark = ShaExtendSpongeStark::<GoldilocksField, 2>::default();
+        let err = stark.generate_trace(vec![op], 8).unwrap_err();
+        assert!(err.to_string().contains("has 2 input addresses"));
+    }
+
+    #[test]
+    fn test_non_default_stride() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type S = ShaExtendSpongeStark<F, D>;
+
+        let stark = S::default();
+        let rows = stark.generate_trace_rows(get_random_input(12), 8).unwrap();
+        for i in 0..rows.len() {
+            test_stark_check_constraints::<F, C, S, D>(
+                stark,
+                &rows[i],
+                &rows[(i + 1) % rows.len()],
+            );
+        }
+    }
+
+    /// Two back-to-back extensions, the second one starting `gap` after the first one ends.
+    fn get_two_ops(gap: usize) -> Vec<ShaExtendSpongeOp> {
+        let mut ops = get_random_input(4);
+        let start = ops.last().unwrap().timestamp + gap;
+        ops.extend(get_random_input(4).into_iter().map(|mut op| {
+            op.timestamp += start;
+            op
+        }));
+        ops
+    }
+
+    fn get_two_ops_rows(gap: usize) -> Vec<[GoldilocksField; NUM_SHA_EXTEND_SPONGE_COLUMNS]> {
+        ShaExtendSpongeStark::<GoldilocksField, 2>::default()
+            .generate_trace_rows(get_two_ops(gap), 8)
+            .unwrap()
+    }
+
+    fn check_all_rows(rows: &[[GoldilocksField; NUM_SHA_EXTEND_SPONGE_COLUMNS]]) {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type S = ShaExtendSpongeStark<GoldilocksField, D>;
+
+        for i in 0..rows.len() {
+            test_stark_check_constraints::<GoldilocksField, C, S, D>(
+                S::default(),
+                &rows[i],
+                &rows[(i + 1) % rows.len()],
+            );
+        }
+    }
+
+    #[test]
+    fn test_increasing_timestamps() {
+        check_all_rows(&get_two_ops_rows(2 * NUM_CHANNELS));
+        check_all_rows(&get_two_ops_rows(1));
+    }
+
+    #[test]
+    fn test_non_increasing_timestamps_error() {
+        let err = ShaExtendSpongeStark::<GoldilocksField, 2>::default()
+            .generate_trace_rows(get_two_ops(0), 8)
+            .unwrap_err();
+        assert!(err.to_string().contains("increasing timestamps"));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_swapped_timestamps() {
+        let mut rows = get_two_ops_rows(2 * NUM_CHANNELS);
+        let timestamp = SHA_EXTEND_SPONGE_COL_MAP.timestamp;
+        for i in 0..NUM_ROUNDS {
+            let first = rows[i][timestamp];
+            rows[i][timestamp] = rows[NUM_ROUNDS + i][timestamp];
+            rows[NUM_ROUNDS + i][timestamp] = first;
+        }
+        check_all_rows(&rows);
+    }
+
+    fn get_rows_with_stride_arg(
+        stride: usize,
+        stride_arg: u32,
+    ) -> Vec<[GoldilocksField; NUM_SHA_EXTEND_SPONGE_COLUMNS]> {
+        let ops = get_random_input(stride)
+            .into_iter()
+            .map(|op| ShaExtendSpongeOp { stride_arg, ..op })
+            .collect();
+        ShaExtendSpongeStark::<GoldilocksField, 2>::default()
+            .generate_trace_rows(ops, 8)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_zero_stride_arg() {
+        // A stride argument of 0 means contiguous words.
+        check_all_rows(&get_rows_with_stride_arg(4, 0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_stride_arg_with_other_stride() {
+        check_all_rows(&get_rows_with_stride_arg(8, 0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_mismatched_stride_arg() {
+        check_all_rows(&get_rows_with_stride_arg(4, 8));
+    }
+
+    #[test]
+    fn test_parallel_trace_matches_serial() {
+        type F = GoldilocksField;
+        let stark = ShaExtendSpongeStark::<F, 2>::default();
+        let ops = get_two_ops(2 * NUM_CHANNELS);
+
+        let timestamp_diffs = ShaExtendSpongeStark::<F, 2>::timestamp_diffs(&ops).unwrap();
+        let mut serial_rows = ops
+            .iter()
+            .zip(timestamp_diffs)
+            .map(|(op, timestamp_diff)| stark.generate_row(op.clone(), timestamp_diff).unwrap())
+            .collect::<Vec<_>>();
+        serial_rows.resize(128, stark.generate_padding_row());
+
+        assert_eq!(
+            stark.generate_trace(ops, 8).unwrap(),
+            trace_rows_to_poly_values(serial_rows)
+        );
+    }
+
+    #[test]
+    fn test_tampered_w_i_fails_ctl() {
+        const D: usize = 2;
+        type F = GoldilocksField;
+
+        let ops = get_random_input(4);
+        let extend_inputs = ops
+            .iter()
+            .map(|op| (op.input.clone().try_into().unwrap(), op.timestamp))
+            .collect();
+        let mut traces = vec![vec![]; NUM_TABLES];
+        traces[Table::ShaExtend as usize] =
+            ShaExtendStark::<F, D>::default().generate_trace(extend_inputs, 8);
+        traces[Table::ShaExtendSponge as usize] = ShaExtendSpongeStark::<F, D>::default()
+            .generate_trace(ops, 8)
+            .unwrap();
+        let ctls = [ctl_sha_extend_inputs(), ctl_sha_extend_outputs()];
+        assert!(verify_ctl_consistency(&traces, &ctls).is_ok());
+
+        let w_i = &mut traces[Table::ShaExtendSponge as usize][SHA_EXTEND_SPONGE_COL_MAP.w_i[0]];
+        w_i.values[0] += F::ONE;
+        assert!(verify_ctl_consistency(&traces, &ctls).is_err());
+    }
+
+    #[test]
+    fn test_sponge_active_filter() {
+        type F = GoldilocksField;
+        let ops = get_random_input(4);
+        let num_ops = ops.len();
+        let trace = ShaExtendSpongeStark::<F, 2>::default()
+            .generate_trace(ops, 8)
+            .unwrap();
+        assert_eq!(trace[0].len(), 64);
+
+        let filter = sponge_active_filter::<F>();
+        for row in 0..trace[0].len() {
+            let active = row < num_ops;
+            assert_eq!(filter.eval_table(&trace, row), F::from_bool(active));
+            let is_padding = trace[SHA_EXTEND_SPONGE_COL_MAP.is_padding].values[row];
+            assert_eq!(is_padding, F::from_bool(!active));
+        }
+        check_all_rows(&get_two_ops_rows(1));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_padding_row_with_round_flag() {
+        let mut rows = get_two_ops_rows(1);
+        let last = rows.len() - 1;
+        rows[last][SHA_EXTEND_SPONGE_COL_MAP.round[0]] = GoldilocksField::ONE;
+        check_all_rows(&rows);
+    }
+
+    fn get_random_input(stride: usize) -> Vec<ShaExtendSpongeOp> {
         let mut w = [0u32; 64];
         for i in 0..16 {
             w[i] = rand::random::<u32>();
@@ -576,8 +930,8 @@ mod test {
         for i in 0..64 {
             addresses.push(MemoryAddress {
                 context: 0,
-                segment: Segment::Code as usize,
-                virt: i * 4,
+                segment: Segment::CODE,
+                virt: i * stride,
             });
         }
 
@@ -602,6 +956,7 @@ mod test {
                 input: input_values,
                 i: i - 16,
                 output_address: addresses[i],
+                stride_arg: stride as u32,
             };
 
             res.push(op);
@@ -621,53 +976,14 @@ mod test {
 
         init_logger();
 
-        let input = get_random_input();
+        let input = get_random_input(4);
         let mut timing = TimingTree::new("prove", log::Level::Debug);
-        let trace_poly_values = stark.generate_trace(input, 8);
-
-        // TODO: Cloning this isn't great; consider having `from_values` accept a reference,
-        // or having `compute_permutation_z_polys` read trace values from the `PolynomialBatch`.
-        let cloned_trace_poly_values = timed!(timing, "clone", trace_poly_values.clone());
-
-        let trace_commitments = timed!(
-            timing,
-            "compute trace commitment",
-            PolynomialBatch::<F, C, D>::from_values(
-                cloned_trace_poly_values,
-                config.fri_config.rate_bits,
-                false,
-                config.fri_config.cap_height,
-                &mut timing,
-                None,
-            )
-        );
-        let degree = 1 << trace_commitments.degree_log;
-
-        // Fake CTL data.
-        let ctl_z_data = CtlZData {
-            helper_columns: vec![PolynomialValues::zero(degree)],
-            z: PolynomialValues::zero(degree),
-            challenge: GrandProductChallenge {
-                beta: F::ZERO,
-                gamma: F::ZERO,
-            },
-            columns: vec![],
-            filter: vec![Some(Filter::new_simple(Column::constant(F::ZERO)))],
-        };
-        let ctl_data = CtlData {
-            zs_columns: vec![ctl_z_data.clone(); config.num_challenges],
-        };
+        let trace_poly_values = stark.generate_trace(input, 8)?;
 
-        prove_single_table(
+        prove_single_table_standalone::<F, C, S, D>(
             &stark,
             &config,
             &trace_poly_values,
-            &trace_commitments,
-            &ctl_data,
-            &GrandProductChallengeSet {
-                challenges: vec![ctl_z_data.challenge; config.num_challenges],
-            },
-            &mut Challenger::new(),
             &mut timing,
         )?;
 
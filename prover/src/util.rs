@@ -1,3 +1,4 @@
+use core::convert::Infallible;
 use itertools::Itertools;
 use std::mem::{size_of, transmute_copy, ManuallyDrop};
 
@@ -7,6 +8,7 @@ use plonky2::field::polynomial::PolynomialValues;
 use plonky2::field::types::Field;
 use plonky2::hash::hash_types::RichField;
 use plonky2::iop::ext_target::ExtensionTarget;
+use plonky2::util::serialization::{IoResult, Write};
 use plonky2::util::transpose;
 #[allow(unused_imports)]
 use plonky2_maybe_rayon::rayon;
@@ -105,3 +107,30 @@ pub(crate) fn __sendable_closure<R, F: FnOnce() -> R + Send>(x: F) -> F {
 
 pub(crate) use __join;
 pub(crate) use join;
+
+/// A `Write` implementor that discards its input and only counts the bytes written, to size a
+/// serialization without allocating it.
+#[derive(Debug, Default)]
+pub struct CountingWriter {
+    bytes_written: usize,
+}
+
+impl CountingWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of bytes written so far.
+    pub fn bytes_written(&self) -> usize {
+        self.bytes_written
+    }
+}
+
+impl Write for CountingWriter {
+    type Error = Infallible;
+
+    fn write_all(&mut self, bytes: &[u8]) -> IoResult<()> {
+        self.bytes_written += bytes.len();
+        Ok(())
+    }
+}
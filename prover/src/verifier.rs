@@ -1,12 +1,13 @@
-use crate::all_stark::{AllStark, Table};
+use crate::all_stark::{AllStark, Table, ALL_TABLES_ACTIVE, NUM_TABLES};
 use crate::config::StarkConfig;
 use crate::constraint_consumer::ConstraintConsumer;
 
 use crate::cross_table_lookup::{
-    num_ctl_helper_columns_by_table, verify_cross_table_lookups, CtlCheckVars,
+    num_ctl_helper_columns_by_table, verify_cross_table_lookups, CrossTableLookup, CtlCheckVars,
     GrandProductChallengeSet,
 };
 use crate::evaluation_frame::StarkEvaluationFrame;
+use crate::get_challenges::seeded_challenger;
 use crate::lookup::LookupCheckVars;
 
 use anyhow::{ensure, Result};
@@ -14,8 +15,10 @@ use plonky2::field::extension::{Extendable, FieldExtension};
 use plonky2::field::types::Field;
 use plonky2::fri::verifier::verify_fri_proof;
 use plonky2::hash::hash_types::RichField;
+use plonky2::iop::challenger::Challenger;
 use plonky2::plonk::config::GenericConfig;
 use plonky2::plonk::plonk_common::reduce_with_powers;
+use plonky2_maybe_rayon::*;
 use std::any::type_name;
 
 use crate::proof::{
@@ -31,150 +34,358 @@ pub fn verify_proof<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, co
 ) -> Result<()>
 where
 {
+    verify_proof_with_active_tables(all_stark, all_proof, config, &ALL_TABLES_ACTIVE)
+}
+
+/// Verify the proofs of `active_tables` only, as proven by `AllRecursiveCircuits` built with
+/// `new_with_active`. Inactive tables contribute nothing to the cross-table lookups, so a lookup
+/// into an inactive table must not be used by any active table.
+pub fn verify_proof_with_active_tables<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    all_stark: &AllStark<F, D>,
+    all_proof: AllProof<F, C, D>,
+    config: &StarkConfig,
+    active_tables: &[bool; NUM_TABLES],
+) -> Result<()> {
+    let setup = VerifierSetup::new(all_stark, config);
+    verify_proof_with_setup(
+        all_stark,
+        &all_proof,
+        config,
+        active_tables,
+        &setup,
+        Challenger::new(),
+    )
+}
+
+/// Verify a proof generated by `prove_with_outputs_seeded` with the same `seed`.
+///
+/// Only meant for tests and debugging, see `seeded_challenger`.
+pub fn verify_proof_seeded<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    all_stark: &AllStark<F, D>,
+    all_proof: AllProof<F, C, D>,
+    config: &StarkConfig,
+    seed: [u64; 4],
+) -> Result<()> {
+    let setup = VerifierSetup::new(all_stark, config);
+    verify_proof_with_setup(
+        all_stark,
+        &all_proof,
+        config,
+        &ALL_TABLES_ACTIVE,
+        &setup,
+        seeded_challenger(seed),
+    )
+}
+
+/// Verify independent proofs in parallel, returning one result per proof, in order.
+///
+/// Each proof is fully verified on its own, with its own challenges: only the data depending on
+/// `all_stark` and `config` alone is computed once for the whole batch.
+pub fn verify_proofs_batch<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    all_stark: &AllStark<F, D>,
+    proofs: &[AllProof<F, C, D>],
+    config: &StarkConfig,
+) -> Vec<Result<()>> {
+    let setup = VerifierSetup::new(all_stark, config);
+    proofs
+        .par_iter()
+        .map(|all_proof| {
+            verify_proof_with_setup(
+                all_stark,
+                all_proof,
+                config,
+                &ALL_TABLES_ACTIVE,
+                &setup,
+                Challenger::new(),
+            )
+        })
+        .collect()
+}
+
+/// The number of helper columns of each table, which doesn't depend on the proof.
+struct VerifierSetup {
+    num_lookup_columns: [usize; NUM_TABLES],
+    num_ctl_helper_cols: Vec<[usize; NUM_TABLES]>,
+}
+
+impl VerifierSetup {
+    fn new<F: RichField + Extendable<D>, const D: usize>(
+        all_stark: &AllStark<F, D>,
+        config: &StarkConfig,
+    ) -> Self {
+        Self {
+            num_lookup_columns: all_stark.num_lookups_helper_columns(config),
+            num_ctl_helper_cols: num_ctl_helper_columns_by_table(
+                &all_stark.cross_table_lookups,
+                all_stark.arithmetic_stark.constraint_degree(),
+            ),
+        }
+    }
+}
+
+fn verify_proof_with_setup<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    all_stark: &AllStark<F, D>,
+    all_proof: &AllProof<F, C, D>,
+    config: &StarkConfig,
+    active_tables: &[bool; NUM_TABLES],
+    setup: &VerifierSetup,
+    challenger: Challenger<F, C::Hasher>,
+) -> Result<()> {
+    for table in Table::all() {
+        ensure!(
+            !active_tables[table as usize]
+                || !all_proof.stark_proofs[table as usize].proof.is_empty(),
+            "The {table:?} table is active but has an empty proof."
+        );
+    }
     let AllProofChallenges {
         stark_challenges,
         ctl_challenges,
     } = all_proof
-        .get_challenges(config)
+        .get_challenges(config, active_tables, challenger)
         .map_err(|_| anyhow::Error::msg("Invalid sampling of proof challenges."))?;
+    // The recursive wrappers of each table are proven with the CTL challenges of the proof, so
+    // they must be the ones the verifier derives.
+    ensure!(
+        all_proof.ctl_challenges == ctl_challenges,
+        "The CTL challenges of the proof don't match the ones derived from its commitments and \
+         public values."
+    );
 
-    let num_lookup_columns = all_stark.num_lookups_helper_columns(config);
-
-    let AllStark {
-        arithmetic_stark,
-        cpu_stark,
-        poseidon_stark,
-        poseidon_sponge_stark,
-        keccak_stark,
-        keccak_sponge_stark,
-        sha_extend_stark,
-        sha_extend_sponge_stark,
-        sha_compress_stark,
-        sha_compress_sponge_stark,
-        logic_stark,
-        memory_stark,
-        cross_table_lookups,
-    } = all_stark;
+    let VerifierSetup {
+        num_lookup_columns,
+        num_ctl_helper_cols,
+    } = setup;
 
-    let num_ctl_helper_cols = num_ctl_helper_columns_by_table(
+    let cross_table_lookups = &all_stark.cross_table_lookups;
+    let ctl_vars_per_table = CtlCheckVars::from_proofs(
+        &all_proof.stark_proofs,
+        active_tables,
         cross_table_lookups,
-        all_stark.arithmetic_stark.constraint_degree(),
+        &ctl_challenges,
+        num_lookup_columns,
+        num_ctl_helper_cols,
+    );
+
+    for table in Table::all() {
+        if let Some(challenges) = &stark_challenges[table as usize] {
+            verify_table_with_challenges(
+                all_stark,
+                table,
+                &all_proof.stark_proofs[table as usize].proof,
+                challenges,
+                &ctl_vars_per_table[table as usize],
+                &ctl_challenges,
+                config,
+            )?;
+        }
+    }
+    let ctl_zs_first = core::array::from_fn(|i| {
+        if active_tables[i] {
+            all_proof.stark_proofs[i]
+                .proof
+                .openings
+                .ctl_zs_first
+                .clone()
+        } else {
+            let num_ctl_zs = CrossTableLookup::num_ctl_zs(
+                cross_table_lookups,
+                Table::all()[i],
+                config.num_challenges,
+            );
+            vec![F::ZERO; num_ctl_zs]
+        }
+    });
+    verify_cross_table_lookups::<F, D>(cross_table_lookups, ctl_zs_first, config)
+}
+
+/// Verify the proof of a single table, as a proof of all tables would. The constraints of the
+/// table, including its side of the cross-table lookups, are checked against the CTL challenges
+/// of `all_proof`, but the CTL sums can't be checked without the other tables.
+///
+/// The challenges are derived as for a proof of all tables, e.g. from `prove`.
+pub(crate) fn verify_table_proof<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    all_stark: &AllStark<F, D>,
+    all_proof: &AllProof<F, C, D>,
+    table: Table,
+    config: &StarkConfig,
+    active_tables: &[bool; NUM_TABLES],
+) -> Result<()> {
+    ensure!(
+        active_tables[table as usize],
+        "The {table:?} table is inactive, so it has no proof to verify."
+    );
+    let AllProofChallenges {
+        stark_challenges,
+        ctl_challenges,
+    } = all_proof
+        .get_challenges(config, active_tables, Challenger::new())
+        .map_err(|_| anyhow::Error::msg("Invalid sampling of proof challenges."))?;
+    ensure!(
+        all_proof.ctl_challenges == ctl_challenges,
+        "The CTL challenges of the proof don't match the ones derived from its commitments and \
+         public values."
     );
 
+    let VerifierSetup {
+        num_lookup_columns,
+        num_ctl_helper_cols,
+    } = VerifierSetup::new(all_stark, config);
     let ctl_vars_per_table = CtlCheckVars::from_proofs(
         &all_proof.stark_proofs,
-        cross_table_lookups,
+        active_tables,
+        &all_stark.cross_table_lookups,
         &ctl_challenges,
         &num_lookup_columns,
         &num_ctl_helper_cols,
     );
-
-    verify_stark_proof_with_challenges(
-        arithmetic_stark,
-        &all_proof.stark_proofs[Table::Arithmetic as usize].proof,
-        &stark_challenges[Table::Arithmetic as usize],
-        &ctl_vars_per_table[Table::Arithmetic as usize],
-        &ctl_challenges,
-        config,
-    )?;
-
-    verify_stark_proof_with_challenges(
-        cpu_stark,
-        &all_proof.stark_proofs[Table::Cpu as usize].proof,
-        &stark_challenges[Table::Cpu as usize],
-        &ctl_vars_per_table[Table::Cpu as usize],
+    verify_table_with_challenges(
+        all_stark,
+        table,
+        &all_proof.stark_proofs[table as usize].proof,
+        stark_challenges[table as usize]
+            .as_ref()
+            .expect("Active tables have challenges"),
+        &ctl_vars_per_table[table as usize],
         &ctl_challenges,
         config,
-    )?;
-    verify_stark_proof_with_challenges(
-        poseidon_stark,
-        &all_proof.stark_proofs[Table::Poseidon as usize].proof,
-        &stark_challenges[Table::Poseidon as usize],
-        &ctl_vars_per_table[Table::Poseidon as usize],
-        &ctl_challenges,
-        config,
-    )?;
-    verify_stark_proof_with_challenges(
-        poseidon_sponge_stark,
-        &all_proof.stark_proofs[Table::PoseidonSponge as usize].proof,
-        &stark_challenges[Table::PoseidonSponge as usize],
-        &ctl_vars_per_table[Table::PoseidonSponge as usize],
-        &ctl_challenges,
-        config,
-    )?;
-    verify_stark_proof_with_challenges(
-        keccak_stark,
-        &all_proof.stark_proofs[Table::Keccak as usize].proof,
-        &stark_challenges[Table::Keccak as usize],
-        &ctl_vars_per_table[Table::Keccak as usize],
-        &ctl_challenges,
-        config,
-    )?;
-    verify_stark_proof_with_challenges(
-        keccak_sponge_stark,
-        &all_proof.stark_proofs[Table::KeccakSponge as usize].proof,
-        &stark_challenges[Table::KeccakSponge as usize],
-        &ctl_vars_per_table[Table::KeccakSponge as usize],
-        &ctl_challenges,
-        config,
-    )?;
-    verify_stark_proof_with_challenges(
-        sha_extend_stark,
-        &all_proof.stark_proofs[Table::ShaExtend as usize].proof,
-        &stark_challenges[Table::ShaExtend as usize],
-        &ctl_vars_per_table[Table::ShaExtend as usize],
-        &ctl_challenges,
-        config,
-    )?;
-    verify_stark_proof_with_challenges(
-        sha_extend_sponge_stark,
-        &all_proof.stark_proofs[Table::ShaExtendSponge as usize].proof,
-        &stark_challenges[Table::ShaExtendSponge as usize],
-        &ctl_vars_per_table[Table::ShaExtendSponge as usize],
-        &ctl_challenges,
-        config,
-    )?;
-    verify_stark_proof_with_challenges(
-        sha_compress_stark,
-        &all_proof.stark_proofs[Table::ShaCompress as usize].proof,
-        &stark_challenges[Table::ShaCompress as usize],
-        &ctl_vars_per_table[Table::ShaCompress as usize],
-        &ctl_challenges,
-        config,
-    )?;
-    verify_stark_proof_with_challenges(
-        sha_compress_sponge_stark,
-        &all_proof.stark_proofs[Table::ShaCompressSponge as usize].proof,
-        &stark_challenges[Table::ShaCompressSponge as usize],
-        &ctl_vars_per_table[Table::ShaCompressSponge as usize],
-        &ctl_challenges,
-        config,
-    )?;
-    verify_stark_proof_with_challenges(
-        logic_stark,
-        &all_proof.stark_proofs[Table::Logic as usize].proof,
-        &stark_challenges[Table::Logic as usize],
-        &ctl_vars_per_table[Table::Logic as usize],
-        &ctl_challenges,
-        config,
-    )?;
-    verify_stark_proof_with_challenges(
-        memory_stark,
-        &all_proof.stark_proofs[Table::Memory as usize].proof,
-        &stark_challenges[Table::Memory as usize],
-        &ctl_vars_per_table[Table::Memory as usize],
-        &ctl_challenges,
-        config,
-    )?;
-    verify_cross_table_lookups::<F, D>(
-        cross_table_lookups,
-        all_proof
-            .stark_proofs
-            .map(|p| p.proof.openings.ctl_zs_first),
-        config,
     )
 }
 
+/// Verify the STARK proof of `table` with the STARK of `all_stark` it was proven with.
+fn verify_table_with_challenges<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    all_stark: &AllStark<F, D>,
+    table: Table,
+    proof: &StarkProof<F, C, D>,
+    challenges: &StarkProofChallenges<F, D>,
+    ctl_vars: &[CtlCheckVars<F, F::Extension, F::Extension, D>],
+    ctl_challenges: &GrandProductChallengeSet<F>,
+    config: &StarkConfig,
+) -> Result<()> {
+    match table {
+        Table::Arithmetic => verify_stark_proof_with_challenges(
+            &all_stark.arithmetic_stark,
+            proof,
+            challenges,
+            ctl_vars,
+            ctl_challenges,
+            config,
+        ),
+        Table::Cpu => verify_stark_proof_with_challenges(
+            &all_stark.cpu_stark,
+            proof,
+            challenges,
+            ctl_vars,
+            ctl_challenges,
+            config,
+        ),
+        Table::Poseidon => verify_stark_proof_with_challenges(
+            &all_stark.poseidon_stark,
+            proof,
+            challenges,
+            ctl_vars,
+            ctl_challenges,
+            config,
+        ),
+        Table::PoseidonSponge => verify_stark_proof_with_challenges(
+            &all_stark.poseidon_sponge_stark,
+            proof,
+            challenges,
+            ctl_vars,
+            ctl_challenges,
+            config,
+        ),
+        Table::Keccak => verify_stark_proof_with_challenges(
+            &all_stark.keccak_stark,
+            proof,
+            challenges,
+            ctl_vars,
+            ctl_challenges,
+            config,
+        ),
+        Table::KeccakSponge => verify_stark_proof_with_challenges(
+            &all_stark.keccak_sponge_stark,
+            proof,
+            challenges,
+            ctl_vars,
+            ctl_challenges,
+            config,
+        ),
+        Table::ShaExtend => verify_stark_proof_with_challenges(
+            &all_stark.sha_extend_stark,
+            proof,
+            challenges,
+            ctl_vars,
+            ctl_challenges,
+            config,
+        ),
+        Table::ShaExtendSponge => verify_stark_proof_with_challenges(
+            &all_stark.sha_extend_sponge_stark,
+            proof,
+            challenges,
+            ctl_vars,
+            ctl_challenges,
+            config,
+        ),
+        Table::ShaCompress => verify_stark_proof_with_challenges(
+            &all_stark.sha_compress_stark,
+            proof,
+            challenges,
+            ctl_vars,
+            ctl_challenges,
+            config,
+        ),
+        Table::ShaCompressSponge => verify_stark_proof_with_challenges(
+            &all_stark.sha_compress_sponge_stark,
+            proof,
+            challenges,
+            ctl_vars,
+            ctl_challenges,
+            config,
+        ),
+        Table::Logic => verify_stark_proof_with_challenges(
+            &all_stark.logic_stark,
+            proof,
+            challenges,
+            ctl_vars,
+            ctl_challenges,
+            config,
+        ),
+        Table::Memory => verify_stark_proof_with_challenges(
+            &all_stark.memory_stark,
+            proof,
+            challenges,
+            ctl_vars,
+            ctl_challenges,
+            config,
+        ),
+    }
+}
+
 pub(crate) fn verify_stark_proof_with_challenges<
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
@@ -355,11 +566,20 @@ fn eval_l_0_and_l_last<F: Field>(log_n: usize, x: F) -> (F, F) {
 
 #[cfg(test)]
 mod tests {
+    use plonky2::field::extension::Extendable;
     use plonky2::field::goldilocks_field::GoldilocksField;
     use plonky2::field::polynomial::PolynomialValues;
-    use plonky2::field::types::Sample;
+    use plonky2::field::types::{Field, Sample};
 
-    use crate::verifier::eval_l_0_and_l_last;
+    use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use plonky2::util::timing::TimingTree;
+
+    use crate::all_stark::{AllStark, Table, ALL_TABLES_ACTIVE};
+    use crate::config::StarkConfig;
+    use crate::generation::generate_traces;
+    use crate::prover::prove_with_traces;
+    use crate::prover::testutils::{exit_kernel, prove_exit_kernel};
+    use crate::verifier::{eval_l_0_and_l_last, verify_proof, verify_proof_with_active_tables};
 
     #[test]
     fn test_eval_l_0_and_l_last() {
@@ -375,4 +595,86 @@ mod tests {
         assert_eq!(l_first_x, expected_l_first_x);
         assert_eq!(l_last_x, expected_l_last_x);
     }
+
+    #[test]
+    fn test_mismatched_ctl_challenges() -> anyhow::Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let (all_stark, config, proof) = prove_exit_kernel::<F, C, D>()?;
+        verify_proof(&all_stark, proof.clone(), &config)?;
+
+        let mut proof = proof;
+        proof.ctl_challenges.challenges[1].gamma += F::ONE;
+        let err = verify_proof(&all_stark, proof, &config).unwrap_err();
+        assert!(err.to_string().contains("CTL challenges"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_table() -> anyhow::Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let (all_stark, config, mut proof) = prove_exit_kernel::<F, C, D>()?;
+        for table in Table::all() {
+            proof.verify_table(&all_stark, table, &config, &ALL_TABLES_ACTIVE)?;
+        }
+
+        // Corrupting an opening of the CPU proof only breaks the CPU table.
+        proof.stark_proofs[Table::Cpu as usize]
+            .proof
+            .openings
+            .local_values[0] += <F as Extendable<D>>::Extension::ONE;
+        for table in Table::all() {
+            let res = proof.verify_table(&all_stark, table, &config, &ALL_TABLES_ACTIVE);
+            assert_eq!(res.is_err(), table == Table::Cpu, "{table:?}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_with_inactive_tables() -> anyhow::Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let kernel = exit_kernel()?;
+        let all_stark = AllStark::<F, D>::default();
+        let config = StarkConfig::standard_fast_config();
+        let mut timing = TimingTree::new("prove", log::Level::Debug);
+        let (traces, public_values, _) =
+            generate_traces::<F, C, D>(&all_stark, &kernel, &config, &mut timing)?;
+        let mut active_tables = [true; 12];
+        active_tables[Table::ShaCompress as usize] = false;
+        active_tables[Table::ShaCompressSponge as usize] = false;
+        let proof = prove_with_traces::<F, C, D>(
+            &all_stark,
+            &config,
+            traces,
+            public_values,
+            &active_tables,
+            &mut timing,
+        )?;
+
+        // Inactive tables aren't proven.
+        for table in Table::all() {
+            assert_eq!(
+                proof.stark_proofs[table as usize].proof.is_empty(),
+                !active_tables[table as usize],
+                "{table:?}"
+            );
+        }
+        verify_proof_with_active_tables(&all_stark, proof.clone(), &config, &active_tables)?;
+        for table in Table::all() {
+            let res = proof.verify_table(&all_stark, table, &config, &active_tables);
+            assert_eq!(res.is_ok(), active_tables[table as usize], "{table:?}");
+        }
+
+        let err = verify_proof(&all_stark, proof, &config).unwrap_err();
+        assert!(err.to_string().contains("empty proof"), "{err}");
+        Ok(())
+    }
 }
@@ -18,6 +18,8 @@ pub enum ProgramError {
     IntegerTooLarge,
     ProverInputError(ProverInputError),
     UnknownContractCode,
+    /// A precompile (Keccak, SHA extend or compress) failed to generate its rows.
+    PrecompileError(anyhow::Error),
 }
 
 #[allow(clippy::enum_variant_names)]
@@ -26,6 +28,7 @@ pub enum MemoryError {
     ContextTooLarge { context: u32 },
     SegmentTooLarge { segment: u32 },
     VirtTooLarge { virt: u32 },
+    UnalignedAccess { virt: u32 },
 }
 
 #[derive(Debug)]
@@ -1,4 +1,6 @@
 use crate::cpu::membus::{NUM_CHANNELS, NUM_GP_CHANNELS};
+use anyhow::{anyhow, Result};
+use hashbrown::HashMap;
 use itertools::Itertools;
 
 #[derive(Clone, Copy, Debug)]
@@ -26,22 +28,54 @@ impl MemoryChannel {
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
 pub struct MemoryAddress {
     pub(crate) context: usize,
-    pub(crate) segment: usize,
+    pub(crate) segment: u32,
     pub(crate) virt: usize,
 }
 
 impl MemoryAddress {
-    pub(crate) fn new(context: usize, segment: Segment, virt: usize) -> Self {
+    /// `segment` is either a built-in `Segment` or a custom numeric identifier.
+    pub fn new(context: usize, segment: impl Into<u32>, virt: usize) -> Self {
         Self {
             context,
-            segment: segment as usize,
+            segment: segment.into(),
             virt,
         }
     }
 
+    pub fn context(&self) -> usize {
+        self.context
+    }
+
+    /// The numeric identifier of the segment, e.g. `Segment::CODE` or a custom one.
+    pub fn segment(&self) -> u32 {
+        self.segment
+    }
+
+    pub fn virt(&self) -> usize {
+        self.virt
+    }
+
     pub(crate) fn increment(&mut self) {
         self.virt = self.virt.saturating_add(4);
     }
+
+    /// Returns this address advanced by `bytes`, or an error if it leaves the 32-bit address
+    /// space instead of wrapping around.
+    pub(crate) fn offset(&self, bytes: u32) -> Result<Self> {
+        let virt = u32::try_from(self.virt)
+            .ok()
+            .and_then(|virt| virt.checked_add(bytes))
+            .ok_or_else(|| {
+                anyhow!(
+                    "Address {:#x} + {bytes:#x} overflows the address space",
+                    self.virt
+                )
+            })?;
+        Ok(Self {
+            virt: virt as usize,
+            ..*self
+        })
+    }
 }
 
 ///
@@ -106,7 +140,7 @@ impl MemoryOp {
     pub(crate) fn sorting_key(&self) -> (usize, usize, usize, usize) {
         (
             self.address.context,
-            self.address.segment,
+            self.address.segment as usize,
             self.address.virt,
             self.timestamp,
         )
@@ -123,10 +157,10 @@ impl MemoryState {
     pub fn new(kernel_code: &[u8]) -> Self {
         let code_u32s = kernel_code.iter().map(|&x| x.into()).collect();
         let mut result = Self::default();
-        result.contexts[0].segments[Segment::Code as usize].content = code_u32s;
+        result.contexts[0].segment_mut(Segment::CODE).content = code_u32s;
 
         let shift_u32s = (0..32).map(|i| (1u32 << i).to_be()).collect_vec();
-        result.contexts[0].segments[Segment::ShiftTable as usize].content = shift_u32s;
+        result.contexts[0].segment_mut(Segment::SHIFT_TABLE).content = shift_u32s;
 
         result
     }
@@ -150,8 +184,9 @@ impl MemoryState {
             return 0;
         }
 
-        let _segment = Segment::all()[address.segment];
-        let val = self.contexts[address.context].segments[address.segment].get(address.virt);
+        let val = self.contexts[address.context]
+            .segment(address.segment)
+            .map_or(0, |segment| segment.get(address.virt));
         log::trace!("read mem {:X} : {:X} ({})", address.virt, val, val);
         /*
         assert!(
@@ -170,8 +205,9 @@ impl MemoryState {
             self.contexts.push(MemoryContextState::default());
         }
 
-        let _segment = Segment::all()[address.segment];
-        self.contexts[address.context].segments[address.segment].set(address.virt, val);
+        self.contexts[address.context]
+            .segment_mut(address.segment)
+            .set(address.virt, val);
     }
 }
 
@@ -184,17 +220,19 @@ impl Default for MemoryState {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub(crate) struct MemoryContextState {
-    /// The content of each memory segment.
-    pub(crate) segments: [MemorySegmentState; Segment::COUNT],
+    /// The content of each memory segment, keyed by segment identifier.
+    pub(crate) segments: HashMap<u32, MemorySegmentState>,
 }
 
-impl Default for MemoryContextState {
-    fn default() -> Self {
-        Self {
-            segments: std::array::from_fn(|_| MemorySegmentState::default()),
-        }
+impl MemoryContextState {
+    pub(crate) fn segment(&self, segment: u32) -> Option<&MemorySegmentState> {
+        self.segments.get(&segment)
+    }
+
+    pub(crate) fn segment_mut(&mut self, segment: u32) -> &mut MemorySegmentState {
+        self.segments.entry(segment).or_default()
     }
 }
 
@@ -215,3 +253,33 @@ impl MemorySegmentState {
         self.content[virtual_addr] = value;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset() {
+        let address = MemoryAddress::new(0, Segment::Code, 0x1000);
+        assert_eq!(address.offset(36).unwrap().virt, 0x1024);
+
+        let address = MemoryAddress::new(0, Segment::Code, u32::MAX as usize - 60);
+        assert_eq!(address.offset(60).unwrap().virt, u32::MAX as usize);
+        assert!(address.offset(64).is_err());
+        assert!(MemoryAddress::new(0, Segment::Code, u32::MAX as usize + 1)
+            .offset(0)
+            .is_err());
+    }
+
+    #[test]
+    fn test_custom_segment() {
+        let address = MemoryAddress::new(1, Segment::FIRST_CUSTOM + 1, 0x20);
+        assert_eq!(address.context(), 1);
+        assert_eq!(address.segment(), Segment::FIRST_CUSTOM + 1);
+        assert_eq!(address.virt(), 0x20);
+        assert_eq!(
+            MemoryAddress::new(0, Segment::Code, 0).segment(),
+            Segment::CODE
+        );
+    }
+}
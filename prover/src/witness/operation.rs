@@ -1,13 +1,17 @@
 use super::util::*;
+use crate::all_stark::Opcode;
 use crate::cpu::columns::CpuColumnsView;
+use crate::cpu::keccak_sponge::{DIGEST_PTR_CHANNEL, DIGEST_PTR_REG};
 use crate::cpu::kernel::assembler::Kernel;
+use crate::cpu::memio::AlignmentMode;
+use crate::cpu::sha_extend_sponge::{STRIDE_CHANNEL, STRIDE_REG};
 use crate::generation::state::GenerationState;
 use crate::memory::segments::Segment;
-use crate::witness::errors::ProgramError;
+use crate::witness::errors::{MemoryError, ProgramError};
 use crate::witness::memory::MemoryAddress;
 use crate::{arithmetic, logic};
 
-use anyhow::{Context, Result};
+use anyhow::{ensure, Context, Result};
 
 use plonky2::field::types::Field;
 
@@ -20,6 +24,7 @@ use plonky2::field::extension::Extendable;
 use plonky2::hash::hash_types::RichField;
 use plonky2::plonk::config::GenericConfig;
 use std::fs;
+use zkm_emulator::syscall;
 
 pub const WORD_SIZE: usize = core::mem::size_of::<u32>();
 
@@ -70,24 +75,24 @@ pub fn generate_pinv_diff<F: Field>(val0: u32, val1: u32, lv: &mut CpuColumnsVie
     logic.diff_pinv = (val0_f - val1_f).try_inverse().unwrap_or(F::ZERO) * num_unequal_limbs_inv;
 }
 
-pub(crate) const SYSSHAEXTEND: usize = 0x00300105;
-pub(crate) const SYSSHACOMPRESS: usize = 0x00010106;
-pub(crate) const SYSKECCAK: usize = 0x010109;
-pub(crate) const SYSGETPID: usize = 4020;
+pub(crate) const SYSSHAEXTEND: usize = syscall::SYS_SHA_EXTEND as usize;
+pub(crate) const SYSSHACOMPRESS: usize = syscall::SYS_SHA_COMPRESS as usize;
+pub(crate) const SYSKECCAK: usize = syscall::SYS_KECCAK as usize;
+pub(crate) const SYSGETPID: usize = syscall::SYS_GETPID as usize;
 pub(crate) const SYSGETGID: usize = 4047;
-pub(crate) const SYSMMAP2: usize = 4210;
-pub(crate) const SYSMMAP: usize = 4090;
-pub(crate) const SYSBRK: usize = 4045;
-pub(crate) const SYSCLONE: usize = 4120;
-pub(crate) const SYSEXITGROUP: usize = 4246;
-pub(crate) const SYSREAD: usize = 4003;
-pub(crate) const SYSWRITE: usize = 4004;
-pub(crate) const SYSFCNTL: usize = 4055;
-pub(crate) const SYSSETTHREADAREA: usize = 4283;
-
-pub(crate) const SYSHINTLEN: usize = 240;
-pub(crate) const SYSHINTREAD: usize = 241;
-pub(crate) const SYSVERIFY: usize = 242;
+pub(crate) const SYSMMAP2: usize = syscall::SYS_MMAP2 as usize;
+pub(crate) const SYSMMAP: usize = syscall::SYS_MMAP as usize;
+pub(crate) const SYSBRK: usize = syscall::SYS_BRK as usize;
+pub(crate) const SYSCLONE: usize = syscall::SYS_CLONE as usize;
+pub(crate) const SYSEXITGROUP: usize = syscall::SYS_EXIT_GROUP as usize;
+pub(crate) const SYSREAD: usize = syscall::SYS_READ as usize;
+pub(crate) const SYSWRITE: usize = syscall::SYS_WRITE as usize;
+pub(crate) const SYSFCNTL: usize = syscall::SYS_FCNTL as usize;
+pub(crate) const SYSSETTHREADAREA: usize = syscall::SYS_SET_THREAD_AREA as usize;
+
+pub(crate) const SYSHINTLEN: usize = syscall::SYS_HINT_LEN as usize;
+pub(crate) const SYSHINTREAD: usize = syscall::SYS_HINT_READ as usize;
+pub(crate) const SYSVERIFY: usize = syscall::SYS_VERIFY as usize;
 
 pub(crate) const FD_STDIN: usize = 0;
 pub(crate) const FD_STDOUT: usize = 1;
@@ -146,6 +151,52 @@ pub(crate) enum Operation {
     Teq(u8, u8),
 }
 
+impl Operation {
+    /// The mnemonic of the instruction this operation was decoded from.
+    pub(crate) fn opcode(&self) -> Opcode {
+        let mnemonic = match self {
+            Operation::Syscall => "SYSCALL".into(),
+            Operation::BinaryLogic(op, ..) => format!("{op:?}").to_uppercase(),
+            Operation::BinaryLogicImm(op, ..) => format!("{op:?}I").to_uppercase(),
+            Operation::BinaryArithmetic(op, ..) | Operation::BinaryArithmeticImm(op, ..) => {
+                format!("{op:?}")
+            }
+            Operation::Count(is_clo, ..) => if *is_clo { "CLO" } else { "CLZ" }.into(),
+            Operation::CondMov(MovCond::EQ, ..) => "MOVZ".into(),
+            Operation::CondMov(MovCond::NE, ..) => "MOVN".into(),
+            Operation::KeccakGeneral => "KECCAK".into(),
+            Operation::Jump(link, _) => if *link == 0 { "JR" } else { "JALR" }.into(),
+            Operation::Jumpi(link, _) => if *link == 0 { "J" } else { "JAL" }.into(),
+            Operation::Branch(cond, ..) => match cond {
+                BranchCond::EQ => "BEQ",
+                BranchCond::NE => "BNE",
+                BranchCond::GE => "BGEZ",
+                BranchCond::LE => "BLEZ",
+                BranchCond::GT => "BGTZ",
+                BranchCond::LT => "BLTZ",
+            }
+            .into(),
+            Operation::JumpDirect(..) => "BAL".into(),
+            Operation::Pc => "PC".into(),
+            Operation::GetContext => "GET_CONTEXT".into(),
+            Operation::SetContext => "SET_CONTEXT".into(),
+            Operation::MloadGeneral(op, ..) | Operation::MstoreGeneral(op, ..) => {
+                format!("{op:?}")
+            }
+            Operation::Nop => "NOP".into(),
+            Operation::Ext(..) => "EXT".into(),
+            Operation::Ins(..) => "INS".into(),
+            Operation::Maddu(..) => "MADDU".into(),
+            Operation::Ror(..) => "ROTR".into(),
+            Operation::Rdhwr(..) => "RDHWR".into(),
+            Operation::Signext(_, _, bits) => if *bits == 8 { "SEB" } else { "SEH" }.into(),
+            Operation::SwapHalf(..) => "WSBH".into(),
+            Operation::Teq(..) => "TEQ".into(),
+        };
+        Opcode(mnemonic)
+    }
+}
+
 pub(crate) fn generate_cond_mov_op<
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
@@ -1014,7 +1065,7 @@ pub(crate) fn verify<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, c
     // Mark the assumption as accessed, pushing it to the head of the list, and return the success code.
     match assumption {
         Some(assumpt) => {
-            state.assumptions_used.borrow_mut().insert(0, assumpt);
+            state.assumptions_used.lock().unwrap().insert(0, assumpt);
         }
         None => panic!("Assumption Not Found"),
     }
@@ -1106,7 +1157,6 @@ pub(crate) fn generate_keccak<
     state: &mut GenerationState<F, C, D>,
     addr: usize,
     len: usize,
-    ptr: usize,
 ) -> Result<()> {
     let mut map_addr = addr;
     let mut cpu_row = CpuColumnsView::default();
@@ -1162,6 +1212,11 @@ pub(crate) fn generate_keccak<
     cpu_row.general.khash_mut().value = hash_data.map(F::from_canonical_u32);
     cpu_row.general.khash_mut().value.reverse();
 
+    // The digest pointer is read again on the sponge row, which binds the writes below to it.
+    let (ptr, ptr_op) = reg_read_with_log(DIGEST_PTR_REG, DIGEST_PTR_CHANNEL, state, &mut cpu_row)
+        .map_err(|err| anyhow::anyhow!("{err:?}"))?;
+    state.traces.push_memory(ptr_op);
+
     keccak_sponge_log(state, keccak_data_addr, keccak_value_byte_be);
     state.traces.push_cpu(cpu_row);
 
@@ -1189,7 +1244,17 @@ pub(crate) fn generate_sha_extend<
     w_ptr: usize,
     a1: usize,
 ) -> Result<()> {
-    assert!(a1 == 0, "arg2 must be 0");
+    // arg2 is the distance in bytes between consecutive words of `w`, 0 meaning contiguous.
+    let stride = if a1 == 0 { 4 } else { a1 };
+    ensure!(
+        stride % 4 == 0,
+        "SHA extend stride {stride} is not a multiple of 4"
+    );
+    let w_base = MemoryAddress::new(0, Segment::Code, w_ptr);
+    let w_address = |i: usize| -> Result<MemoryAddress> {
+        let bytes = u32::try_from(i * stride).context("SHA extend offset overflows")?;
+        w_base.offset(bytes)
+    };
 
     for i in 16..64 {
         let mut cpu_row = CpuColumnsView::default();
@@ -1197,7 +1262,7 @@ pub(crate) fn generate_sha_extend<
         let mut input_addresses = vec![];
         // let mut input_value_bit_be = vec![];
         let mut input_le_bytes = vec![];
-        let addr = MemoryAddress::new(0, Segment::Code, w_ptr + (i - 15) * 4);
+        let addr = w_address(i - 15)?;
         let (w_i_minus_15, mem_op) = mem_read_gp_with_log_and_fill(0, addr, state, &mut cpu_row);
         state.traces.push_memory(mem_op);
         input_addresses.push(addr);
@@ -1213,7 +1278,7 @@ pub(crate) fn generate_sha_extend<
         xor_logic_log(state, s0_inter, w_i_minus_15 >> 3);
 
         // Read w[i-2].
-        let addr = MemoryAddress::new(0, Segment::Code, w_ptr + (i - 2) * 4);
+        let addr = w_address(i - 2)?;
         let (w_i_minus_2, mem_op) = mem_read_gp_with_log_and_fill(1, addr, state, &mut cpu_row);
         state.traces.push_memory(mem_op);
         input_addresses.push(addr);
@@ -1230,7 +1295,7 @@ pub(crate) fn generate_sha_extend<
         xor_logic_log(state, s1_inter, w_i_minus_2 >> 10);
 
         // Read w[i-16].
-        let addr = MemoryAddress::new(0, Segment::Code, w_ptr + (i - 16) * 4);
+        let addr = w_address(i - 16)?;
         let (w_i_minus_16, mem_op) = mem_read_gp_with_log_and_fill(2, addr, state, &mut cpu_row);
         state.traces.push_memory(mem_op);
         input_addresses.push(addr);
@@ -1238,7 +1303,7 @@ pub(crate) fn generate_sha_extend<
         input_le_bytes.push(w_i_minus_16.to_le_bytes());
 
         // Read w[i-7].
-        let addr = MemoryAddress::new(0, Segment::Code, w_ptr + (i - 7) * 4);
+        let addr = w_address(i - 7)?;
         let (w_i_minus_7, mem_op) = mem_read_gp_with_log_and_fill(3, addr, state, &mut cpu_row);
         state.traces.push_memory(mem_op);
         input_addresses.push(addr);
@@ -1260,8 +1325,8 @@ pub(crate) fn generate_sha_extend<
             w_i_minus_15,
             w_i_minus_2
         );
-        let addr = MemoryAddress::new(0, Segment::Code, w_ptr + i * 4);
-        log::debug!("extend write {:X} {:X}", w_ptr + i * 4, w_i);
+        let addr = w_address(i)?;
+        log::debug!("extend write {:X} {:X}", addr.virt, w_i);
         let mem_op = mem_write_gp_log_and_fill(4, addr, state, &mut cpu_row, w_i);
 
         state.traces.push_memory(mem_op);
@@ -1277,7 +1342,20 @@ pub(crate) fn generate_sha_extend<
         cpu_row.mem_channels[1].value = F::from_canonical_usize(Segment::Code as usize);
         cpu_row.mem_channels[2].value = F::from_canonical_usize(addr.virt);
         cpu_row.general.element_mut().value = F::from_canonical_u32(w_i);
-        sha_extend_sponge_log(state, input_addresses, input_le_bytes, addr, i - 16);
+        // The stride argument is read again on each sponge row, which binds the sponge's stride
+        // to it.
+        let (stride_arg, stride_op) =
+            reg_read_with_log(STRIDE_REG, STRIDE_CHANNEL, state, &mut cpu_row)
+                .map_err(|err| anyhow::anyhow!("{err:?}"))?;
+        state.traces.push_memory(stride_op);
+        sha_extend_sponge_log(
+            state,
+            input_addresses,
+            input_le_bytes,
+            addr,
+            i - 16,
+            stride_arg as u32,
+        );
         state.traces.push_cpu(cpu_row);
     }
 
@@ -1313,9 +1391,11 @@ pub(crate) fn generate_sha_compress<
     let mut w_i_values = vec![];
     let mut w_i_addresses = vec![];
     let mut state_values = vec![];
+    let h_base = MemoryAddress::new(0, Segment::Code, h_ptr);
+    let w_base = MemoryAddress::new(0, Segment::Code, w_ptr);
 
     for i in 0..8 {
-        let addr = MemoryAddress::new(0, Segment::Code, h_ptr + i * 4);
+        let addr = h_base.offset(i as u32 * 4)?;
         let (value, mem_op) = mem_read_gp_with_log_and_fill(i, addr, state, &mut cpu_row);
         state.traces.push_memory(mem_op);
         hx[i] = value;
@@ -1357,7 +1437,7 @@ pub(crate) fn generate_sha_compress<
             j = 0;
         }
 
-        let addr = MemoryAddress::new(0, Segment::Code, w_ptr + i * 4);
+        let addr = w_base.offset(i as u32 * 4)?;
         let (w_i, mem_op) = mem_read_gp_with_log_and_fill(j, addr, state, &mut cpu_row);
         state.traces.push_memory(mem_op);
         j += 1;
@@ -1447,11 +1527,11 @@ pub(crate) fn generate_sha_compress<
     let mut cpu_row = CpuColumnsView::default();
     cpu_row.clock = F::from_canonical_usize(state.traces.clock());
     for i in 0..8 {
-        let addr = MemoryAddress::new(0, Segment::Code, h_ptr + i * 4);
+        let addr = h_base.offset(i as u32 * 4)?;
         let mem_op =
             mem_write_gp_log_and_fill(i, addr, state, &mut cpu_row, hx[i].wrapping_add(v[i]));
         state.traces.push_memory(mem_op);
-        log::debug!("write {:X} {:X}", h_ptr + i * 4, hx[i].wrapping_add(v[i]));
+        log::debug!("write {:X} {:X}", addr.virt, hx[i].wrapping_add(v[i]));
     }
     state.traces.push_cpu(cpu_row);
     Ok(())
@@ -1645,6 +1725,11 @@ pub(crate) fn generate_syscall<
         }
         _ => {
             row.general.syscall_mut().sysnum[11] = F::ONE;
+            if let Some(handler) = kernel.syscall_handlers.get(sys_num as u32) {
+                let [r0, r1] = handler.handle([a0 as u32, a1 as u32, a2 as u32]);
+                v0 = r0 as usize;
+                v1 = r1 as usize;
+            }
             Ok(())
         }
     };
@@ -1672,17 +1757,33 @@ pub(crate) fn generate_syscall<
         let _ = commit(state, a1, a2);
     }
     if is_keccak {
-        let _ = generate_keccak(state, a0, a1, a2);
+        generate_keccak(state, a0, a1).map_err(ProgramError::PrecompileError)?;
     }
     if is_sha_compress {
-        let _ = generate_sha_compress(state, a0, a1);
+        generate_sha_compress(state, a0, a1).map_err(ProgramError::PrecompileError)?;
     }
     if is_sha_extend {
-        let _ = generate_sha_extend(state, a0, a1);
+        generate_sha_extend(state, a0, a1).map_err(ProgramError::PrecompileError)?;
     }
     result
 }
 
+/// Fails if `op` accesses `virt` at an address which is not a multiple of its size while
+/// alignment is enforced.
+fn check_alignment(alignment: AlignmentMode, op: MemOp, virt: u32) -> Result<(), ProgramError> {
+    let mask = match op {
+        MemOp::LW | MemOp::LL | MemOp::SW | MemOp::SC => 3,
+        MemOp::LH | MemOp::LHU | MemOp::SH => 1,
+        _ => 0,
+    };
+    if alignment == AlignmentMode::Enforced && virt & mask != 0 {
+        return Err(ProgramError::MemoryError(MemoryError::UnalignedAccess {
+            virt,
+        }));
+    }
+    Ok(())
+}
+
 pub(crate) fn generate_mload_general<
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
@@ -1699,6 +1800,7 @@ pub(crate) fn generate_mload_general<
     let (rt, log_in2) = reg_read_with_log(rt_reg, 1, state, &mut row)?;
 
     let virt_raw = (rs as u32).wrapping_add(sign_extend::<16>(offset));
+    check_alignment(state.alignment, op, virt_raw)?;
     let virt = virt_raw & 0xFFFF_FFFC;
     let address = MemoryAddress::new(0, Segment::Code, virt as usize);
     let (mem, log_in3) = mem_read_gp_with_log_and_fill(2, address, state, &mut row);
@@ -1817,6 +1919,7 @@ pub(crate) fn generate_mstore_general<
     let (rt, log_in2) = reg_read_with_log(rt_reg, 1, state, &mut row)?;
 
     let virt_raw = (rs as u32).wrapping_add(sign_extend::<16>(offset));
+    check_alignment(state.alignment, op, virt_raw)?;
     let virt = virt_raw & 0xFFFF_FFFC;
     let address = MemoryAddress::new(0, Segment::Code, virt as usize);
     let (mem, log_in3) = mem_read_gp_with_log_and_fill(2, address, state, &mut row);
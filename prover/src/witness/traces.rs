@@ -6,8 +6,9 @@ use plonky2::timed;
 use plonky2::util::timing::TimingTree;
 use plonky2_maybe_rayon::rayon;
 use std::cmp::max;
+use std::collections::BTreeMap;
 
-use crate::all_stark::{AllStark, MIN_TRACE_LEN, NUM_TABLES};
+use crate::all_stark::{AllStark, Opcode, MIN_TRACE_LEN, NUM_TABLES};
 use crate::arithmetic::{BinaryOperator, Operation};
 use crate::config::StarkConfig;
 use crate::cpu::columns::CpuColumnsView;
@@ -59,6 +60,9 @@ pub(crate) struct Traces<T: Copy> {
     pub(crate) sha_compress_inputs:
         Vec<([u8; sha_compress_stark::NUM_INPUTS], MemoryAddress, usize)>,
     pub(crate) sha_compress_sponge_ops: Vec<ShaCompressSpongeOp>,
+    /// The number of instructions executed, by opcode. Only counted if set, see
+    /// `AllStark::opcode_histogram`, as each count costs an allocation.
+    pub(crate) opcode_counts: Option<BTreeMap<Opcode, usize>>,
 }
 
 impl<T: Copy> Traces<T> {
@@ -76,6 +80,7 @@ impl<T: Copy> Traces<T> {
             sha_extend_sponge_ops: vec![],
             sha_compress_inputs: vec![],
             sha_compress_sponge_ops: vec![],
+            opcode_counts: None,
         }
     }
 
@@ -232,7 +237,7 @@ impl<T: Copy> Traces<T> {
         all_stark: &AllStark<T, D>,
         config: &StarkConfig,
         timing: &mut TimingTree,
-    ) -> [Vec<PolynomialValues<T>>; NUM_TABLES]
+    ) -> anyhow::Result<[Vec<PolynomialValues<T>>; NUM_TABLES]>
     where
         T: RichField + Extendable<D>,
     {
@@ -251,6 +256,7 @@ impl<T: Copy> Traces<T> {
             sha_extend_sponge_ops,
             sha_compress_inputs,
             sha_compress_sponge_ops,
+            opcode_counts: _,
         } = self;
 
         let mut memory_trace = vec![];
@@ -262,7 +268,7 @@ impl<T: Copy> Traces<T> {
         let mut keccak_sponge_trace = vec![];
         let mut logic_trace = vec![];
         let mut sha_extend_trace = vec![];
-        let mut sha_extend_sponge_trace = vec![];
+        let mut sha_extend_sponge_trace = Ok(vec![]);
         let mut sha_compress_trace = vec![];
         let mut sha_compress_sponge_trace = vec![];
         timed!(
@@ -301,7 +307,7 @@ impl<T: Copy> Traces<T> {
             )
         );
 
-        [
+        Ok([
             arithmetic_trace,
             cpu_trace,
             poseidon_trace,
@@ -309,12 +315,12 @@ impl<T: Copy> Traces<T> {
             keccak_trace,
             keccak_sponge_trace,
             sha_extend_trace,
-            sha_extend_sponge_trace,
+            sha_extend_sponge_trace?,
             sha_compress_trace,
             sha_compress_sponge_trace,
             logic_trace,
             memory_trace,
-        ]
+        ])
     }
 }
 
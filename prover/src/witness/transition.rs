@@ -327,6 +327,10 @@ fn fill_op_flag<F: Field>(op: Operation, row: &mut CpuColumnsView<F>) {
         Operation::BinaryArithmetic(arithmetic::BinaryOperator::SLLV, ..)
         | Operation::BinaryArithmetic(arithmetic::BinaryOperator::SRLV, ..)
         | Operation::BinaryArithmetic(arithmetic::BinaryOperator::SRAV, ..) => &mut flags.shift,
+        Operation::BinaryArithmetic(arithmetic::BinaryOperator::MULT, ..)
+        | Operation::BinaryArithmetic(arithmetic::BinaryOperator::MULTU, ..)
+        | Operation::BinaryArithmetic(arithmetic::BinaryOperator::DIV, ..)
+        | Operation::BinaryArithmetic(arithmetic::BinaryOperator::DIVU, ..) => &mut flags.hilo_op,
         Operation::BinaryArithmetic(..) => &mut flags.binary_op,
         Operation::BinaryArithmeticImm(..) => &mut flags.binary_imm_op,
         Operation::KeccakGeneral => &mut flags.keccak_general,
@@ -553,7 +557,11 @@ fn try_perform_instruction<
     }
     */
 
-    perform_op(state, op, row, kernel)
+    perform_op(state, op, row, kernel)?;
+    if let Some(opcode_counts) = &mut state.traces.opcode_counts {
+        *opcode_counts.entry(op.opcode()).or_default() += 1;
+    }
+    Ok(())
 }
 
 fn log_kernel_instruction<
@@ -644,7 +652,9 @@ pub(crate) fn transition<
                     offset_name,
                     //state.stack(),
                     0,
-                    state.memory.contexts[0].segments[Segment::KernelGeneral as usize].content,
+                    state.memory.contexts[0]
+                        .segment(Segment::KERNEL_GENERAL)
+                        .map(|segment| &segment.content),
                 );
             }
             state.rollback(checkpoint);
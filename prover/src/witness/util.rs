@@ -87,7 +87,7 @@ pub(crate) fn mem_read_code_with_log_and_fill<
     channel.used = F::ONE;
     channel.is_read = F::ONE;
     channel.addr_context = F::from_canonical_usize(address.context);
-    channel.addr_segment = F::from_canonical_usize(address.segment);
+    channel.addr_segment = F::from_canonical_u32(address.segment);
     channel.addr_virtual = F::from_canonical_usize(address.virt);
     channel.value = F::from_canonical_u32(val);
 
@@ -151,7 +151,7 @@ pub(crate) fn reg_read_with_log<
     channel.used = F::ONE;
     channel.is_read = F::ONE;
     channel.addr_context = F::from_canonical_usize(address.context);
-    channel.addr_segment = F::from_canonical_usize(address.segment);
+    channel.addr_segment = F::from_canonical_u32(address.segment);
     channel.addr_virtual = F::from_canonical_usize(address.virt);
     channel.value = F::from_canonical_u32(result as u32);
 
@@ -217,7 +217,7 @@ pub(crate) fn reg_write_with_log<
     channel.used = used;
     channel.is_read = F::ZERO;
     channel.addr_context = F::from_canonical_usize(address.context);
-    channel.addr_segment = F::from_canonical_usize(address.segment);
+    channel.addr_segment = F::from_canonical_u32(address.segment);
     channel.addr_virtual = F::from_canonical_usize(address.virt);
     channel.value = F::from_canonical_u32(value as u32);
     Ok(op)
@@ -249,7 +249,7 @@ pub(crate) fn reg_zero_write_with_log<
     channel.used = F::ZERO;
     channel.is_read = F::ZERO;
     channel.addr_context = F::from_canonical_usize(address.context);
-    channel.addr_segment = F::from_canonical_usize(address.segment);
+    channel.addr_segment = F::from_canonical_u32(address.segment);
     channel.addr_virtual = F::from_canonical_usize(address.virt);
     channel.value = F::from_canonical_u32(value as u32);
     op
@@ -318,7 +318,7 @@ pub(crate) fn mem_read_gp_with_log_and_fill<
     channel.used = F::ONE;
     channel.is_read = F::ONE;
     channel.addr_context = F::from_canonical_usize(address.context);
-    channel.addr_segment = F::from_canonical_usize(address.segment);
+    channel.addr_segment = F::from_canonical_u32(address.segment);
     channel.addr_virtual = F::from_canonical_usize(address.virt);
     channel.value = F::from_canonical_u32(val);
     (val, op)
@@ -342,7 +342,7 @@ pub(crate) fn mem_write_gp_log_and_fill<
     channel.used = F::ONE;
     channel.is_read = F::ZERO;
     channel.addr_context = F::from_canonical_usize(address.context);
-    channel.addr_segment = F::from_canonical_usize(address.segment);
+    channel.addr_segment = F::from_canonical_u32(address.segment);
     channel.addr_virtual = F::from_canonical_usize(address.virt);
     channel.value = F::from_canonical_u32(val);
     op
@@ -566,6 +566,7 @@ pub(crate) fn sha_extend_sponge_log<
     inputs: Vec<[u8; 4]>, // le bytes
     output_address: MemoryAddress,
     round: usize,
+    stride_arg: u32,
 ) {
     // Since the Sha extend reads byte by byte, and the memory unit is of 4-byte, we just need to read
     // the same memory for 4 sha-extend ops
@@ -599,6 +600,7 @@ pub(crate) fn sha_extend_sponge_log<
         input: extend_input,
         i: round,
         output_address,
+        stride_arg,
     });
 }
 
@@ -0,0 +1,43 @@
+mod common;
+
+use plonky2::util::timing::TimingTree;
+use zkm_prover::all_stark::{AllStark, Table};
+use zkm_prover::config::StarkConfig;
+use zkm_prover::fixed_recursive_verifier::AllRecursiveCircuits;
+
+use common::{degree_ranges, exit_kernel, C, D, F};
+
+// A program which never calls the Keccak or SHA precompiles can be proven without their tables.
+#[test]
+fn test_prove_root_without_precompile_tables() -> anyhow::Result<()> {
+    env_logger::try_init().unwrap_or_default();
+
+    let kernel = exit_kernel()?;
+
+    let mut active_tables = [true; 12];
+    for table in [
+        Table::Keccak,
+        Table::KeccakSponge,
+        Table::ShaExtend,
+        Table::ShaExtendSponge,
+        Table::ShaCompress,
+        Table::ShaCompressSponge,
+    ] {
+        active_tables[table as usize] = false;
+    }
+
+    let all_stark = AllStark::<F, D>::default();
+    let config = StarkConfig::standard_fast_config();
+    let degree_bits_ranges = degree_ranges(&all_stark, std::slice::from_ref(&kernel), &config)?;
+    let all_circuits = AllRecursiveCircuits::<F, C, D>::new_with_active(
+        &all_stark,
+        &degree_bits_ranges,
+        &config,
+        &active_tables,
+    );
+    assert_eq!(all_circuits.root.active_tables(), active_tables);
+
+    let mut timing = TimingTree::new("prove root", log::Level::Info);
+    let root_receipt = all_circuits.prove_root(&all_stark, &kernel, &config, &mut timing)?;
+    all_circuits.verify_root(root_receipt)
+}
@@ -0,0 +1,52 @@
+mod common;
+
+use zkm_prover::aggregation::{AggregationCoordinator, AggregationMessage};
+use zkm_prover::all_stark::AllStark;
+use zkm_prover::config::StarkConfig;
+use zkm_prover::fixed_recursive_verifier::AllRecursiveCircuits;
+
+use common::{degree_ranges, exit_segments, prove_roots, C, D, F};
+
+// Segment receipts sent out of order from other threads are aggregated in execution order into a
+// block receipt, while a single segment can't make a block.
+#[test]
+fn test_aggregation_coordinator() -> anyhow::Result<()> {
+    env_logger::try_init().unwrap_or_default();
+
+    let segments = exit_segments(3)?;
+    let all_stark = AllStark::<F, D>::default();
+    let config = StarkConfig::standard_fast_config();
+    let degree_bits_ranges = degree_ranges(&all_stark, &segments, &config)?;
+    let all_circuits =
+        AllRecursiveCircuits::<F, C, D>::new(&all_stark, &degree_bits_ranges, &config);
+    let receipts = prove_roots(&all_circuits, &all_stark, &segments, &config)?;
+
+    let (sender, mut coordinator) = AggregationCoordinator::new();
+    for index in [2, 0, 1] {
+        let sender = sender.clone();
+        let receipt = receipts[index].clone();
+        std::thread::spawn(move || sender.send(AggregationMessage::Segment { index, receipt }))
+            .join()
+            .unwrap()?;
+        coordinator.poll(&all_circuits)?;
+    }
+    sender.send(AggregationMessage::Complete)?;
+    let block_receipt = coordinator.run(&all_circuits)?;
+    all_circuits.verify_block(&block_receipt)?;
+    assert_eq!(
+        block_receipt.values().roots_before,
+        receipts[0].values().roots_before
+    );
+    assert_eq!(
+        block_receipt.values().roots_after,
+        receipts[2].values().roots_after
+    );
+
+    let (sender, coordinator) = AggregationCoordinator::new();
+    let receipt = receipts[0].clone();
+    sender.send(AggregationMessage::Segment { index: 0, receipt })?;
+    sender.send(AggregationMessage::Complete)?;
+    let err = coordinator.run(&all_circuits).unwrap_err();
+    assert!(err.to_string().contains("at least two segments"), "{err}");
+    Ok(())
+}
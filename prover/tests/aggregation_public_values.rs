@@ -0,0 +1,46 @@
+mod common;
+
+use zkm_prover::all_stark::AllStark;
+use zkm_prover::config::StarkConfig;
+use zkm_prover::fixed_recursive_verifier::AllRecursiveCircuits;
+use zkm_prover::proof::PublicValues;
+
+use common::{degree_ranges, exit_segments, prove_roots, C, D, F};
+
+// An aggregation receipt starts where its lhs child starts and ends where its rhs child ends.
+#[test]
+fn test_aggregation_public_values() -> anyhow::Result<()> {
+    env_logger::try_init().unwrap_or_default();
+
+    let segments = exit_segments(2)?;
+    let all_stark = AllStark::<F, D>::default();
+    let config = StarkConfig::standard_fast_config();
+    let degree_bits_ranges = degree_ranges(&all_stark, &segments, &config)?;
+    let all_circuits =
+        AllRecursiveCircuits::<F, C, D>::new(&all_stark, &degree_bits_ranges, &config);
+
+    let root_receipts = prove_roots(&all_circuits, &all_stark, &segments, &config)?;
+    let (lhs, rhs) = (root_receipts[0].values(), root_receipts[1].values());
+    let agg_receipt =
+        all_circuits.prove_aggregation(false, &root_receipts[0], false, &root_receipts[1])?;
+    all_circuits.verify_aggregation(&agg_receipt)?;
+
+    // Swapped receipts don't chain, which is caught before proving.
+    let err = all_circuits
+        .prove_aggregation(false, &root_receipts[1], false, &root_receipts[0])
+        .unwrap_err();
+    assert!(err.to_string().contains("doesn't continue"));
+
+    let values = agg_receipt.values();
+    assert_eq!(values.roots_before, lhs.roots_before);
+    assert_eq!(values.roots_after, rhs.roots_after);
+    assert_eq!(
+        values,
+        PublicValues {
+            roots_before: lhs.roots_before.clone(),
+            roots_after: rhs.roots_after.clone(),
+            userdata: lhs.userdata.clone(),
+        }
+    );
+    Ok(())
+}
@@ -0,0 +1,35 @@
+mod common;
+
+use plonky2::field::types::Field;
+use plonky2::util::timing::TimingTree;
+use zkm_prover::all_stark::{AllStark, Table};
+use zkm_prover::config::StarkConfig;
+use zkm_prover::prover::prove;
+use zkm_prover::verifier::verify_proofs_batch;
+
+use common::{exit_kernel, C, D, F};
+
+// A corrupted proof in a batch fails on its own, without affecting the other results.
+#[test]
+fn test_verify_proofs_batch() -> anyhow::Result<()> {
+    env_logger::try_init().unwrap_or_default();
+
+    let all_stark = AllStark::<F, D>::default();
+    let config = StarkConfig::standard_fast_config();
+    let kernel = exit_kernel()?;
+    let mut timing = TimingTree::new("prove", log::Level::Info);
+    let proof = prove::<F, C, D>(&all_stark, &kernel, &config, &mut timing)?;
+
+    let mut proofs = vec![proof.clone(), proof.clone(), proof];
+    proofs[1].stark_proofs[Table::Cpu as usize]
+        .proof
+        .openings
+        .ctl_zs_first[0] += F::ONE;
+
+    let results = verify_proofs_batch(&all_stark, &proofs, &config);
+    assert_eq!(results.len(), 3);
+    assert!(results[0].is_ok());
+    assert!(results[1].is_err());
+    assert!(results[2].is_ok());
+    Ok(())
+}
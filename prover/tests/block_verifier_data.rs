@@ -0,0 +1,178 @@
+mod common;
+
+use plonky2::field::types::Field;
+use plonky2::recursion::cyclic_recursion::check_cyclic_proof_verifier_data;
+use zkm_prover::all_stark::AllStark;
+use zkm_prover::block_verifier::verify_block_with_vk;
+use zkm_prover::config::StarkConfig;
+use zkm_prover::fixed_recursive_verifier::{AggregationChildKind, AllRecursiveCircuits};
+
+use common::{degree_ranges, exit_segments, prove_roots, C, D, F};
+
+// A block proof must verify with only the extracted verifier data.
+#[test]
+fn test_verify_block_with_verifier_data() -> anyhow::Result<()> {
+    env_logger::try_init().unwrap_or_default();
+
+    let segments = exit_segments(2)?;
+    let all_stark = AllStark::<F, D>::default();
+    let config = StarkConfig::standard_fast_config();
+    let degree_bits_ranges = degree_ranges(&all_stark, &segments, &config)?;
+    let all_circuits =
+        AllRecursiveCircuits::<F, C, D>::new(&all_stark, &degree_bits_ranges, &config);
+
+    let root_receipts = prove_roots(&all_circuits, &all_stark, &segments, &config)?;
+    let agg_receipt =
+        all_circuits.prove_aggregation(false, &root_receipts[0], false, &root_receipts[1])?;
+    let block_receipt = all_circuits.prove_block(None, &agg_receipt)?;
+
+    let verifier_data = all_circuits.block_verifier_data();
+    let digest = all_circuits.vk_digest();
+    drop(all_circuits);
+
+    let proof = block_receipt.proof();
+    verifier_data.verify(proof.clone())?;
+    check_cyclic_proof_verifier_data(&proof, &verifier_data.verifier_only, &verifier_data.common)?;
+    assert_ne!(digest, [F::ZERO; 4]);
+    Ok(())
+}
+
+// `verify_block_with_vk` accepts a block proof given only the verifier data, and rejects it
+// against verifier data whose constants differ.
+#[test]
+fn test_verify_block_with_vk() -> anyhow::Result<()> {
+    env_logger::try_init().unwrap_or_default();
+
+    let segments = exit_segments(2)?;
+    let all_stark = AllStark::<F, D>::default();
+    let config = StarkConfig::standard_fast_config();
+    let degree_bits_ranges = degree_ranges(&all_stark, &segments, &config)?;
+    let all_circuits =
+        AllRecursiveCircuits::<F, C, D>::new(&all_stark, &degree_bits_ranges, &config);
+
+    let root_receipts = prove_roots(&all_circuits, &all_stark, &segments, &config)?;
+    let agg_receipt =
+        all_circuits.prove_aggregation(false, &root_receipts[0], false, &root_receipts[1])?;
+    let block_receipt = all_circuits.prove_block(None, &agg_receipt)?;
+
+    let verifier_data = all_circuits.block_verifier_data();
+    drop(all_circuits);
+
+    let proof = block_receipt.proof();
+    verify_block_with_vk(
+        proof.clone(),
+        &verifier_data.verifier_only,
+        &verifier_data.common,
+    )?;
+
+    let mut wrong_verifier_only = verifier_data.verifier_only.clone();
+    wrong_verifier_only.circuit_digest.elements[0] += F::ONE;
+    assert!(verify_block_with_vk(proof, &wrong_verifier_only, &verifier_data.common).is_err());
+    Ok(())
+}
+
+// Two blocks, each over an aggregation of two segments, chain into a single block receipt.
+#[test]
+fn test_prove_block_chain() -> anyhow::Result<()> {
+    env_logger::try_init().unwrap_or_default();
+
+    let segments = exit_segments(4)?;
+    let all_stark = AllStark::<F, D>::default();
+    let config = StarkConfig::standard_fast_config();
+    let degree_bits_ranges = degree_ranges(&all_stark, &segments, &config)?;
+    let all_circuits =
+        AllRecursiveCircuits::<F, C, D>::new(&all_stark, &degree_bits_ranges, &config);
+
+    let root_receipts = prove_roots(&all_circuits, &all_stark, &segments, &config)?;
+    let agg_receipts = root_receipts
+        .chunks(2)
+        .map(|pair| all_circuits.prove_aggregation(false, &pair[0], false, &pair[1]))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let block_receipt = all_circuits.prove_block_chain(&agg_receipts)?;
+    all_circuits.verify_block(&block_receipt)?;
+    assert_eq!(
+        block_receipt.values().roots_after.root,
+        agg_receipts[1].values().roots_after.root
+    );
+
+    assert!(all_circuits.prove_block_chain(&[]).is_err());
+    Ok(())
+}
+
+// Two independent blocks, each over an aggregation of two segments, aggregate into a receipt
+// spanning both, which is proven as a block in turn. An aggregation receipt can't pass for a
+// block one.
+#[test]
+fn test_aggregate_block_receipts() -> anyhow::Result<()> {
+    env_logger::try_init().unwrap_or_default();
+
+    let segments = exit_segments(4)?;
+    let all_stark = AllStark::<F, D>::default();
+    let config = StarkConfig::standard_fast_config();
+    let degree_bits_ranges = degree_ranges(&all_stark, &segments, &config)?;
+    let all_circuits =
+        AllRecursiveCircuits::<F, C, D>::new(&all_stark, &degree_bits_ranges, &config);
+
+    let root_receipts = prove_roots(&all_circuits, &all_stark, &segments, &config)?;
+    let agg_receipts = root_receipts
+        .chunks(2)
+        .map(|pair| all_circuits.prove_aggregation(false, &pair[0], false, &pair[1]))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let block_receipts = agg_receipts
+        .iter()
+        .map(|agg_receipt| all_circuits.prove_block(None, agg_receipt))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let super_receipt = all_circuits.prove_aggregation_with_kinds(
+        AggregationChildKind::Block,
+        &block_receipts[0],
+        AggregationChildKind::Block,
+        &block_receipts[1],
+    )?;
+    all_circuits.verify_aggregation(&super_receipt)?;
+    assert_eq!(
+        super_receipt.values().roots_before,
+        root_receipts[0].values().roots_before
+    );
+    assert_eq!(
+        super_receipt.values().roots_after,
+        root_receipts[3].values().roots_after
+    );
+    let block_receipt = all_circuits.prove_block(None, &super_receipt)?;
+    all_circuits.verify_block(&block_receipt)?;
+
+    assert!(all_circuits
+        .prove_aggregation_with_kinds(
+            AggregationChildKind::Block,
+            &agg_receipts[0],
+            AggregationChildKind::Block,
+            &block_receipts[1],
+        )
+        .is_err());
+    Ok(())
+}
+
+// With a userdata counter limb, a genesis block still proves and verifies. Chaining blocks
+// requires userdata whose counter limb increments, which the test programs don't produce, since
+// userdata is a hash of the program input; see `test_connect_block_userdata_counter`.
+#[test]
+fn test_prove_block_with_counter_limbs() -> anyhow::Result<()> {
+    env_logger::try_init().unwrap_or_default();
+
+    let segments = exit_segments(2)?;
+    let all_stark = AllStark::<F, D>::default();
+    let config = StarkConfig::standard_fast_config();
+    let degree_bits_ranges = degree_ranges(&all_stark, &segments, &config)?;
+    let mut all_circuits =
+        AllRecursiveCircuits::<F, C, D>::new(&all_stark, &degree_bits_ranges, &config);
+    assert!(all_circuits.set_block_counter_limbs(&[32]).is_err());
+    all_circuits.set_block_counter_limbs(&[0])?;
+
+    let root_receipts = prove_roots(&all_circuits, &all_stark, &segments, &config)?;
+    let agg_receipt =
+        all_circuits.prove_aggregation(false, &root_receipts[0], false, &root_receipts[1])?;
+    let block_receipt = all_circuits.prove_block(None, &agg_receipt)?;
+    all_circuits.verify_block(&block_receipt)?;
+    Ok(())
+}
@@ -0,0 +1,36 @@
+mod common;
+
+use std::marker::PhantomData;
+
+use plonky2::util::serialization::{DefaultGateSerializer, DefaultGeneratorSerializer};
+use zkm_prover::all_stark::AllStark;
+use zkm_prover::config::StarkConfig;
+use zkm_prover::fixed_recursive_verifier::AllRecursiveCircuits;
+
+use common::{degree_ranges, exit_kernel, C, D, F};
+
+// `to_bytes` preallocates exactly `serialized_size` bytes.
+#[test]
+fn test_serialized_size_matches_to_bytes() -> anyhow::Result<()> {
+    env_logger::try_init().unwrap_or_default();
+
+    let all_stark = AllStark::<F, D>::default();
+    let config = StarkConfig::standard_fast_config();
+    let degree_bits_ranges = degree_ranges(&all_stark, &[exit_kernel()?], &config)?;
+    let all_circuits =
+        AllRecursiveCircuits::<F, C, D>::new(&all_stark, &degree_bits_ranges, &config);
+
+    let gate_serializer = DefaultGateSerializer;
+    let generator_serializer = DefaultGeneratorSerializer::<C, D> {
+        _phantom: PhantomData,
+    };
+    let size = all_circuits
+        .serialized_size(&gate_serializer, &generator_serializer)
+        .map_err(|_| anyhow::anyhow!("Failed to size the circuits"))?;
+    let bytes = all_circuits
+        .to_bytes(&gate_serializer, &generator_serializer)
+        .map_err(|_| anyhow::anyhow!("Failed to serialize the circuits"))?;
+    assert_eq!(bytes.len(), size);
+    assert_eq!(bytes.capacity(), size);
+    Ok(())
+}
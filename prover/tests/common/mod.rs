@@ -0,0 +1,78 @@
+//! Fixtures shared by the integration tests. Each test crate only uses some of them.
+#![allow(dead_code)]
+
+use std::ops::Range;
+
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use plonky2::util::timing::TimingTree;
+use zkm_prover::all_stark::AllStark;
+use zkm_prover::config::StarkConfig;
+use zkm_prover::cpu::kernel::assembler::Kernel;
+use zkm_prover::fixed_recursive_verifier::AllRecursiveCircuits;
+use zkm_prover::generation::state::Receipt;
+
+pub const D: usize = 2;
+pub type C = PoseidonGoldilocksConfig;
+pub type F = <C as GenericConfig<D>>::F;
+
+/// `addiu $a0, $zero, 0`, a step which doesn't change the state of `exit_kernel`.
+const CLEAR_A0: &str = "0x24040000";
+/// `addiu $v0, $zero, 4246; syscall`, i.e. `exit_group(0)`.
+const EXIT: &str = "0x24021096, 0x0000000c";
+
+/// A program exiting right away.
+pub fn exit_kernel() -> anyhow::Result<Kernel> {
+    exit_kernel_after(0)
+}
+
+/// A program exiting after `num_steps` steps clearing `$a0`, in a single segment.
+pub fn exit_kernel_after(num_steps: usize) -> anyhow::Result<Kernel> {
+    let mut code = vec![CLEAR_A0; num_steps];
+    code.push(EXIT);
+    Kernel::from_asm(&format!(".word {}", code.join(", ")), 0x0040_0000)
+}
+
+/// `exit_kernel_after(num_segments - 1)` split into `num_segments` segments, the last of which
+/// runs the exit syscall.
+pub fn exit_segments(num_segments: usize) -> anyhow::Result<Vec<Kernel>> {
+    exit_kernel_after(num_segments - 1)?.split_at_steps(&(1..num_segments).collect::<Vec<_>>())
+}
+
+/// The smallest degree ranges covering every kernel of `kernels`, from their profiles.
+pub fn degree_ranges(
+    all_stark: &AllStark<F, D>,
+    kernels: &[Kernel],
+    config: &StarkConfig,
+) -> anyhow::Result<[Range<usize>; 12]> {
+    let mut degree_bits_ranges: Option<[Range<usize>; 12]> = None;
+    for kernel in kernels {
+        let ranges =
+            all_stark.recommend_degree_ranges(&all_stark.profile::<C>(kernel, config)?.into());
+        degree_bits_ranges = Some(match degree_bits_ranges {
+            Some(mut degree_bits_ranges) => {
+                for (range, other) in degree_bits_ranges.iter_mut().zip(ranges) {
+                    *range = range.start.min(other.start)..range.end.max(other.end);
+                }
+                degree_bits_ranges
+            }
+            None => ranges,
+        });
+    }
+    degree_bits_ranges.ok_or_else(|| anyhow::anyhow!("No kernels to profile."))
+}
+
+/// Proves a root receipt of each kernel of `kernels`.
+pub fn prove_roots(
+    all_circuits: &AllRecursiveCircuits<F, C, D>,
+    all_stark: &AllStark<F, D>,
+    kernels: &[Kernel],
+    config: &StarkConfig,
+) -> anyhow::Result<Vec<Receipt<F, C, D>>> {
+    kernels
+        .iter()
+        .map(|kernel| {
+            let mut timing = TimingTree::new("prove root", log::Level::Info);
+            all_circuits.prove_root(all_stark, kernel, config, &mut timing)
+        })
+        .collect()
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use plonky2::util::timing::TimingTree;
+use zkm_prover::all_stark::AllStark;
+use zkm_prover::config::StarkConfig;
+use zkm_prover::fixed_recursive_verifier::{AllRecursiveCircuits, THRESHOLD_DEGREE_BITS};
+
+use common::{degree_ranges, exit_kernel, C, D, F};
+
+// A compressed root receipt verifies against the compression circuit, is shrunk down to the
+// recursion threshold, and keeps the public values of the original receipt.
+#[test]
+fn test_compress_root_receipt() -> anyhow::Result<()> {
+    env_logger::try_init().unwrap_or_default();
+
+    let kernel = exit_kernel()?;
+    let all_stark = AllStark::<F, D>::default();
+    let config = StarkConfig::standard_fast_config();
+    let degree_bits_ranges = degree_ranges(&all_stark, std::slice::from_ref(&kernel), &config)?;
+    let all_circuits =
+        AllRecursiveCircuits::<F, C, D>::new(&all_stark, &degree_bits_ranges, &config);
+
+    let mut timing = TimingTree::new("prove root", log::Level::Info);
+    let root_receipt = all_circuits.prove_root(&all_stark, &kernel, &config, &mut timing)?;
+
+    let compressed = all_circuits.compress(&root_receipt)?;
+    all_circuits.verify_compressed(&compressed)?;
+    assert_eq!(
+        all_circuits.compressed_verifier_data().common.degree_bits(),
+        THRESHOLD_DEGREE_BITS
+    );
+    assert_eq!(
+        compressed.proof().public_inputs,
+        root_receipt.proof().public_inputs
+    );
+    assert_eq!(
+        compressed.values().roots_after.root,
+        root_receipt.values().roots_after.root
+    );
+
+    // A compressed proof isn't a root proof.
+    assert!(all_circuits.verify_root(compressed).is_err());
+    Ok(())
+}
@@ -0,0 +1,38 @@
+mod common;
+
+use plonky2::util::timing::TimingTree;
+use zkm_prover::all_stark::{AllStark, Table};
+use zkm_prover::config::StarkConfig;
+use zkm_prover::fixed_recursive_verifier::AllRecursiveCircuits;
+
+use common::{degree_ranges, exit_kernel, C, D, F};
+
+// The CPU circuits are only built for a size smaller than the CPU trace of the segment, so
+// proving it fails with an out-of-range error rather than a missing circuit.
+#[test]
+fn test_prove_root_degree_out_of_range() -> anyhow::Result<()> {
+    env_logger::try_init().unwrap_or_default();
+
+    let kernel = exit_kernel()?;
+    let all_stark = AllStark::<F, D>::default();
+    let config = StarkConfig::standard_fast_config();
+    let mut degree_bits_range = degree_ranges(&all_stark, std::slice::from_ref(&kernel), &config)?;
+    let cpu_degree_bits = degree_bits_range[Table::Cpu as usize].start;
+    degree_bits_range[Table::Cpu as usize] = cpu_degree_bits - 1..cpu_degree_bits;
+
+    let all_circuits =
+        AllRecursiveCircuits::<F, C, D>::new(&all_stark, &degree_bits_range, &config);
+
+    let mut timing = TimingTree::new("prove root", log::Level::Info);
+    let err = all_circuits
+        .prove_root(&all_stark, &kernel, &config, &mut timing)
+        .unwrap_err();
+    let msg = err.to_string();
+    assert!(
+        msg.starts_with("Proof degree out of configured range"),
+        "{msg}"
+    );
+    let hint = format!("export CPU=\"{}..", cpu_degree_bits - 1);
+    assert!(msg.contains(&hint), "{msg}");
+    Ok(())
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use plonky2::util::timing::TimingTree;
+use zkm_prover::all_stark::AllStark;
+use zkm_prover::config::StarkConfig;
+use zkm_prover::proof::AllProof;
+use zkm_prover::prover::{prove, prove_with_outputs_seeded};
+use zkm_prover::verifier::{verify_proof, verify_proof_seeded};
+
+use common::{exit_kernel, C, D, F};
+
+// Proving the same segment twice must yield byte-identical proofs, CTL challenges included.
+#[test]
+fn test_deterministic_proof() -> anyhow::Result<()> {
+    env_logger::try_init().unwrap_or_default();
+
+    let all_stark = AllStark::<F, D>::default();
+    let config = StarkConfig::standard_fast_config();
+
+    let mut proofs = vec![];
+    for _ in 0..2 {
+        let kernel = exit_kernel()?;
+        let mut timing = TimingTree::new("prove", log::Level::Debug);
+        let proof = prove::<F, C, D>(&all_stark, &kernel, &config, &mut timing)?;
+        proofs.push(proof.to_bytes()?);
+    }
+
+    assert_eq!(proofs[0], proofs[1]);
+    Ok(())
+}
+
+// Proofs from the same seed are byte-identical, and only verify with that seed.
+#[test]
+fn test_seeded_proof() -> anyhow::Result<()> {
+    env_logger::try_init().unwrap_or_default();
+
+    let all_stark = AllStark::<F, D>::default();
+    let config = StarkConfig::standard_fast_config();
+    let seed = [1, 2, 3, u64::MAX];
+
+    let prove_seeded = |seed| -> anyhow::Result<AllProof<F, C, D>> {
+        let kernel = exit_kernel()?;
+        let mut timing = TimingTree::new("prove", log::Level::Debug);
+        let (proof, _outputs) =
+            prove_with_outputs_seeded::<F, C, D>(&all_stark, &kernel, &config, &mut timing, seed)?;
+        Ok(proof)
+    };
+
+    let proof = prove_seeded(seed)?;
+    assert_eq!(proof.to_bytes()?, prove_seeded(seed)?.to_bytes()?);
+    assert_ne!(proof.to_bytes()?, prove_seeded([1, 2, 3, 4])?.to_bytes()?);
+
+    verify_proof_seeded(&all_stark, proof.clone(), &config, seed)?;
+    assert!(verify_proof_seeded(&all_stark, proof.clone(), &config, [0; 4]).is_err());
+    assert!(verify_proof(&all_stark, proof, &config).is_err());
+    Ok(())
+}
@@ -0,0 +1,40 @@
+mod common;
+
+use plonky2::util::timing::TimingTree;
+use zkm_prover::all_stark::AllStark;
+use zkm_prover::config::StarkConfig;
+use zkm_prover::fixed_recursive_verifier::AllRecursiveCircuits;
+
+use common::{degree_ranges, exit_kernel, C, D, F};
+
+// The table STARKs can be proven with fewer FRI queries than the recursion circuits use, traded
+// for more proof-of-work, as long as the circuits are built for that inner config.
+#[test]
+fn test_prove_root_with_lower_query_inner_config() -> anyhow::Result<()> {
+    env_logger::try_init().unwrap_or_default();
+
+    let mut inner_config = StarkConfig::standard_fast_config();
+    inner_config.fri_config.num_query_rounds = 28;
+    inner_config.fri_config.proof_of_work_bits = 20;
+    inner_config.security_bits = 2 * 28 + 20;
+
+    let kernel = exit_kernel()?;
+    let all_stark = AllStark::<F, D>::default();
+    let degree_bits_ranges =
+        degree_ranges(&all_stark, std::slice::from_ref(&kernel), &inner_config)?;
+    let all_circuits =
+        AllRecursiveCircuits::<F, C, D>::new(&all_stark, &degree_bits_ranges, &inner_config);
+    all_circuits.check_inner_config(&inner_config)?;
+
+    let mut timing = TimingTree::new("prove root", log::Level::Info);
+    let root_receipt = all_circuits.prove_root(&all_stark, &kernel, &inner_config, &mut timing)?;
+    all_circuits.verify_root(root_receipt)?;
+
+    // A config the circuits weren't built for is rejected before proving.
+    let default_config = StarkConfig::standard_fast_config();
+    let err = all_circuits
+        .prove_root(&all_stark, &kernel, &default_config, &mut timing)
+        .unwrap_err();
+    assert!(err.to_string().contains("FRI query rounds"));
+    Ok(())
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use std::time::Instant;
+
+use plonky2::util::timing::TimingTree;
+use zkm_prover::all_stark::AllStark;
+use zkm_prover::config::StarkConfig;
+use zkm_prover::fixed_recursive_verifier::AllRecursiveCircuits;
+use zkm_prover::prover::{ProvePhase, ProveTimings};
+
+use common::{degree_ranges, exit_kernel, C, D, F};
+
+// Every table is proven and shrunk exactly once, in table order, between trace generation and
+// the root proof.
+#[test]
+fn test_prove_root_progress() -> anyhow::Result<()> {
+    env_logger::try_init().unwrap_or_default();
+
+    let kernel = exit_kernel()?;
+    let all_stark = AllStark::<F, D>::default();
+    let config = StarkConfig::standard_fast_config();
+    let degree_bits_ranges = degree_ranges(&all_stark, std::slice::from_ref(&kernel), &config)?;
+    let all_circuits =
+        AllRecursiveCircuits::<F, C, D>::new(&all_stark, &degree_bits_ranges, &config);
+
+    let mut timing = TimingTree::new("prove root", log::Level::Info);
+    let mut phases = vec![];
+    let root_receipt = all_circuits.prove_root_with_progress(
+        &all_stark,
+        &kernel,
+        &config,
+        &mut timing,
+        &mut |phase| phases.push(phase),
+    )?;
+    all_circuits.verify_root(root_receipt)?;
+
+    let num_tables = degree_bits_ranges.len();
+    assert_eq!(phases.len(), 2 + 2 * num_tables);
+    assert!(matches!(phases[0], ProvePhase::TraceGeneration { .. }));
+    assert!(matches!(phases.last(), Some(ProvePhase::Root { .. })));
+    let starks = phases
+        .iter()
+        .filter_map(|phase| match phase {
+            ProvePhase::Stark { table, .. } => Some(*table as usize),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let shrinks = phases
+        .iter()
+        .filter_map(|phase| match phase {
+            ProvePhase::Shrink { table, .. } => Some(*table as usize),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(starks, (0..num_tables).collect::<Vec<_>>());
+    assert_eq!(shrinks, (0..num_tables).collect::<Vec<_>>());
+    Ok(())
+}
+
+// The timings cover every phase, and together almost all of the time spent proving.
+#[test]
+fn test_prove_root_with_timings() -> anyhow::Result<()> {
+    env_logger::try_init().unwrap_or_default();
+
+    let kernel = exit_kernel()?;
+    let all_stark = AllStark::<F, D>::default();
+    let config = StarkConfig::standard_fast_config();
+    let degree_bits_ranges = degree_ranges(&all_stark, std::slice::from_ref(&kernel), &config)?;
+    let all_circuits =
+        AllRecursiveCircuits::<F, C, D>::new(&all_stark, &degree_bits_ranges, &config);
+
+    let start = Instant::now();
+    let (root_receipt, timings) =
+        all_circuits.prove_root_with_timings(&all_stark, &kernel, &config)?;
+    let wall_time = start.elapsed();
+    all_circuits.verify_root(root_receipt)?;
+
+    assert_ne!(timings, ProveTimings::default());
+    assert!(!timings.trace_gen.is_zero());
+    assert!(!timings.root.is_zero());
+    assert!(timings.per_table_stark.iter().all(|d| !d.is_zero()));
+    assert!(timings.per_table_shrink.iter().all(|d| !d.is_zero()));
+
+    // The native verification of the STARK proofs isn't timed.
+    let total = timings.total();
+    assert!(total <= wall_time);
+    assert!(total >= wall_time / 2, "{total:?} out of {wall_time:?}");
+    Ok(())
+}
@@ -0,0 +1,46 @@
+mod common;
+
+use std::time::Instant;
+
+use plonky2::util::timing::TimingTree;
+use zkm_prover::all_stark::AllStark;
+use zkm_prover::config::StarkConfig;
+use zkm_prover::fixed_recursive_verifier::AllRecursiveCircuits;
+
+use common::{degree_ranges, exit_kernel, C, D, F};
+
+// Skipping the native verification of the STARK proofs still gives a valid root receipt. The
+// time saved is logged, as it is only significant on large traces.
+#[test]
+fn test_prove_root_unchecked() -> anyhow::Result<()> {
+    env_logger::try_init().unwrap_or_default();
+
+    let kernel = exit_kernel()?;
+    let all_stark = AllStark::<F, D>::default();
+    let config = StarkConfig::standard_fast_config();
+    let degree_bits_ranges = degree_ranges(&all_stark, std::slice::from_ref(&kernel), &config)?;
+    let all_circuits =
+        AllRecursiveCircuits::<F, C, D>::new(&all_stark, &degree_bits_ranges, &config);
+
+    let mut timing = TimingTree::new("prove root", log::Level::Info);
+    let start = Instant::now();
+    let checked = all_circuits.prove_root(&all_stark, &kernel, &config, &mut timing)?;
+    let checked_time = start.elapsed();
+
+    let mut timing = TimingTree::new("prove root unchecked", log::Level::Info);
+    let start = Instant::now();
+    let unchecked = all_circuits.prove_root_unchecked(&all_stark, &kernel, &config, &mut timing)?;
+    let unchecked_time = start.elapsed();
+
+    log::info!(
+        "prove_root took {:?}, prove_root_unchecked took {:?}",
+        checked_time,
+        unchecked_time
+    );
+    assert_eq!(
+        checked.proof().public_inputs,
+        unchecked.proof().public_inputs
+    );
+    all_circuits.verify_root(unchecked)?;
+    Ok(())
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use plonky2::util::timing::TimingTree;
+use zkm_prover::all_stark::AllStark;
+use zkm_prover::config::StarkConfig;
+use zkm_prover::fixed_recursive_verifier::AllRecursiveCircuits;
+
+use common::{degree_ranges, exit_kernel_after, C, D, F};
+
+// Proving one execution as three segments yields receipts chained by their memory roots, which
+// aggregate into a receipt spanning the whole execution.
+#[test]
+fn test_prove_segments() -> anyhow::Result<()> {
+    env_logger::try_init().unwrap_or_default();
+
+    let kernel = exit_kernel_after(2)?;
+    let boundaries = [1, 2];
+
+    let all_stark = AllStark::<F, D>::default();
+    let config = StarkConfig::standard_fast_config();
+    let degree_bits_ranges =
+        degree_ranges(&all_stark, &kernel.split_at_steps(&boundaries)?, &config)?;
+    let all_circuits =
+        AllRecursiveCircuits::<F, C, D>::new(&all_stark, &degree_bits_ranges, &config);
+
+    let mut timing = TimingTree::new("prove segments", log::Level::Info);
+    let receipts =
+        all_circuits.prove_segments(&all_stark, &kernel, &config, &boundaries, &mut timing)?;
+    assert_eq!(receipts.len(), 3);
+    for pair in receipts.windows(2) {
+        assert_eq!(
+            pair[0].values().roots_after.root,
+            pair[1].values().roots_before.root
+        );
+    }
+
+    let receipt = all_circuits.prove_aggregation_all(&receipts)?;
+    let whole = receipt.values();
+    assert_eq!(
+        whole.roots_before.root,
+        receipts[0].values().roots_before.root
+    );
+    assert_eq!(
+        whole.roots_after.root,
+        receipts[2].values().roots_after.root
+    );
+    all_circuits.verify_aggregation(&receipt)
+}
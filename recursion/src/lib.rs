@@ -7,7 +7,7 @@ use plonky2::field::goldilocks_field::GoldilocksField;
 use plonky2::plonk::config::PoseidonGoldilocksConfig;
 use plonky2::util::timing::TimingTree;
 use plonky2x::backend::circuit::Groth16WrapperParameters;
-use plonky2x::backend::wrapper::wrap::WrappedCircuit;
+use plonky2x::backend::wrapper::wrap::{WrappedCircuit, WrappedOutput};
 use plonky2x::frontend::builder::CircuitBuilder as WrapperBuilder;
 use plonky2x::prelude::DefaultParameters;
 use std::marker::PhantomData;
@@ -21,6 +21,7 @@ use zkm_prover::all_stark::AllStark;
 use zkm_prover::config::StarkConfig;
 use zkm_prover::fixed_recursive_verifier::AllRecursiveCircuits;
 use zkm_prover::generation::state::Receipt;
+use zkm_prover::proof::PublicValues;
 
 type F = GoldilocksField;
 const D: usize = 2;
@@ -89,47 +90,98 @@ pub fn aggregate_proof(
     Ok(new_agg_receipt)
 }
 
+/// Number of 32-bit words of each memory root in the block public inputs.
+pub const BN254_ROOT_WORDS: usize = 8;
+/// Number of userdata bytes in the block public inputs, zero-padded, followed by the length of
+/// the meaningful prefix.
+pub const BN254_USERDATA_BYTES: usize = 32;
+/// Number of 64-bit elements of each copy of the block circuit's verifier data, which follow the
+/// public values: the circuit digest, then a cap of 16 hashes of the constants and sigmas.
+pub const BN254_VERIFIER_DATA_ELEMENTS: usize = 68;
+
+/// Bit size of each public input of the block proof, in order, as committed to by the BN254
+/// wrapper and laid out for the Solidity verifier:
+///
+/// | public inputs | bits | content                             |
+/// |---------------|------|-------------------------------------|
+/// | `0..8`        | 32   | `roots_before`, one word per input  |
+/// | `8..16`       | 32   | `roots_after`, one word per input   |
+/// | `16..48`      | 8    | `userdata`, one byte per input      |
+/// | `48`          | 32   | `userdata` length                   |
+/// | `49..117`     | 64   | block circuit verifier data         |
+/// | `117..185`    | 64   | block circuit cyclic verifier data  |
+///
+/// Both copies of the verifier data are the block circuit's own; the first is the one block
+/// proofs aggregated into the block were checked against.
+pub fn bn254_public_input_bit_sizes() -> Vec<usize> {
+    let mut bit_sizes = vec![32; 2 * BN254_ROOT_WORDS];
+    bit_sizes.extend(vec![8; BN254_USERDATA_BYTES]);
+    bit_sizes.push(32);
+    bit_sizes.extend(vec![64; 2 * BN254_VERIFIER_DATA_ELEMENTS]);
+    bit_sizes
+}
+
+/// Wrapping of block proofs for on-chain verification.
+pub trait WrapToBn254 {
+    /// Recursively verify a block proof in a circuit over a BN254-friendly config, whose proof
+    /// can then be turned into a Groth16 proof with `as_groth16`. Returns the wrapped proof along
+    /// with the public values it commits to, in the layout of `bn254_public_input_bit_sizes`.
+    fn wrap_to_bn254(
+        &self,
+        block_receipt: &Receipt<F, C, D>,
+    ) -> anyhow::Result<(WrappedOutput<OuterParameters, D>, PublicValues)>;
+}
+
+impl WrapToBn254 for AllRecursiveCircuits<F, C, D> {
+    fn wrap_to_bn254(
+        &self,
+        block_receipt: &Receipt<F, C, D>,
+    ) -> anyhow::Result<(WrappedOutput<OuterParameters, D>, PublicValues)> {
+        self.verify_block(block_receipt)?;
+
+        let timing = TimingTree::new("agg circuit_data", log::Level::Info);
+        let gate_serializer = DefaultGateSerializer;
+        let generator_serializer = DefaultGeneratorSerializer {
+            _phantom: PhantomData::<C>,
+        };
+        let circuit_data = self
+            .block
+            .circuit
+            .to_bytes(&gate_serializer, &generator_serializer)
+            .map_err(|_| anyhow::anyhow!("Failed to serialize the block circuit"))?;
+        let circuit_data = CircuitData::<F, C, D>::from_bytes(
+            circuit_data.as_slice(),
+            &gate_serializer,
+            &generator_serializer,
+        )
+        .map_err(|_| anyhow::anyhow!("Failed to deserialize the block circuit"))?;
+
+        let builder = WrapperBuilder::<DefaultParameters, 2>::new();
+        let mut circuit = builder.build();
+        circuit.set_data(circuit_data);
+        let wrapped_circuit = WrappedCircuit::<InnerParameters, OuterParameters, D>::build(
+            circuit,
+            Some((vec![], bn254_public_input_bit_sizes())),
+        );
+        let wrapped_proof = wrapped_circuit.prove(&block_receipt.proof())?;
+        timing.filter(Duration::from_millis(100)).print();
+
+        Ok((wrapped_proof, block_receipt.values()))
+    }
+}
+
 pub fn wrap_stark_bn254(
     all_circuits: &AllRecursiveCircuits<F, C, D>,
     new_agg_receipt: Receipt<F, C, D>,
     output_dir: &str,
 ) -> anyhow::Result<()> {
-    let mut timing = TimingTree::new("agg prove_block", log::Level::Info);
-
+    let timing = TimingTree::new("agg prove_block", log::Level::Info);
     let block_receipt = all_circuits.prove_block(None, &new_agg_receipt)?;
-    all_circuits.verify_block(&block_receipt)?;
     timing.filter(Duration::from_millis(100)).print();
-    timing = TimingTree::new("agg circuit_data", log::Level::Info);
-    let gate_serializer = DefaultGateSerializer;
-    let generator_serializer = DefaultGeneratorSerializer {
-        _phantom: PhantomData::<C>,
-    };
-    let circuit_data = all_circuits
-        .block
-        .circuit
-        .to_bytes(&gate_serializer, &generator_serializer)
-        .unwrap();
-    let circuit_data = CircuitData::<F, C, D>::from_bytes(
-        circuit_data.as_slice(),
-        &gate_serializer,
-        &generator_serializer,
-    )
-    .unwrap();
-
-    let builder = WrapperBuilder::<DefaultParameters, 2>::new();
-    let mut circuit = builder.build();
-    circuit.set_data(circuit_data);
-    let mut bit_size = vec![32usize; 16];
-    bit_size.extend(vec![8; 32]);
-    bit_size.extend(vec![64; 68]);
-    let wrapped_circuit = WrappedCircuit::<InnerParameters, OuterParameters, D>::build(
-        circuit,
-        Some((vec![], bit_size)),
-    );
 
-    std::fs::create_dir_all(output_dir)?;
+    let (wrapped_proof, public_values) = all_circuits.wrap_to_bn254(&block_receipt)?;
 
-    let wrapped_proof = wrapped_circuit.prove(&block_receipt.proof()).unwrap();
+    std::fs::create_dir_all(output_dir)?;
     wrapped_proof.save(output_dir)?;
 
     let src_public_inputs = match &block_receipt {
@@ -140,10 +192,7 @@ pub fn wrap_stark_bn254(
     let outdir_path = std::path::Path::new(&output_dir);
 
     let public_values_file = outdir_path.join(PUBLIC_INPUT_PATH);
-    std::fs::write(
-        public_values_file,
-        serde_json::to_string(&block_receipt.values())?,
-    )?;
+    std::fs::write(public_values_file, serde_json::to_string(&public_values)?)?;
 
     let block_public_inputs = serde_json::json!({
         "public_inputs": src_public_inputs,
@@ -154,7 +203,6 @@ pub fn wrap_stark_bn254(
         serde_json::to_string(&block_public_inputs)?,
     )?;
 
-    timing.filter(Duration::from_millis(100)).print();
     Ok(())
 }
 
@@ -199,12 +247,95 @@ fn degree_from_env() -> [Range<usize>; 12] {
 pub mod tests {
     use super::*;
     use ethers::utils::hex::hex;
+    use plonky2::field::types::Field;
     use std::fs::File;
     use std::io::BufReader;
     use zkm_emulator::utils::{load_elf_with_patch, split_prog_into_segs};
     use zkm_prover::cpu::kernel::assembler::segment_kernel;
 
     const ELF_PATH: &str = "./elf-files/sha2-elf";
+
+    #[test]
+    fn test_bn254_public_input_layout() {
+        let bit_sizes = bn254_public_input_bit_sizes();
+        assert_eq!(bit_sizes.len(), 185);
+        assert!(bit_sizes[..16].iter().all(|&bits| bits == 32));
+        assert!(bit_sizes[16..48].iter().all(|&bits| bits == 8));
+        assert_eq!(bit_sizes[48], 32);
+        assert!(bit_sizes[49..].iter().all(|&bits| bits == 64));
+    }
+
+    // The wrapped proof verifies, and the block public inputs it commits to follow the layout of
+    // `bn254_public_input_bit_sizes`.
+    #[test]
+    #[ignore]
+    fn test_wrap_to_bn254() -> anyhow::Result<()> {
+        env_logger::try_init().unwrap_or_default();
+        let seg_path = "/tmp/output_wrap";
+        let mut state = load_elf_with_patch(ELF_PATH, vec![]);
+        state.add_input_stream(&hex::decode(
+            "711e9609339e92b03ddc0a211827dba421f38f9ed8b9d806e1ffdd8c15ffa03d",
+        )?);
+        state.add_input_stream(&"world!".as_bytes().to_vec());
+        let (_total_steps, seg_num, _state) = split_prog_into_segs(state, seg_path, "", 8192);
+        assert!(seg_num >= 2);
+
+        let all_stark = AllStark::<F, D>::default();
+        let config = StarkConfig::standard_fast_config();
+        let all_circuits = create_recursive_circuit();
+        let mut receipts = vec![];
+        for seg_id in 0..2 {
+            let seg_reader = BufReader::new(File::open(format!("{}/{}", seg_path, seg_id))?);
+            let input = segment_kernel("", "", "", seg_reader);
+            let mut timing = TimingTree::new("prove root", log::Level::Info);
+            receipts.push(all_circuits.prove_root_with_assumption(
+                &all_stark,
+                &input,
+                &config,
+                &mut timing,
+                vec![],
+            )?);
+        }
+        let agg_receipt = aggregate_proof(
+            &all_circuits,
+            receipts[0].clone(),
+            receipts[1].clone(),
+            false,
+            false,
+        )?;
+        let block_receipt = all_circuits.prove_block(None, &agg_receipt)?;
+
+        let (wrapped_proof, public_values) = all_circuits.wrap_to_bn254(&block_receipt)?;
+        plonky2::plonk::verifier::verify(
+            wrapped_proof.proof.clone(),
+            &wrapped_proof.verifier_data,
+            &wrapped_proof.common_data,
+        )?;
+        assert_eq!(public_values, block_receipt.values());
+
+        let public_inputs = block_receipt.proof().public_inputs;
+        let bit_sizes = bn254_public_input_bit_sizes();
+        assert_eq!(public_inputs.len(), bit_sizes.len());
+        let words = public_values
+            .roots_before
+            .root
+            .iter()
+            .chain(&public_values.roots_after.root)
+            .copied()
+            .chain(public_values.padded_userdata().unwrap().map(u32::from))
+            .chain([public_values.userdata.len() as u32]);
+        for (i, word) in words.enumerate() {
+            assert_eq!(
+                public_inputs[i],
+                F::from_canonical_u32(word),
+                "public input {i}"
+            );
+        }
+        let (block_vk, cyclic_vk) = public_inputs[49..].split_at(BN254_VERIFIER_DATA_ELEMENTS);
+        assert_eq!(block_vk, cyclic_vk);
+        Ok(())
+    }
+
     #[test]
     #[ignore]
     fn sha2_test_e2e() -> anyhow::Result<()> {
@@ -202,7 +202,7 @@ func (obj *SnarkProver) generateVerifySol(inputDir string) error {
 		fmt.Println(err)
 		os.Exit(1)
 	}
-	piData := publicInputsOnly.PublicInputs[48:]
+	piData := publicInputsOnly.PublicInputs[49:]
 	circuitDIgest := obj.combineToBigInt(piData, 0)
 	config.Digest = circuitDIgest.String()
 
@@ -36,6 +36,10 @@ pub const VERIFY: u32 = 0x00_00_00_F2;
 pub const KECCAK_PERMUTE: u32 = 0x00_01_01_09;
 
 /// Executes `SHA_EXTEND`.
+///
+/// `$a0` points to the message schedule `w`, whose words are `$a1` bytes apart: 0 means
+/// contiguous, as 4 does, and any other value must be a multiple of 4. Before strides were
+/// supported, `$a1` had to be 0, so existing callers keep working.
 pub const SHA_EXTEND: u32 = 0x00_30_01_05;
 
 /// Executes `SHA_COMPRESS`.
@@ -21,6 +21,12 @@ pub extern "C" fn syscall_sha256_compress(w: *mut u32, state: *mut u32) {
     }
 }
 
+/// Extends the first 16 words of the contiguous message schedule `w` to all 64 words.
+///
+/// ### Safety
+///
+/// The caller must ensure that `w` is a valid pointer to 64 words, aligned along a four byte
+/// boundary. `SHA_EXTEND` also supports strided schedules, see `syscalls::SHA_EXTEND`.
 #[allow(unused_variables)]
 #[no_mangle]
 pub extern "C" fn syscall_sha256_extend(w: *mut u32) {